@@ -0,0 +1,793 @@
+//! pure Rust exact minimum-weight perfect matching
+//!
+//! This is the fallback of [`crate::blossom_v`] when the Blossom V library is not present in the tree.
+//! It implements Edmonds' blossom algorithm in the O(n^3) primal-dual form (following the well-known implementation of
+//! Joris van Rantwijk), and uses only integer arithmetic so that the matching is exact; ties between equal-weight
+//! matchings are broken by the order of the input edges, which may differ from Blossom V.
+//!
+
+/// absence of a vertex, an endpoint or an edge
+const NONE: usize = usize::MAX;
+
+const LABEL_FREE: u8 = 0;
+const LABEL_S: u8 = 1;
+const LABEL_T: u8 = 2;
+/// temporary mark used when scanning for the base of a new blossom
+const LABEL_BREADCRUMB: u8 = 4;
+
+/// find a minimum-weight perfect matching of a general graph; panics if no perfect matching exists.
+/// the return value `matched[i]` is the peer vertex of vertex `i`, the same format as Blossom V
+pub fn minimum_weight_perfect_matching(node_num: usize, weighted_edges: &[(usize, usize, i64)]) -> Vec<usize> {
+    if node_num == 0 {
+        return vec![];
+    }
+    // a maximum-cardinality maximum-weight matching under weights `C - w` is a minimum-weight perfect matching if any exists
+    let maximum_weight = weighted_edges.iter().map(|&(_, _, w)| w).max().unwrap_or(0);
+    let edges: Vec<(usize, usize, i64)> = weighted_edges
+        .iter()
+        .map(|&(i, j, w)| {
+            assert!(i < node_num && j < node_num && i != j, "invalid edge ({}, {})", i, j);
+            // all weights are doubled so that every dual variable stays an integer
+            (i, j, 2 * (maximum_weight + 1 - w))
+        })
+        .collect();
+    let mut matcher = MaximumWeightMatcher::new(node_num, edges);
+    matcher.solve();
+    let matched = matcher.matched_vertices();
+    for (i, &peer) in matched.iter().enumerate() {
+        assert!(peer != NONE, "perfect matching doesn't exist: vertex {} is not matched", i);
+    }
+    matched
+}
+
+/// maximum-weight maximum-cardinality matching, where all dual variables are stored doubled
+struct MaximumWeightMatcher {
+    /// number of vertices; blossoms are indexed in `vertex_num..2*vertex_num`
+    vertex_num: usize,
+    edges: Vec<(usize, usize, i64)>,
+    /// `endpoints[p]` is the vertex at endpoint `p`, where edge `k` has endpoints `2k` and `2k+1`
+    endpoints: Vec<usize>,
+    /// remote endpoints of the edges incident to each vertex
+    neighbor_endpoints: Vec<Vec<usize>>,
+    /// the remote endpoint of the matched edge of each vertex
+    mate: Vec<usize>,
+    label: Vec<u8>,
+    /// the endpoint through which a top-level blossom got its label
+    label_end: Vec<usize>,
+    /// the top-level blossom containing each vertex
+    in_blossom: Vec<usize>,
+    blossom_parent: Vec<usize>,
+    /// sub-blossoms in cyclic order, starting from the base
+    blossom_children: Vec<Vec<usize>>,
+    blossom_base: Vec<usize>,
+    /// `blossom_endpoints[b][i]` connects `blossom_children[b][i]` and `blossom_children[b][i+1]`
+    blossom_endpoints: Vec<Vec<usize>>,
+    /// least-slack edge to a different S-blossom (or S-vertex for free vertices)
+    best_edge: Vec<usize>,
+    /// least-slack edges of a non-trivial S-blossom to each neighboring S-blossom
+    blossom_best_edges: Vec<Option<Vec<usize>>>,
+    unused_blossoms: Vec<usize>,
+    dual_variables: Vec<i64>,
+    allow_edge: Vec<bool>,
+    queue: Vec<usize>,
+}
+
+impl MaximumWeightMatcher {
+    fn new(vertex_num: usize, edges: Vec<(usize, usize, i64)>) -> Self {
+        let maximum_weight = edges.iter().map(|&(_, _, w)| w).max().unwrap_or(0).max(0);
+        let mut endpoints = Vec::with_capacity(2 * edges.len());
+        let mut neighbor_endpoints = vec![vec![]; vertex_num];
+        for (k, &(i, j, _)) in edges.iter().enumerate() {
+            endpoints.push(i);
+            endpoints.push(j);
+            neighbor_endpoints[i].push(2 * k + 1);
+            neighbor_endpoints[j].push(2 * k);
+        }
+        let edge_num = edges.len();
+        Self {
+            vertex_num,
+            edges,
+            endpoints,
+            neighbor_endpoints,
+            mate: vec![NONE; vertex_num],
+            label: vec![LABEL_FREE; 2 * vertex_num],
+            label_end: vec![NONE; 2 * vertex_num],
+            in_blossom: (0..vertex_num).collect(),
+            blossom_parent: vec![NONE; 2 * vertex_num],
+            blossom_children: vec![vec![]; 2 * vertex_num],
+            blossom_base: (0..vertex_num).chain(std::iter::repeat(NONE).take(vertex_num)).collect(),
+            blossom_endpoints: vec![vec![]; 2 * vertex_num],
+            best_edge: vec![NONE; 2 * vertex_num],
+            blossom_best_edges: vec![None; 2 * vertex_num],
+            unused_blossoms: (vertex_num..2 * vertex_num).collect(),
+            dual_variables: std::iter::repeat(maximum_weight)
+                .take(vertex_num)
+                .chain(std::iter::repeat(0).take(vertex_num))
+                .collect(),
+            allow_edge: vec![false; edge_num],
+            queue: vec![],
+        }
+    }
+
+    fn slack(&self, k: usize) -> i64 {
+        let (i, j, w) = self.edges[k];
+        self.dual_variables[i] + self.dual_variables[j] - 2 * w
+    }
+
+    fn blossom_leaves(&self, b: usize) -> Vec<usize> {
+        let mut leaves = vec![];
+        let mut stack = vec![b];
+        while let Some(b) = stack.pop() {
+            if b < self.vertex_num {
+                leaves.push(b);
+            } else {
+                stack.extend(self.blossom_children[b].iter().rev());
+            }
+        }
+        leaves
+    }
+
+    /// index into a cyclic list with a possibly negative index
+    fn cyclic(list: &[usize], index: isize) -> usize {
+        list[index.rem_euclid(list.len() as isize) as usize]
+    }
+
+    fn assign_label(&mut self, w: usize, t: u8, p: usize) {
+        let b = self.in_blossom[w];
+        debug_assert!(self.label[w] == LABEL_FREE && self.label[b] == LABEL_FREE);
+        self.label[w] = t;
+        self.label[b] = t;
+        self.label_end[w] = p;
+        self.label_end[b] = p;
+        self.best_edge[w] = NONE;
+        self.best_edge[b] = NONE;
+        if t == LABEL_S {
+            let leaves = self.blossom_leaves(b);
+            self.queue.extend(leaves);
+        } else if t == LABEL_T {
+            let base = self.blossom_base[b];
+            debug_assert!(self.mate[base] != NONE);
+            let mate_endpoint = self.mate[base];
+            self.assign_label(self.endpoints[mate_endpoint], LABEL_S, mate_endpoint ^ 1);
+        }
+    }
+
+    /// trace back from `v` and `w` to find either a new blossom base or an augmenting path (returns NONE)
+    fn scan_blossom(&mut self, mut v: usize, mut w: usize) -> usize {
+        let mut path = vec![];
+        let mut base = NONE;
+        while v != NONE || w != NONE {
+            let mut b = self.in_blossom[v];
+            if self.label[b] & LABEL_BREADCRUMB != 0 {
+                base = self.blossom_base[b];
+                break;
+            }
+            debug_assert_eq!(self.label[b], LABEL_S);
+            path.push(b);
+            self.label[b] = LABEL_S | LABEL_BREADCRUMB;
+            if self.label_end[b] == NONE {
+                v = NONE;
+            } else {
+                v = self.endpoints[self.label_end[b]];
+                b = self.in_blossom[v];
+                debug_assert_eq!(self.label[b], LABEL_T);
+                v = self.endpoints[self.label_end[b]];
+            }
+            if w != NONE {
+                std::mem::swap(&mut v, &mut w);
+            }
+        }
+        for b in path {
+            self.label[b] = LABEL_S;
+        }
+        base
+    }
+
+    fn add_blossom(&mut self, base: usize, k: usize) {
+        let (mut v, mut w, _) = self.edges[k];
+        let bb = self.in_blossom[base];
+        let mut bv = self.in_blossom[v];
+        let mut bw = self.in_blossom[w];
+        let b = self.unused_blossoms.pop().expect("blossom index exhausted");
+        self.blossom_base[b] = base;
+        self.blossom_parent[b] = NONE;
+        self.blossom_parent[bb] = b;
+        let mut path = vec![];
+        let mut endpoints = vec![];
+        while bv != bb {
+            self.blossom_parent[bv] = b;
+            path.push(bv);
+            endpoints.push(self.label_end[bv]);
+            v = self.endpoints[self.label_end[bv]];
+            bv = self.in_blossom[v];
+        }
+        path.push(bb);
+        path.reverse();
+        endpoints.reverse();
+        endpoints.push(2 * k);
+        while bw != bb {
+            self.blossom_parent[bw] = b;
+            path.push(bw);
+            endpoints.push(self.label_end[bw] ^ 1);
+            w = self.endpoints[self.label_end[bw]];
+            bw = self.in_blossom[w];
+        }
+        debug_assert_eq!(self.label[bb], LABEL_S);
+        self.label[b] = LABEL_S;
+        self.label_end[b] = self.label_end[bb];
+        self.dual_variables[b] = 0;
+        self.blossom_children[b] = path.clone();
+        self.blossom_endpoints[b] = endpoints;
+        for leaf in self.blossom_leaves(b) {
+            if self.label[self.in_blossom[leaf]] == LABEL_T {
+                // T-vertices inside the new blossom become S-vertices
+                self.queue.push(leaf);
+            }
+            self.in_blossom[leaf] = b;
+        }
+        // compute the least-slack edges to neighboring S-blossoms
+        let mut best_edge_to = vec![NONE; 2 * self.vertex_num];
+        for &bv in path.iter() {
+            let neighbor_lists: Vec<Vec<usize>> = match self.blossom_best_edges[bv].take() {
+                Some(list) => vec![list],
+                None => self
+                    .blossom_leaves(bv)
+                    .into_iter()
+                    .map(|leaf| self.neighbor_endpoints[leaf].iter().map(|p| p / 2).collect())
+                    .collect(),
+            };
+            for neighbor_list in neighbor_lists {
+                for k in neighbor_list {
+                    let (mut i, mut j, _) = self.edges[k];
+                    if self.in_blossom[j] == b {
+                        std::mem::swap(&mut i, &mut j);
+                    }
+                    debug_assert_eq!(self.in_blossom[i], b);
+                    let bj = self.in_blossom[j];
+                    if bj != b
+                        && self.label[bj] == LABEL_S
+                        && (best_edge_to[bj] == NONE || self.slack(k) < self.slack(best_edge_to[bj]))
+                    {
+                        best_edge_to[bj] = k;
+                    }
+                }
+            }
+            self.best_edge[bv] = NONE;
+        }
+        let best_edges: Vec<usize> = best_edge_to.into_iter().filter(|&k| k != NONE).collect();
+        self.best_edge[b] = NONE;
+        for &k in best_edges.iter() {
+            if self.best_edge[b] == NONE || self.slack(k) < self.slack(self.best_edge[b]) {
+                self.best_edge[b] = k;
+            }
+        }
+        self.blossom_best_edges[b] = Some(best_edges);
+    }
+
+    fn expand_blossom(&mut self, b: usize, end_stage: bool) {
+        let children = self.blossom_children[b].clone();
+        for &s in children.iter() {
+            self.blossom_parent[s] = NONE;
+            if s < self.vertex_num {
+                self.in_blossom[s] = s;
+            } else if end_stage && self.dual_variables[s] == 0 {
+                self.expand_blossom(s, end_stage);
+            } else {
+                for leaf in self.blossom_leaves(s) {
+                    self.in_blossom[leaf] = s;
+                }
+            }
+        }
+        if !end_stage && self.label[b] == LABEL_T {
+            // relabel the sub-blossoms on the even-length path from the entry child to the base
+            let endpoints = self.blossom_endpoints[b].clone();
+            let entry_child = self.in_blossom[self.endpoints[self.label_end[b] ^ 1]];
+            let mut j = children.iter().position(|&c| c == entry_child).unwrap() as isize;
+            let (j_step, endpoint_trick) = if j & 1 == 1 {
+                j -= children.len() as isize;
+                (1, 0)
+            } else {
+                (-1, 1)
+            };
+            let mut p = self.label_end[b];
+            while j != 0 {
+                self.label[self.endpoints[p ^ 1]] = LABEL_FREE;
+                let q = Self::cyclic(&endpoints, j - endpoint_trick as isize) ^ endpoint_trick ^ 1;
+                self.label[self.endpoints[q]] = LABEL_FREE;
+                self.assign_label(self.endpoints[p ^ 1], LABEL_T, p);
+                self.allow_edge[Self::cyclic(&endpoints, j - endpoint_trick as isize) / 2] = true;
+                j += j_step;
+                p = Self::cyclic(&endpoints, j - endpoint_trick as isize) ^ endpoint_trick;
+                self.allow_edge[p / 2] = true;
+                j += j_step;
+            }
+            let bv = Self::cyclic(&children, j);
+            self.label[self.endpoints[p ^ 1]] = LABEL_T;
+            self.label[bv] = LABEL_T;
+            self.label_end[self.endpoints[p ^ 1]] = p;
+            self.label_end[bv] = p;
+            self.best_edge[bv] = NONE;
+            j += j_step;
+            while Self::cyclic(&children, j) != entry_child {
+                let bv = Self::cyclic(&children, j);
+                if self.label[bv] == LABEL_S {
+                    j += j_step;
+                    continue;
+                }
+                let labeled = self.blossom_leaves(bv).into_iter().find(|&v| self.label[v] != LABEL_FREE);
+                if let Some(v) = labeled {
+                    debug_assert_eq!(self.label[v], LABEL_T);
+                    debug_assert_eq!(self.in_blossom[v], bv);
+                    self.label[v] = LABEL_FREE;
+                    self.label[self.endpoints[self.mate[self.blossom_base[bv]]]] = LABEL_FREE;
+                    self.assign_label(v, LABEL_T, self.label_end[v]);
+                }
+                j += j_step;
+            }
+        }
+        self.label[b] = LABEL_FREE;
+        self.label_end[b] = NONE;
+        self.blossom_children[b].clear();
+        self.blossom_endpoints[b].clear();
+        self.blossom_base[b] = NONE;
+        self.blossom_best_edges[b] = None;
+        self.best_edge[b] = NONE;
+        self.unused_blossoms.push(b);
+    }
+
+    /// swap matched and unmatched edges along the path from vertex `v` to the base of blossom `b`
+    fn augment_blossom(&mut self, b: usize, v: usize) {
+        let mut t = v;
+        while self.blossom_parent[t] != b {
+            t = self.blossom_parent[t];
+        }
+        if t >= self.vertex_num {
+            self.augment_blossom(t, v);
+        }
+        let children = self.blossom_children[b].clone();
+        let endpoints = self.blossom_endpoints[b].clone();
+        let i = children.iter().position(|&c| c == t).unwrap();
+        let mut j = i as isize;
+        let (j_step, endpoint_trick) = if i & 1 == 1 {
+            j -= children.len() as isize;
+            (1, 0)
+        } else {
+            (-1, 1)
+        };
+        while j != 0 {
+            j += j_step;
+            let t = Self::cyclic(&children, j);
+            let p = Self::cyclic(&endpoints, j - endpoint_trick as isize) ^ endpoint_trick;
+            if t >= self.vertex_num {
+                self.augment_blossom(t, self.endpoints[p]);
+            }
+            j += j_step;
+            let t = Self::cyclic(&children, j);
+            if t >= self.vertex_num {
+                self.augment_blossom(t, self.endpoints[p ^ 1]);
+            }
+            self.mate[self.endpoints[p]] = p ^ 1;
+            self.mate[self.endpoints[p ^ 1]] = p;
+        }
+        // rotate the sub-blossoms so that the new base is at the front
+        self.blossom_children[b] = children[i..].iter().chain(children[..i].iter()).cloned().collect();
+        self.blossom_endpoints[b] = endpoints[i..].iter().chain(endpoints[..i].iter()).cloned().collect();
+        self.blossom_base[b] = self.blossom_base[self.blossom_children[b][0]];
+        debug_assert_eq!(self.blossom_base[b], v);
+    }
+
+    fn augment_matching(&mut self, k: usize) {
+        let (v, w, _) = self.edges[k];
+        for (mut s, mut p) in [(v, 2 * k + 1), (w, 2 * k)] {
+            loop {
+                let bs = self.in_blossom[s];
+                debug_assert_eq!(self.label[bs], LABEL_S);
+                if bs >= self.vertex_num {
+                    self.augment_blossom(bs, s);
+                }
+                self.mate[s] = p;
+                if self.label_end[bs] == NONE {
+                    break; // reached a single vertex
+                }
+                let t = self.endpoints[self.label_end[bs]];
+                let bt = self.in_blossom[t];
+                debug_assert_eq!(self.label[bt], LABEL_T);
+                s = self.endpoints[self.label_end[bt]];
+                let j = self.endpoints[self.label_end[bt] ^ 1];
+                debug_assert_eq!(self.blossom_base[bt], t);
+                if bt >= self.vertex_num {
+                    self.augment_blossom(bt, j);
+                }
+                self.mate[j] = self.label_end[bt];
+                p = self.label_end[bt] ^ 1;
+            }
+        }
+    }
+
+    fn solve(&mut self) {
+        let n = self.vertex_num;
+        for _stage in 0..n {
+            self.label.iter_mut().for_each(|l| *l = LABEL_FREE);
+            self.best_edge.iter_mut().for_each(|e| *e = NONE);
+            self.blossom_best_edges[n..].iter_mut().for_each(|e| *e = None);
+            self.allow_edge.iter_mut().for_each(|a| *a = false);
+            self.queue.clear();
+            for v in 0..n {
+                if self.mate[v] == NONE && self.label[self.in_blossom[v]] == LABEL_FREE {
+                    self.assign_label(v, LABEL_S, NONE);
+                }
+            }
+            let mut augmented = false;
+            loop {
+                while !augmented {
+                    let Some(v) = self.queue.pop() else {
+                        break;
+                    };
+                    debug_assert_eq!(self.label[self.in_blossom[v]], LABEL_S);
+                    for idx in 0..self.neighbor_endpoints[v].len() {
+                        let p = self.neighbor_endpoints[v][idx];
+                        let k = p / 2;
+                        let w = self.endpoints[p];
+                        if self.in_blossom[v] == self.in_blossom[w] {
+                            continue;
+                        }
+                        let mut k_slack = 0;
+                        if !self.allow_edge[k] {
+                            k_slack = self.slack(k);
+                            if k_slack <= 0 {
+                                self.allow_edge[k] = true;
+                            }
+                        }
+                        if self.allow_edge[k] {
+                            if self.label[self.in_blossom[w]] == LABEL_FREE {
+                                self.assign_label(w, LABEL_T, p ^ 1);
+                            } else if self.label[self.in_blossom[w]] == LABEL_S {
+                                let base = self.scan_blossom(v, w);
+                                if base != NONE {
+                                    self.add_blossom(base, k);
+                                } else {
+                                    self.augment_matching(k);
+                                    augmented = true;
+                                    break;
+                                }
+                            } else if self.label[w] == LABEL_FREE {
+                                debug_assert_eq!(self.label[self.in_blossom[w]], LABEL_T);
+                                self.label[w] = LABEL_T;
+                                self.label_end[w] = p ^ 1;
+                            }
+                        } else if self.label[self.in_blossom[w]] == LABEL_S {
+                            let b = self.in_blossom[v];
+                            if self.best_edge[b] == NONE || k_slack < self.slack(self.best_edge[b]) {
+                                self.best_edge[b] = k;
+                            }
+                        } else if self.label[w] == LABEL_FREE
+                            && (self.best_edge[w] == NONE || k_slack < self.slack(self.best_edge[w]))
+                        {
+                            self.best_edge[w] = k;
+                        }
+                    }
+                }
+                if augmented {
+                    break;
+                }
+                // no augmenting path under the current duals: compute the dual update
+                let mut delta_type = 0;
+                let mut delta = 0;
+                let mut delta_edge = NONE;
+                let mut delta_blossom = NONE;
+                for v in 0..n {
+                    if self.label[self.in_blossom[v]] == LABEL_FREE && self.best_edge[v] != NONE {
+                        let d = self.slack(self.best_edge[v]);
+                        if delta_type == 0 || d < delta {
+                            delta = d;
+                            delta_type = 2;
+                            delta_edge = self.best_edge[v];
+                        }
+                    }
+                }
+                for b in 0..2 * n {
+                    if self.blossom_parent[b] == NONE && self.label[b] == LABEL_S && self.best_edge[b] != NONE {
+                        let k_slack = self.slack(self.best_edge[b]);
+                        debug_assert_eq!(k_slack % 2, 0);
+                        let d = k_slack / 2;
+                        if delta_type == 0 || d < delta {
+                            delta = d;
+                            delta_type = 3;
+                            delta_edge = self.best_edge[b];
+                        }
+                    }
+                }
+                for b in n..2 * n {
+                    if self.blossom_base[b] != NONE
+                        && self.blossom_parent[b] == NONE
+                        && self.label[b] == LABEL_T
+                        && (delta_type == 0 || self.dual_variables[b] < delta)
+                    {
+                        delta = self.dual_variables[b];
+                        delta_type = 4;
+                        delta_blossom = b;
+                    }
+                }
+                if delta_type == 0 {
+                    // no further improvement possible: maximum cardinality is reached
+                    delta_type = 1;
+                    delta = self.dual_variables[..n].iter().cloned().min().unwrap().max(0);
+                }
+                for v in 0..n {
+                    match self.label[self.in_blossom[v]] {
+                        LABEL_S => self.dual_variables[v] -= delta,
+                        LABEL_T => self.dual_variables[v] += delta,
+                        _ => {}
+                    }
+                }
+                for b in n..2 * n {
+                    if self.blossom_base[b] != NONE && self.blossom_parent[b] == NONE {
+                        match self.label[b] {
+                            LABEL_S => self.dual_variables[b] += delta,
+                            LABEL_T => self.dual_variables[b] -= delta,
+                            _ => {}
+                        }
+                    }
+                }
+                match delta_type {
+                    1 => break,
+                    2 => {
+                        self.allow_edge[delta_edge] = true;
+                        let (mut i, j, _) = self.edges[delta_edge];
+                        if self.label[self.in_blossom[i]] == LABEL_FREE {
+                            i = j;
+                        }
+                        debug_assert_eq!(self.label[self.in_blossom[i]], LABEL_S);
+                        self.queue.push(i);
+                    }
+                    3 => {
+                        self.allow_edge[delta_edge] = true;
+                        let (i, _, _) = self.edges[delta_edge];
+                        debug_assert_eq!(self.label[self.in_blossom[i]], LABEL_S);
+                        self.queue.push(i);
+                    }
+                    _ => self.expand_blossom(delta_blossom, false),
+                }
+            }
+            if !augmented {
+                break;
+            }
+            // expand all S-blossoms with zero dual at the end of each stage
+            for b in n..2 * n {
+                if self.blossom_parent[b] == NONE
+                    && self.blossom_base[b] != NONE
+                    && self.label[b] == LABEL_S
+                    && self.dual_variables[b] == 0
+                {
+                    self.expand_blossom(b, true);
+                }
+            }
+        }
+    }
+
+    fn matched_vertices(&self) -> Vec<usize> {
+        self.mate
+            .iter()
+            .map(|&p| if p == NONE { NONE } else { self.endpoints[p] })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::{Rng, SeedableRng};
+    use crate::reproducible_rand::Xoroshiro128StarStar;
+
+    /// exhaustively search the minimum weight of all perfect matchings
+    fn brute_force_minimum_weight(node_num: usize, weighted_edges: &[(usize, usize, i64)]) -> Option<i64> {
+        let mut weights = vec![vec![None; node_num]; node_num];
+        for &(i, j, w) in weighted_edges.iter() {
+            weights[i][j] = Some(w);
+            weights[j][i] = Some(w);
+        }
+        fn search(weights: &Vec<Vec<Option<i64>>>, matched: &mut Vec<bool>) -> Option<i64> {
+            let Some(i) = matched.iter().position(|&m| !m) else {
+                return Some(0);
+            };
+            matched[i] = true;
+            let mut best: Option<i64> = None;
+            for j in 0..matched.len() {
+                if matched[j] {
+                    continue;
+                }
+                if let Some(w) = weights[i][j] {
+                    matched[j] = true;
+                    if let Some(rest) = search(weights, matched) {
+                        best = Some(best.map_or(w + rest, |b| b.min(w + rest)));
+                    }
+                    matched[j] = false;
+                }
+            }
+            matched[i] = false;
+            best
+        }
+        search(&weights, &mut vec![false; node_num])
+    }
+
+    /// exhaustively count the perfect matchings of the given total weight
+    #[cfg(feature = "blossom_v")]
+    fn brute_force_count_matchings(node_num: usize, weighted_edges: &[(usize, usize, i64)], total_weight: i64) -> usize {
+        let mut weights = vec![vec![None; node_num]; node_num];
+        for &(i, j, w) in weighted_edges.iter() {
+            weights[i][j] = Some(w);
+            weights[j][i] = Some(w);
+        }
+        fn search(weights: &Vec<Vec<Option<i64>>>, matched: &mut Vec<bool>, remaining: i64) -> usize {
+            let Some(i) = matched.iter().position(|&m| !m) else {
+                return (remaining == 0) as usize;
+            };
+            matched[i] = true;
+            let mut count = 0;
+            for j in 0..matched.len() {
+                if matched[j] {
+                    continue;
+                }
+                if let Some(w) = weights[i][j] {
+                    matched[j] = true;
+                    count += search(weights, matched, remaining - w);
+                    matched[j] = false;
+                }
+            }
+            matched[i] = false;
+            count
+        }
+        search(&weights, &mut vec![false; node_num], total_weight)
+    }
+
+    #[test]
+    fn blossom_rs_small_graph() {
+        // cargo test blossom_rs_small_graph -- --nocapture
+        // the example in `test.rs`, negated to minimum weight
+        let weighted_edges = vec![
+            (0, 1, 3),
+            (1, 2, 2),
+            (2, 0, 3),
+            (0, 3, 1),
+            (1, 4, 2),
+            (2, 5, 1),
+            (3, 4, 0),
+            (3, 5, 0),
+            (4, 5, 0),
+        ];
+        let matched = minimum_weight_perfect_matching(6, &weighted_edges);
+        println!("{:?}", matched);
+        assert_eq!(matched, vec![3, 2, 1, 0, 5, 4]);
+    }
+
+    /// check that `matched` is a perfect matching in the graph and return its total weight
+    fn matching_weight(matched: &[usize], weighted_edges: &[(usize, usize, i64)]) -> i64 {
+        let mut total_weight = 0;
+        for (i, &j) in matched.iter().enumerate() {
+            assert_eq!(matched[j], i);
+            if i < j {
+                let &(_, _, w) = weighted_edges
+                    .iter()
+                    .find(|&&(a, b, _)| (a, b) == (i, j) || (a, b) == (j, i))
+                    .expect("matched edge must exist");
+                total_weight += w;
+            }
+        }
+        total_weight
+    }
+
+    #[test]
+    fn blossom_rs_tie_breaking() {
+        // cargo test blossom_rs_tie_breaking -- --nocapture
+        // a 4-cycle with equal weights has two minimum-weight perfect matchings
+        let square = vec![(0, 1, 1), (1, 2, 1), (2, 3, 1), (3, 0, 1)];
+        let matched = minimum_weight_perfect_matching(4, &square);
+        println!("square: {:?}", matched);
+        assert_eq!(matching_weight(&matched, &square), 2);
+        assert_eq!(
+            matched,
+            minimum_weight_perfect_matching(4, &square),
+            "tie-breaking must be deterministic"
+        );
+        // complete graphs with equal or zero weights have many minimum-weight perfect matchings
+        for weight in [0, 3] {
+            for node_num in [2, 4, 6, 8] {
+                let mut weighted_edges = vec![];
+                for i in 0..node_num {
+                    for j in i + 1..node_num {
+                        weighted_edges.push((i, j, weight));
+                    }
+                }
+                let matched = minimum_weight_perfect_matching(node_num, &weighted_edges);
+                assert_eq!(matching_weight(&matched, &weighted_edges), weight * node_num as i64 / 2);
+                assert_eq!(matched, minimum_weight_perfect_matching(node_num, &weighted_edges));
+            }
+        }
+        // random graphs with a narrow weight range, so that most of them have ties
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(456);
+        for _ in 0..1000 {
+            let node_num = 2 * rng.gen_range(1..5);
+            let mut weighted_edges = vec![];
+            for i in 0..node_num {
+                for j in i + 1..node_num {
+                    if rng.gen::<f64>() < 0.8 {
+                        weighted_edges.push((i, j, rng.gen_range(0..2)));
+                    }
+                }
+            }
+            let Some(expected) = brute_force_minimum_weight(node_num, &weighted_edges) else {
+                continue; // no perfect matching
+            };
+            let matched = minimum_weight_perfect_matching(node_num, &weighted_edges);
+            assert_eq!(matching_weight(&matched, &weighted_edges), expected, "{:?}", weighted_edges);
+            assert_eq!(matched, minimum_weight_perfect_matching(node_num, &weighted_edges));
+        }
+    }
+
+    #[test]
+    fn blossom_rs_random_graphs() {
+        // cargo test blossom_rs_random_graphs -- --nocapture
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(123);
+        for _ in 0..2000 {
+            let node_num = 2 * rng.gen_range(1..6);
+            let mut weighted_edges = vec![];
+            for i in 0..node_num {
+                for j in i + 1..node_num {
+                    if rng.gen::<f64>() < 0.7 {
+                        weighted_edges.push((i, j, rng.gen_range(-5..20)));
+                    }
+                }
+            }
+            let Some(expected) = brute_force_minimum_weight(node_num, &weighted_edges) else {
+                continue; // no perfect matching
+            };
+            let matched = minimum_weight_perfect_matching(node_num, &weighted_edges);
+            assert_eq!(matching_weight(&matched, &weighted_edges), expected, "{:?}", weighted_edges);
+        }
+    }
+
+    #[cfg(feature = "blossom_v")]
+    #[test]
+    fn blossom_rs_equivalent_to_blossom_v() {
+        // cargo test blossom_rs_equivalent_to_blossom_v -- --nocapture
+        use crate::blossom_v::safe_minimum_weight_perfect_matching_integer_weights;
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(789);
+        let (mut unique, mut tied) = (0, 0);
+        for round in 0..2000 {
+            let node_num = 2 * rng.gen_range(1..6);
+            // half of the graphs use a narrow weight range, so that most of them have ties
+            let maximum_weight = if round % 2 == 0 { 3 } else { 1000 };
+            let mut weighted_edges = vec![];
+            for i in 0..node_num {
+                for j in i + 1..node_num {
+                    if rng.gen::<f64>() < 0.7 {
+                        weighted_edges.push((i, j, rng.gen_range(0..maximum_weight)));
+                    }
+                }
+            }
+            let Some(expected) = brute_force_minimum_weight(node_num, &weighted_edges) else {
+                continue; // no perfect matching
+            };
+            let matched = minimum_weight_perfect_matching(node_num, &weighted_edges);
+            let blossom_v_matched = safe_minimum_weight_perfect_matching_integer_weights(
+                node_num,
+                weighted_edges.iter().map(|&(i, j, w)| (i, j, w as libc::c_int)).collect(),
+            );
+            assert_eq!(matching_weight(&matched, &weighted_edges), expected, "{:?}", weighted_edges);
+            assert_eq!(
+                matching_weight(&blossom_v_matched, &weighted_edges),
+                expected,
+                "{:?}",
+                weighted_edges
+            );
+            if brute_force_count_matchings(node_num, &weighted_edges, expected) == 1 {
+                assert_eq!(matched, blossom_v_matched, "{:?}", weighted_edges);
+                unique += 1;
+            } else {
+                tied += 1;
+            }
+        }
+        println!("{unique} graphs with a unique minimum-weight perfect matching, {tied} graphs with ties");
+        assert!(unique > 0 && tied > 0);
+    }
+}
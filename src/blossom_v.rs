@@ -13,8 +13,16 @@ cfg_if::cfg_if! {
 
     } else {
 
-        fn minimum_weight_perfect_matching(_node_num: c_int, _edge_num: c_int, _edges: *const c_int, _weights: *const c_int, _matched: *mut c_int) {
-            unimplemented!("need blossom V library, see README.md")
+        /// fall back to the pure Rust implementation when blossom V library is not available, see README.md
+        unsafe fn minimum_weight_perfect_matching(node_num: c_int, edge_num: c_int, edges: *const c_int, weights: *const c_int, matched: *mut c_int) {
+            let edges = std::slice::from_raw_parts(edges, 2 * edge_num as usize);
+            let weights = std::slice::from_raw_parts(weights, edge_num as usize);
+            let weighted_edges: Vec<(usize, usize, i64)> = weights.iter().enumerate()
+                .map(|(e, &weight)| (edges[2 * e] as usize, edges[2 * e + 1] as usize, weight as i64)).collect();
+            let output = super::blossom_rs::minimum_weight_perfect_matching(node_num as usize, &weighted_edges);
+            for (i, peer) in output.into_iter().enumerate() {
+                *matched.add(i) = peer as c_int;
+            }
         }
 
     }
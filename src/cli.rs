@@ -53,6 +53,8 @@ pub enum TestCommands {
 pub enum ToolCommands {
     /// built-in tests
     Benchmark(BenchmarkParameters),
+    /// enumerate all the combinations of a few faults to find the minimum number of faults that causes a logical error
+    FaultDistance(FaultDistanceParameters),
 }
 
 #[derive(Clone)]
@@ -228,6 +230,16 @@ pub struct BenchmarkParameters {
     /// if provided, will fetch a Json from file to update noise model
     #[clap(long)]
     pub load_noise_model_from_file: Option<String>,
+    /// if provided, will build the circuit and the noise model from a Stim circuit file instead of `code_type`;
+    /// `dis`, `nms` and `ps` are still required but only used in the output, and each configuration simulates the same circuit
+    #[clap(long)]
+    pub load_stim_circuit_from_file: Option<String>,
+    /// if provided, will build the circuit and the noise model from a Stim detector error model file instead of `code_type`;
+    /// detectors are placed by their `detector(x, y, t)` coordinates, and the compact simulator is used unless
+    /// `simulator` says otherwise; decomposed errors (`^`) are sampled as a whole but decoded as independent components;
+    /// see `load_stim_circuit_from_file`
+    #[clap(long)]
+    pub load_detector_error_model_from_file: Option<String>,
     /// logging to the default visualizer file at visualize/data/visualizer.json
     #[clap(long, action)]
     pub enable_visualizer: bool,
@@ -251,16 +263,47 @@ pub struct BenchmarkParameters {
     pub fusion_blossom_syndrome_export_filename: String,
     /// when provided, it will override the default nms[0] value and generate a compact simulator using `SimulatorCompactExtender`;
     /// note that not all decoders can adapt to this, because they still use the original simulator to construct their decoding structure.
-    /// the only supported decoder is `fusion`.
-    #[clap(long, requires = "use_compact_simulator")]
+    /// the only supported decoder is `fusion`, unless decoding with `sliding_window_commit_rounds`.
+    #[clap(long)]
     pub simulator_compact_extender_noisy_measurements: Option<usize>,
-    /// use compact simulator to generate syndromes instead
-    #[clap(long, action)]
+    /// decode the experiment of `simulator_compact_extender_noisy_measurements` rounds with sliding windows of nms[0] rounds,
+    /// committing the corrections of the first given number of rounds in each window; the window decoder is built on nms[0]
+    /// rounds, so the decoding graph doesn't grow with the experiment. only `mwpm` and `fusion` decoders are supported.
+    /// the logical error rate per round is appended to the output
+    #[clap(long, requires = "simulator_compact_extender_noisy_measurements")]
+    pub sliding_window_commit_rounds: Option<usize>,
+    /// use compact simulator to generate syndromes instead, same as `--simulator compact`
+    #[clap(long, action, conflicts_with = "simulator")]
     pub use_compact_simulator: bool,
     /// use compressed compact simulator, further reducing the memory requirement;
     /// note that this optimizes memory but sacrifices speed, since all the error sources are generated dynamically on the fly
-    #[clap(long, requires = "use_compact_simulator")]
+    #[clap(long)]
     pub use_compact_simulator_compressed: bool,
+    /// estimate the logical error rate with importance sampling: for increasing weights up to `max_weight`, sample error
+    /// configurations with exactly that many error sources happening, and then recombine the failure rates with the probability
+    /// of each weight; `max_repeats`, `min_failed_cases` and `time_budget` are split evenly across the weights. the sampling
+    /// stops early once the larger weights are negligible, otherwise the result is a lower bound and a warning is printed;
+    /// when no sampled shot fails, `pL_dev` is the absolute 95% upper bound of the logical error rate instead of the relative
+    /// one; suitable for physical error rates well below threshold
+    #[clap(long)]
+    pub importance_sampling_max_weight: Option<usize>,
+    /// [a1,a2,...,an] report the logical error rate of the accepted shots after aborting the given fractions of shots with the
+    /// smallest complementary gap; the decoder must enable `soft_output`, which is supported by `mwpm`, `fusion` and `union-find`.
+    /// the logical error rate of each abort rate is appended to the output; the complementary gaps are binned with a relative
+    /// resolution of about 1%, and the shots in the bin at the threshold are aborted proportionally
+    #[clap(long, value_parser = ValueParser::new(VecF64Parser), conflicts_with = "importance_sampling_max_weight")]
+    pub soft_output_abort_rates: Option<std::vec::Vec<f64>>,
+    /// discard the shots that violate the post-selection rules, e.g. {"min_complementary_gap":5,"max_detected_erasures":2};
+    /// `max_repeats` still limits all the simulated shots but `min_failed_cases` only counts the accepted failed shots.
+    /// the number of discarded shots, the accept rate and the logical error rate of the accepted shots are appended to the output,
+    /// where the columns conditioned on the accepted shots are `-` if all the shots are discarded
+    #[clap(long, value_parser = ValueParser::new(SerdeJsonParser), conflicts_with = "importance_sampling_max_weight")]
+    pub post_select: Option<serde_json::Value>,
+    /// select the simulator that generates syndromes: `full` by default, `compact` for the compact simulator (required by the
+    /// extender, the compressed simulator and importance sampling, and the default for a detector error model), or `batch` for
+    /// the bit-packed batch simulator that samples and validates 64 shots at once
+    #[clap(long, value_enum)]
+    pub simulator: Option<tool::BenchmarkSimulator>,
     /// use deterministic seed for debugging purpose
     #[clap(long)]
     pub deterministic_seed: Option<u64>,
@@ -269,6 +312,19 @@ pub struct BenchmarkParameters {
     pub error_pattern: Option<serde_json::Value>,
 }
 
+#[derive(Parser, Clone, Debug)]
+pub struct FaultDistanceParameters {
+    /// the maximum number of faults to enumerate; note that the number of configurations grows exponentially with it
+    #[clap(long, default_value_t = 2)]
+    pub max_weight: usize,
+    /// keep enumerating larger weights after finding the first logical error
+    #[clap(long, action)]
+    pub all_weights: bool,
+    /// the code, noise model and decoder, which are constructed in the same way as `benchmark`
+    #[clap(flatten)]
+    pub benchmark_parameters: BenchmarkParameters,
+}
+
 #[derive(Parser, Clone, Debug)]
 pub struct ServerParameters {
     /// listening on <addr>:<port>, default to 8066
@@ -15,6 +15,7 @@ use clap::ValueEnum;
 #[cfg(feature = "python_binding")]
 use pyo3::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use ErrorType::*;
 
 /// commonly used code type that has built-in functions to automatically build up the simulator.
@@ -39,6 +40,10 @@ pub enum CodeType {
     RotatedTailoredCodeBellInit,
     /// periodic boundary condition of rotated tailored surface code, code distances must be even number
     PeriodicRotatedTailoredCode,
+    /// triangular 6.6.6 color code with weight-4 and weight-6 plaquettes, code distances must be the same odd number (di = dj)
+    ColorCode666,
+    /// triangular 4.8.8 color code with weight-4, weight-6 and weight-8 plaquettes, code distances must be the same odd number (di = dj)
+    ColorCode488,
     /// unknown code type, user must provide necessary information and build circuit-level implementation
     Customized,
 }
@@ -943,6 +948,92 @@ pub fn build_code(simulator: &mut Simulator) {
         CodeType::Customized => {
             // skip user customized code
         }
+        &CodeType::ColorCode666 | &CodeType::ColorCode488 => {
+            let d = code_size.di;
+            let noisy_measurements = code_size.noisy_measurements;
+            assert_eq!(
+                code_size.di, code_size.dj,
+                "triangular color code has a single code distance, di must equal dj"
+            );
+            assert!(
+                d >= 3 && d % 2 == 1,
+                "code distance must be odd integer no less than 3, current: d = {}",
+                d
+            );
+            let layout = ColorCodeLayout::new(code_type, d);
+            // Z stabilizers are measured first and then X stabilizers, each taking `block_steps` CNOT layers
+            let block_steps = layout.block_steps;
+            simulator.measurement_cycles = 2 * block_steps + 2;
+            let height = simulator.measurement_cycles * (noisy_measurements + 1) + 1;
+            let mut qubit_types: Vec<Vec<Option<QubitType>>> = vec![vec![None; layout.horizontal]; layout.vertical];
+            for &(i, j) in layout.data_qubits.iter() {
+                qubit_types[i][j] = Some(QubitType::Data);
+            }
+            let mut gates = vec![BTreeMap::<(usize, usize), (GateType, (usize, usize))>::new(); 2 * block_steps];
+            for plaquette in layout.plaquettes.iter() {
+                let z_ancilla = plaquette.z_ancilla;
+                let x_ancilla = plaquette.x_ancilla;
+                qubit_types[z_ancilla.0][z_ancilla.1] = Some(QubitType::StabZ);
+                qubit_types[x_ancilla.0][x_ancilla.1] = Some(QubitType::StabX);
+                for (step, data) in plaquette.schedule.iter().enumerate() {
+                    if let Some(data) = *data {
+                        let z_gates = &mut gates[step];
+                        assert!(z_gates.insert(z_ancilla, (GateType::CXGateTarget, data)).is_none());
+                        assert!(z_gates.insert(data, (GateType::CXGateControl, z_ancilla)).is_none());
+                        let x_gates = &mut gates[block_steps + step];
+                        assert!(x_gates.insert(x_ancilla, (GateType::CXGateControl, data)).is_none());
+                        assert!(x_gates.insert(data, (GateType::CXGateTarget, x_ancilla)).is_none());
+                    }
+                }
+            }
+            let mut nodes = Vec::with_capacity(height);
+            for t in 0..height {
+                let mut row_i = Vec::with_capacity(layout.vertical);
+                for (i, qubit_types_i) in qubit_types.iter().enumerate() {
+                    let mut row_j = Vec::with_capacity(layout.horizontal);
+                    for (j, qubit_type) in qubit_types_i.iter().enumerate() {
+                        if let Some(qubit_type) = *qubit_type {
+                            let mut gate_type = GateType::None;
+                            let mut gate_peer = None;
+                            match t % simulator.measurement_cycles {
+                                1 => {
+                                    // initialization
+                                    gate_type = match qubit_type {
+                                        QubitType::StabZ => GateType::InitializeZ,
+                                        QubitType::StabX => GateType::InitializeX,
+                                        _ => GateType::None,
+                                    };
+                                }
+                                0 => {
+                                    // measurement
+                                    gate_type = match qubit_type {
+                                        QubitType::StabZ => GateType::MeasureZ,
+                                        QubitType::StabX => GateType::MeasureX,
+                                        _ => GateType::None,
+                                    };
+                                }
+                                stage => {
+                                    // CNOT layers
+                                    if let Some(&(gate, (pi, pj))) = gates[stage - 2].get(&(i, j)) {
+                                        gate_type = gate;
+                                        gate_peer = Some(pos!(t, pi, pj));
+                                    }
+                                }
+                            }
+                            row_j.push(Some(Box::new(SimulatorNode::new(qubit_type, gate_type, gate_peer))));
+                        } else {
+                            row_j.push(None);
+                        }
+                    }
+                    row_i.push(row_j);
+                }
+                nodes.push(row_i)
+            }
+            simulator.vertical = layout.vertical;
+            simulator.horizontal = layout.horizontal;
+            simulator.height = height;
+            simulator.nodes = nodes;
+        }
         &CodeType::StandardXZZXCode | &CodeType::RotatedXZZXCode => {
             let di = code_size.di;
             let dj = code_size.dj;
@@ -1134,6 +1225,134 @@ pub fn build_code(simulator: &mut Simulator) {
     }
 }
 
+/// a plaquette of the triangular color code, measured by a Z ancilla and an X ancilla next to it
+struct ColorCodePlaquette {
+    z_ancilla: (usize, usize),
+    x_ancilla: (usize, usize),
+    /// the data qubit to interact with at each CNOT layer, `None` if idle
+    schedule: Vec<Option<(usize, usize)>>,
+}
+
+/// 2D layout of the triangular color codes; the logical operator is supported on the bottom side of 6.6.6 code
+/// (the last row) and the left side of 4.8.8 code (the first two columns)
+struct ColorCodeLayout {
+    vertical: usize,
+    horizontal: usize,
+    /// number of CNOT layers to measure all the Z (or X) stabilizers
+    block_steps: usize,
+    data_qubits: Vec<(usize, usize)>,
+    plaquettes: Vec<ColorCodePlaquette>,
+}
+
+impl ColorCodeLayout {
+    fn new(code_type: &CodeType, d: usize) -> Self {
+        match code_type {
+            CodeType::ColorCode666 => {
+                // hexagonal lattice embedded in triangular coordinates (x, y) with x, y >= 0 and x + y <= l;
+                // a site is a plaquette center if (x + 2y + 1) % 3 == 0, otherwise a data qubit
+                let l = 3 * (d - 1) / 2;
+                let to_grid = |x: usize, y: usize| (l - y, 2 * x + y);
+                let is_inside = |x: isize, y: isize| x >= 0 && y >= 0 && x + y <= l as isize;
+                let directions: [(isize, isize); 6] = [(1, 0), (0, 1), (-1, 1), (-1, 0), (0, -1), (1, -1)];
+                let mut data_qubits = vec![];
+                let mut plaquettes = vec![];
+                for y in 0..=l {
+                    for x in 0..=l - y {
+                        if (x + 2 * y + 1) % 3 != 0 {
+                            data_qubits.push(to_grid(x, y));
+                            continue;
+                        }
+                        let (i, j) = to_grid(x, y);
+                        let schedule = directions
+                            .iter()
+                            .map(|&(dx, dy)| {
+                                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                                is_inside(nx, ny).then(|| to_grid(nx as usize, ny as usize))
+                            })
+                            .collect();
+                        plaquettes.push(ColorCodePlaquette {
+                            z_ancilla: (i, j),
+                            x_ancilla: (i, j + 1),
+                            schedule,
+                        });
+                    }
+                }
+                Self {
+                    vertical: l + 1,
+                    horizontal: 2 * l + 2,
+                    block_steps: 6,
+                    data_qubits,
+                    plaquettes,
+                }
+            }
+            CodeType::ColorCode488 => {
+                // square-octagon cells (a, b) with a, b >= 0 and a + b <= m, centered at (3a + 2, 3b + 2);
+                // a cell is a square if a + b is even, otherwise an octagon
+                let m = (d - 2) as isize;
+                let is_inside = |a: isize, b: isize| a >= 0 && b >= 0 && a + b <= m;
+                let to_grid = |x: isize, y: isize| ((x + 2) as usize, (y + 2) as usize);
+                let mut data_set = BTreeSet::new();
+                // each data qubit is a corner of a square, kept if it's shared by at least two cells
+                for a in -1..=m + 1 {
+                    for b in -1..=m + 1 {
+                        if (a + b) % 2 != 0 {
+                            continue;
+                        }
+                        for (sx, sy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                            let cells = [(a, b), (a + sx, b), (a, b + sy)];
+                            if cells.iter().filter(|&&(ca, cb)| is_inside(ca, cb)).count() >= 2 {
+                                data_set.insert(to_grid(3 * a + sx, 3 * b + sy));
+                            }
+                        }
+                    }
+                }
+                // the three corners of the triangle
+                data_set.insert(to_grid(-1, -1));
+                data_set.insert(to_grid(-2, 3 * m - 1));
+                data_set.insert(to_grid(3 * m - 1, -2));
+                let square_steps: [(isize, isize); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
+                let octagon_steps: [(isize, isize); 8] =
+                    [(2, 1), (1, 2), (-1, 2), (-2, 1), (-2, -1), (-1, -2), (1, -2), (2, -1)];
+                let mut plaquettes = vec![];
+                for a in 0..=m {
+                    for b in 0..=m - a {
+                        let (x, y) = (3 * a, 3 * b);
+                        let offsets: &[(isize, isize)] = if (a + b) % 2 == 0 { &square_steps } else { &octagon_steps };
+                        let mut schedule: Vec<_> = offsets
+                            .iter()
+                            .map(|&(dx, dy)| Some(to_grid(x + dx, y + dy)).filter(|data| data_set.contains(data)))
+                            .collect();
+                        schedule.resize(8, None);
+                        let (i, j) = to_grid(x, y);
+                        plaquettes.push(ColorCodePlaquette {
+                            z_ancilla: (i, j),
+                            x_ancilla: (i, j + 1),
+                            schedule,
+                        });
+                    }
+                }
+                let data_qubits: Vec<_> = data_set.into_iter().collect();
+                let positions = || {
+                    let ancillas = plaquettes
+                        .iter()
+                        .flat_map(|plaquette| [plaquette.z_ancilla, plaquette.x_ancilla]);
+                    data_qubits.iter().cloned().chain(ancillas)
+                };
+                let vertical = positions().map(|(i, _)| i).max().unwrap() + 1;
+                let horizontal = positions().map(|(_, j)| j).max().unwrap() + 1;
+                Self {
+                    vertical,
+                    horizontal,
+                    block_steps: 8,
+                    data_qubits,
+                    plaquettes,
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
 /// 2D position of the qubits; time axis is always pointing up
 pub fn visualize_positions(simulator: &Simulator) -> Vec<Vec<VisualizePosition>> {
     (0..simulator.vertical)
@@ -1413,7 +1632,54 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             let logical_n = left_cardinality % 2 != 0; // odd cardinality means there is a logical X error
             Some((logical_p, logical_n))
         }
-        _ => None,
+        &CodeType::ColorCode666 | &CodeType::ColorCode488 => {
+            // both logical X and logical Z are supported on the same side of the triangle
+            let side_positions: Vec<Position> = if code_type == &CodeType::ColorCode666 {
+                (0..simulator.horizontal)
+                    .map(|j| pos!(top_t, simulator.vertical - 1, j))
+                    .collect()
+            } else {
+                (0..simulator.vertical)
+                    .flat_map(|i| (0..2).map(move |j| pos!(top_t, i, j)))
+                    .collect()
+            };
+            let mut z_cardinality = 0;
+            let mut x_cardinality = 0;
+            for position in side_positions.iter() {
+                if !simulator.is_node_exist(position) {
+                    continue;
+                }
+                let node = simulator.get_node_unwrap(position);
+                if node.qubit_type != QubitType::Data {
+                    continue;
+                }
+                if node.propagated == Z || node.propagated == Y {
+                    z_cardinality += 1;
+                }
+                if node.propagated == X || node.propagated == Y {
+                    x_cardinality += 1;
+                }
+            }
+            let logical_i = x_cardinality % 2 != 0; // odd cardinality means there is a logical X error
+            let logical_j = z_cardinality % 2 != 0; // odd cardinality means there is a logical Z error
+            Some((logical_i, logical_j))
+        }
+        &CodeType::Customized => {
+            // customized codes may mark virtual data qubits as observables, e.g. the ones imported from a Stim circuit
+            let mut logicals = [false; 2];
+            let mut has_observable = false;
+            simulator_iter!(simulator, position, node, t => top_t, {
+                if let Some(observable) = node.miscellaneous.as_ref().and_then(|value| value.get("observable")) {
+                    let observable = observable.as_u64().expect("observable index must be an integer") as usize;
+                    assert!(observable < 2, "only observable 0 (logical_i) and 1 (logical_j) are supported");
+                    has_observable = true;
+                    if node.propagated != I {
+                        logicals[observable] = !logicals[observable];
+                    }
+                }
+            });
+            has_observable.then_some((logicals[0], logicals[1]))
+        }
     };
     // recover the errors
     for (position, error) in correction.iter() {
@@ -1479,6 +1745,7 @@ pub(crate) fn register(py: Python<'_>, m: &PyModule) -> PyResult<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[macro_export]
     macro_rules! assert_measurement {
@@ -1826,4 +2093,145 @@ mod tests {
         let mut visualizer = Visualizer::new(Some(visualize_data_folder() + visualize_filename.as_str())).unwrap();
         visualizer.add_component(&simulator).unwrap();
     }
+
+    #[test]
+    fn code_builder_visualize_color_code_666() {
+        // cargo test code_builder_visualize_color_code_666 -- --nocapture
+        let visualize_filename = "code_builder_visualize_color_code_666.json".to_string();
+        print_visualize_link(visualize_filename.clone());
+        let d = 5;
+        let noisy_measurements = 0;
+        let simulator = Simulator::new(CodeType::ColorCode666, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        let mut visualizer = Visualizer::new(Some(visualize_data_folder() + visualize_filename.as_str())).unwrap();
+        visualizer.add_component(&simulator).unwrap();
+    }
+
+    #[test]
+    fn code_builder_visualize_color_code_488() {
+        // cargo test code_builder_visualize_color_code_488 -- --nocapture
+        let visualize_filename = "code_builder_visualize_color_code_488.json".to_string();
+        print_visualize_link(visualize_filename.clone());
+        let d = 5;
+        let noisy_measurements = 0;
+        let simulator = Simulator::new(CodeType::ColorCode488, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        let mut visualizer = Visualizer::new(Some(visualize_data_folder() + visualize_filename.as_str())).unwrap();
+        visualizer.add_component(&simulator).unwrap();
+    }
+
+    #[test]
+    fn code_builder_color_code_size() {
+        // cargo test code_builder_color_code_size -- --nocapture
+        for (code_type, data_qubit_counts) in [
+            (CodeType::ColorCode666, [7, 19, 37, 61]),
+            (CodeType::ColorCode488, [7, 21, 43, 73]),
+        ] {
+            for (index, d) in [3, 5, 7, 9].into_iter().enumerate() {
+                let simulator = Simulator::new(code_type, CodeSize::new(2, d, d));
+                code_builder_sanity_check(&simulator).unwrap();
+                let mut data_qubit_count = 0;
+                let mut stab_z_count = 0;
+                let mut stab_x_count = 0;
+                for i in 0..simulator.vertical {
+                    for j in 0..simulator.horizontal {
+                        let position = pos!(0, i, j);
+                        if !simulator.is_node_exist(&position) {
+                            continue;
+                        }
+                        match simulator.get_node_unwrap(&position).qubit_type {
+                            QubitType::Data => data_qubit_count += 1,
+                            QubitType::StabZ => stab_z_count += 1,
+                            QubitType::StabX => stab_x_count += 1,
+                            _ => unreachable!(),
+                        }
+                    }
+                }
+                assert_eq!(data_qubit_count, data_qubit_counts[index]);
+                assert_eq!(stab_z_count, stab_x_count);
+                // a single logical qubit is encoded
+                assert_eq!(data_qubit_count, 2 * stab_z_count + 1);
+            }
+        }
+    }
+
+    /// the syndrome of a single X or Z error on each data qubit, packed with its logical effect in the top two bits
+    fn color_code_error_signatures(simulator: &mut Simulator, error: ErrorType) -> Vec<u64> {
+        let mut data_positions = vec![];
+        for i in 0..simulator.vertical {
+            for j in 0..simulator.horizontal {
+                let position = pos!(1, i, j);
+                if simulator.is_node_exist(&position) && simulator.get_node_unwrap(&position).qubit_type == QubitType::Data {
+                    data_positions.push(position);
+                }
+            }
+        }
+        let mut defect_indices = HashMap::<Position, usize>::new();
+        let mut signatures = vec![];
+        for position in data_positions.iter() {
+            simulator.clear_all_errors();
+            simulator.get_node_mut_unwrap(position).error = error;
+            simulator.propagate_errors();
+            let mut signature = 0u64;
+            for defect in simulator.generate_sparse_measurement().to_vec() {
+                let defect_count = defect_indices.len();
+                let index = *defect_indices.entry(defect).or_insert(defect_count);
+                assert!(index < 62, "too many stabilizers to pack the syndrome");
+                signature ^= 1 << index;
+            }
+            let (logical_i, logical_j) = code_builder_validate_correction(simulator, &SparseCorrection::new()).unwrap();
+            signature |= (logical_i as u64) << 62 | (logical_j as u64) << 63;
+            signatures.push(signature);
+        }
+        signatures
+    }
+
+    /// visit the XOR of every combination of `remaining` more signatures after `start`; stops when `visitor` returns true
+    fn visit_signature_combinations(
+        signatures: &[u64],
+        start: usize,
+        remaining: usize,
+        accumulated: u64,
+        visitor: &mut impl FnMut(u64) -> bool,
+    ) -> bool {
+        if remaining == 0 {
+            return visitor(accumulated);
+        }
+        for index in start..=signatures.len() - remaining {
+            if visit_signature_combinations(signatures, index + 1, remaining - 1, accumulated ^ signatures[index], visitor) {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn code_builder_color_code_distance() {
+        // cargo test code_builder_color_code_distance -- --nocapture
+        let syndrome_mask = (1u64 << 62) - 1;
+        for d in [3, 5, 7] {
+            for code_type in [CodeType::ColorCode666, CodeType::ColorCode488] {
+                let mut simulator = Simulator::new(code_type, CodeSize::new(0, d, d));
+                // an undetectable X (Z) error can only flip the logical i (j), find its minimum weight
+                for (error, logical_bit, other_logical_bit) in [(X, 1u64 << 62, 1u64 << 63), (Z, 1u64 << 63, 1u64 << 62)] {
+                    let signatures = color_code_error_signatures(&mut simulator, error);
+                    let mut minimum_logical_weight = None;
+                    for weight in 1..=d {
+                        let found = visit_signature_combinations(&signatures, 0, weight, 0, &mut |signature| {
+                            if signature & syndrome_mask != 0 {
+                                return false;
+                            }
+                            assert_eq!(signature & other_logical_bit, 0, "{:?} {:?} d={}", code_type, error, d);
+                            signature & logical_bit != 0
+                        });
+                        if found {
+                            minimum_logical_weight = Some(weight);
+                            break;
+                        }
+                    }
+                    assert_eq!(minimum_logical_weight, Some(d), "{:?} {:?} d={}", code_type, error, d);
+                }
+            }
+        }
+    }
 }
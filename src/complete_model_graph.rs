@@ -1,12 +1,14 @@
 //! build complete model graph from model graph
 //!
 
+use super::erasure_graph::{ErasureEdge, ErasureGraphModifier};
 use super::float_ord::FloatOrd;
 use super::model_graph::*;
 use super::priority_queue::PriorityQueue;
 use super::simulator::*;
 use serde::Serialize;
-use std::collections::BTreeMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone, Serialize)]
@@ -78,6 +80,40 @@ impl PrecomputedData {
     }
 }
 
+/// the shortest paths from the defects after the erased edges are set to 0 weight, found without changing the model graph:
+/// such a path is a chain of the precomputed shortest paths and the erased edges, so it's searched on a small complete graph
+/// of the defects and the endpoints of the erased edges, see [`CompleteModelGraph::erasure_shortest_paths`]
+#[derive(Debug, Clone)]
+pub struct ErasureShortestPaths {
+    /// the defects at the beginning, followed by the other endpoints of the erased edges
+    pub positions: Vec<Position>,
+    /// `distances[source][index]` is the weight of the shortest path from the `source`-th defect to `positions[index]`,
+    /// where `index == positions.len()` stands for the boundary
+    pub distances: Vec<Vec<f64>>,
+    /// `previous[source][index]` is the previous position on the shortest path and whether the step is an erased edge
+    previous: Vec<Vec<Option<(usize, bool)>>>,
+}
+
+impl ErasureShortestPaths {
+    /// the index of the boundary
+    pub fn boundary(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// the steps `(index, next, is_erased)` along the shortest path from the `source`-th defect to `target`
+    pub fn path(&self, source: usize, target: usize) -> Vec<(usize, usize, bool)> {
+        let mut path = vec![];
+        let mut index = target;
+        while index != source {
+            let (previous, is_erased) = self.previous[source][index].expect("must exist a path");
+            path.push((previous, index, is_erased));
+            index = previous;
+        }
+        path.reverse();
+        path
+    }
+}
+
 impl CompleteModelGraph {
     pub fn new(simulator: &Simulator, model_graph: Arc<ModelGraph>) -> Self {
         assert!(simulator.volume() > 0, "cannot build graph out of zero-sized simulator");
@@ -214,6 +250,17 @@ impl CompleteModelGraph {
     pub fn build_correction_matching(&mut self, source: &Position, target: &Position) -> SparseCorrection {
         let model_graph = Arc::clone(&self.model_graph);
         let mut correction = SparseCorrection::new();
+        for (position, next) in self.matching_path(source, target).iter() {
+            let model_graph_node = model_graph.get_node_unwrap(position);
+            let next_edge = model_graph_node.edges.get(next);
+            correction.extend(&next_edge.as_ref().unwrap().correction);
+        }
+        correction
+    }
+
+    /// the model graph edges `(position, next)` along the shortest path from `source` to `target`
+    pub fn matching_path(&mut self, source: &Position, target: &Position) -> Vec<(Position, Position)> {
+        let mut path = Vec::new();
         let mut source = source.clone();
         if self.precompute_complete_model_graph {
             while &source != target {
@@ -224,14 +271,11 @@ impl CompleteModelGraph {
                     println!("target_edge none: source: {source:?}, target: {target:?}");
                 }
                 let edge = target_edge.as_ref().unwrap();
-                let next = &edge.next;
-                let model_graph_node = model_graph.get_node_unwrap(&source);
-                let next_edge = model_graph_node.edges.get(next);
-                let next_correction = &next_edge.as_ref().unwrap().correction;
-                correction.extend(next_correction);
-                source = next.clone();
+                let next = edge.next.clone();
+                path.push((source, next.clone()));
+                source = next;
             }
-            correction
+            path
         } else {
             self.precompute_dijkstra_with_end_position(target, &source);
             // logic is different from what's happening if `precompute_complete_model_graph` is set
@@ -242,16 +286,13 @@ impl CompleteModelGraph {
                     "after running `precompute_dijkstra`, this node must be visited"
                 );
                 let next: Position = (**(node.previous.as_ref().expect("must exist a path"))).clone();
-                let model_graph_node = model_graph.get_node_unwrap(&source);
-                let next_edge = model_graph_node.edges.get(&next);
-                let next_correction = &next_edge.as_ref().unwrap().correction;
-                correction.extend(next_correction);
+                path.push((source, next.clone()));
                 source = next;
             }
             Arc::get_mut(self.get_node_mut_unwrap(target).precomputed.as_mut().unwrap())
                 .unwrap()
                 .clear_edges(); // free memory immediately
-            correction
+            path
         }
     }
 
@@ -259,28 +300,214 @@ impl CompleteModelGraph {
     pub fn build_correction_boundary(&mut self, position: &Position) -> SparseCorrection {
         let model_graph = Arc::clone(&self.model_graph);
         let mut correction = SparseCorrection::new();
+        for (position, next) in self.boundary_path(position).iter() {
+            let model_graph_node = model_graph.get_node_unwrap(position);
+            if next == position {
+                // this is the boundary
+                correction.extend(&model_graph_node.boundary.as_ref().unwrap().correction);
+            } else {
+                let next_edge = model_graph_node.edges.get(next);
+                correction.extend(&next_edge.as_ref().unwrap().correction);
+            }
+        }
+        correction
+    }
+
+    /// the model graph edges `(position, next)` along the shortest path to the boundary, ending with `(position, position)`
+    /// which stands for the boundary edge of `position`
+    pub fn boundary_path(&self, position: &Position) -> Vec<(Position, Position)> {
+        let mut path = Vec::new();
         let mut position = position.clone();
         loop {
             let node = self.get_node_unwrap(&position);
             let precomputed = node.precomputed.as_ref().unwrap();
             let boundary = precomputed.boundary.as_ref().unwrap();
-            let next = &boundary.next;
-            let model_graph_node = model_graph.get_node_unwrap(&position);
-            if next == &position {
-                // this is the boundary
-                let boundary_correction = &model_graph_node.boundary.as_ref().unwrap().correction;
-                correction.extend(boundary_correction);
+            let next = boundary.next.clone();
+            path.push((position.clone(), next.clone()));
+            if next == position {
                 break;
+            }
+            position = next;
+        }
+        path
+    }
+
+    /// record the `erasure_edges` with their original weights without changing the model graph, which overlays them as
+    /// 0-weighted edges on the precomputed shortest paths in [`Self::erasure_shortest_paths`]
+    pub fn erasure_overlay(&self, erasure_edges: Vec<ErasureEdge>) -> ErasureGraphModifier<f64> {
+        let mut erasure_graph_modifier = ErasureGraphModifier::new();
+        for erasure_edge in erasure_edges.into_iter() {
+            let original_weight = match &erasure_edge {
+                ErasureEdge::Connection(position1, position2) => {
+                    let node1 = self.model_graph.get_node_unwrap(position1);
+                    node1.edges.get(position2).expect("neighbor must exist").weight
+                }
+                ErasureEdge::Boundary(position) => {
+                    let node = self.model_graph.get_node_unwrap(position);
+                    node.boundary.as_ref().expect("boundary must exist").weight
+                }
+            };
+            erasure_graph_modifier.push_modified_edge(erasure_edge, original_weight);
+        }
+        erasure_graph_modifier
+    }
+
+    /// find the shortest paths from each defect when the edges recorded in `erasure_graph_modifier` have 0 weight, reusing
+    /// the precomputed complete model graph so that neither the model graph nor the precomputed data need to be changed.
+    /// Two consecutive precomputed shortest paths are never shorter than the precomputed shortest path between their ends,
+    /// so Dijkstra's algorithm only expands the source and the endpoints of the erased edges
+    pub fn erasure_shortest_paths(
+        &self,
+        defects: &[Position],
+        erasure_graph_modifier: &ErasureGraphModifier<f64>,
+    ) -> ErasureShortestPaths {
+        assert!(
+            self.precompute_complete_model_graph,
+            "the complete model graph must be precomputed"
+        );
+        let mut positions = defects.to_vec();
+        let mut indices: HashMap<Position, usize> = HashMap::new();
+        for (index, position) in positions.iter().enumerate() {
+            indices.insert(position.clone(), index);
+        }
+        let mut index_of = |position: &Position| -> usize {
+            *indices.entry(position.clone()).or_insert_with(|| {
+                positions.push(position.clone());
+                positions.len() - 1
+            })
+        };
+        // the erased subgraph
+        let mut erased_neighbors: Vec<Vec<usize>> = vec![];
+        let mut erased_boundaries: Vec<usize> = vec![];
+        for (erasure_edge, _) in erasure_graph_modifier.modified.iter() {
+            match erasure_edge {
+                ErasureEdge::Connection(position1, position2) => {
+                    let (index1, index2) = (index_of(position1), index_of(position2));
+                    erased_neighbors.resize(erased_neighbors.len().max(index1.max(index2) + 1), vec![]);
+                    erased_neighbors[index1].push(index2);
+                    erased_neighbors[index2].push(index1);
+                }
+                ErasureEdge::Boundary(position) => erased_boundaries.push(index_of(position)),
+            }
+        }
+        let boundary = positions.len();
+        erased_neighbors.resize(boundary, vec![]);
+        let mut is_erased_boundary = vec![false; boundary];
+        for &index in erased_boundaries.iter() {
+            is_erased_boundary[index] = true;
+        }
+        let is_endpoint = |index: usize| !erased_neighbors[index].is_empty() || is_erased_boundary[index];
+        // the precomputed weights from the positions that may be expanded
+        let precomputed_weights: Vec<Vec<(usize, f64)>> = positions
+            .iter()
+            .enumerate()
+            .map(|(index, position)| {
+                if index >= defects.len() && !is_endpoint(index) {
+                    return vec![];
+                }
+                let precomputed = self.get_node_unwrap(position).precomputed.as_ref().unwrap();
+                let mut weights: Vec<(usize, f64)> = positions
+                    .iter()
+                    .enumerate()
+                    .filter(|&(peer_index, _)| peer_index != index)
+                    .filter_map(|(peer_index, peer)| precomputed.edges.get(peer).map(|edge| (peer_index, edge.weight)))
+                    .collect();
+                if let Some(edge) = precomputed.boundary.as_ref() {
+                    weights.push((boundary, edge.weight));
+                }
+                weights
+            })
+            .collect();
+        // run heap-based Dijkstra's algorithm from each defect
+        let mut distances = Vec::with_capacity(defects.len());
+        let mut previous = Vec::with_capacity(defects.len());
+        for source in 0..defects.len() {
+            let mut source_distances = vec![f64::INFINITY; boundary + 1];
+            let mut source_previous = vec![None; boundary + 1];
+            let mut visited = vec![false; boundary + 1];
+            let mut pq = PriorityQueue::<usize, Reverse<FloatOrd<f64>>>::new();
+            source_distances[source] = 0.;
+            pq.push(source, Reverse(FloatOrd(0.)));
+            while let Some((index, Reverse(FloatOrd(distance)))) = pq.pop() {
+                visited[index] = true;
+                if index == boundary || (index != source && !is_endpoint(index)) {
+                    continue;
+                }
+                let mut relax = |peer_index: usize, weight: f64, is_erased: bool| {
+                    let peer_distance = distance + weight;
+                    if !visited[peer_index] && peer_distance < source_distances[peer_index] {
+                        source_distances[peer_index] = peer_distance;
+                        source_previous[peer_index] = Some((index, is_erased));
+                        pq.push(peer_index, Reverse(FloatOrd(peer_distance)));
+                    }
+                };
+                for &peer_index in erased_neighbors[index].iter() {
+                    relax(peer_index, 0., true);
+                }
+                if is_erased_boundary[index] {
+                    relax(boundary, 0., true);
+                }
+                for &(peer_index, weight) in precomputed_weights[index].iter() {
+                    relax(peer_index, weight, false);
+                }
+            }
+            distances.push(source_distances);
+            previous.push(source_previous);
+        }
+        ErasureShortestPaths {
+            positions,
+            distances,
+            previous,
+        }
+    }
+
+    /// build correction along the shortest path from the `source`-th defect to `target` in the erasure shortest paths
+    pub fn build_correction_erasure_path(
+        &self,
+        erasure_shortest_paths: &ErasureShortestPaths,
+        source: usize,
+        target: usize,
+    ) -> SparseCorrection {
+        let mut correction = SparseCorrection::new();
+        let positions = &erasure_shortest_paths.positions;
+        for (index, next, is_erased) in erasure_shortest_paths.path(source, target).into_iter() {
+            let position = &positions[index];
+            if next == erasure_shortest_paths.boundary() {
+                if is_erased {
+                    let model_graph_node = self.model_graph.get_node_unwrap(position);
+                    correction.extend(&model_graph_node.boundary.as_ref().unwrap().correction);
+                } else {
+                    for (position, next) in self.boundary_path(position).iter() {
+                        correction.extend(&self.edge_correction(position, next));
+                    }
+                }
+            } else if is_erased {
+                correction.extend(&self.edge_correction(position, &positions[next]));
             } else {
-                let next_edge = model_graph_node.edges.get(next);
-                let next_correction = &next_edge.as_ref().unwrap().correction;
-                correction.extend(next_correction);
-                position = next.clone();
+                // follow the precomputed shortest path
+                let mut position = position.clone();
+                let target = &positions[next];
+                while &position != target {
+                    let precomputed = self.get_node_unwrap(&position).precomputed.as_ref().unwrap();
+                    let next = precomputed.edges.get(target).unwrap().next.clone();
+                    correction.extend(&self.edge_correction(&position, &next));
+                    position = next;
+                }
             }
         }
         correction
     }
 
+    /// the correction of a model graph edge, where `position == next` stands for the boundary edge
+    fn edge_correction(&self, position: &Position, next: &Position) -> Arc<SparseCorrection> {
+        let model_graph_node = self.model_graph.get_node_unwrap(position);
+        if position == next {
+            Arc::clone(&model_graph_node.boundary.as_ref().unwrap().correction)
+        } else {
+            Arc::clone(&model_graph_node.edges.get(next).unwrap().correction)
+        }
+    }
+
     /// run full Dijkstra's algorithm and identify the active region
     pub fn precompute_dijkstra(&mut self, position: &Position) {
         self.precompute_dijkstra_with_end_position(position, &pos!(usize::MAX, usize::MAX, usize::MAX))
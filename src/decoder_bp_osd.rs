@@ -0,0 +1,605 @@
+//! belief propagation + ordered statistics decoder on hypergraph
+//!
+//! Min-sum belief propagation runs on the parity check matrix whose rows are the vertices and whose columns are the hyperedges
+//! of the [`ModelHypergraph`]. If the hard decision of BP doesn't satisfy the syndrome within `max_iterations`, the
+//! hyperedges are sorted by their posterior probabilities and ordered statistics decoding (OSD) finds a solution that
+//! satisfies the syndrome, exhaustively searching over the `osd_order` most likely hyperedges outside the information set.
+//!
+//! The part of the Gaussian elimination that doesn't depend on the posteriors is done once when constructing the decoder:
+//! the linearly dependent vertices are dropped from the OSD matrix, and their dependencies become parity checks that
+//! tell whether a syndrome can be generated by the hypergraph at all. An impossible syndrome is reported by
+//! `"syndrome_out_of_span": true` in the runtime statistics together with an empty correction.
+//!
+
+use super::decoder_mwpm::*;
+use super::model_graph::*;
+use super::model_hypergraph::*;
+use super::noise_model::*;
+use super::simulator::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// the probability of a hyperedge is clamped within `[MIN_PROBABILITY, 1 - MIN_PROBABILITY]` to keep the messages finite
+const MIN_PROBABILITY: f64 = 1e-12;
+
+/// OSD searches `2^osd_order` candidate solutions, each costing a pass over the information set
+const MAX_OSD_ORDER: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct BPOSDDecoder {
+    /// model hypergraph
+    pub model_hypergraph: Arc<ModelHypergraph>,
+    /// save configuration for later usage
+    pub config: BPOSDDecoderConfig,
+    /// the prior probability of each hyperedge
+    pub priors: Arc<Vec<f64>>,
+    /// the message slots of hyperedge `e` are `edge_offsets[e]..edge_offsets[e + 1]`, one for each vertex of the hyperedge
+    pub edge_offsets: Arc<Vec<usize>>,
+    /// the vertex of each message slot
+    pub slot_vertices: Arc<Vec<usize>>,
+    /// the hyperedge of each message slot
+    pub slot_edges: Arc<Vec<usize>>,
+    /// the message slots of each vertex
+    pub vertex_slots: Arc<Vec<Vec<usize>>>,
+    /// the hyperedges caused by a single Pauli error at some position, which become random when the position is erased
+    pub erasure_edges: Arc<HashMap<Position, Vec<usize>>>,
+    /// a maximal set of vertices whose rows in the parity check matrix are linearly independent, the only rows used by OSD
+    pub independent_vertices: Arc<Vec<usize>>,
+    /// each dependent row is the sum of some independent rows, so the syndrome must have even parity on these vertices
+    pub syndrome_parity_checks: Arc<Vec<Vec<usize>>>,
+    /// variable-to-check messages
+    variable_to_check: Vec<f64>,
+    /// check-to-variable messages
+    check_to_variable: Vec<f64>,
+    /// posterior log-likelihood ratio of each hyperedge, positive means no error
+    posteriors: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BPOSDDecoderConfig {
+    /// combined probability can improve accuracy, but will cause probabilities differ a lot even in the case of i.i.d. noise model
+    #[serde(alias = "ucp")] // abbreviation
+    #[serde(default = "mwpm_default_configs::use_combined_probability")]
+    pub use_combined_probability: bool,
+    /// the maximum number of belief propagation iterations before falling back to OSD
+    #[serde(alias = "mi")] // abbreviation
+    #[serde(default = "bp_osd_default_configs::max_iterations")]
+    pub max_iterations: usize,
+    /// the fraction of the previous check-to-variable message kept in each iteration, 0 means no damping
+    #[serde(alias = "dp")] // abbreviation
+    #[serde(default = "bp_osd_default_configs::damping")]
+    pub damping: f64,
+    /// the normalization factor of min-sum messages, which compensates the overestimation of the min-sum approximation
+    #[serde(alias = "msf")] // abbreviation
+    #[serde(default = "bp_osd_default_configs::min_sum_scaling_factor")]
+    pub min_sum_scaling_factor: f64,
+    /// the number of hyperedges outside the information set that are exhaustively searched, 0 means OSD-0;
+    /// at most [`MAX_OSD_ORDER`] because the search is exponential
+    #[serde(alias = "oo")] // abbreviation
+    #[serde(default = "bp_osd_default_configs::osd_order")]
+    pub osd_order: usize,
+}
+
+pub mod bp_osd_default_configs {
+    pub fn max_iterations() -> usize {
+        30
+    }
+    pub fn damping() -> f64 {
+        0.
+    }
+    pub fn min_sum_scaling_factor() -> f64 {
+        0.5 // degenerate circuit-level hyperedges make min-sum messages overconfident
+    }
+    pub fn osd_order() -> usize {
+        7
+    }
+}
+
+/// the log-likelihood ratio of a probability, positive means no error
+fn log_likelihood_ratio(probability: f64) -> f64 {
+    let probability = probability.clamp(MIN_PROBABILITY, 1. - MIN_PROBABILITY);
+    ((1. - probability) / probability).ln()
+}
+
+impl BPOSDDecoder {
+    /// create a new BP+OSD decoder with decoder configuration
+    pub fn new(
+        simulator: &Simulator,
+        noise_model: Arc<NoiseModel>,
+        decoder_configuration: &serde_json::Value,
+        parallel: usize,
+        use_brief_edge: bool,
+    ) -> Result<Self, String> {
+        // read attribute of decoder configuration
+        let config: BPOSDDecoderConfig =
+            serde_json::from_value(decoder_configuration.clone()).map_err(|error| error.to_string())?;
+        if !(0. ..1.).contains(&config.damping) {
+            return Err(format!("damping must be within [0, 1), found {}", config.damping));
+        }
+        if config.osd_order > MAX_OSD_ORDER {
+            return Err(format!(
+                "osd_order must be at most {MAX_OSD_ORDER}, found {}",
+                config.osd_order
+            ));
+        }
+        // build model hypergraph
+        let mut simulator = simulator.clone();
+        let mut model_hypergraph = ModelHypergraph::new(&simulator);
+        model_hypergraph.build(
+            &mut simulator,
+            Arc::clone(&noise_model),
+            &WeightFunction::AutotuneImproved,
+            parallel,
+            config.use_combined_probability,
+            use_brief_edge,
+        );
+        // build the sparse parity check matrix
+        let mut priors = Vec::with_capacity(model_hypergraph.weighted_edges.len());
+        let mut edge_offsets = vec![0];
+        let mut slot_vertices = vec![];
+        let mut slot_edges = vec![];
+        let mut vertex_slots = vec![vec![]; model_hypergraph.vertex_positions.len()];
+        let mut erasure_edges = HashMap::<Position, Vec<usize>>::new();
+        for (edge_index, (defect_vertices, hyperedge_group)) in model_hypergraph.weighted_edges.iter().enumerate() {
+            priors.push(hyperedge_group.hyperedge.probability);
+            for position in defect_vertices.0.iter() {
+                let vertex_index = model_hypergraph.vertex_indices[position];
+                vertex_slots[vertex_index].push(slot_vertices.len());
+                slot_vertices.push(vertex_index);
+                slot_edges.push(edge_index);
+            }
+            edge_offsets.push(slot_vertices.len());
+            for hyperedge in hyperedge_group.all_hyperedges.iter() {
+                if hyperedge.error_pattern.len() == 1 {
+                    let (position, _) = hyperedge.error_pattern.iter().next().unwrap();
+                    let edges = erasure_edges.entry(position.clone()).or_default();
+                    if !edges.contains(&edge_index) {
+                        edges.push(edge_index);
+                    }
+                }
+            }
+        }
+        // eliminate the rows one by one, each row is reduced by the previous independent rows
+        let edge_count = priors.len();
+        let vertex_count = vertex_slots.len();
+        let mut independent_vertices = vec![];
+        let mut syndrome_parity_checks = vec![];
+        let mut basis: Vec<(usize, Vec<u64>, Vec<u64>)> = vec![]; // (pivot column, row, combination of vertices)
+        for (vertex_index, slots) in vertex_slots.iter().enumerate() {
+            let mut row = vec![0u64; edge_count.div_ceil(64)];
+            for &slot in slots.iter() {
+                row[slot_edges[slot] / 64] ^= 1 << (slot_edges[slot] % 64);
+            }
+            let mut combination = vec![0u64; vertex_count.div_ceil(64)];
+            combination[vertex_index / 64] |= 1 << (vertex_index % 64);
+            for (pivot, basis_row, basis_combination) in basis.iter() {
+                if get_bit(&row, *pivot) {
+                    xor_assign(&mut row, basis_row);
+                    xor_assign(&mut combination, basis_combination);
+                }
+            }
+            match (0..edge_count).find(|&column| get_bit(&row, column)) {
+                Some(pivot) => {
+                    basis.push((pivot, row, combination));
+                    independent_vertices.push(vertex_index);
+                }
+                None => {
+                    let vertices = (0..vertex_count).filter(|&vertex| get_bit(&combination, vertex)).collect();
+                    syndrome_parity_checks.push(vertices);
+                }
+            }
+        }
+        let slot_count = slot_vertices.len();
+        Ok(Self {
+            posteriors: vec![0.; priors.len()],
+            model_hypergraph: Arc::new(model_hypergraph),
+            config,
+            priors: Arc::new(priors),
+            edge_offsets: Arc::new(edge_offsets),
+            slot_vertices: Arc::new(slot_vertices),
+            slot_edges: Arc::new(slot_edges),
+            vertex_slots: Arc::new(vertex_slots),
+            erasure_edges: Arc::new(erasure_edges),
+            independent_vertices: Arc::new(independent_vertices),
+            syndrome_parity_checks: Arc::new(syndrome_parity_checks),
+            variable_to_check: vec![0.; slot_count],
+            check_to_variable: vec![0.; slot_count],
+        })
+    }
+
+    /// decode given measurement results
+    #[allow(dead_code)]
+    pub fn decode(&mut self, sparse_measurement: &SparseMeasurement) -> (SparseCorrection, serde_json::Value) {
+        self.decode_with_erasure(sparse_measurement, &SparseErasures::new())
+    }
+
+    /// decode given measurement results and detected erasures
+    pub fn decode_with_erasure(
+        &mut self,
+        sparse_measurement: &SparseMeasurement,
+        sparse_detected_erasures: &SparseErasures,
+    ) -> (SparseCorrection, serde_json::Value) {
+        if sparse_measurement.is_empty() {
+            return (SparseCorrection::new(), json!({}));
+        }
+        let begin = Instant::now();
+        let mut syndrome = vec![false; self.vertex_slots.len()];
+        for position in sparse_measurement.iter() {
+            let vertex_index = *self
+                .model_hypergraph
+                .vertex_indices
+                .get(position)
+                .expect("measurement cannot happen at impossible position");
+            syndrome[vertex_index] = true;
+        }
+        let syndrome_in_span = self
+            .syndrome_parity_checks
+            .iter()
+            .all(|vertices| vertices.iter().filter(|&&vertex_index| syndrome[vertex_index]).count() % 2 == 0);
+        if !syndrome_in_span {
+            return (SparseCorrection::new(), json!({ "syndrome_out_of_span": true }));
+        }
+        // the erased positions suffer from random Pauli errors
+        let mut log_likelihood_ratios: Vec<f64> = self.priors.iter().map(|&p| log_likelihood_ratio(p)).collect();
+        for position in sparse_detected_erasures.iter() {
+            if let Some(edges) = self.erasure_edges.get(position) {
+                for &edge_index in edges.iter() {
+                    log_likelihood_ratios[edge_index] = 0.;
+                }
+            }
+        }
+        let (bp_iterations, bp_solution) = self.belief_propagation(&syndrome, &log_likelihood_ratios);
+        let time_bp = begin.elapsed().as_secs_f64();
+        let begin = Instant::now();
+        let bp_converged = bp_solution.is_some();
+        let solution = match bp_solution {
+            Some(solution) => solution,
+            None => self.ordered_statistics_decoding(&syndrome, &log_likelihood_ratios),
+        };
+        let time_osd = begin.elapsed().as_secs_f64();
+        // build correction
+        let begin = Instant::now();
+        let mut correction = SparseCorrection::new();
+        for edge_index in solution.into_iter() {
+            correction.extend(&self.model_hypergraph.weighted_edges[edge_index].1.hyperedge.correction);
+        }
+        let time_build_correction = begin.elapsed().as_secs_f64();
+        (
+            correction,
+            json!({
+                "bp_iterations": bp_iterations,
+                "bp_converged": bp_converged,
+                "time_bp": time_bp,
+                "time_osd": time_osd,
+                "time_build_correction": time_build_correction,
+            }),
+        )
+    }
+
+    /// run min-sum belief propagation, return the number of iterations and the hyperedges of the hard decision
+    /// if it satisfies the syndrome; the posteriors are always updated for OSD
+    fn belief_propagation(&mut self, syndrome: &[bool], log_likelihood_ratios: &[f64]) -> (usize, Option<Vec<usize>>) {
+        let edge_count = log_likelihood_ratios.len();
+        for (edge_index, &log_likelihood_ratio) in log_likelihood_ratios.iter().enumerate() {
+            for slot in self.edge_offsets[edge_index]..self.edge_offsets[edge_index + 1] {
+                self.variable_to_check[slot] = log_likelihood_ratio;
+            }
+        }
+        self.check_to_variable.fill(0.);
+        self.posteriors.copy_from_slice(log_likelihood_ratios);
+        let mut hard_decision = vec![false; edge_count];
+        for iteration in 1..=self.config.max_iterations {
+            // check update
+            for (vertex_index, slots) in self.vertex_slots.iter().enumerate() {
+                let mut sign_product = if syndrome[vertex_index] { -1. } else { 1. };
+                let mut minimum = f64::INFINITY;
+                let mut second_minimum = f64::INFINITY;
+                let mut minimum_slot = usize::MAX;
+                for &slot in slots.iter() {
+                    let message = self.variable_to_check[slot];
+                    if message < 0. {
+                        sign_product = -sign_product;
+                    }
+                    let magnitude = message.abs();
+                    if magnitude < minimum {
+                        second_minimum = minimum;
+                        minimum = magnitude;
+                        minimum_slot = slot;
+                    } else if magnitude < second_minimum {
+                        second_minimum = magnitude;
+                    }
+                }
+                for &slot in slots.iter() {
+                    let message = self.variable_to_check[slot];
+                    let sign = if message < 0. { -sign_product } else { sign_product };
+                    let magnitude = if slot == minimum_slot { second_minimum } else { minimum };
+                    let updated = sign * magnitude * self.config.min_sum_scaling_factor;
+                    self.check_to_variable[slot] = if iteration == 1 {
+                        updated
+                    } else {
+                        self.config.damping * self.check_to_variable[slot] + (1. - self.config.damping) * updated
+                    };
+                }
+            }
+            // variable update
+            for edge_index in 0..edge_count {
+                let slots = self.edge_offsets[edge_index]..self.edge_offsets[edge_index + 1];
+                let posterior =
+                    log_likelihood_ratios[edge_index] + slots.clone().map(|slot| self.check_to_variable[slot]).sum::<f64>();
+                for slot in slots {
+                    self.variable_to_check[slot] = posterior - self.check_to_variable[slot];
+                }
+                self.posteriors[edge_index] = posterior;
+                hard_decision[edge_index] = posterior < 0.;
+            }
+            // check whether the hard decision satisfies the syndrome
+            let satisfied = self.vertex_slots.iter().enumerate().all(|(vertex_index, slots)| {
+                let parity = slots.iter().filter(|&&slot| hard_decision[self.slot_edges[slot]]).count() % 2 == 1;
+                parity == syndrome[vertex_index]
+            });
+            if satisfied {
+                let solution = (0..edge_count).filter(|&edge_index| hard_decision[edge_index]).collect();
+                return (iteration, Some(solution));
+            }
+        }
+        (self.config.max_iterations, None)
+    }
+
+    /// find the most likely solution among the ones that only differ in the `osd_order` most likely hyperedges outside
+    /// the information set, where the hyperedges are sorted by their posterior probabilities; the syndrome must be in the
+    /// span of the hypergraph
+    fn ordered_statistics_decoding(&self, syndrome: &[bool], log_likelihood_ratios: &[f64]) -> Vec<usize> {
+        let edge_count = log_likelihood_ratios.len();
+        let mut order: Vec<usize> = (0..edge_count).collect();
+        order.sort_by(|&a, &b| self.posteriors[a].total_cmp(&self.posteriors[b]));
+        let mut columns = vec![0; edge_count];
+        for (column, &edge_index) in order.iter().enumerate() {
+            columns[edge_index] = column;
+        }
+        // dense matrix of the independent rows with columns in the sorted order, plus the syndrome as the last column
+        let vertex_count = self.independent_vertices.len();
+        let words = (edge_count + 1).div_ceil(64);
+        let mut rows = vec![vec![0u64; words]; vertex_count];
+        for (row, &vertex_index) in rows.iter_mut().zip(self.independent_vertices.iter()) {
+            for &slot in self.vertex_slots[vertex_index].iter() {
+                let column = columns[self.slot_edges[slot]];
+                row[column / 64] |= 1 << (column % 64);
+            }
+            if syndrome[vertex_index] {
+                row[edge_count / 64] |= 1 << (edge_count % 64);
+            }
+        }
+        // Gaussian elimination into reduced row echelon form, choosing the most likely independent columns as pivots
+        let mut pivots = vec![];
+        for column in 0..edge_count {
+            let rank = pivots.len();
+            if rank == vertex_count {
+                break;
+            }
+            let Some(pivot_row) = (rank..vertex_count).find(|&row| get_bit(&rows[row], column)) else {
+                continue;
+            };
+            rows.swap(rank, pivot_row);
+            let (upper, lower) = rows.split_at_mut(rank + 1);
+            let (upper, pivot) = upper.split_at_mut(rank);
+            let pivot = &pivot[0];
+            for row in upper.iter_mut().chain(lower.iter_mut()) {
+                if get_bit(row, column) {
+                    xor_assign(row, pivot);
+                }
+            }
+            pivots.push(column);
+        }
+        debug_assert_eq!(pivots.len(), vertex_count, "the rows are linearly independent");
+        let weight_of = |column: usize| log_likelihood_ratios[order[column]];
+        // exhaustively search the most likely columns outside the information set
+        let mut is_pivot = vec![false; edge_count];
+        for &column in pivots.iter() {
+            is_pivot[column] = true;
+        }
+        let flip_columns: Vec<usize> = (0..edge_count)
+            .filter(|&column| !is_pivot[column])
+            .take(self.config.osd_order)
+            .collect();
+        let mut best_weight = f64::INFINITY;
+        let mut best_solution = vec![];
+        for mask in 0..(1usize << flip_columns.len()) {
+            let mut solution = vec![];
+            let mut weight = 0.;
+            for (index, &column) in flip_columns.iter().enumerate() {
+                if mask & (1 << index) != 0 {
+                    solution.push(column);
+                    weight += weight_of(column);
+                }
+            }
+            let flipped = solution.len();
+            for (row, &column) in rows.iter().zip(pivots.iter()) {
+                let mut bit = get_bit(row, edge_count);
+                for &flip_column in solution[..flipped].iter() {
+                    bit ^= get_bit(row, flip_column);
+                }
+                if bit {
+                    solution.push(column);
+                    weight += weight_of(column);
+                }
+            }
+            if weight < best_weight {
+                best_weight = weight;
+                best_solution = solution;
+            }
+        }
+        best_solution.into_iter().map(|column| order[column]).collect()
+    }
+}
+
+fn get_bit(row: &[u64], column: usize) -> bool {
+    row[column / 64] & (1 << (column % 64)) != 0
+}
+
+fn xor_assign(row: &mut [u64], other: &[u64]) {
+    for (word, other_word) in row.iter_mut().zip(other.iter()) {
+        *word ^= other_word;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::code_builder::*;
+    use super::super::fault_distance::*;
+    use super::super::noise_model_builder::*;
+    use super::super::simulator_compact::*;
+    use super::super::tool::*;
+    use super::super::types::ErrorType::*;
+    use super::super::types::*;
+    use super::*;
+    use crate::cli::BenchmarkParameters;
+    use clap::Parser;
+
+    #[test]
+    fn bp_osd_decoder_code_capacity() {
+        // cargo test bp_osd_decoder_code_capacity -- --nocapture
+        let d = 5;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.001;
+        // build simulator
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        // build noise model
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, p, p, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        // build decoder
+        let mut bp_osd_decoder = BPOSDDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false).unwrap();
+        // every single-qubit error is corrected, including Y errors which are hyperedges
+        let mut error_positions = vec![];
+        simulator_iter_real!(simulator, position, node, t => 0, {
+            if node.qubit_type == QubitType::Data {
+                error_positions.push(position.clone());
+            }
+        });
+        for position in error_positions.iter() {
+            for error in [X, Z, Y] {
+                simulator.clear_all_errors();
+                simulator.set_error_check(&noise_model, position, &error);
+                simulator.propagate_errors();
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                let (correction, _runtime_statistics) = bp_osd_decoder.decode(&sparse_measurement);
+                code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+                let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                assert!(!logical_i && !logical_j, "{error:?} error at {position} is not corrected");
+            }
+        }
+        // two errors: {"[0][6][6]":"Y","[0][8][2]":"Z"}
+        simulator.clear_all_errors();
+        simulator.set_error_check(&noise_model, &pos!(0, 6, 6), &Y);
+        simulator.set_error_check(&noise_model, &pos!(0, 8, 2), &Z);
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        let (correction, _runtime_statistics) = bp_osd_decoder.decode(&sparse_measurement);
+        code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+        let (logical_i, logical_j) = simulator.validate_correction(&correction);
+        assert!(!logical_i && !logical_j);
+    }
+
+    #[test]
+    fn bp_osd_decoder_periodic_code_capacity() {
+        // cargo test bp_osd_decoder_periodic_code_capacity -- --nocapture
+        let d = 4;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.001;
+        // build simulator
+        let mut simulator = Simulator::new(CodeType::PeriodicRotatedTailoredCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        // build noise model
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, p, p, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        // build decoder, the X and Z stabilizers of a periodic code both multiply to identity
+        let mut bp_osd_decoder = BPOSDDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false).unwrap();
+        let vertex_count = bp_osd_decoder.vertex_slots.len();
+        assert_eq!(bp_osd_decoder.independent_vertices.len(), vertex_count - 2);
+        assert_eq!(bp_osd_decoder.syndrome_parity_checks.len(), 2);
+        // every single-qubit error is corrected
+        let mut error_positions = vec![];
+        simulator_iter_real!(simulator, position, node, t => 0, {
+            if node.qubit_type == QubitType::Data {
+                error_positions.push(position.clone());
+            }
+        });
+        for position in error_positions.iter() {
+            for error in [X, Z, Y] {
+                simulator.clear_all_errors();
+                simulator.set_error_check(&noise_model, position, &error);
+                simulator.propagate_errors();
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                let (correction, _runtime_statistics) = bp_osd_decoder.decode(&sparse_measurement);
+                code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+                let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                assert!(!logical_i && !logical_j, "{error:?} error at {position} is not corrected");
+            }
+        }
+        // a single defect cannot be generated by any error
+        let mut sparse_measurement = SparseMeasurement::new();
+        sparse_measurement.insert_defect_measurement(&bp_osd_decoder.model_hypergraph.vertex_positions[0]);
+        let (correction, runtime_statistics) = bp_osd_decoder.decode(&sparse_measurement);
+        assert!(correction.is_empty());
+        assert_eq!(runtime_statistics["syndrome_out_of_span"], json!(true));
+    }
+
+    #[test]
+    fn bp_osd_decoder_invalid_config() {
+        // cargo test bp_osd_decoder_invalid_config -- --nocapture
+        let simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, 3, 3));
+        let noise_model = Arc::new(NoiseModel::new(&simulator));
+        for (decoder_config, message) in [
+            (json!({ "osd_order": 64 }), "osd_order"),
+            (json!({ "damping": 1 }), "damping"),
+            (json!({ "unknown_field": 1 }), "unknown_field"),
+        ] {
+            let result = BPOSDDecoder::new(&simulator, noise_model.clone(), &decoder_config, 1, false);
+            assert!(result.is_err());
+            let error = result.err().unwrap();
+            println!("{decoder_config}: {error}");
+            assert!(error.contains(message));
+        }
+        // the benchmark reports the error instead of aborting
+        let parameters = BenchmarkParameters::parse_from([
+            "benchmark",
+            "[3]",
+            "[0]",
+            "[0.01]",
+            "--decoder",
+            "bposd",
+            "--decoder-config",
+            r#"{"osd_order":64}"#,
+        ]);
+        assert!(parameters.run().unwrap_err().contains("osd_order"));
+    }
+
+    #[test]
+    fn bp_osd_decoder_circuit_level() {
+        // cargo test bp_osd_decoder_circuit_level -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(1, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel
+            .apply(&mut simulator, &mut noise_model, &json!({}), 0.001, 0.5, 0.)
+            .unwrap();
+        let noise_model = Arc::new(noise_model);
+        let bp_osd_decoder = BPOSDDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false).unwrap();
+        // every single fault is corrected
+        let mut simulator_compact = SimulatorCompact::from_simulator(simulator, noise_model, 1);
+        let classes = fault_classes(&mut simulator_compact);
+        let general_decoder = GeneralDecoder::BPOSD(bp_osd_decoder);
+        let is_failed = |(logical_i, logical_j): (bool, bool)| logical_i || logical_j;
+        let result = enumerate_fault_configurations(&simulator_compact, &general_decoder, &classes, 1, 1, &is_failed);
+        println!("{} configurations, {} failing", result.configurations, result.failing);
+        assert_eq!(result.failing, 0, "failing error pattern: {:?}", result.failing_error_pattern);
+    }
+}
@@ -0,0 +1,445 @@
+//! distributed union-find decoder
+//!
+//! runs the fast-channel distributed union-find algorithm in [`super::distributed_uf_decoder`] on the model graph,
+//! so that its latency model (number of clock cycles) can be benchmarked against [`super::decoder_union_find::UnionFindDecoder`]
+//!
+
+use super::complete_model_graph::*;
+use super::decoder_mwpm::*;
+use super::decoder_union_find::*;
+use super::distributed_uf_decoder::*;
+use super::erasure_graph::*;
+use super::model_graph::*;
+use super::noise_model::*;
+use super::serde_json;
+use super::simulator::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// distributed union-find decoder, initialized and cloned for multiple threads
+#[derive(Debug, Clone, Serialize)]
+pub struct DistributedUnionFindDecoder {
+    /// model graph is immutably shared
+    pub model_graph: Arc<ModelGraph>,
+    /// erasure graph is immutably shared
+    pub erasure_graph: Arc<ErasureGraph>,
+    /// complete model graph each thread maintain its own precomputed data
+    pub complete_model_graph: CompleteModelGraph,
+    /// index to position mapping (immutable shared), index is the address of the processing unit
+    pub index_to_position: Arc<Vec<Position>>,
+    /// position to index mapping (immutable shared)
+    pub position_to_index: Arc<HashMap<Position, usize>>,
+    /// input nodes without error syndrome, each corresponds to a node in the model graph
+    pub input_nodes: Arc<Vec<InputNode<Position>>>,
+    /// direct neighbors given by the edges of the model graph
+    pub input_neighbors: Arc<Vec<InputNeighbor>>,
+    /// fast channels along the t, i and j axis
+    pub input_fast_channels: Arc<Vec<InputFastChannel>>,
+    /// (smaller index, larger index) to the index in `input_neighbors`, used to load erasures
+    #[serde(skip)]
+    pub neighbor_indices: Arc<HashMap<(usize, usize), usize>>,
+    /// used to compute the distance along the time axis
+    pub measurement_cycles: usize,
+    /// save configuration for later usage
+    pub config: DistributedUnionFindDecoderConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DistributedUnionFindDecoderConfig {
+    /// build complete model graph at first, but this will consume O(N^2) memory and increase initialization time,
+    /// disable this when you're simulating large code
+    #[serde(alias = "pcmg")] // abbreviation
+    #[serde(default = "mwpm_default_configs::precompute_complete_model_graph")]
+    pub precompute_complete_model_graph: bool,
+    /// weight function, by default using [`WeightFunction::AutotuneImproved`]
+    #[serde(alias = "wf")] // abbreviation
+    #[serde(default = "mwpm_default_configs::weight_function")]
+    pub weight_function: WeightFunction,
+    /// combined probability can improve accuracy, but will cause probabilities differ a lot even in the case of i.i.d. noise model
+    #[serde(alias = "ucp")] // abbreviation
+    #[serde(default = "mwpm_default_configs::use_combined_probability")]
+    pub use_combined_probability: bool,
+    /// maximum weight will be 2 * max_half_weight, so that each time an edge can grow 1; by default is 1: unweighted union-find decoder
+    #[serde(alias = "mhw")] // abbreviation
+    #[serde(default = "union_find_default_configs::max_half_weight")]
+    pub max_half_weight: usize,
+    /// fast channels connect nodes at distance (fast_channel_interval) ^ k along each axis; 0 or 1 disables fast channels
+    #[serde(alias = "fci")] // abbreviation
+    #[serde(default = "distributed_union_find_default_configs::fast_channel_interval")]
+    pub fast_channel_interval: usize,
+    /// skip building correction, only for benchmarking decoder speed; building correction can be very expensive in dense errors
+    #[serde(alias = "bsbc")] // abbreviation
+    #[serde(default = "union_find_default_configs::benchmark_skip_building_correction")]
+    pub benchmark_skip_building_correction: bool,
+}
+
+pub mod distributed_union_find_default_configs {
+    pub fn fast_channel_interval() -> usize {
+        2
+    }
+}
+
+impl DistributedUnionFindDecoder {
+    /// create a new distributed union-find decoder with decoder configuration
+    pub fn new(
+        simulator: &Simulator,
+        noise_model: Arc<NoiseModel>,
+        decoder_configuration: &serde_json::Value,
+        parallel: usize,
+        use_brief_edge: bool,
+    ) -> Self {
+        // read attribute of decoder configuration
+        let config: DistributedUnionFindDecoderConfig = serde_json::from_value(decoder_configuration.clone()).unwrap();
+        // build model graph
+        let mut simulator = simulator.clone();
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(
+            &mut simulator,
+            Arc::clone(&noise_model),
+            &config.weight_function,
+            parallel,
+            config.use_combined_probability,
+            use_brief_edge,
+        );
+        let model_graph = Arc::new(model_graph);
+        // build erasure graph
+        let mut erasure_graph = ErasureGraph::new(&simulator);
+        erasure_graph.build(&mut simulator, Arc::clone(&noise_model), parallel);
+        let erasure_graph = Arc::new(erasure_graph);
+        // build complete model graph
+        let mut complete_model_graph = CompleteModelGraph::new(&simulator, Arc::clone(&model_graph));
+        complete_model_graph.optimize_weight_greater_than_sum_boundary = false; // disable this optimization for any matching pair to exist
+        complete_model_graph.precompute(&simulator, config.precompute_complete_model_graph, parallel);
+        // build processing units
+        let mut index_to_position = Vec::<Position>::new();
+        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if model_graph.is_node_exist(position) {
+            index_to_position.push(position.clone());
+        });
+        let mut position_to_index = HashMap::<Position, usize>::with_capacity(index_to_position.len());
+        for (index, position) in index_to_position.iter().cloned().enumerate() {
+            position_to_index.insert(position, index);
+        }
+        // calculate scaling factor of edges, the same as the union-find decoder
+        let mut maximum_weight = 0.;
+        for position in index_to_position.iter() {
+            let model_graph_node = model_graph.get_node_unwrap(position);
+            for (_peer_position, edge) in model_graph_node.edges.iter() {
+                if edge.probability > 0. && edge.weight > maximum_weight {
+                    maximum_weight = edge.weight;
+                }
+            }
+            if let Some(boundary) = &model_graph_node.boundary {
+                if boundary.probability > 0. && boundary.weight > maximum_weight {
+                    maximum_weight = boundary.weight;
+                }
+            }
+        }
+        let scale_weight = |weight: f64| -> usize {
+            if maximum_weight == 0. {
+                // pure erasure channel could lead to this, all possible errors has weight = 0
+                2 * config.max_half_weight
+            } else {
+                let half_weight = ((config.max_half_weight as f64) * weight / maximum_weight).round() as usize;
+                2 * half_weight.clamp(1, config.max_half_weight)
+            }
+        };
+        let mut input_nodes = Vec::with_capacity(index_to_position.len());
+        let mut input_neighbors = Vec::new();
+        let mut neighbor_indices = HashMap::new();
+        for (index, position) in index_to_position.iter().enumerate() {
+            let model_graph_node = model_graph.get_node_unwrap(position);
+            let boundary_cost = match &model_graph_node.boundary {
+                Some(boundary) if boundary.probability > 0. => Some(scale_weight(boundary.weight)),
+                _ => None,
+            };
+            input_nodes.push(InputNode::new(position.clone(), false, boundary_cost));
+            for (peer_position, edge) in model_graph_node.edges.iter() {
+                let peer_index = position_to_index[peer_position];
+                if edge.probability > 0. && index < peer_index {
+                    neighbor_indices.insert((index, peer_index), input_neighbors.len());
+                    input_neighbors.push(InputNeighbor::new(index, peer_index, 0, scale_weight(edge.weight), 1));
+                }
+            }
+        }
+        // build fast channels along each axis, only between the same type of stabilizers
+        let mut input_fast_channels = Vec::new();
+        if config.fast_channel_interval > 1 {
+            let axis_steps = [(simulator.measurement_cycles, 0, 0), (0, 2, 0), (0, 0, 2)];
+            for (index, position) in index_to_position.iter().enumerate() {
+                let qubit_type = simulator.get_node_unwrap(position).qubit_type;
+                for (step_t, step_i, step_j) in axis_steps.iter() {
+                    let mut interval = config.fast_channel_interval;
+                    loop {
+                        let peer_position = pos!(
+                            position.t + interval * step_t,
+                            position.i + interval * step_i,
+                            position.j + interval * step_j
+                        );
+                        if !simulator.is_valid_position(&peer_position) {
+                            break;
+                        }
+                        if let Some(&peer_index) = position_to_index.get(&peer_position) {
+                            if simulator.get_node_unwrap(&peer_position).qubit_type == qubit_type {
+                                input_fast_channels.push(InputFastChannel::new(index, peer_index, 1));
+                            }
+                        }
+                        interval *= config.fast_channel_interval;
+                    }
+                }
+            }
+        }
+        Self {
+            model_graph,
+            erasure_graph,
+            complete_model_graph,
+            index_to_position: Arc::new(index_to_position),
+            position_to_index: Arc::new(position_to_index),
+            input_nodes: Arc::new(input_nodes),
+            input_neighbors: Arc::new(input_neighbors),
+            input_fast_channels: Arc::new(input_fast_channels),
+            neighbor_indices: Arc::new(neighbor_indices),
+            measurement_cycles: simulator.measurement_cycles,
+            config,
+        }
+    }
+
+    /// instantiate the processing units given measurement results and detected erasures
+    pub fn build_distributed_union_find(
+        &self,
+        sparse_measurement: &SparseMeasurement,
+        sparse_detected_erasures: &SparseErasures,
+    ) -> DistributedUnionFind<Position> {
+        let mut nodes = self.input_nodes.as_ref().clone();
+        for position in sparse_measurement.iter() {
+            nodes[self.position_to_index[position]].is_error_syndrome = true;
+        }
+        let mut neighbors = self.input_neighbors.as_ref().clone();
+        let mut erased_boundaries = Vec::new();
+        if !sparse_detected_erasures.is_empty() {
+            let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.erasure_graph);
+            for erasure_edge in erasure_edges.iter() {
+                match erasure_edge {
+                    ErasureEdge::Connection(position1, position2) => {
+                        let index1 = self.position_to_index[position1];
+                        let index2 = self.position_to_index[position2];
+                        let neighbor_index = self.neighbor_indices[&(index1.min(index2), index1.max(index2))];
+                        let neighbor = &mut neighbors[neighbor_index];
+                        neighbor.increased = neighbor.length;
+                    }
+                    ErasureEdge::Boundary(position) => {
+                        erased_boundaries.push(self.position_to_index[position]);
+                    }
+                }
+            }
+        }
+        let measurement_cycles = self.measurement_cycles;
+        let mut distributed_union_find = DistributedUnionFind::new(
+            nodes,
+            neighbors,
+            self.input_fast_channels.as_ref().clone(),
+            move |a: &Position, b: &Position| {
+                a.t.abs_diff(b.t) / measurement_cycles + (a.i.abs_diff(b.i) + a.j.abs_diff(b.j)) / 2
+            },
+            |a: &Position, b: &Position| a.cmp(b),
+        );
+        for index in erased_boundaries.into_iter() {
+            let boundary_cost = distributed_union_find.nodes[index]
+                .boundary_cost
+                .expect("boundary must exist");
+            distributed_union_find.processing_units[index].boundary_increased = boundary_cost;
+        }
+        distributed_union_find
+    }
+
+    /// decode given measurement results
+    #[allow(dead_code)]
+    pub fn decode(&mut self, sparse_measurement: &SparseMeasurement) -> (SparseCorrection, serde_json::Value) {
+        self.decode_with_erasure(sparse_measurement, &SparseErasures::new())
+    }
+
+    /// decode given measurement results and detected erasures
+    pub fn decode_with_erasure(
+        &mut self,
+        sparse_measurement: &SparseMeasurement,
+        sparse_detected_erasures: &SparseErasures,
+    ) -> (SparseCorrection, serde_json::Value) {
+        if sparse_measurement.is_empty() {
+            return (
+                SparseCorrection::new(),
+                json!({
+                    "time_run_to_stable": 0.,
+                    "time_prepare_decoders": 0.,
+                    "time_build_correction": 0.,
+                    "clock_cycles": 0,
+                }),
+            );
+        }
+        let begin = Instant::now();
+        let mut distributed_union_find = self.build_distributed_union_find(sparse_measurement, sparse_detected_erasures);
+        let time_prepare_decoders = begin.elapsed().as_secs_f64();
+        // decode
+        let begin = Instant::now();
+        let clock_cycles = distributed_union_find.run_to_stable();
+        let time_run_to_stable = begin.elapsed().as_secs_f64();
+        // build correction based on the clusters
+        let (time_build_correction, correction) = {
+            let begin = Instant::now();
+            let mut correction = SparseCorrection::new();
+            if !self.config.benchmark_skip_building_correction {
+                // invalidate previous cache to save memory
+                self.complete_model_graph.invalidate_previous_dijkstra();
+                // in order to build correction, first collect the nodes for each cluster
+                let mut cluster_nodes = BTreeMap::<usize, Vec<usize>>::new();
+                for position in sparse_measurement.iter() {
+                    let index = self.position_to_index[position];
+                    let root = distributed_union_find.processing_units[index].updated_root;
+                    cluster_nodes.entry(root).or_default().push(index);
+                }
+                // odd clusters stop growing once any of their nodes touches the boundary
+                let mut cluster_boundary_indices = BTreeMap::<usize, usize>::new();
+                for (index, processing_unit) in distributed_union_find.processing_units.iter().enumerate() {
+                    if distributed_union_find.is_node_touching_boundary(index) {
+                        cluster_boundary_indices.entry(processing_unit.updated_root).or_insert(index);
+                    }
+                }
+                // then build correction based on each correction
+                for (root, mut error_syndromes) in cluster_nodes.into_iter() {
+                    assert_eq!(
+                        error_syndromes.len(),
+                        distributed_union_find.processing_units[root].debug_cardinality
+                    );
+                    if error_syndromes.len() % 2 == 1 {
+                        let cluster_boundary_index = *cluster_boundary_indices
+                            .get(&root)
+                            .expect("boundary of odd cluster must exists");
+                        // connect to a boundary and others internally
+                        error_syndromes.push(cluster_boundary_index); // let it match with others
+                        let cluster_boundary_position = &self.index_to_position[cluster_boundary_index];
+                        let boundary_correction =
+                            self.complete_model_graph.build_correction_boundary(cluster_boundary_position);
+                        correction.extend(&boundary_correction);
+                    }
+                    let half_len = error_syndromes.len() / 2;
+                    for i in 0..half_len {
+                        let index1 = error_syndromes[i];
+                        let index2 = error_syndromes[i + half_len];
+                        if index1 != index2 {
+                            let position1 = &self.index_to_position[index1];
+                            let position2 = &self.index_to_position[index2];
+                            let matching_correction =
+                                self.complete_model_graph.build_correction_matching(position1, position2);
+                            correction.extend(&matching_correction);
+                        }
+                    }
+                }
+            }
+            (begin.elapsed().as_secs_f64(), correction)
+        };
+        (
+            correction,
+            json!({
+                "time_run_to_stable": time_run_to_stable,
+                "time_prepare_decoders": time_prepare_decoders,
+                "time_build_correction": time_build_correction,
+                "clock_cycles": clock_cycles,
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::code_builder::*;
+    use super::super::types::ErrorType::*;
+    use super::*;
+
+    #[test]
+    fn distributed_union_find_decoder_code_capacity() {
+        // cargo test distributed_union_find_decoder_code_capacity -- --nocapture
+        let d = 5;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.001;
+        // build simulator
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        // build noise model
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, p, p, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        // build decoder
+        let decoder_config = json!({
+            "precompute_complete_model_graph": true,
+        });
+        let mut distributed_union_find_decoder = DistributedUnionFindDecoder::new(
+            &Arc::new(simulator.clone()),
+            Arc::clone(&noise_model),
+            &decoder_config,
+            1,
+            false,
+        );
+        // the same error patterns that the union-find decoder should correct
+        for errors in [
+            vec![pos!(0, 4, 6), pos!(0, 5, 9), pos!(0, 7, 1), pos!(0, 9, 1)],
+            vec![pos!(0, 6, 6), pos!(0, 8, 2), pos!(0, 8, 4)],
+            vec![pos!(0, 3, 9), pos!(0, 8, 8)],
+            vec![pos!(0, 6, 4), pos!(0, 6, 6), pos!(0, 5, 7)],
+        ] {
+            simulator.clear_all_errors();
+            for position in errors.iter() {
+                simulator.set_error_check(&noise_model, position, &Z);
+            }
+            simulator.propagate_errors();
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            let (correction, runtime_statistics) = distributed_union_find_decoder.decode(&sparse_measurement);
+            println!("runtime_statistics: {}", runtime_statistics);
+            assert!(runtime_statistics["clock_cycles"].as_u64().unwrap() > 0);
+            code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+            let (logical_i, logical_j) = simulator.validate_correction(&correction);
+            assert!(!logical_i && !logical_j);
+        }
+    }
+    #[test]
+    fn distributed_union_find_decoder_erasure() {
+        // cargo test distributed_union_find_decoder_erasure -- --nocapture
+        let d = 5;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.001;
+        let pe = 0.05;
+        // build simulator
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        // build noise model
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, p, p, pe);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        // build decoder
+        let mut distributed_union_find_decoder =
+            DistributedUnionFindDecoder::new(&Arc::new(simulator.clone()), Arc::clone(&noise_model), &json!({}), 1, false);
+        // a chain of Z errors from the boundary longer than half the distance is only corrected with the erasures
+        let errors = [pos!(0, 1, 1), pos!(0, 3, 1), pos!(0, 5, 1), pos!(0, 7, 1)];
+        simulator.clear_all_errors();
+        let mut sparse_detected_erasures = SparseErasures::new();
+        for position in errors.iter() {
+            simulator.set_error_check(&noise_model, position, &Z);
+            sparse_detected_erasures.insert_erasure(position);
+        }
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        println!("sparse_measurement: {:?}", sparse_measurement);
+        for (erasures, expect_success) in [(SparseErasures::new(), false), (sparse_detected_erasures, true)] {
+            let (correction, runtime_statistics) =
+                distributed_union_find_decoder.decode_with_erasure(&sparse_measurement, &erasures);
+            println!("runtime_statistics: {}", runtime_statistics);
+            code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+            let (logical_i, logical_j) = simulator.validate_correction(&correction);
+            assert_eq!(!logical_i && !logical_j, expect_success);
+        }
+    }
+}
@@ -1,10 +1,13 @@
 //! minimum-weight perfect matching decoder
 //!
 
+use super::erasure_graph::ErasureEdge;
 use super::model_graph::*;
+use super::model_hypergraph::*;
 use super::noise_model::*;
 use super::serde_json;
 use super::simulator::*;
+use super::soft_output::*;
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
@@ -32,6 +35,9 @@ pub struct FusionDecoder {
     pub fusion_solver: fusion_blossom::mwpm_solver::SolverSerial,
     /// save configuration for later usage
     pub config: FusionDecoderConfig,
+    /// computes the complementary gap as the soft output, only built when `soft_output` is enabled
+    #[serde(skip)]
+    pub complementary_gap_solver: Option<Arc<ComplementaryGapSolver>>,
 }
 
 impl Clone for FusionDecoder {
@@ -51,6 +57,7 @@ impl Clone for FusionDecoder {
             adaptor: self.adaptor.clone(),
             fusion_solver,
             config: self.config.clone(),
+            complementary_gap_solver: self.complementary_gap_solver.clone(),
         }
     }
 }
@@ -78,6 +85,14 @@ pub struct FusionDecoderConfig {
     pub log_matchings: bool,
     #[serde(default = "fusion_default_configs::max_tree_size")]
     pub max_tree_size: usize,
+    /// correlated matching: reweight the edges that share a hyperedge with the matched edges and then match again
+    #[serde(alias = "ucm")] // abbreviation
+    #[serde(default = "mwpm_default_configs::use_correlated_matching")]
+    pub use_correlated_matching: bool,
+    /// report the complementary gap of each shot as `soft_output` in the runtime statistics, see [`crate::soft_output`]
+    #[serde(alias = "so")] // abbreviation
+    #[serde(default = "mwpm_default_configs::soft_output")]
+    pub soft_output: bool,
 }
 
 pub mod fusion_default_configs {
@@ -113,13 +128,29 @@ impl FusionDecoder {
         // erasure_graph.build(&mut simulator, Arc::clone(&noise_model), parallel);
         // let erasure_graph = Arc::new(erasure_graph);
         // build solver
-        let adaptor = FusionBlossomAdaptor::new(&config, &mut simulator, noise_model, parallel, use_brief_edge);
+        let adaptor = FusionBlossomAdaptor::new(&config, &mut simulator, Arc::clone(&noise_model), parallel, use_brief_edge);
         let fusion_solver = fusion_blossom::mwpm_solver::SolverSerial::new(&adaptor.initializer);
         fusion_solver.primal_module.write().max_tree_size = config.max_tree_size;
+        // the adaptor doesn't keep the model graph, so build another one for the soft output
+        let complementary_gap_solver = if config.soft_output {
+            let mut model_graph = ModelGraph::new(&simulator);
+            model_graph.build(
+                &mut simulator,
+                noise_model,
+                &config.weight_function,
+                parallel,
+                config.use_combined_probability,
+                use_brief_edge,
+            );
+            Some(Arc::new(ComplementaryGapSolver::new(&simulator, &model_graph)))
+        } else {
+            None
+        };
         Self {
             adaptor: Arc::new(adaptor),
             fusion_solver,
             config,
+            complementary_gap_solver,
         }
     }
 
@@ -135,23 +166,55 @@ impl FusionDecoder {
         sparse_measurement: &SparseMeasurement,
         sparse_detected_erasures: &SparseErasures,
     ) -> (SparseCorrection, serde_json::Value) {
+        let (correction, _matched_defects, runtime_statistics) =
+            self.decode_impl(sparse_measurement, sparse_detected_erasures, false);
+        (correction, runtime_statistics)
+    }
+
+    /// decode given measurement results and detected erasures, also returning the perfect matching of the defects where
+    /// a defect matched with a virtual vertex has no peer
+    pub fn decode_matching_with_erasure(
+        &mut self,
+        sparse_measurement: &SparseMeasurement,
+        sparse_detected_erasures: &SparseErasures,
+    ) -> (SparseCorrection, MatchedDefects, serde_json::Value) {
+        self.decode_impl(sparse_measurement, sparse_detected_erasures, true)
+    }
+
+    fn decode_impl(
+        &mut self,
+        sparse_measurement: &SparseMeasurement,
+        sparse_detected_erasures: &SparseErasures,
+        collect_matching: bool,
+    ) -> (SparseCorrection, MatchedDefects, serde_json::Value) {
         if self.config.skip_decoding {
-            return (SparseCorrection::new(), json!({}));
+            return (SparseCorrection::new(), vec![], json!({}));
         }
         assert!(sparse_detected_erasures.is_empty(), "fusion decoder doesn't support erasure error yet: we'll do it in the next version to support 0-weight edges and dynamic setting");
         let mut correction = SparseCorrection::new();
         let mut time_fusion = 0.;
         let mut time_build_correction = 0.;
         let mut log_matchings = Vec::with_capacity(0);
+        let mut matched_defects = Vec::with_capacity(0);
         // list nontrivial measurements to be matched
         if !sparse_measurement.is_empty() {
             // run the Blossom algorithm
             let begin = Instant::now();
-            let syndrome_pattern = self
+            let mut syndrome_pattern = self
                 .adaptor
                 .generate_syndrome_pattern(sparse_measurement, sparse_detected_erasures);
             self.fusion_solver.solve(&syndrome_pattern);
-            let subgraph: Vec<usize> = self.fusion_solver.subgraph();
+            let mut subgraph: Vec<usize> = self.fusion_solver.subgraph();
+            if self.config.use_correlated_matching {
+                // reweight the partner edges of the matched edges and then match again
+                let dynamic_weights = self.adaptor.correlated_dynamic_weights(&subgraph);
+                if !dynamic_weights.is_empty() {
+                    self.fusion_solver.clear();
+                    syndrome_pattern.dynamic_weights = dynamic_weights;
+                    self.fusion_solver.solve(&syndrome_pattern);
+                    subgraph = self.fusion_solver.subgraph();
+                }
+            }
             if self.config.log_matchings {
                 // log the subgraph
                 let mut subgraph_edges = vec![];
@@ -188,6 +251,18 @@ impl FusionDecoder {
                     "edges": perfect_matching_edges,
                 }));
             }
+            if collect_matching {
+                let perfect_matching = self.fusion_solver.perfect_matching();
+                for (node_ptr_1, node_ptr_2) in perfect_matching.peer_matchings.iter() {
+                    let position_1 = &self.adaptor.vertex_to_position_mapping[node_ptr_1.get_representative_vertex()];
+                    let position_2 = &self.adaptor.vertex_to_position_mapping[node_ptr_2.get_representative_vertex()];
+                    matched_defects.push((position_1.clone(), Some(position_2.clone())));
+                }
+                for (node_ptr, _virtual_vertex) in perfect_matching.virtual_matchings.iter() {
+                    let position = &self.adaptor.vertex_to_position_mapping[node_ptr.get_representative_vertex()];
+                    matched_defects.push((position.clone(), None));
+                }
+            }
             self.fusion_solver.clear();
             time_fusion += begin.elapsed().as_secs_f64();
             correction = self.adaptor.subgraph_to_correction(&subgraph);
@@ -198,11 +273,17 @@ impl FusionDecoder {
             "time_fusion": time_fusion,
             "time_build_correction": time_build_correction,
         });
+        if let Some(complementary_gap_solver) = &self.complementary_gap_solver {
+            let begin = Instant::now();
+            let soft_output = complementary_gap_solver.solve(&sparse_measurement.to_vec(), &[]);
+            runtime_statistics["soft_output"] = json!(soft_output);
+            runtime_statistics["time_soft_output"] = json!(begin.elapsed().as_secs_f64());
+        }
         if self.config.log_matchings {
             let runtime_statistics = runtime_statistics.as_object_mut().unwrap();
             runtime_statistics.insert("log_matchings".to_string(), json!(log_matchings));
         }
-        (correction, runtime_statistics)
+        (correction, matched_defects, runtime_statistics)
     }
 }
 
@@ -224,6 +305,9 @@ pub struct FusionBlossomAdaptor {
     pub initializer: SolverInitializer,
     /// fusion blossom position for visualization
     pub positions: Vec<VisualizePosition>,
+    /// the partner edges of each edge and their reweighted weights, only built for correlated matching
+    #[serde(default)]
+    pub correlated_edges: Vec<Vec<(EdgeIndex, Weight)>>,
 }
 
 impl FusionBlossomAdaptor {
@@ -237,7 +321,7 @@ impl FusionBlossomAdaptor {
         let mut model_graph = ModelGraph::new(simulator);
         model_graph.build(
             simulator,
-            noise_model_graph,
+            Arc::clone(&noise_model_graph),
             &config.weight_function,
             parallel_init,
             config.use_combined_probability,
@@ -273,6 +357,7 @@ impl FusionBlossomAdaptor {
         });
         let mut weighted_edges_unscaled = Vec::<(usize, usize, f64)>::new();
         let mut edge_to_correction_mapping = Vec::new();
+        let mut edge_indices = std::collections::HashMap::<ErasureEdge, EdgeIndex>::new();
         simulator_iter!(simulator, position, node, {
             // then add edges and also virtual nodes
             if position.t != 0
@@ -288,6 +373,7 @@ impl FusionBlossomAdaptor {
                         .as_ref()
                         .expect("virtual boundary required to plot properly in fusion blossom");
                     let virtual_index = position_to_vertex_mapping[virtual_position];
+                    edge_indices.insert(ErasureEdge::Boundary(position.clone()), weighted_edges_unscaled.len());
                     weighted_edges_unscaled.push((vertex_index, virtual_index, model_graph_boundary.weight));
                     edge_to_correction_mapping.push(model_graph_boundary.correction.as_ref().clone());
                 }
@@ -295,26 +381,52 @@ impl FusionBlossomAdaptor {
                     let peer_idx = position_to_vertex_mapping[peer_position];
                     if vertex_index < peer_idx {
                         // avoid duplicate edges
+                        let edge = ModelGraphCorrelations::edge_of(position, peer_position);
+                        edge_indices.insert(edge, weighted_edges_unscaled.len());
                         weighted_edges_unscaled.push((vertex_index, peer_idx, model_graph_edge.weight));
                         edge_to_correction_mapping.push(model_graph_edge.correction.as_ref().clone());
                     }
                 }
             }
         });
-        initializer.weighted_edges = {
-            // re-weight edges and parse to integer
-            let mut maximum_weight = 0.;
-            for (_, _, weight) in weighted_edges_unscaled.iter() {
-                if weight > &maximum_weight {
-                    maximum_weight = *weight;
+        // re-weight edges and parse to integer
+        let mut maximum_weight = 0.;
+        for (_, _, weight) in weighted_edges_unscaled.iter() {
+            if weight > &maximum_weight {
+                maximum_weight = *weight;
+            }
+        }
+        let scale: f64 = config.max_half_weight as f64 / maximum_weight;
+        let scaled_weight = |weight: f64| 2 * (weight * scale).ceil() as fusion_blossom::util::Weight;
+        initializer.weighted_edges = weighted_edges_unscaled
+            .iter()
+            .map(|(a, b, weight)| (*a, *b, scaled_weight(*weight)))
+            .collect();
+        let mut correlated_edges = vec![];
+        if config.use_correlated_matching {
+            let mut model_hypergraph = ModelHypergraph::new(simulator);
+            model_hypergraph.build(
+                simulator,
+                noise_model_graph,
+                &config.weight_function,
+                parallel_init,
+                config.use_combined_probability,
+                use_brief_edge,
+            );
+            let model_graph_correlations = ModelGraphCorrelations::new(simulator, &model_graph, &model_hypergraph);
+            correlated_edges = vec![vec![]; initializer.weighted_edges.len()];
+            for (edge, partners) in model_graph_correlations.partners.iter() {
+                let Some(&edge_index) = edge_indices.get(edge) else {
+                    continue; // the edge is filtered out
+                };
+                for (partner, conditional_probability) in partners.iter() {
+                    if let Some(&partner_index) = edge_indices.get(partner) {
+                        let weight = scaled_weight(config.weight_function.weight_of(*conditional_probability));
+                        correlated_edges[edge_index].push((partner_index, weight));
+                    }
                 }
             }
-            let scale: f64 = config.max_half_weight as f64 / maximum_weight;
-            weighted_edges_unscaled
-                .iter()
-                .map(|(a, b, weight)| (*a, *b, 2 * (weight * scale).ceil() as fusion_blossom::util::Weight))
-                .collect()
-        };
+        }
         Self {
             initializer,
             positions,
@@ -322,9 +434,27 @@ impl FusionBlossomAdaptor {
             position_to_vertex_mapping,
             stabilizer_filter,
             edge_to_correction_mapping,
+            correlated_edges,
         }
     }
 
+    /// the dynamic weights of correlated matching given the edges of the first matching, only lowering the weights
+    pub fn correlated_dynamic_weights(&self, subgraph: &[EdgeIndex]) -> Vec<(EdgeIndex, Weight)> {
+        let mut dynamic_weights = std::collections::BTreeMap::<EdgeIndex, Weight>::new();
+        for &edge_index in subgraph.iter() {
+            for &(partner_index, weight) in self.correlated_edges[edge_index].iter() {
+                let current_weight = dynamic_weights
+                    .entry(partner_index)
+                    .or_insert(self.initializer.weighted_edges[partner_index].2);
+                *current_weight = (*current_weight).min(weight);
+            }
+        }
+        dynamic_weights
+            .into_iter()
+            .filter(|&(edge_index, weight)| weight < self.initializer.weighted_edges[edge_index].2)
+            .collect()
+    }
+
     pub fn generate_syndrome_pattern(
         &self,
         sparse_measurement: &SparseMeasurement,
@@ -539,6 +669,10 @@ pub struct FusionBlossomAdaptorExtender {
 
 impl FusionBlossomAdaptorExtender {
     pub fn new(first: FusionBlossomAdaptor, second: FusionBlossomAdaptor, noisy_measurements: usize) -> Self {
+        assert!(
+            first.correlated_edges.is_empty(),
+            "correlated matching is not supported by the adaptor extender yet"
+        );
         assert!(
             second.initializer.weighted_edges.len() > first.initializer.weighted_edges.len(),
             "must differ"
@@ -733,7 +867,9 @@ mod tests {
         // build noise model
         let mut noise_model = NoiseModel::new(&simulator);
         let noise_model_builder = NoiseModelBuilder::ErasureOnlyPhenomenological;
-        noise_model_builder.apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe);
+        noise_model_builder
+            .apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe)
+            .unwrap();
         simulator.compress_error_rates(&mut noise_model);
         noise_model_sanity_check(&simulator, &noise_model).unwrap();
         let noise_model = Arc::new(noise_model);
@@ -822,7 +958,9 @@ mod tests {
             });
             let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, di, dj));
             let mut noise_model = NoiseModel::new(&simulator);
-            NoiseModelBuilder::StimNoiseModel.apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.);
+            NoiseModelBuilder::StimNoiseModel
+                .apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.)
+                .unwrap();
             code_builder_sanity_check(&simulator).unwrap();
             noise_model_sanity_check(&simulator, &noise_model).unwrap();
             let fusion_decoder = FusionDecoder::new(&simulator, Arc::new(noise_model), &config, 1, true);
@@ -839,4 +977,44 @@ mod tests {
         let ground_truth = build_adaptor(test_noisy_measurement);
         generated.assert_eq(&ground_truth).unwrap();
     }
+
+    #[test]
+    fn fusion_decoder_correlated_matching() {
+        // cargo test fusion_decoder_correlated_matching -- --nocapture
+        let d = 5;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.1;
+        // build simulator
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        // build noise model
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p / 3., p / 3., p / 3., 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        // load errors onto the simulator: the X components of the two Y errors are only likely together with their Z components
+        let sparse_error_pattern: SparseErrorPattern =
+            serde_json::from_value(json!({"[0][1][5]":"X","[0][2][4]":"Y","[0][3][3]":"Y"})).unwrap();
+        simulator
+            .load_sparse_error_pattern(&sparse_error_pattern, &noise_model)
+            .expect("success");
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        println!("sparse_measurement: {:?}", sparse_measurement);
+        for (decoder_config, expect_success) in [(json!({}), false), (json!({"use_correlated_matching": true}), true)] {
+            let mut fusion_decoder = FusionDecoder::new(
+                &Arc::new(simulator.clone()),
+                Arc::clone(&noise_model),
+                &decoder_config,
+                1,
+                false,
+            );
+            let (correction, _runtime_statistics) = fusion_decoder.decode(&sparse_measurement);
+            println!("{decoder_config} correction: {:?}", correction);
+            code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+            let (logical_i, logical_j) = simulator.validate_correction(&correction);
+            assert_eq!(!logical_i && !logical_j, expect_success);
+        }
+    }
 }
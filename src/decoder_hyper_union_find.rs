@@ -1,6 +1,7 @@
 //! union-find decoder on hypergraph
 
 use super::decoder_mwpm::*;
+use super::erasure_graph::*;
 use super::model_graph::*;
 use super::noise_model::*;
 use super::simulator::*;
@@ -8,6 +9,7 @@ use crate::model_hypergraph::*;
 use crate::mwpf::mwpf_solver::*;
 use crate::mwpf::util::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -20,6 +22,8 @@ pub struct HyperUnionFindDecoder {
     pub solver: SolverSerialUnionFind,
     /// the initializer of the solver, used for customized clone
     pub initializer: Arc<SolverInitializer>,
+    /// the hyperedges that become 0-weighted when each position is erased
+    pub erasure_hyperedges: Arc<HashMap<Position, Vec<usize>>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +61,7 @@ impl Clone for HyperUnionFindDecoder {
             config: self.config.clone(),
             solver: SolverSerialUnionFind::new(&self.initializer, self.config.hyperion_config.clone()),
             initializer: self.initializer.clone(),
+            erasure_hyperedges: self.erasure_hyperedges.clone(),
         }
     }
 }
@@ -83,6 +88,10 @@ impl HyperUnionFindDecoder {
             config.use_combined_probability,
             use_brief_edge,
         );
+        // build erasure graph and find the hyperedges of each erasure
+        let mut erasure_graph = ErasureGraph::new(&simulator);
+        erasure_graph.build(&mut simulator, Arc::clone(&noise_model), parallel);
+        let erasure_hyperedges = model_hypergraph.build_erasure_hyperedges(&mut simulator, &erasure_graph);
         let model_hypergraph = Arc::new(model_hypergraph);
         let (vertex_num, weighted_edges) = model_hypergraph.generate_mwpf_hypergraph(config.max_weight);
         let initializer = Arc::new(SolverInitializer::new(vertex_num, weighted_edges));
@@ -92,6 +101,7 @@ impl HyperUnionFindDecoder {
             config,
             solver,
             initializer,
+            erasure_hyperedges: Arc::new(erasure_hyperedges),
         }
     }
 
@@ -107,9 +117,6 @@ impl HyperUnionFindDecoder {
         sparse_measurement: &SparseMeasurement,
         sparse_detected_erasures: &SparseErasures,
     ) -> (SparseCorrection, serde_json::Value) {
-        if !sparse_detected_erasures.is_empty() {
-            unimplemented!()
-        }
         // run decode
         let begin = Instant::now();
         let defect_vertices: Vec<_> = sparse_measurement
@@ -122,7 +129,9 @@ impl HyperUnionFindDecoder {
                     .expect("measurement cannot happen at impossible position")
             })
             .collect();
-        let syndrome_pattern = SyndromePattern::new(defect_vertices, vec![]);
+        // the solver sets the weights of the erased hyperedges to 0
+        let erasures = ModelHypergraph::erased_hyperedges(&self.erasure_hyperedges, sparse_detected_erasures);
+        let syndrome_pattern = SyndromePattern::new(defect_vertices, erasures);
         self.solver.solve(&syndrome_pattern);
         let subgraph = self.solver.subgraph();
         self.solver.clear();
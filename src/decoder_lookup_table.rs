@@ -0,0 +1,410 @@
+//! lookup-table decoder generated from the noise model
+//!
+//! All the combinations of up to `max_weight` Pauli error sources of a [`SimulatorCompact`] are enumerated, and the
+//! syndrome of each combination is mapped to the correction of the most likely error in its most likely logical coset.
+//! The table grows as the number of error sources to the power of `max_weight`, so it's only practical for small patches,
+//! where it models a decoder with constant latency. The table can be saved to a file and loaded later, and the syndromes
+//! out of the table (including all the syndromes with erasures) are decoded by a fallback decoder.
+//!
+
+use super::noise_model::*;
+use super::simulator::*;
+use super::simulator_compact::*;
+use super::tool::GeneralDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// lookup-table decoder, initialized and cloned for multiple threads
+#[derive(Clone)]
+pub struct LookupTableDecoder {
+    /// the correction of each syndrome in the table
+    pub table: Arc<HashMap<Vec<Position>, SparseCorrection>>,
+    /// the decoder of the syndromes out of the table
+    pub fallback_decoder: Box<GeneralDecoder>,
+    /// save configuration for later usage
+    pub config: LookupTableDecoderConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LookupTableDecoderConfig {
+    /// the maximum number of error sources in an enumerated error pattern
+    #[serde(alias = "mw")] // abbreviation
+    #[serde(default = "lookup_table_default_configs::max_weight")]
+    pub max_weight: usize,
+    /// load the table from this file if it exists, otherwise save the generated table to this file
+    #[serde(alias = "tp")] // abbreviation
+    #[serde(default = "lookup_table_default_configs::table_path")]
+    pub table_path: Option<String>,
+    /// the decoder of the syndromes out of the table, e.g. `mwpm` or `union-find`
+    #[serde(default = "lookup_table_default_configs::fallback")]
+    pub fallback: String,
+    /// the decoder configuration of the fallback decoder
+    #[serde(alias = "fc")] // abbreviation
+    #[serde(default = "lookup_table_default_configs::fallback_config")]
+    pub fallback_config: serde_json::Value,
+}
+
+pub mod lookup_table_default_configs {
+    pub fn max_weight() -> usize {
+        2
+    }
+    pub fn table_path() -> Option<String> {
+        None
+    }
+    pub fn fallback() -> String {
+        "mwpm".to_string()
+    }
+    pub fn fallback_config() -> serde_json::Value {
+        json!({})
+    }
+}
+
+/// the lookup table saved in a file, together with the parameters used to generate it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LookupTable {
+    pub max_weight: usize,
+    /// the probability and the sorted defects of each Pauli error source, used to detect a table generated for a
+    /// different code or noise model
+    pub error_sources: Vec<(f64, Vec<Position>)>,
+    /// the syndromes and their corrections
+    pub entries: Vec<(Vec<Position>, SparseCorrection)>,
+}
+
+/// a Pauli error source in the enumeration
+struct PauliSource {
+    defects: Vec<Position>,
+    /// the likelihood ratio `p / (1 - p)`
+    ratio: f64,
+    coset: usize,
+    correction: SparseCorrection,
+}
+
+/// the enumerated error patterns of the same syndrome
+struct SyndromeCosets {
+    /// the total likelihood ratio of each logical coset
+    probabilities: [f64; 4],
+    /// the most likely error pattern of each logical coset
+    best: [(f64, Vec<usize>); 4],
+}
+
+impl LookupTableDecoder {
+    /// create a lookup-table decoder, loading the table from `table_path` if it exists
+    pub fn new(
+        simulator: &Simulator,
+        noise_model: Arc<NoiseModel>,
+        decoder_configuration: &serde_json::Value,
+        fallback_decoder: GeneralDecoder,
+        parallel: usize,
+    ) -> Result<Self, String> {
+        let config: LookupTableDecoderConfig =
+            serde_json::from_value(decoder_configuration.clone()).map_err(|error| error.to_string())?;
+        let mut simulator_compact = SimulatorCompact::from_simulator(simulator.clone(), noise_model, parallel);
+        let table = match &config.table_path {
+            Some(table_path) if fs::metadata(table_path).is_ok() => {
+                let content = fs::read_to_string(table_path).map_err(|error| error.to_string())?;
+                let table: LookupTable = serde_json::from_str(&content).map_err(|error| error.to_string())?;
+                if table.max_weight != config.max_weight {
+                    return Err(format!(
+                        "the lookup table at {table_path} is generated with max weight {}, but now {}",
+                        table.max_weight, config.max_weight
+                    ));
+                }
+                let error_sources = Self::pauli_error_sources(&simulator_compact);
+                // the probabilities may lose the last bit in the JSON round trip
+                let is_same_source = |(p1, defects1): &(f64, Vec<Position>), (p2, defects2): &(f64, Vec<Position>)| {
+                    defects1 == defects2 && (p1 - p2).abs() <= 1e-12 * p1.abs().max(p2.abs())
+                };
+                if table.error_sources.len() != error_sources.len()
+                    || !table
+                        .error_sources
+                        .iter()
+                        .zip(error_sources.iter())
+                        .all(|(a, b)| is_same_source(a, b))
+                {
+                    return Err(format!(
+                        "the lookup table at {table_path} is generated with different error sources, \
+                        i.e. a different code or noise model"
+                    ));
+                }
+                table
+            }
+            _ => {
+                let table = Self::generate_table(&mut simulator_compact, simulator.height - 1, config.max_weight);
+                if let Some(table_path) = &config.table_path {
+                    let content = serde_json::to_string(&table).map_err(|error| error.to_string())?;
+                    fs::write(table_path, content).map_err(|error| error.to_string())?;
+                }
+                table
+            }
+        };
+        Ok(Self {
+            table: Arc::new(table.entries.into_iter().collect()),
+            fallback_decoder: Box::new(fallback_decoder),
+            config,
+        })
+    }
+
+    /// the probability and the sorted defects of each Pauli error source in the enumeration
+    fn pauli_error_sources(simulator_compact: &SimulatorCompact) -> Vec<(f64, Vec<Position>)> {
+        let mut error_sources = vec![];
+        for error_source in simulator_compact.error_sources.iter() {
+            if let ErrorSource::Pauli { p, defects, .. } = error_source {
+                if *p > 0. {
+                    let mut defects = defects.clone();
+                    defects.sort();
+                    error_sources.push((*p, defects));
+                }
+            }
+        }
+        error_sources
+    }
+
+    /// enumerate the combinations of up to `max_weight` Pauli error sources, where the corrections are at `top_t`
+    pub fn generate_table(simulator_compact: &mut SimulatorCompact, top_t: usize, max_weight: usize) -> LookupTable {
+        let mut sources = vec![];
+        for index in 0..simulator_compact.error_sources.len() {
+            let ErrorSource::Pauli {
+                p, defects, correction, ..
+            } = &simulator_compact.error_sources[index]
+            else {
+                continue; // erasures are decoded by the fallback decoder
+            };
+            if *p <= 0. {
+                continue;
+            }
+            let mut defects = defects.clone();
+            defects.sort();
+            let mut sparse_correction = SparseCorrection::new();
+            for (position, error) in correction.iter() {
+                sparse_correction.add(pos!(top_t, position.i, position.j), *error);
+            }
+            let ratio = p.min(0.5) / (1. - p.min(0.5));
+            simulator_compact.load_faults(&[(index, None)]);
+            let (logical_i, logical_j) = simulator_compact.validate_correction(&SparseCorrection::new());
+            sources.push(PauliSource {
+                defects,
+                ratio,
+                coset: logical_i as usize | (logical_j as usize) << 1,
+                correction: sparse_correction,
+            });
+        }
+        simulator_compact.clear();
+        let mut syndromes = HashMap::<Vec<Position>, SyndromeCosets>::new();
+        Self::enumerate_patterns(&sources, 0, max_weight, &[], 1., 0, &mut vec![], &mut syndromes);
+        let mut entries = Vec::with_capacity(syndromes.len());
+        for (defects, cosets) in syndromes.into_iter() {
+            let coset = (0..4)
+                .max_by(|&a, &b| cosets.probabilities[a].total_cmp(&cosets.probabilities[b]))
+                .unwrap();
+            let mut correction = SparseCorrection::new();
+            for &index in cosets.best[coset].1.iter() {
+                correction.extend(&sources[index].correction);
+            }
+            entries.push((defects, correction));
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        LookupTable {
+            max_weight,
+            error_sources: Self::pauli_error_sources(simulator_compact),
+            entries,
+        }
+    }
+
+    /// record the current error pattern and extend it with the error sources from `start`
+    #[allow(clippy::too_many_arguments)]
+    fn enumerate_patterns(
+        sources: &[PauliSource],
+        start: usize,
+        remaining_weight: usize,
+        defects: &[Position],
+        ratio: f64,
+        coset: usize,
+        pattern: &mut Vec<usize>,
+        syndromes: &mut HashMap<Vec<Position>, SyndromeCosets>,
+    ) {
+        let cosets = syndromes.entry(defects.to_vec()).or_insert_with(|| SyndromeCosets {
+            probabilities: [0.; 4],
+            best: std::array::from_fn(|_| (0., vec![])),
+        });
+        cosets.probabilities[coset] += ratio;
+        if ratio > cosets.best[coset].0 {
+            cosets.best[coset] = (ratio, pattern.clone());
+        }
+        if remaining_weight == 0 {
+            return;
+        }
+        for (index, source) in sources.iter().enumerate().skip(start) {
+            let next_defects = symmetric_difference(defects, &source.defects);
+            pattern.push(index);
+            Self::enumerate_patterns(
+                sources,
+                index + 1,
+                remaining_weight - 1,
+                &next_defects,
+                ratio * source.ratio,
+                coset ^ source.coset,
+                pattern,
+                syndromes,
+            );
+            pattern.pop();
+        }
+    }
+
+    /// decode given measurement results
+    #[allow(dead_code)]
+    pub fn decode(&mut self, sparse_measurement: &SparseMeasurement) -> (SparseCorrection, serde_json::Value) {
+        self.decode_with_erasure(sparse_measurement, &SparseErasures::new())
+    }
+
+    /// decode given measurement results and detected erasures; the `hit` of the runtime statistics is summed over the shots
+    /// by the benchmark into the `<lookup_table_hits>` and `<lookup_table_misses>` columns
+    pub fn decode_with_erasure(
+        &mut self,
+        sparse_measurement: &SparseMeasurement,
+        sparse_detected_erasures: &SparseErasures,
+    ) -> (SparseCorrection, serde_json::Value) {
+        let begin = Instant::now();
+        let correction = if sparse_detected_erasures.is_empty() {
+            self.table.get(&sparse_measurement.to_vec()).cloned()
+        } else {
+            None
+        };
+        let time_lookup = begin.elapsed().as_secs_f64();
+        match correction {
+            Some(correction) => (
+                correction,
+                json!({
+                    "hit": true,
+                    "time_lookup": time_lookup,
+                }),
+            ),
+            None => {
+                let (correction, fallback_runtime_statistics) = self
+                    .fallback_decoder
+                    .decode_with_erasure(sparse_measurement, sparse_detected_erasures);
+                (
+                    correction,
+                    json!({
+                        "hit": false,
+                        "time_lookup": time_lookup,
+                        "fallback": fallback_runtime_statistics,
+                    }),
+                )
+            }
+        }
+    }
+}
+
+/// the symmetric difference of two sorted lists of positions
+fn symmetric_difference(a: &[Position], b: &[Position]) -> Vec<Position> {
+    let mut result = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => {
+                result.push(a[i].clone());
+                i += 1;
+            }
+            std::cmp::Ordering::Greater => {
+                result.push(b[j].clone());
+                j += 1;
+            }
+            std::cmp::Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    result.extend(a[i..].iter().cloned());
+    result.extend(b[j..].iter().cloned());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::code_builder::*;
+    use super::super::decoder_mwpm::*;
+    use super::super::types::ErrorType::*;
+    use super::super::types::*;
+    use super::*;
+
+    #[test]
+    fn lookup_table_decoder_code_capacity() {
+        // cargo test lookup_table_decoder_code_capacity -- --nocapture
+        let d = 5;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.01;
+        // build simulator
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        // build noise model
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, p, p, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        // build decoder and save the table
+        let table_path = std::env::temp_dir().join("qecp_lookup_table_decoder_code_capacity.json");
+        let _ = fs::remove_file(&table_path);
+        let decoder_config = json!({"max_weight": 1, "table_path": table_path.to_str().unwrap()});
+        let build_decoder = |decoder_config: &serde_json::Value| {
+            let fallback_decoder =
+                GeneralDecoder::MWPM(MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false).unwrap());
+            LookupTableDecoder::new(&simulator, Arc::clone(&noise_model), decoder_config, fallback_decoder, 1)
+        };
+        let generated_decoder = build_decoder(&decoder_config).unwrap();
+        // the table is loaded from the file, and rejected if generated with a different max weight
+        let mut decoder = build_decoder(&decoder_config).unwrap();
+        assert_eq!(decoder.table.len(), generated_decoder.table.len());
+        for (defects, correction) in generated_decoder.table.iter() {
+            assert_eq!(decoder.table[defects].to_vec(), correction.to_vec());
+        }
+        assert!(build_decoder(&json!({"max_weight": 2, "table_path": table_path.to_str().unwrap()})).is_err());
+        // the table is also rejected if the error sources have the same count but different probabilities
+        let mut other_noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut other_noise_model, p, p, 2. * p, 0.);
+        simulator.compress_error_rates(&mut other_noise_model);
+        let other_noise_model = Arc::new(other_noise_model);
+        let fallback_decoder = GeneralDecoder::MWPM(
+            MWPMDecoder::new(&simulator, Arc::clone(&other_noise_model), &json!({}), 1, false).unwrap(),
+        );
+        let result = LookupTableDecoder::new(&simulator, other_noise_model, &decoder_config, fallback_decoder, 1);
+        assert!(result.is_err());
+        fs::remove_file(&table_path).unwrap();
+        // every single-qubit error is found in the table and corrected
+        let mut error_positions = vec![];
+        simulator_iter_real!(simulator, position, node, t => 0, {
+            if node.qubit_type == QubitType::Data {
+                error_positions.push(position.clone());
+            }
+        });
+        for position in error_positions.iter() {
+            for error in [X, Z, Y] {
+                simulator.clear_all_errors();
+                simulator.set_error_check(&noise_model, position, &error);
+                simulator.propagate_errors();
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                let (correction, runtime_statistics) = decoder.decode(&sparse_measurement);
+                assert_eq!(runtime_statistics["hit"], json!(true));
+                code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+                let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                assert!(!logical_i && !logical_j, "{error:?} error at {position} is not corrected");
+            }
+        }
+        // two errors are out of the table and decoded by the fallback decoder
+        simulator.clear_all_errors();
+        simulator.set_error_check(&noise_model, &pos!(0, 1, 1), &X);
+        simulator.set_error_check(&noise_model, &pos!(0, 5, 5), &X);
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        let (correction, runtime_statistics) = decoder.decode(&sparse_measurement);
+        assert_eq!(runtime_statistics["hit"], json!(false));
+        code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+        let (logical_i, logical_j) = simulator.validate_correction(&correction);
+        assert!(!logical_i && !logical_j);
+    }
+}
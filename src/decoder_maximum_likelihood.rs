@@ -0,0 +1,654 @@
+//! maximum-likelihood decoder for small codes
+//!
+//! Each noise model node contributes its single-qubit Pauli errors and its correlated two-qubit Pauli errors as two
+//! error factors, whose outcomes are mutually exclusive just like how the simulator samples them; each additional noise is
+//! another factor with a single outcome. The probability of each logical coset given the syndrome is then computed
+//! exactly by contracting the tensor network of the factors and the parity constraints. The factors are contracted in a
+//! greedy order that keeps few vertices open, and a vertex is closed, i.e. checked against the syndrome, right after its
+//! last factor; the number of states is thus exponential only in the number of open vertices, which is roughly a single
+//! round of stabilizer measurements. This is practical for small codes like d ≤ 5 with code-capacity or phenomenological
+//! noise, and serves as the ground truth of the other decoders. Erasure errors and cosmic-ray events are not modeled.
+//! Along with the coset probabilities, the most likely error of each state is tracked so that the correction is the most
+//! likely error in the most likely coset.
+//!
+
+use super::noise_model::*;
+use super::simulator::*;
+use super::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// the names of the logical cosets, indexed by `logical_i as usize | (logical_j as usize) << 1`
+const COSET_NAMES: [&str; 4] = ["I", "logical_i", "logical_j", "logical_ij"];
+
+#[derive(Debug, Clone)]
+pub struct MaximumLikelihoodDecoder {
+    /// the independent error factors, sorted by the contraction order
+    pub factors: Arc<Vec<ErrorFactor>>,
+    /// the vertex index of each possible defect, sorted by the position
+    pub vertex_indices: Arc<HashMap<Position, usize>>,
+    /// the vertices closed right after contracting each factor
+    pub closed_vertices: Arc<Vec<Vec<usize>>>,
+    /// save configuration for later usage
+    pub config: MaximumLikelihoodDecoderConfig,
+}
+
+/// a set of mutually exclusive error outcomes, e.g. the X, Y and Z errors at a noise model node
+#[derive(Debug, Clone)]
+pub struct ErrorFactor {
+    /// the union of the defect vertices of all the outcomes
+    pub vertices: Vec<usize>,
+    /// the outcomes with distinct defect vertices or logical effects
+    pub outcomes: Vec<ErrorOutcome>,
+}
+
+/// the errors of a factor with the same defect vertices and the same logical effect
+#[derive(Debug, Clone)]
+pub struct ErrorOutcome {
+    /// the defect vertices
+    pub vertices: Vec<usize>,
+    /// the logical effect, indexed as [`COSET_NAMES`]
+    pub coset: usize,
+    /// the total probability of these errors, which are mutually exclusive
+    pub probability: f64,
+    /// the correction of the most likely error
+    pub correction: Arc<SparseCorrection>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaximumLikelihoodDecoderConfig {
+    /// discard the states whose probability is below `truncation` times the total probability after each contraction,
+    /// which approximates the coset probabilities of larger codes; 0 means exact
+    #[serde(alias = "tr")] // abbreviation
+    #[serde(default = "maximum_likelihood_default_configs::truncation")]
+    pub truncation: f64,
+    /// the maximum number of open vertices during the contraction; there are `4 * 2^max_open_vertices` states of about
+    /// 48 bytes each (including the buffer of the previous states), i.e. 200MB per thread at the default value of 20
+    #[serde(alias = "mov")] // abbreviation
+    #[serde(default = "maximum_likelihood_default_configs::max_open_vertices")]
+    pub max_open_vertices: usize,
+}
+
+pub mod maximum_likelihood_default_configs {
+    pub fn truncation() -> f64 {
+        0.
+    }
+    pub fn max_open_vertices() -> usize {
+        20
+    }
+}
+
+/// the most likely error of a state, as a linked list of factor outcomes shared between states
+struct Witness {
+    factor: usize,
+    outcome: usize,
+    previous: Option<Rc<Witness>>,
+}
+
+impl MaximumLikelihoodDecoder {
+    /// create a new maximum-likelihood decoder with decoder configuration
+    pub fn new(
+        simulator: &Simulator,
+        noise_model: Arc<NoiseModel>,
+        decoder_configuration: &serde_json::Value,
+        _parallel: usize,
+    ) -> Result<Self, String> {
+        // read attribute of decoder configuration
+        let config: MaximumLikelihoodDecoderConfig =
+            serde_json::from_value(decoder_configuration.clone()).map_err(|error| error.to_string())?;
+        if !(0. ..1.).contains(&config.truncation) {
+            return Err(format!("truncation must be within [0, 1), found {}", config.truncation));
+        }
+        if config.max_open_vertices >= usize::BITS as usize - 2 {
+            return Err(format!("max_open_vertices {} is too large", config.max_open_vertices));
+        }
+        if Self::has_erasure(simulator, &noise_model) {
+            return Err("maximum-likelihood decoder doesn't support erasure errors yet".to_string());
+        }
+        // each group of mutually exclusive errors forms a factor
+        let mut simulator = simulator.clone();
+        let mut error_groups = Vec::<Vec<(SparseErrorPattern, f64)>>::new();
+        simulator_iter!(simulator, position, node, {
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            let mut error_group = vec![];
+            for error_type in ErrorType::all_possible_errors().into_iter() {
+                let p = noise_model_node.pauli_error_rates.error_rate(&error_type);
+                if p > 0. {
+                    let mut sparse_errors = SparseErrorPattern::new();
+                    sparse_errors.add(position.clone(), error_type);
+                    error_group.push((sparse_errors, p));
+                }
+            }
+            error_groups.push(error_group);
+            if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
+                let mut error_group = vec![];
+                for error_type in CorrelatedPauliErrorType::all_possible_errors().into_iter() {
+                    let p = correlated_pauli_error_rates.error_rate(&error_type);
+                    if p > 0. {
+                        let gate_peer = node
+                            .gate_peer
+                            .as_ref()
+                            .expect("correlated error must corresponds to a two-qubit gate");
+                        let mut sparse_errors = SparseErrorPattern::new();
+                        sparse_errors.add(position.clone(), error_type.my_error());
+                        sparse_errors.add((**gate_peer).clone(), error_type.peer_error());
+                        error_group.push((sparse_errors, p));
+                    }
+                }
+                error_groups.push(error_group);
+            }
+        });
+        for additional_noise in noise_model.additional_noise.iter() {
+            if additional_noise.probability > 0. && !additional_noise.pauli_errors.is_empty() {
+                error_groups.push(vec![(additional_noise.pauli_errors.clone(), additional_noise.probability)]);
+            }
+        }
+        // simulate each error and merge the errors of a factor by their defects and logical effects
+        let mut vertex_positions = HashSet::<Position>::new();
+        let mut outcome_groups = Vec::<Vec<(Vec<Position>, usize, f64, SparseCorrection)>>::new();
+        for error_group in error_groups.into_iter() {
+            let mut outcomes = Vec::<(Vec<Position>, usize, f64, SparseCorrection)>::new();
+            let mut best_probabilities = Vec::<f64>::new();
+            for (sparse_errors, p) in error_group.into_iter() {
+                let (sparse_correction, sparse_measurement, _) = simulator.fast_measurement_given_few_errors(&sparse_errors);
+                let defects = sparse_measurement.to_vec();
+                simulator.clear_all_errors();
+                let (logical_i, logical_j) = simulator.validate_correction(&sparse_correction);
+                let coset = logical_i as usize | (logical_j as usize) << 1;
+                if defects.is_empty() && coset == 0 {
+                    continue; // no effect, same as no error
+                }
+                vertex_positions.extend(defects.iter().cloned());
+                match outcomes.iter().position(|outcome| outcome.0 == defects && outcome.1 == coset) {
+                    Some(index) => {
+                        outcomes[index].2 += p;
+                        if p > best_probabilities[index] {
+                            best_probabilities[index] = p;
+                            outcomes[index].3 = sparse_correction;
+                        }
+                    }
+                    None => {
+                        outcomes.push((defects, coset, p, sparse_correction));
+                        best_probabilities.push(p);
+                    }
+                }
+            }
+            if !outcomes.is_empty() {
+                outcome_groups.push(outcomes);
+            }
+        }
+        simulator.clear_all_errors();
+        let mut vertex_positions: Vec<Position> = vertex_positions.into_iter().collect();
+        vertex_positions.sort_by_key(|position| (position.t, position.i, position.j));
+        let vertex_indices: HashMap<Position, usize> = vertex_positions
+            .iter()
+            .enumerate()
+            .map(|(vertex_index, position)| (position.clone(), vertex_index))
+            .collect();
+        let factors: Vec<ErrorFactor> = outcome_groups
+            .into_iter()
+            .map(|outcomes| {
+                let outcomes: Vec<ErrorOutcome> = outcomes
+                    .into_iter()
+                    .map(|(defects, coset, probability, correction)| {
+                        let mut vertices: Vec<usize> = defects.iter().map(|position| vertex_indices[position]).collect();
+                        vertices.sort();
+                        ErrorOutcome {
+                            vertices,
+                            coset,
+                            probability,
+                            correction: Arc::new(correction),
+                        }
+                    })
+                    .collect();
+                let vertices: BTreeSet<usize> =
+                    outcomes.iter().flat_map(|outcome| outcome.vertices.iter().cloned()).collect();
+                ErrorFactor {
+                    vertices: vertices.into_iter().collect(),
+                    outcomes,
+                }
+            })
+            .collect();
+        let factors = Self::contraction_order(factors, vertex_positions.len());
+        // close each vertex right after its last factor
+        let mut last_factors = vec![None; vertex_positions.len()];
+        for (factor_index, factor) in factors.iter().enumerate() {
+            for &vertex_index in factor.vertices.iter() {
+                last_factors[vertex_index] = Some(factor_index);
+            }
+        }
+        let mut closed_vertices = vec![vec![]; factors.len()];
+        for (vertex_index, last_factor) in last_factors.into_iter().enumerate() {
+            let last_factor = last_factor.expect("each vertex belongs to at least one factor");
+            closed_vertices[last_factor].push(vertex_index);
+        }
+        // the open vertices only depend on the contraction order, so the limit is checked once for all the syndromes
+        let max_open_vertices = Self::max_open_vertices(&factors, &closed_vertices, vertex_positions.len());
+        if max_open_vertices > config.max_open_vertices {
+            return Err(format!(
+                "{} open vertices exceed max_open_vertices {}, the code is too large for maximum-likelihood decoding",
+                max_open_vertices, config.max_open_vertices
+            ));
+        }
+        Ok(Self {
+            factors: Arc::new(factors),
+            vertex_indices: Arc::new(vertex_indices),
+            closed_vertices: Arc::new(closed_vertices),
+            config,
+        })
+    }
+
+    /// whether any error source of the noise model causes erasures, which are not modeled
+    fn has_erasure(simulator: &Simulator, noise_model: &NoiseModel) -> bool {
+        let mut has_erasure = noise_model
+            .additional_noise
+            .iter()
+            .any(|additional_noise| additional_noise.probability > 0. && !additional_noise.erasures.is_empty());
+        simulator_iter!(simulator, position, {
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            has_erasure |= noise_model_node.erasure_error_rate > 0.
+                || noise_model_node
+                    .correlated_erasure_error_rates
+                    .as_ref()
+                    .is_some_and(|correlated_erasure_error_rates| correlated_erasure_error_rates.error_probability() > 0.);
+        });
+        has_erasure
+    }
+
+    /// the maximum number of vertices open at the same time when contracting the factors in order
+    fn max_open_vertices(factors: &[ErrorFactor], closed_vertices: &[Vec<usize>], vertex_count: usize) -> usize {
+        let mut is_open = vec![false; vertex_count];
+        let mut open_vertices = 0;
+        let mut max_open_vertices = 0;
+        for (factor, closed_vertices) in factors.iter().zip(closed_vertices.iter()) {
+            for &vertex_index in factor.vertices.iter() {
+                if !is_open[vertex_index] {
+                    is_open[vertex_index] = true;
+                    open_vertices += 1;
+                }
+            }
+            max_open_vertices = max_open_vertices.max(open_vertices);
+            for &vertex_index in closed_vertices.iter() {
+                is_open[vertex_index] = false;
+                open_vertices -= 1;
+            }
+        }
+        max_open_vertices
+    }
+
+    /// greedily order the factors to keep the number of open vertices small: each step contracts the factor that opens
+    /// the fewest new vertices minus the vertices it closes, preferring the earliest vertices among the ties
+    fn contraction_order(mut factors: Vec<ErrorFactor>, vertex_count: usize) -> Vec<ErrorFactor> {
+        factors.sort_by_key(|factor| factor.vertices.clone());
+        let mut remaining = vec![0usize; vertex_count];
+        for factor in factors.iter() {
+            for &vertex_index in factor.vertices.iter() {
+                remaining[vertex_index] += 1;
+            }
+        }
+        let mut is_open = vec![false; vertex_count];
+        let mut is_contracted = vec![false; factors.len()];
+        let mut order = Vec::with_capacity(factors.len());
+        for _ in 0..factors.len() {
+            let cost = |factor: &ErrorFactor| -> isize {
+                let opened = factor.vertices.iter().filter(|&&vertex_index| !is_open[vertex_index]).count();
+                let closed = factor
+                    .vertices
+                    .iter()
+                    .filter(|&&vertex_index| remaining[vertex_index] == 1)
+                    .count();
+                opened as isize - closed as isize
+            };
+            let next = (0..factors.len())
+                .filter(|&factor_index| !is_contracted[factor_index])
+                .min_by_key(|&factor_index| (cost(&factors[factor_index]), factor_index))
+                .unwrap();
+            is_contracted[next] = true;
+            for &vertex_index in factors[next].vertices.iter() {
+                remaining[vertex_index] -= 1;
+                is_open[vertex_index] = remaining[vertex_index] > 0;
+            }
+            order.push(next);
+        }
+        let mut factors: Vec<Option<ErrorFactor>> = factors.into_iter().map(Some).collect();
+        order.into_iter().map(|index| factors[index].take().unwrap()).collect()
+    }
+
+    /// decode given measurement results
+    #[allow(dead_code)]
+    pub fn decode(&mut self, sparse_measurement: &SparseMeasurement) -> (SparseCorrection, serde_json::Value) {
+        self.decode_with_erasure(sparse_measurement, &SparseErasures::new())
+    }
+
+    /// decode given measurement results and detected erasures
+    pub fn decode_with_erasure(
+        &mut self,
+        sparse_measurement: &SparseMeasurement,
+        sparse_detected_erasures: &SparseErasures,
+    ) -> (SparseCorrection, serde_json::Value) {
+        assert!(
+            sparse_detected_erasures.is_empty(),
+            "maximum-likelihood decoder doesn't support erasure errors yet"
+        );
+        let begin = Instant::now();
+        let mut syndrome = vec![false; self.vertex_indices.len()];
+        for position in sparse_measurement.iter() {
+            let vertex_index = *self
+                .vertex_indices
+                .get(position)
+                .expect("measurement cannot happen at impossible position");
+            syndrome[vertex_index] = true;
+        }
+        let (coset_probabilities, best_errors, max_open_vertices) = self.contract(&syndrome);
+        let time_contraction = begin.elapsed().as_secs_f64();
+        // build correction from the most likely error in the most likely coset
+        let begin = Instant::now();
+        let total_probability: f64 = coset_probabilities.iter().sum();
+        assert!(total_probability > 0., "the syndrome is impossible under the noise model");
+        let coset = (0..4)
+            .max_by(|&a, &b| coset_probabilities[a].total_cmp(&coset_probabilities[b]))
+            .unwrap();
+        let mut correction = SparseCorrection::new();
+        let mut witness = best_errors[coset].as_ref();
+        while let Some(node) = witness {
+            correction.extend(&self.factors[node.factor].outcomes[node.outcome].correction);
+            witness = node.previous.as_ref();
+        }
+        let time_build_correction = begin.elapsed().as_secs_f64();
+        let coset_probabilities: serde_json::Map<String, serde_json::Value> = COSET_NAMES
+            .iter()
+            .zip(coset_probabilities.iter())
+            .map(|(name, probability)| (name.to_string(), json!(probability / total_probability)))
+            .collect();
+        (
+            correction,
+            json!({
+                "coset": COSET_NAMES[coset],
+                "coset_probabilities": coset_probabilities,
+                "max_open_vertices": max_open_vertices,
+                "time_contraction": time_contraction,
+                "time_build_correction": time_build_correction,
+            }),
+        )
+    }
+
+    /// contract all the factors given the syndrome, return the unnormalized probability and the most likely error of
+    /// each logical coset, and the maximum number of open vertices during the contraction
+    fn contract(&self, syndrome: &[bool]) -> ([f64; 4], [Option<Rc<Witness>>; 4], usize) {
+        // the dense states are indexed by the logical coset in the lowest 2 bits and the parity of the open vertices
+        let mut probabilities = vec![1., 0., 0., 0.];
+        let mut best_probabilities = vec![1., 0., 0., 0.];
+        let mut best_errors: Vec<Option<Rc<Witness>>> = vec![None; 4];
+        let mut vertex_slots: Vec<Option<usize>> = vec![None; syndrome.len()];
+        let mut free_slots = BTreeSet::<usize>::new();
+        let mut open_vertices = 0;
+        let mut max_open_vertices = 0;
+        for (factor_index, factor) in self.factors.iter().enumerate() {
+            for &vertex_index in factor.vertices.iter() {
+                vertex_slots[vertex_index].get_or_insert_with(|| {
+                    open_vertices += 1;
+                    free_slots.pop_first().unwrap_or_else(|| {
+                        // the parity of the new vertex is always even
+                        let slot = probabilities.len().trailing_zeros() as usize - 2;
+                        debug_assert!(slot < self.config.max_open_vertices, "checked when building the decoder");
+                        let size = probabilities.len() * 2;
+                        probabilities.resize(size, 0.);
+                        best_probabilities.resize(size, 0.);
+                        best_errors.resize(size, None);
+                        slot
+                    })
+                });
+            }
+            max_open_vertices = max_open_vertices.max(open_vertices);
+            // each state receives the probability from the states differing by the mask of each outcome
+            let masks: Vec<usize> = factor
+                .outcomes
+                .iter()
+                .map(|outcome| {
+                    outcome.vertices.iter().fold(outcome.coset, |mask, &vertex_index| {
+                        mask ^ 1 << (2 + vertex_slots[vertex_index].unwrap())
+                    })
+                })
+                .collect();
+            let p_none = 1. - factor.outcomes.iter().map(|outcome| outcome.probability).sum::<f64>();
+            let previous_probabilities = std::mem::replace(&mut probabilities, vec![0.; best_errors.len()]);
+            let previous_best_probabilities = std::mem::replace(&mut best_probabilities, vec![0.; best_errors.len()]);
+            let previous_best_errors = std::mem::replace(&mut best_errors, vec![None; previous_probabilities.len()]);
+            for index in 0..probabilities.len() {
+                let mut probability = previous_probabilities[index] * p_none;
+                let mut best = previous_best_probabilities[index] * p_none;
+                let mut best_outcome = None;
+                for (outcome_index, outcome) in factor.outcomes.iter().enumerate() {
+                    let source = index ^ masks[outcome_index];
+                    probability += previous_probabilities[source] * outcome.probability;
+                    if previous_best_probabilities[source] * outcome.probability > best {
+                        best = previous_best_probabilities[source] * outcome.probability;
+                        best_outcome = Some(outcome_index);
+                    }
+                }
+                probabilities[index] = probability;
+                best_probabilities[index] = best;
+                best_errors[index] = match best_outcome {
+                    Some(outcome_index) => Some(Rc::new(Witness {
+                        factor: factor_index,
+                        outcome: outcome_index,
+                        previous: previous_best_errors[index ^ masks[outcome_index]].clone(),
+                    })),
+                    None => previous_best_errors[index].clone(),
+                };
+            }
+            // close the vertices that have no more factors: the parity must match the syndrome
+            let mut closed_mask = 0;
+            let mut expected = 0;
+            for &vertex_index in self.closed_vertices[factor_index].iter() {
+                let slot = vertex_slots[vertex_index].take().unwrap();
+                free_slots.insert(slot);
+                open_vertices -= 1;
+                closed_mask |= 1 << (2 + slot);
+                if syndrome[vertex_index] {
+                    expected |= 1 << (2 + slot);
+                }
+            }
+            if closed_mask != 0 {
+                for index in 0..probabilities.len() {
+                    if index & closed_mask == 0 {
+                        let source = index | expected;
+                        probabilities[index] = probabilities[source];
+                        best_probabilities[index] = best_probabilities[source];
+                        best_errors[index] = best_errors[source].take();
+                    }
+                }
+                for index in (0..probabilities.len()).filter(|index| index & closed_mask != 0) {
+                    probabilities[index] = 0.;
+                    best_probabilities[index] = 0.;
+                    best_errors[index] = None;
+                }
+            }
+            if self.config.truncation > 0. {
+                let threshold = self.config.truncation * probabilities.iter().sum::<f64>();
+                for index in 0..probabilities.len() {
+                    if probabilities[index] < threshold {
+                        probabilities[index] = 0.;
+                        best_probabilities[index] = 0.;
+                        best_errors[index] = None;
+                    }
+                }
+            }
+        }
+        debug_assert_eq!(open_vertices, 0, "all the vertices must be closed");
+        let coset_probabilities = [probabilities[0], probabilities[1], probabilities[2], probabilities[3]];
+        let mut best_errors = best_errors.into_iter();
+        let best_errors = std::array::from_fn(|_| best_errors.next().unwrap());
+        (coset_probabilities, best_errors, max_open_vertices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::code_builder::*;
+    use super::super::fault_distance::*;
+    use super::super::noise_model_builder::*;
+    use super::super::simulator_compact::*;
+    use super::super::tool::*;
+    use super::super::types::ErrorType::*;
+    use super::*;
+
+    /// compare the decoder with brute force: each of `error_positions` suffers from one of the mutually exclusive `errors`
+    /// independently, where each error is given with its probability
+    fn maximum_likelihood_decoder_brute_force(
+        mut simulator: Simulator,
+        noise_model: Arc<NoiseModel>,
+        error_positions: &[Position],
+        errors: &[(ErrorType, f64)],
+    ) {
+        let mut decoder = MaximumLikelihoodDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1).unwrap();
+        let no_error_probability = 1. - errors.iter().map(|(_, probability)| probability).sum::<f64>();
+        // brute-force the coset probabilities of each syndrome
+        let mut brute_force = HashMap::<Vec<Position>, [f64; 4]>::new();
+        for pattern in 0..(errors.len() + 1).pow(error_positions.len() as u32) {
+            simulator.clear_all_errors();
+            let mut probability = 1.;
+            let mut remaining = pattern;
+            for position in error_positions.iter() {
+                let choice = remaining % (errors.len() + 1);
+                remaining /= errors.len() + 1;
+                if choice == 0 {
+                    probability *= no_error_probability;
+                } else {
+                    let (error, error_probability) = errors[choice - 1];
+                    simulator.set_error_check(&noise_model, position, &error);
+                    probability *= error_probability;
+                }
+            }
+            simulator.propagate_errors();
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            let (logical_i, logical_j) = simulator.validate_correction(&SparseCorrection::new());
+            let coset = logical_i as usize | (logical_j as usize) << 1;
+            brute_force.entry(sparse_measurement.to_vec()).or_insert([0.; 4])[coset] += probability;
+        }
+        for (defects, coset_probabilities) in brute_force.iter() {
+            let sparse_measurement = SparseMeasurement::new_set(defects.iter().cloned().collect());
+            let (correction, runtime_statistics) = decoder.decode(&sparse_measurement);
+            let total_probability: f64 = coset_probabilities.iter().sum();
+            for (coset, name) in COSET_NAMES.iter().enumerate() {
+                let probability = runtime_statistics["coset_probabilities"][name].as_f64().unwrap();
+                assert!((probability - coset_probabilities[coset] / total_probability).abs() < 1e-9);
+            }
+            // the correction must clear the syndrome and stay in the most likely coset
+            simulator.clear_all_errors();
+            for (position, error) in correction.iter() {
+                simulator.set_error_check(&noise_model, &pos!(0, position.i, position.j), error);
+            }
+            simulator.propagate_errors();
+            assert_eq!(simulator.generate_sparse_measurement().to_vec(), *defects);
+            let (logical_i, logical_j) = simulator.validate_correction(&SparseCorrection::new());
+            let coset = logical_i as usize | (logical_j as usize) << 1;
+            assert_eq!(json!(COSET_NAMES[coset]), runtime_statistics["coset"]);
+            // symmetric errors may tie the cosets up to rounding errors
+            assert!((0..4).all(|other| coset_probabilities[other] <= coset_probabilities[coset] * (1. + 1e-9)));
+        }
+    }
+
+    #[test]
+    fn maximum_likelihood_decoder_coset_probabilities() {
+        // cargo test maximum_likelihood_decoder_coset_probabilities -- --nocapture
+        let d = 3;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.1;
+        // build simulator with only X errors, so that all the error patterns can be enumerated
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, 0., 0., 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        let mut error_positions = vec![];
+        simulator_iter_real!(simulator, position, node, t => 0, {
+            if node.qubit_type == QubitType::Data {
+                error_positions.push(position.clone());
+            }
+        });
+        maximum_likelihood_decoder_brute_force(simulator, noise_model, &error_positions, &[(X, p)]);
+    }
+
+    #[test]
+    fn maximum_likelihood_decoder_depolarizing() {
+        // cargo test maximum_likelihood_decoder_depolarizing -- --nocapture
+        let d = 3;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.05;
+        // the X, Y and Z errors at the same qubit are mutually exclusive
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p / 3., p / 3., p / 3., 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        let mut error_positions = vec![];
+        simulator_iter!(simulator, position, {
+            if noise_model.get_node_unwrap(position).pauli_error_rates.error_probability() > 0. {
+                error_positions.push(position.clone());
+            }
+        });
+        assert_eq!(error_positions.len(), d * d);
+        maximum_likelihood_decoder_brute_force(
+            simulator,
+            noise_model,
+            &error_positions,
+            &[(X, p / 3.), (Z, p / 3.), (Y, p / 3.)],
+        );
+    }
+
+    #[test]
+    fn maximum_likelihood_decoder_invalid_config() {
+        // cargo test maximum_likelihood_decoder_invalid_config -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(0, 5, 5));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.01, 0.01, 0.01, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        for (decoder_config, message) in [
+            (json!({ "max_open_vertices": 2 }), "open vertices"),
+            (json!({ "max_open_vertices": 64 }), "max_open_vertices"),
+            (json!({ "truncation": 1 }), "truncation"),
+            (json!({ "unknown_field": 1 }), "unknown_field"),
+        ] {
+            let result = MaximumLikelihoodDecoder::new(&simulator, Arc::clone(&noise_model), &decoder_config, 1);
+            let error = result.unwrap_err();
+            println!("{decoder_config}: {error}");
+            assert!(error.contains(message));
+        }
+        // erasure errors are rejected when building the decoder instead of when decoding
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.01, 0.01, 0.01, 0.01);
+        simulator.compress_error_rates(&mut noise_model);
+        let result = MaximumLikelihoodDecoder::new(&simulator, Arc::new(noise_model), &json!({}), 1);
+        assert!(result.unwrap_err().contains("erasure"));
+    }
+
+    #[test]
+    fn maximum_likelihood_decoder_circuit_level() {
+        // cargo test maximum_likelihood_decoder_circuit_level -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(1, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel
+            .apply(&mut simulator, &mut noise_model, &json!({}), 0.001, 0.5, 0.)
+            .unwrap();
+        let noise_model = Arc::new(noise_model);
+        let decoder = MaximumLikelihoodDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1).unwrap();
+        // every single fault is corrected
+        let mut simulator_compact = SimulatorCompact::from_simulator(simulator, noise_model, 1);
+        let classes = fault_classes(&mut simulator_compact);
+        let general_decoder = GeneralDecoder::MaximumLikelihood(decoder);
+        let is_failed = |(logical_i, logical_j): (bool, bool)| logical_i || logical_j;
+        let result = enumerate_fault_configurations(&simulator_compact, &general_decoder, &classes, 1, 1, &is_failed);
+        println!("{} configurations, {} failing", result.configurations, result.failing);
+        assert_eq!(result.failing, 0, "failing error pattern: {:?}", result.failing_error_pattern);
+    }
+}
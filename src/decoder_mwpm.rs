@@ -5,13 +5,18 @@ use super::blossom_v;
 use super::complete_model_graph::*;
 use super::erasure_graph::*;
 use super::model_graph::*;
+use super::model_hypergraph::*;
 use super::noise_model::*;
 use super::serde_json;
 use super::simulator::*;
+use super::soft_output::*;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Instant;
 
+/// the matching of the defects, where a defect matched with the boundary has no peer
+pub type MatchedDefects = Vec<(Position, Option<Position>)>;
+
 /// MWPM decoder, initialized and cloned for multiple threads
 #[derive(Debug, Clone, Serialize)]
 pub struct MWPMDecoder {
@@ -25,6 +30,12 @@ pub struct MWPMDecoder {
     pub config: MWPMDecoderConfig,
     /// an immutably shared simulator that is used to change model graph on the fly for correcting erasure errors
     pub simulator: Arc<Simulator>,
+    /// the partner edges of each model graph edge, only built for correlated matching
+    #[serde(skip)]
+    pub model_graph_correlations: Option<Arc<ModelGraphCorrelations>>,
+    /// computes the complementary gap as the soft output, only built when `soft_output` is enabled
+    #[serde(skip)]
+    pub complementary_gap_solver: Option<Arc<ComplementaryGapSolver>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +56,15 @@ pub struct MWPMDecoderConfig {
     pub use_combined_probability: bool,
     #[serde(default = "mwpm_default_configs::log_matchings")]
     pub log_matchings: bool,
+    /// correlated matching: reweight the edges in the other decoding graph that share a hyperedge (e.g. a Y error) with the
+    /// matched edges and then match again, at the cost of building the model hypergraph and decoding twice
+    #[serde(alias = "ucm")] // abbreviation
+    #[serde(default = "mwpm_default_configs::use_correlated_matching")]
+    pub use_correlated_matching: bool,
+    /// report the complementary gap of each shot as `soft_output` in the runtime statistics, see [`crate::soft_output`]
+    #[serde(alias = "so")] // abbreviation
+    #[serde(default = "mwpm_default_configs::soft_output")]
+    pub soft_output: bool,
 }
 
 pub mod mwpm_default_configs {
@@ -61,6 +81,12 @@ pub mod mwpm_default_configs {
     pub fn log_matchings() -> bool {
         false
     }
+    pub fn use_correlated_matching() -> bool {
+        false
+    }
+    pub fn soft_output() -> bool {
+        false
+    }
 }
 
 impl MWPMDecoder {
@@ -71,9 +97,16 @@ impl MWPMDecoder {
         decoder_configuration: &serde_json::Value,
         parallel: usize,
         use_brief_edge: bool,
-    ) -> Self {
+    ) -> Result<Self, String> {
         // read attribute of decoder configuration
-        let config: MWPMDecoderConfig = serde_json::from_value(decoder_configuration.clone()).unwrap();
+        let config: MWPMDecoderConfig =
+            serde_json::from_value(decoder_configuration.clone()).map_err(|error| error.to_string())?;
+        if config.use_correlated_matching && config.precompute_complete_model_graph {
+            return Err(
+                "correlated matching changes the model graph on the fly; please disable `precompute_complete_model_graph`"
+                    .to_string(),
+            );
+        }
         // build model graph
         let mut simulator = simulator.clone();
         let mut model_graph = ModelGraph::new(&simulator);
@@ -85,6 +118,25 @@ impl MWPMDecoder {
             config.use_combined_probability,
             use_brief_edge,
         );
+        // build model hypergraph to find the correlated edges
+        let model_graph_correlations = if config.use_correlated_matching {
+            let mut model_hypergraph = ModelHypergraph::new(&simulator);
+            model_hypergraph.build(
+                &mut simulator,
+                Arc::clone(&noise_model),
+                &config.weight_function,
+                parallel,
+                config.use_combined_probability,
+                use_brief_edge,
+            );
+            Some(Arc::new(ModelGraphCorrelations::new(
+                &simulator,
+                &model_graph,
+                &model_hypergraph,
+            )))
+        } else {
+            None
+        };
         let model_graph = Arc::new(model_graph);
         // build erasure graph
         let mut erasure_graph = ErasureGraph::new(&simulator);
@@ -93,13 +145,20 @@ impl MWPMDecoder {
         // build complete model graph
         let mut complete_model_graph = CompleteModelGraph::new(&simulator, Arc::clone(&model_graph));
         complete_model_graph.precompute(&simulator, config.precompute_complete_model_graph, parallel);
-        Self {
+        let complementary_gap_solver = if config.soft_output {
+            Some(Arc::new(ComplementaryGapSolver::new(&simulator, &model_graph)))
+        } else {
+            None
+        };
+        Ok(Self {
             model_graph,
             erasure_graph,
             complete_model_graph,
             config,
             simulator: Arc::new(simulator),
-        }
+            model_graph_correlations,
+            complementary_gap_solver,
+        })
     }
 
     /// decode given measurement results
@@ -114,9 +173,18 @@ impl MWPMDecoder {
         sparse_measurement: &SparseMeasurement,
         sparse_detected_erasures: &SparseErasures,
     ) -> (SparseCorrection, serde_json::Value) {
-        if !sparse_detected_erasures.is_empty() {
-            assert!(!self.config.precompute_complete_model_graph, "if erasure happens, the precomputed complete graph is invalid; please disable `precompute_complete_model_graph` or `pcmg` in the decoder configuration");
-        }
+        let (correction, _matched_defects, runtime_statistics) =
+            self.decode_matching_with_erasure(sparse_measurement, sparse_detected_erasures);
+        (correction, runtime_statistics)
+    }
+
+    /// decode given measurement results and detected erasures, also returning the matching of the defects where
+    /// a defect matched with the boundary has no peer
+    pub fn decode_matching_with_erasure(
+        &mut self,
+        sparse_measurement: &SparseMeasurement,
+        sparse_detected_erasures: &SparseErasures,
+    ) -> (SparseCorrection, MatchedDefects, serde_json::Value) {
         let mut correction = SparseCorrection::new();
         // list nontrivial measurements to be matched
         let to_be_matched = sparse_measurement.to_vec();
@@ -124,18 +192,25 @@ impl MWPMDecoder {
         let mut time_blossom_v = 0.;
         let mut time_build_correction = 0.;
         let mut matching_edges: Vec<(Position, Position)> = Vec::with_capacity(0);
+        let mut matched_defects: MatchedDefects = Vec::new();
         if !to_be_matched.is_empty() {
             // println!{"to_be_matched: {:?}", to_be_matched};
             let begin = Instant::now();
-            // add the edges to the graph
             let m_len = to_be_matched.len(); // virtual boundary of `i` is `i + m_len`
-            let node_num = m_len * 2;
-            // Z (X) stabilizers are (fully) connected, boundaries are fully connected
-            // stabilizer to boundary is one-to-one connected
-            let mut weighted_edges = Vec::<(usize, usize, f64)>::new();
+
             // update model graph weights to consider erasure information
             let mut erasure_graph_modifier = ErasureGraphModifier::<f64>::new();
-            if !sparse_detected_erasures.is_empty() {
+            // with the precomputed complete model graph, the erased edges are overlaid on the precomputed shortest paths
+            // instead of changing the model graph, which would require running the all-pair Dijkstra's algorithm again
+            let mut erasure_shortest_paths = None;
+            if !sparse_detected_erasures.is_empty() && self.config.precompute_complete_model_graph {
+                let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.erasure_graph);
+                let erasure_overlay = self.complete_model_graph.erasure_overlay(erasure_edges);
+                erasure_shortest_paths = Some(
+                    self.complete_model_graph
+                        .erasure_shortest_paths(&to_be_matched, &erasure_overlay),
+                );
+            } else if !sparse_detected_erasures.is_empty() {
                 // if erasure exists, the model graph will be duplicated on demand
                 let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.erasure_graph);
                 let model_graph_mut = self.complete_model_graph.get_model_graph_mut();
@@ -167,32 +242,47 @@ impl MWPMDecoder {
                 }
                 self.complete_model_graph.model_graph_changed(&self.simulator);
             }
-            // invalidate previous cache to save memory
-            self.complete_model_graph.invalidate_previous_dijkstra();
-            for i in 0..m_len {
-                let position = &to_be_matched[i];
-                let (edges, boundary) = self.complete_model_graph.get_edges(position, &to_be_matched);
-                if let Some(weight) = boundary {
-                    // eprintln!{"boundary {} {} ", i, weight};
-                    weighted_edges.push((i, i + m_len, weight));
+            time_prepare_graph += begin.elapsed().as_secs_f64();
+            let mut matching = self.match_defects(
+                &to_be_matched,
+                erasure_shortest_paths.as_ref(),
+                &mut time_prepare_graph,
+                &mut time_blossom_v,
+            );
+            // correlated matching: reweight the partner edges of the matched edges and then match again
+            let mut correlation_modifier = ErasureGraphModifier::<f64>::new();
+            if let Some(model_graph_correlations) = self.model_graph_correlations.clone() {
+                let begin = Instant::now();
+                let mut matched_edges = Vec::new();
+                for i in 0..m_len {
+                    let j = matching[i];
+                    if j < i {
+                        let path = self.complete_model_graph.matching_path(&to_be_matched[i], &to_be_matched[j]);
+                        matched_edges.extend(path);
+                    } else if j >= m_len {
+                        matched_edges.extend(self.complete_model_graph.boundary_path(&to_be_matched[i]));
+                    }
                 }
-                for &(j, weight) in edges.iter() {
-                    if i < j {
-                        // remove duplicated edges
-                        // eprintln!{"edge {} {} {} ", i, j, weight};
-                        weighted_edges.push((i, j, weight));
+                let model_graph_mut = self.complete_model_graph.get_model_graph_mut();
+                for (position, next) in matched_edges.iter() {
+                    let edge = ModelGraphCorrelations::edge_of(position, next);
+                    for (partner, conditional_probability) in
+                        model_graph_correlations.partners.get(&edge).into_iter().flatten()
+                    {
+                        let weight = self.config.weight_function.weight_of(*conditional_probability);
+                        let original_weight = model_graph_edge_weight(model_graph_mut, partner);
+                        if weight < original_weight {
+                            set_model_graph_edge_weight(model_graph_mut, partner, weight);
+                            correlation_modifier.push_modified_edge(partner.clone(), original_weight);
+                        }
                     }
                 }
-                for j in (i + 1)..m_len {
-                    // virtual boundaries are always fully connected
-                    weighted_edges.push((i + m_len, j + m_len, 0.));
+                time_prepare_graph += begin.elapsed().as_secs_f64();
+                if correlation_modifier.has_modified_edges() {
+                    self.complete_model_graph.model_graph_changed(&self.simulator);
+                    matching = self.match_defects(&to_be_matched, None, &mut time_prepare_graph, &mut time_blossom_v);
                 }
             }
-            time_prepare_graph += begin.elapsed().as_secs_f64();
-            // run the Blossom algorithm
-            let begin = Instant::now();
-            let matching = blossom_v::safe_minimum_weight_perfect_matching(node_num, weighted_edges);
-            time_blossom_v += begin.elapsed().as_secs_f64();
             // build correction based on the matching
             let begin = Instant::now();
             for i in 0..m_len {
@@ -202,13 +292,23 @@ impl MWPMDecoder {
                     // only add correction if j < i, so that the same correction is not applied twice
                     // println!("match peer {:?} {:?}", to_be_matched[i], to_be_matched[j]);
                     let b = &to_be_matched[j];
-                    let matching_correction = self.complete_model_graph.build_correction_matching(a, b);
+                    let matching_correction = match &erasure_shortest_paths {
+                        Some(paths) => self.complete_model_graph.build_correction_erasure_path(paths, i, j),
+                        None => self.complete_model_graph.build_correction_matching(a, b),
+                    };
                     correction.extend(&matching_correction);
+                    matched_defects.push((a.clone(), Some(b.clone())));
                 } else if j >= m_len {
                     // matched with boundary
                     // println!("match boundary {:?}", to_be_matched[i]);
-                    let boundary_correction = self.complete_model_graph.build_correction_boundary(a);
+                    let boundary_correction = match &erasure_shortest_paths {
+                        Some(paths) => self
+                            .complete_model_graph
+                            .build_correction_erasure_path(paths, i, paths.boundary()),
+                        None => self.complete_model_graph.build_correction_boundary(a),
+                    };
                     correction.extend(&boundary_correction);
+                    matched_defects.push((a.clone(), None));
                 }
                 if self.config.log_matchings {
                     let peer_position = if j < i {
@@ -235,8 +335,19 @@ impl MWPMDecoder {
                 }
             }
             time_build_correction += begin.elapsed().as_secs_f64();
+            // recover the reweighted edges of correlated matching, in the reverse order of modification
+            if correlation_modifier.has_modified_edges() {
+                let model_graph_mut = self.complete_model_graph.get_model_graph_mut();
+                while correlation_modifier.has_modified_edges() {
+                    let (edge, weight) = correlation_modifier.pop_modified_edge();
+                    set_model_graph_edge_weight(model_graph_mut, &edge, weight);
+                }
+                if !erasure_graph_modifier.has_modified_edges() {
+                    self.complete_model_graph.model_graph_changed(&self.simulator);
+                }
+            }
             // recover the modified edges
-            if !sparse_detected_erasures.is_empty() {
+            if erasure_graph_modifier.has_modified_edges() {
                 let model_graph_mut = self.complete_model_graph.get_model_graph_mut();
                 while erasure_graph_modifier.has_modified_edges() {
                     let (erasure_edge, weight) = erasure_graph_modifier.pop_modified_edge();
@@ -269,6 +380,13 @@ impl MWPMDecoder {
             "time_blossom_v": time_blossom_v,
             "time_build_correction": time_build_correction,
         });
+        if let Some(complementary_gap_solver) = &self.complementary_gap_solver {
+            let begin = Instant::now();
+            let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.erasure_graph);
+            let soft_output = complementary_gap_solver.solve(&to_be_matched, &erasure_edges);
+            runtime_statistics["soft_output"] = json!(soft_output);
+            runtime_statistics["time_soft_output"] = json!(begin.elapsed().as_secs_f64());
+        }
         if self.config.log_matchings {
             let runtime_statistics = runtime_statistics.as_object_mut().unwrap();
             runtime_statistics.insert(
@@ -280,16 +398,103 @@ impl MWPMDecoder {
                 }]),
             );
         }
-        (correction, runtime_statistics)
+        (correction, matched_defects, runtime_statistics)
+    }
+
+    /// run the Blossom algorithm on the current model graph, or on the shortest paths with the erased edges if given;
+    /// the peer `j >= m_len` of the defect `i` means the boundary
+    fn match_defects(
+        &mut self,
+        to_be_matched: &[Position],
+        erasure_shortest_paths: Option<&ErasureShortestPaths>,
+        time_prepare_graph: &mut f64,
+        time_blossom_v: &mut f64,
+    ) -> Vec<usize> {
+        let begin = Instant::now();
+        let m_len = to_be_matched.len(); // virtual boundary of `i` is `i + m_len`
+        let node_num = m_len * 2;
+        // Z (X) stabilizers are (fully) connected, boundaries are fully connected
+        // stabilizer to boundary is one-to-one connected
+        let mut weighted_edges = Vec::<(usize, usize, f64)>::new();
+        // invalidate previous cache to save memory
+        self.complete_model_graph.invalidate_previous_dijkstra();
+        for i in 0..m_len {
+            let position = &to_be_matched[i];
+            let (edges, boundary) = match erasure_shortest_paths {
+                Some(paths) => {
+                    let distances = &paths.distances[i];
+                    let edges = (0..m_len)
+                        .filter(|&j| j != i && distances[j].is_finite())
+                        .map(|j| (j, distances[j]))
+                        .collect();
+                    let boundary = Some(distances[paths.boundary()]).filter(|weight| weight.is_finite());
+                    (edges, boundary)
+                }
+                None => self.complete_model_graph.get_edges(position, to_be_matched),
+            };
+            if let Some(weight) = boundary {
+                // eprintln!{"boundary {} {} ", i, weight};
+                weighted_edges.push((i, i + m_len, weight));
+            }
+            for &(j, weight) in edges.iter() {
+                if i < j {
+                    // remove duplicated edges
+                    // eprintln!{"edge {} {} {} ", i, j, weight};
+                    weighted_edges.push((i, j, weight));
+                }
+            }
+            for j in (i + 1)..m_len {
+                // virtual boundaries are always fully connected
+                weighted_edges.push((i + m_len, j + m_len, 0.));
+            }
+        }
+        *time_prepare_graph += begin.elapsed().as_secs_f64();
+        // run the Blossom algorithm
+        let begin = Instant::now();
+        let matching = blossom_v::safe_minimum_weight_perfect_matching(node_num, weighted_edges);
+        *time_blossom_v += begin.elapsed().as_secs_f64();
+        matching
+    }
+}
+
+/// the weight of an elected edge in the model graph
+fn model_graph_edge_weight(model_graph: &ModelGraph, edge: &ErasureEdge) -> f64 {
+    match edge {
+        ErasureEdge::Connection(position1, position2) => {
+            let node1 = model_graph.get_node_unwrap(position1);
+            node1.edges.get(position2).expect("neighbor must exist").weight
+        }
+        ErasureEdge::Boundary(position) => {
+            let node = model_graph.get_node_unwrap(position);
+            node.boundary.as_ref().expect("boundary must exist").weight
+        }
+    }
+}
+
+/// set the weight of an elected edge in the model graph, keeping the edge symmetric
+fn set_model_graph_edge_weight(model_graph: &mut ModelGraph, edge: &ErasureEdge, weight: f64) {
+    match edge {
+        ErasureEdge::Connection(position1, position2) => {
+            let node1 = model_graph.get_node_mut_unwrap(position1);
+            node1.edges.get_mut(position2).expect("neighbor must exist").weight = weight;
+            let node2 = model_graph.get_node_mut_unwrap(position2);
+            node2.edges.get_mut(position1).expect("neighbor must exist").weight = weight;
+        }
+        ErasureEdge::Boundary(position) => {
+            let node = model_graph.get_node_mut_unwrap(position);
+            node.boundary.as_mut().expect("boundary must exist").weight = weight;
+        }
     }
 }
 
-#[cfg(feature = "blossom_v")]
 #[cfg(test)]
 mod tests {
     use super::super::code_builder::*;
     use super::super::noise_model_builder::*;
+    use super::super::reproducible_rand::Xoroshiro128StarStar;
+    use super::super::types::ErrorType::*;
     use super::*;
+    use rand_core::SeedableRng;
 
     // 2022.6.16: mwpm decoder should correct this pattern because UF decoder does
     // {"[0][1][5]":"Z","[0][2][6]":"Z","[0][4][4]":"X","[0][5][7]":"X","[0][9][7]":"Y"}, {"erasures":["[0][1][3]","[0][1][5]","[0][2][6]","[0][4][4]","[0][5][7]","[0][6][6]","[0][9][7]"]}
@@ -307,7 +512,9 @@ mod tests {
         // build noise model
         let mut noise_model = NoiseModel::new(&simulator);
         let noise_model_builder = NoiseModelBuilder::ErasureOnlyPhenomenological;
-        noise_model_builder.apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe);
+        noise_model_builder
+            .apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe)
+            .unwrap();
         simulator.compress_error_rates(&mut noise_model);
         noise_model_sanity_check(&simulator, &noise_model).unwrap();
         let noise_model = Arc::new(noise_model);
@@ -319,7 +526,8 @@ mod tests {
             &decoder_config,
             1,
             false,
-        );
+        )
+        .unwrap();
         // load errors onto the simulator
         let sparse_error_pattern: SparseErrorPattern =
             serde_json::from_value(json!({"[0][1][5]":"Z","[0][2][6]":"Z","[0][4][4]":"X","[0][5][7]":"X","[0][9][7]":"Y"}))
@@ -350,4 +558,168 @@ mod tests {
         let (logical_i, logical_j) = simulator.validate_correction(&correction);
         assert!(!logical_i && !logical_j);
     }
+
+    #[test]
+    fn mwpm_decoder_erasure_precomputed_complete_model_graph() {
+        // cargo test mwpm_decoder_erasure_precomputed_complete_model_graph -- --nocapture
+        let d = 5;
+        let noisy_measurements = 2;
+        let p = 0.01;
+        let pe = 0.05;
+        // build simulator
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        // build noise model
+        let mut noise_model = NoiseModel::new(&simulator);
+        let noise_model_builder = NoiseModelBuilder::MixedPhenomenological;
+        noise_model_builder
+            .apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe)
+            .unwrap();
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        // build decoder
+        let mut mwpm_decoder = MWPMDecoder::new(
+            &Arc::new(simulator.clone()),
+            Arc::clone(&noise_model),
+            &json!({"pcmg": true}),
+            1,
+            false,
+        )
+        .unwrap();
+        simulator.set_rng(Xoroshiro128StarStar::seed_from_u64(0));
+        for _ in 0..20 {
+            simulator.generate_random_errors(&noise_model);
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            let sparse_detected_erasures = simulator.generate_sparse_detected_erasures();
+            // the shortest paths with erasures must be the same as precomputing on the model graph with 0-weight erased edges
+            let defects = sparse_measurement.to_vec();
+            let erasure_edges = sparse_detected_erasures.get_erasure_edges(&mwpm_decoder.erasure_graph);
+            let erasure_overlay = mwpm_decoder.complete_model_graph.erasure_overlay(erasure_edges.clone());
+            let paths = mwpm_decoder
+                .complete_model_graph
+                .erasure_shortest_paths(&defects, &erasure_overlay);
+            let mut model_graph = mwpm_decoder.model_graph.as_ref().clone();
+            for erasure_edge in erasure_edges.iter() {
+                set_model_graph_edge_weight(&mut model_graph, erasure_edge, 0.);
+            }
+            let mut complete_model_graph = CompleteModelGraph::new(&simulator, Arc::new(model_graph));
+            complete_model_graph.precompute(&simulator, true, 1);
+            for (i, defect) in defects.iter().enumerate() {
+                let (edges, boundary) = complete_model_graph.get_edges(defect, &defects);
+                for (j, weight) in edges.into_iter() {
+                    assert!((paths.distances[i][j] - weight).abs() < 1e-9);
+                }
+                let boundary_distance = paths.distances[i][paths.boundary()];
+                assert_eq!(boundary.is_some(), boundary_distance.is_finite());
+                assert!(boundary.map_or(true, |weight| (boundary_distance - weight).abs() < 1e-9));
+            }
+            // the correction must clear all the defects
+            let (correction, _runtime_statistics) =
+                mwpm_decoder.decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
+            code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+        }
+    }
+
+    #[test]
+    fn mwpm_decoder_correlated_matching() {
+        // cargo test mwpm_decoder_correlated_matching -- --nocapture
+        let d = 5;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.1;
+        // build simulator
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        // build noise model
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p / 3., p / 3., p / 3., 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        // load errors onto the simulator: the X components of the two Y errors are only likely together with their Z components
+        let sparse_error_pattern: SparseErrorPattern =
+            serde_json::from_value(json!({"[0][1][5]":"X","[0][2][4]":"Y","[0][3][3]":"Y"})).unwrap();
+        simulator
+            .load_sparse_error_pattern(&sparse_error_pattern, &noise_model)
+            .expect("success");
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        println!("sparse_measurement: {:?}", sparse_measurement);
+        for (decoder_config, expect_success) in [(json!({}), false), (json!({"use_correlated_matching": true}), true)] {
+            let mut mwpm_decoder = MWPMDecoder::new(
+                &Arc::new(simulator.clone()),
+                Arc::clone(&noise_model),
+                &decoder_config,
+                1,
+                false,
+            )
+            .unwrap();
+            let (correction, _runtime_statistics) = mwpm_decoder.decode(&sparse_measurement);
+            println!("{decoder_config} correction: {:?}", correction);
+            code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+            let (logical_i, logical_j) = simulator.validate_correction(&correction);
+            assert_eq!(!logical_i && !logical_j, expect_success);
+        }
+    }
+
+    #[test]
+    fn mwpm_decoder_correlated_matching_rejects_precomputed_graph() {
+        // cargo test mwpm_decoder_correlated_matching_rejects_precomputed_graph -- --nocapture
+        let d = 5;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.01;
+        // build simulator
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        // build noise model
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p / 3., p / 3., p / 3., 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let decoder_config = json!({"use_correlated_matching": true, "precompute_complete_model_graph": true});
+        let result = MWPMDecoder::new(&Arc::new(simulator), noise_model, &decoder_config, 1, false);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn mwpm_decoder_tied_weight_corrections() {
+        // cargo test mwpm_decoder_tied_weight_corrections -- --nocapture
+        let d = 5;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.01;
+        // build simulator
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        // build noise model: all the edges have the same weight, so most syndromes have several minimum-weight matchings
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, p, p, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        let mut error_positions = vec![];
+        simulator_iter!(simulator, position, _node, {
+            if noise_model.get_node_unwrap(position).pauli_error_rates.error_probability() > 0. {
+                error_positions.push(position.clone());
+            }
+        });
+        // build decoder
+        let mut mwpm_decoder =
+            MWPMDecoder::new(&Arc::new(simulator.clone()), Arc::clone(&noise_model), &json!({}), 1, false).unwrap();
+        // any two errors are within the code distance, so every minimum-weight correction must succeed
+        for error in [X, Z, Y] {
+            for (i, position_1) in error_positions.iter().enumerate() {
+                for position_2 in error_positions.iter().skip(i + 1) {
+                    simulator.clear_all_errors();
+                    simulator.set_error_check(&noise_model, position_1, &error);
+                    simulator.set_error_check(&noise_model, position_2, &error);
+                    simulator.propagate_errors();
+                    let sparse_measurement = simulator.generate_sparse_measurement();
+                    let (correction, _runtime_statistics) = mwpm_decoder.decode(&sparse_measurement);
+                    code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+                    let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                    assert!(!logical_i && !logical_j, "{error} {position_1} {position_2}");
+                }
+            }
+        }
+    }
 }
@@ -120,6 +120,8 @@ impl ParallelFusionDecoder {
             skip_decoding: config.skip_decoding,
             log_matchings: config.log_matchings,
             max_tree_size: usize::MAX,
+            use_correlated_matching: false,
+            soft_output: false,
         }, &mut simulator, noise_model, parallel, use_brief_edge);
         let partition_info = config.partition_config.clone().unwrap_or(PartitionConfig::new(adaptor.vertex_to_position_mapping.len())).info();
         let fusion_solver = fusion_blossom::mwpm_solver::SolverParallel::new(&adaptor.initializer, &partition_info, config.primal_dual_config.clone());
@@ -234,7 +236,9 @@ mod tests {
         // build noise model
         let mut noise_model = NoiseModel::new(&simulator);
         let noise_model_builder = NoiseModelBuilder::ErasureOnlyPhenomenological;
-        noise_model_builder.apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe);
+        noise_model_builder
+            .apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe)
+            .unwrap();
         simulator.compress_error_rates(&mut noise_model);
         noise_model_sanity_check(&simulator, &noise_model).unwrap();
         let noise_model = Arc::new(noise_model);
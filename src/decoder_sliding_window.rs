@@ -0,0 +1,321 @@
+//! sliding-window decoder for long memory experiments
+//!
+//! The spacetime decoding graph of a memory experiment grows linearly with the number of rounds, which makes the decoders
+//! run out of memory for thousands of rounds. This decoder instead wraps an existing decoder built on a window of
+//! `window_noisy_measurements` rounds and slides it through the experiment: in each window, the matched paths in the
+//! first `commit_rounds` rounds are committed to the correction, and those crossing into later rounds leave artificial
+//! defects behind, which are decoded together with the real defects in the next window. The last window is aligned with
+//! the final perfect measurement round and commits everything.
+//!
+
+use super::complete_model_graph::*;
+use super::model_graph::*;
+use super::noise_model::*;
+use super::serde_json;
+use super::simulator::*;
+use super::tool::GeneralDecoder;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// sliding-window decoder, initialized and cloned for multiple threads
+#[derive(Clone)]
+pub struct SlidingWindowDecoder {
+    /// the decoder of a single window, which must be able to report the matching of the defects
+    pub window_decoder: Box<GeneralDecoder>,
+    /// the complete model graph of a single window to find the paths of the matched defects, only built when the window
+    /// decoder doesn't keep one, see [`GeneralDecoder::complete_model_graph_mut`]
+    pub complete_model_graph: Option<CompleteModelGraph>,
+    /// the number of noisy measurement rounds of the whole experiment
+    pub noisy_measurements: usize,
+    /// the number of noisy measurement rounds of a single window
+    pub window_noisy_measurements: usize,
+    /// the number of rounds committed in each window
+    pub commit_rounds: usize,
+    /// the time interval between two measurement rounds
+    pub measurement_cycles: usize,
+}
+
+impl SlidingWindowDecoder {
+    /// create a sliding-window decoder, where `simulator` has `window_noisy_measurements` noisy measurement rounds and
+    /// `window_decoder` is built on it
+    pub fn new(
+        simulator: &Simulator,
+        noise_model: Arc<NoiseModel>,
+        window_decoder: GeneralDecoder,
+        noisy_measurements: usize,
+        commit_rounds: usize,
+        parallel: usize,
+        use_brief_edge: bool,
+    ) -> Result<Self, String> {
+        let window_noisy_measurements = simulator.height / simulator.measurement_cycles - 1;
+        if noisy_measurements < window_noisy_measurements {
+            return Err(format!(
+                "the experiment of {noisy_measurements} rounds is shorter than the window of {window_noisy_measurements} rounds"
+            ));
+        }
+        if commit_rounds == 0 || commit_rounds > window_noisy_measurements {
+            return Err(format!(
+                "the commit rounds must be within [1, {window_noisy_measurements}], now {commit_rounds}"
+            ));
+        }
+        let mut possible_erasure = false;
+        simulator_iter!(simulator, position, node, {
+            let peer = node
+                .gate_peer
+                .as_ref()
+                .map(|gate_peer| noise_model.get_node_unwrap(gate_peer));
+            possible_erasure |= noise_model.get_node_unwrap(position).possible_erasure(peer);
+        });
+        if possible_erasure {
+            return Err("sliding window decoding doesn't support erasure errors yet".to_string());
+        }
+        // the paths of the matched defects must be found on the same weights as the window decoder
+        let complete_model_graph = match &window_decoder {
+            GeneralDecoder::MWPM(_) => None,
+            #[cfg(feature = "fusion_blossom")]
+            GeneralDecoder::Fusion(fusion_decoder) => {
+                let mut simulator = simulator.clone();
+                let mut model_graph = ModelGraph::new(&simulator);
+                model_graph.build(
+                    &mut simulator,
+                    noise_model,
+                    &fusion_decoder.config.weight_function,
+                    parallel,
+                    fusion_decoder.config.use_combined_probability,
+                    use_brief_edge,
+                );
+                let mut complete_model_graph = CompleteModelGraph::new(&simulator, Arc::new(model_graph));
+                complete_model_graph.precompute(&simulator, false, parallel);
+                Some(complete_model_graph)
+            }
+            _ => return Err("sliding window decoding only supports `mwpm` and `fusion` decoders".to_string()),
+        };
+        Ok(Self {
+            window_decoder: Box::new(window_decoder),
+            complete_model_graph,
+            noisy_measurements,
+            window_noisy_measurements,
+            commit_rounds,
+            measurement_cycles: simulator.measurement_cycles,
+        })
+    }
+
+    /// decode given measurement results
+    #[allow(dead_code)]
+    pub fn decode(&mut self, sparse_measurement: &SparseMeasurement) -> (SparseCorrection, serde_json::Value) {
+        self.decode_with_erasure(sparse_measurement, &SparseErasures::new())
+    }
+
+    /// decode given measurement results and detected erasures
+    pub fn decode_with_erasure(
+        &mut self,
+        sparse_measurement: &SparseMeasurement,
+        sparse_detected_erasures: &SparseErasures,
+    ) -> (SparseCorrection, serde_json::Value) {
+        assert!(
+            sparse_detected_erasures.is_empty(),
+            "the decoding graph has no erasure error, which is checked when constructing the decoder"
+        );
+        let measurement_cycles = self.measurement_cycles;
+        let mut correction = SparseCorrection::new();
+        let mut defects: BTreeSet<Position> = sparse_measurement.iter().cloned().collect();
+        let mut windows = 0;
+        let mut time_window_decoder = 0.;
+        let mut time_build_correction = 0.;
+        // the window starts right after the `bias`-th round, all the defects before it are resolved
+        let mut bias = 0;
+        loop {
+            windows += 1;
+            let is_last = bias + self.window_noisy_measurements >= self.noisy_measurements;
+            if is_last {
+                bias = self.noisy_measurements - self.window_noisy_measurements;
+            }
+            let bias_t = bias * measurement_cycles;
+            // the last window also includes the final perfect measurement round
+            let window_end_t = if is_last {
+                usize::MAX
+            } else {
+                bias_t + self.window_noisy_measurements * measurement_cycles
+            };
+            let commit_end_t = if is_last {
+                usize::MAX
+            } else {
+                (self.commit_rounds + 1) * measurement_cycles
+            };
+            let window_measurement = SparseMeasurement::new_set(
+                defects
+                    .iter()
+                    .filter(|position| position.t <= window_end_t)
+                    .map(|position| pos!(position.t - bias_t, position.i, position.j))
+                    .collect(),
+            );
+            let begin = Instant::now();
+            let (_, matched_defects, _) = self
+                .window_decoder
+                .decode_matching_with_erasure(&window_measurement, &SparseErasures::new())
+                .expect("the window decoder is checked to report the matching when constructing the decoder");
+            time_window_decoder += begin.elapsed().as_secs_f64();
+            let complete_model_graph = match self.complete_model_graph.as_mut() {
+                Some(complete_model_graph) => complete_model_graph,
+                None => self.window_decoder.complete_model_graph_mut().unwrap(),
+            };
+            let model_graph = Arc::clone(&complete_model_graph.model_graph);
+            // commit the edges of the matched paths that touch the committed rounds
+            let begin = Instant::now();
+            let mut toggle = |position: &Position| {
+                let position = pos!(position.t + bias_t, position.i, position.j);
+                if !defects.remove(&position) {
+                    defects.insert(position);
+                }
+            };
+            for (position, peer) in matched_defects.iter() {
+                let path = match peer {
+                    Some(peer) => complete_model_graph.matching_path(position, peer),
+                    None => complete_model_graph.boundary_path(position),
+                };
+                for (position, next) in path.iter() {
+                    if position.t.min(next.t) >= commit_end_t {
+                        continue;
+                    }
+                    let model_graph_node = model_graph.get_node_unwrap(position);
+                    if next == position {
+                        // this is the boundary
+                        correction.extend(&model_graph_node.boundary.as_ref().unwrap().correction);
+                        toggle(position);
+                    } else {
+                        correction.extend(&model_graph_node.edges.get(next).unwrap().correction);
+                        toggle(position);
+                        toggle(next);
+                    }
+                }
+            }
+            time_build_correction += begin.elapsed().as_secs_f64();
+            if is_last {
+                debug_assert!(defects.is_empty(), "the last window must resolve all the defects");
+                break;
+            }
+            bias += self.commit_rounds;
+            debug_assert!(
+                defects.iter().all(|position| position.t > bias * measurement_cycles),
+                "the committed rounds must be resolved"
+            );
+        }
+        (
+            correction,
+            json!({
+                "to_be_matched": sparse_measurement.len(),
+                "windows": windows,
+                "time_window_decoder": time_window_decoder,
+                "time_build_correction": time_build_correction,
+            }),
+        )
+    }
+}
+
+/// the logical error rate per round of a memory experiment of `rounds` rounds, assuming the logical errors of each round
+/// are independent so that the experiment fails when an odd number of rounds fail
+pub fn logical_error_rate_per_round(logical_error_rate: f64, rounds: usize) -> f64 {
+    (1. - (1. - 2. * logical_error_rate).powf(1. / rounds as f64)) / 2.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::code_builder::*;
+    #[cfg(feature = "fusion_blossom")]
+    use super::super::decoder_fusion::*;
+    use super::super::decoder_mwpm::*;
+    use super::super::decoder_union_find::*;
+    use super::super::noise_model_builder::*;
+    use super::super::simulator_compact::*;
+    use super::*;
+
+    #[test]
+    fn sliding_window_decoder_single_faults() {
+        // cargo test sliding_window_decoder_single_faults -- --nocapture
+        let d = 3;
+        let p = 0.001;
+        let build_simulator = |noisy_measurements: usize| -> (Simulator, Arc<NoiseModel>) {
+            let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, d, d));
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::StimNoiseModel
+                .apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.)
+                .unwrap();
+            simulator.compress_error_rates(&mut noise_model);
+            (simulator, Arc::new(noise_model))
+        };
+        let window_noisy_measurements = 3;
+        let noisy_measurements = 8;
+        let (window_simulator, window_noise_model) = build_simulator(window_noisy_measurements);
+        let mut window_decoders = vec![GeneralDecoder::MWPM(
+            MWPMDecoder::new(&window_simulator, Arc::clone(&window_noise_model), &json!({}), 1, false).unwrap(),
+        )];
+        #[cfg(feature = "fusion_blossom")]
+        window_decoders.push(GeneralDecoder::Fusion(FusionDecoder::new(
+            &window_simulator,
+            Arc::clone(&window_noise_model),
+            &json!({}),
+            1,
+            false,
+        )));
+        let (simulator, noise_model) = build_simulator(noisy_measurements);
+        let mut simulator_compact = SimulatorCompact::from_simulator(simulator, noise_model, 1);
+        let faults = simulator_compact.faults();
+        for window_decoder in window_decoders.into_iter() {
+            let mut decoder = SlidingWindowDecoder::new(
+                &window_simulator,
+                Arc::clone(&window_noise_model),
+                window_decoder,
+                noisy_measurements,
+                1,
+                1,
+                false,
+            )
+            .unwrap();
+            // every single fault of the whole experiment must be corrected
+            for fault in faults.iter() {
+                simulator_compact.load_faults(&[*fault]);
+                let sparse_measurement = simulator_compact.generate_sparse_measurement();
+                let (correction, runtime_statistics) = decoder.decode(&sparse_measurement);
+                assert_eq!(runtime_statistics["windows"], json!(6));
+                let (logical_i, logical_j) = simulator_compact.validate_correction(&correction);
+                assert!(!logical_i && !logical_j, "fault {fault:?} is not corrected");
+            }
+        }
+    }
+
+    #[test]
+    fn sliding_window_decoder_configuration_errors() {
+        // cargo test sliding_window_decoder_configuration_errors -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(3, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel
+            .apply(&mut simulator, &mut noise_model, &json!({}), 0.001, 0.5, 0.)
+            .unwrap();
+        let noise_model = Arc::new(noise_model);
+        let new_sliding_window = |window_decoder: GeneralDecoder, noise_model: &Arc<NoiseModel>| {
+            SlidingWindowDecoder::new(&simulator, Arc::clone(noise_model), window_decoder, 8, 1, 1, false)
+        };
+        // the window decoder must report the matching of the defects
+        let union_find_decoder = UnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+        let result = new_sliding_window(GeneralDecoder::UnionFind(union_find_decoder), &noise_model);
+        assert!(result.err().unwrap().contains("only supports"));
+        // erasure errors are rejected instead of panicking when decoding
+        let mut erasure_simulator = simulator.clone();
+        let mut erasure_noise_model = NoiseModel::new(&erasure_simulator);
+        erasure_simulator.set_error_rates(&mut erasure_noise_model, 0.001, 0.001, 0.001, 0.001);
+        let erasure_noise_model = Arc::new(erasure_noise_model);
+        let mwpm_decoder = MWPMDecoder::new(&simulator, Arc::clone(&erasure_noise_model), &json!({}), 1, false).unwrap();
+        let result = new_sliding_window(GeneralDecoder::MWPM(mwpm_decoder), &erasure_noise_model);
+        assert!(result.err().unwrap().contains("erasure"));
+    }
+
+    #[test]
+    fn sliding_window_decoder_logical_error_rate_per_round() {
+        // cargo test sliding_window_decoder_logical_error_rate_per_round -- --nocapture
+        assert!((logical_error_rate_per_round(0.1, 1) - 0.1).abs() < 1e-12);
+        let per_round = logical_error_rate_per_round(0.1, 10);
+        // the experiment fails if an odd number of rounds fail
+        assert!(((1. - (1. - 2. * per_round).powi(10)) / 2. - 0.1).abs() < 1e-12);
+    }
+}
@@ -127,7 +127,8 @@ impl TailoredMWPMDecoder {
             }),
             parallel,
             use_brief_edge,
-        );
+        )
+        .expect("the tailored decoder never enables correlated matching");
         Self {
             tailored_model_graph,
             tailored_complete_model_graph,
@@ -384,7 +385,7 @@ impl TailoredMWPMDecoder {
                             .unwrap()
                             .as_array()
                             .unwrap()
-                            .get(0)
+                            .first()
                             .expect("since residual is not empty, mwpm should give at least one matching")
                             .as_object()
                             .unwrap()
@@ -729,7 +730,6 @@ impl TailoredMWPMDecoder {
     }
 }
 
-#[cfg(feature = "blossom_v")]
 #[cfg(test)]
 mod tests {
     use super::super::code_builder::*;
@@ -1247,7 +1247,9 @@ mod tests {
         simulator.set_error_rates(&mut noise_model, px, py, pz, 0.);
         let noise_model_builder = NoiseModelBuilder::TailoredScBellInitCircuit;
         let noise_model_configuration: serde_json::Value = json!({});
-        noise_model_builder.apply(&mut simulator, &mut noise_model, &noise_model_configuration, p, bias_eta, 0.);
+        noise_model_builder
+            .apply(&mut simulator, &mut noise_model, &noise_model_configuration, p, bias_eta, 0.)
+            .unwrap();
         simulator.compress_error_rates(&mut noise_model);
         noise_model_sanity_check(&simulator, &noise_model).unwrap();
         let noise_model = Arc::new(noise_model);
@@ -1307,7 +1309,9 @@ mod tests {
         simulator.set_error_rates(&mut noise_model, px, py, pz, 0.);
         let noise_model_builder = NoiseModelBuilder::TailoredScBellInitPhenomenological;
         let noise_model_configuration: serde_json::Value = json!({});
-        noise_model_builder.apply(&mut simulator, &mut noise_model, &noise_model_configuration, p, bias_eta, 0.);
+        noise_model_builder
+            .apply(&mut simulator, &mut noise_model, &noise_model_configuration, p, bias_eta, 0.)
+            .unwrap();
         simulator.compress_error_rates(&mut noise_model);
         noise_model_sanity_check(&simulator, &noise_model).unwrap();
         let noise_model = Arc::new(noise_model);
@@ -1379,7 +1383,9 @@ mod tests {
             simulator.set_error_rates(&mut noise_model, px, py, pz, 0.);
             let noise_model_builder = NoiseModelBuilder::TailoredScBellInitPhenomenological;
             let noise_model_configuration: serde_json::Value = json!({});
-            noise_model_builder.apply(&mut simulator, &mut noise_model, &noise_model_configuration, p, bias_eta, 0.);
+            noise_model_builder
+                .apply(&mut simulator, &mut noise_model, &noise_model_configuration, p, bias_eta, 0.)
+                .unwrap();
             simulator.compress_error_rates(&mut noise_model);
             noise_model_sanity_check(&simulator, &noise_model).unwrap();
             let noise_model = Arc::new(noise_model);
@@ -9,6 +9,7 @@ use super::model_graph::*;
 use super::noise_model::*;
 use super::serde_json;
 use super::simulator::*;
+use super::soft_output::*;
 use super::union_find::*;
 use crate::derive_more::{Deref, DerefMut};
 use crate::parking_lot::RwLock;
@@ -58,6 +59,9 @@ pub struct UnionFindDecoder {
     pub count_memory_access: usize, // use the same way to count as in AFS paper
     /// save configuration for later usage
     pub config: UnionFindDecoderConfig,
+    /// computes the complementary gap as the soft output, only built when `soft_output` is enabled
+    #[serde(skip)]
+    pub complementary_gap_solver: Option<Arc<ComplementaryGapSolver>>,
     /// internal cache used by iteration
     fusion_list: Vec<(usize, usize)>,
     /// internal variable that works like `odd_clusters_set: BTreeSet<usize>` but with constant performance
@@ -168,6 +172,10 @@ pub struct UnionFindDecoderConfig {
     #[serde(alias = "bsbc")] // abbreviation
     #[serde(default = "union_find_default_configs::benchmark_skip_building_correction")]
     pub benchmark_skip_building_correction: bool,
+    /// report the complementary gap of each shot as `soft_output` in the runtime statistics, see [`crate::soft_output`]
+    #[serde(alias = "so")] // abbreviation
+    #[serde(default = "mwpm_default_configs::soft_output")]
+    pub soft_output: bool,
 }
 
 pub mod union_find_default_configs {
@@ -318,6 +326,11 @@ impl UnionFindDecoder {
             idle_cluster_boundaries.push(vec![]);
         }
         let union_find = UnionFind::new(nodes.len());
+        let complementary_gap_solver = if config.soft_output {
+            Some(Arc::new(ComplementaryGapSolver::new(&simulator, &model_graph)))
+        } else {
+            None
+        };
         Self {
             model_graph,
             erasure_graph,
@@ -340,6 +353,7 @@ impl UnionFindDecoder {
             count_iteration: 0,
             count_memory_access: 0,
             config,
+            complementary_gap_solver,
             // internal caches
             fusion_list: Vec::new(),
             odd_clusters_set_active_timestamp: 0,
@@ -511,7 +525,7 @@ impl UnionFindDecoder {
                 for position in sparse_measurement.iter() {
                     let index = self.position_to_index[position];
                     let root = self.union_find.find(index);
-                    cluster_nodes.entry(root).or_insert_with(Vec::new);
+                    cluster_nodes.entry(root).or_default();
                     cluster_nodes.get_mut(&root).unwrap().push(index);
                 }
                 // then build correction based on each correction
@@ -551,23 +565,28 @@ impl UnionFindDecoder {
             }
             (begin.elapsed().as_secs_f64(), correction)
         };
-        (
-            correction,
-            json!({
-                "time_run_to_stable": time_run_to_stable,
-                "time_prepare_decoders": time_prepare_decoders,
-                "time_uf_grow_step": self.time_uf_grow_step,
-                "time_uf_grow": self.time_uf_grow,
-                "count_uf_grow": self.count_uf_grow,
-                "time_uf_merge": self.time_uf_merge,
-                "time_uf_update": self.time_uf_update,
-                "time_uf_remove": self.time_uf_remove,
-                "time_build_correction": time_build_correction,
-                "count_node_visited": self.count_node_visited,
-                "count_iteration": self.count_iteration,
-                "count_memory_access": self.count_memory_access,
-            }),
-        )
+        let mut runtime_statistics = json!({
+            "time_run_to_stable": time_run_to_stable,
+            "time_prepare_decoders": time_prepare_decoders,
+            "time_uf_grow_step": self.time_uf_grow_step,
+            "time_uf_grow": self.time_uf_grow,
+            "count_uf_grow": self.count_uf_grow,
+            "time_uf_merge": self.time_uf_merge,
+            "time_uf_update": self.time_uf_update,
+            "time_uf_remove": self.time_uf_remove,
+            "time_build_correction": time_build_correction,
+            "count_node_visited": self.count_node_visited,
+            "count_iteration": self.count_iteration,
+            "count_memory_access": self.count_memory_access,
+        });
+        if let Some(complementary_gap_solver) = &self.complementary_gap_solver {
+            let begin = Instant::now();
+            let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.erasure_graph);
+            let soft_output = complementary_gap_solver.solve(&sparse_measurement.to_vec(), &erasure_edges);
+            runtime_statistics["soft_output"] = json!(soft_output);
+            runtime_statistics["time_soft_output"] = json!(begin.elapsed().as_secs_f64());
+        }
+        (correction, runtime_statistics)
     }
 
     /// run single iterations until no non-terminating (odd and not yet touching boundary) clusters exist
@@ -1169,7 +1188,9 @@ mod tests {
         // build noise model
         let mut noise_model = NoiseModel::new(&simulator);
         let noise_model_builder = NoiseModelBuilder::ErasureOnlyPhenomenological;
-        noise_model_builder.apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe);
+        noise_model_builder
+            .apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe)
+            .unwrap();
         simulator.compress_error_rates(&mut noise_model);
         noise_model_sanity_check(&simulator, &noise_model).unwrap();
         let noise_model = Arc::new(noise_model);
@@ -1211,7 +1232,9 @@ mod tests {
         // build noise model
         let mut noise_model = NoiseModel::new(&simulator);
         let noise_model_builder = NoiseModelBuilder::ErasureOnlyPhenomenological;
-        noise_model_builder.apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe);
+        noise_model_builder
+            .apply(&mut simulator, &mut noise_model, &json!({}), p, 1., pe)
+            .unwrap();
         simulator.compress_error_rates(&mut noise_model);
         noise_model_sanity_check(&simulator, &noise_model).unwrap();
         let noise_model = Arc::new(noise_model);
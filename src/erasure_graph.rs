@@ -24,7 +24,7 @@ pub struct ErasureGraph {
 }
 
 /// an edge cause by an erasure is either connection between measurement nodes or to boundary
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, Hash)]
 pub enum ErasureEdge {
     Connection(Position, Position),
     Boundary(Position),
@@ -94,20 +94,12 @@ impl ErasureGraph {
             }
             let noise_model_node = noise_model.get_node_unwrap(position);
             // whether it's possible to have erasure error at this node
-            let possible_erasure_error =
-                noise_model_node.erasure_error_rate > 0. || noise_model_node.correlated_erasure_error_rates.is_some() || {
-                    let node = simulator.get_node_unwrap(position);
-                    if let Some(gate_peer) = node.gate_peer.as_ref() {
-                        let peer_noise_model_node = noise_model.get_node_unwrap(gate_peer);
-                        if let Some(correlated_erasure_error_rates) = &peer_noise_model_node.correlated_erasure_error_rates {
-                            correlated_erasure_error_rates.error_probability() > 0.
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    }
-                };
+            let node = simulator.get_node_unwrap(position);
+            let peer_noise_model_node = node
+                .gate_peer
+                .as_ref()
+                .map(|gate_peer| noise_model.get_node_unwrap(gate_peer));
+            let possible_erasure_error = noise_model_node.possible_erasure(peer_noise_model_node);
             if possible_erasure_error {
                 let mut erasure_edges = Vec::new();
                 for error in all_possible_errors.iter() {
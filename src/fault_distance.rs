@@ -0,0 +1,255 @@
+//! Fault Distance
+//!
+//! Enumerate all the combinations of a few faults of a [`SimulatorCompact`] and decode each of them, in order to find the
+//! minimum number of faults that causes a logical error with a given decoder. This is the circuit-level effective distance,
+//! which can be smaller than the code distance, e.g., because of hook errors in a bad gate schedule.
+//!
+//! Faults that are indistinguishable to the decoder (same defects and erasures) and have the same logical effect are merged
+//! into a [`FaultClass`], and only the configurations of distinct classes are enumerated. Two faults of the same class cancel
+//! each other's Pauli errors, so for Pauli noise the number of failing configurations at the fault distance is exact.
+//!
+//! The faults of an erasure error source are the alternative outcomes of a single event, so they are only merged with the
+//! faults of the same error source, and a configuration contains at most one of them.
+//!
+
+use crate::simulator::*;
+use crate::simulator_compact::*;
+use crate::tool::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// the faults that are indistinguishable to the decoder and have the same logical effect
+#[derive(Debug, Clone)]
+pub struct FaultClass {
+    /// a representative fault of this class
+    pub fault: CompactFault,
+    /// the number of faults in this class
+    pub multiplicity: usize,
+    /// the index of the erasure error source that all the faults of this class come from, `None` for Pauli error sources
+    pub erasure_source: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FaultWeightResult {
+    pub weight: usize,
+    /// the number of configurations of `weight` faults from distinct classes and distinct erasure error sources
+    pub configurations: usize,
+    /// the number of configurations that cause a logical error
+    pub failing: usize,
+    /// an error pattern that causes a logical error, if any
+    pub failing_error_pattern: Option<SparseErrorPattern>,
+}
+
+/// merge all the non-trivial faults of the compact simulator into classes
+pub fn fault_classes(simulator_compact: &mut SimulatorCompact) -> Vec<FaultClass> {
+    let mut classes = Vec::<FaultClass>::new();
+    let mut class_indices = HashMap::new();
+    for fault in simulator_compact.faults() {
+        simulator_compact.load_faults(&[fault]);
+        let defects = simulator_compact.generate_sparse_measurement().to_vec();
+        let erasures: Vec<Position> = simulator_compact
+            .generate_sparse_detected_erasures()
+            .iter()
+            .cloned()
+            .collect();
+        let (logical_i, logical_j) = simulator_compact.validate_correction(&SparseCorrection::new());
+        if defects.is_empty() && erasures.is_empty() && !logical_i && !logical_j {
+            continue; // a trivial fault never changes the outcome
+        }
+        let (error_source_index, _) = fault;
+        let erasure_source = match simulator_compact.error_sources[error_source_index] {
+            ErrorSource::Erasure { .. } => Some(error_source_index),
+            ErrorSource::Pauli { .. } => None,
+        };
+        let index = *class_indices
+            .entry((defects, erasures, logical_i, logical_j, erasure_source))
+            .or_insert_with(|| {
+                classes.push(FaultClass {
+                    fault,
+                    multiplicity: 0,
+                    erasure_source,
+                });
+                classes.len() - 1
+            });
+        classes[index].multiplicity += 1;
+    }
+    classes
+}
+
+/// call `visitor` on every increasing sequence of class indices that starts with `combination` and has `weight` elements
+/// in total, where no two classes come from the same erasure error source
+fn for_each_combination(
+    combination: &mut Vec<usize>,
+    classes: &[FaultClass],
+    weight: usize,
+    visitor: &mut impl FnMut(&[usize]),
+) {
+    if combination.len() == weight {
+        visitor(combination);
+        return;
+    }
+    let start = combination.last().map(|last| last + 1).unwrap_or(0);
+    for index in start..classes.len() {
+        if let Some(erasure_source) = classes[index].erasure_source {
+            if combination
+                .iter()
+                .any(|&other| classes[other].erasure_source == Some(erasure_source))
+            {
+                continue; // the outcomes of a single erasure event are mutually exclusive
+            }
+        }
+        combination.push(index);
+        for_each_combination(combination, classes, weight, visitor);
+        combination.pop();
+    }
+}
+
+/// decode every configuration of `weight` faults from distinct classes and distinct erasure error sources using `parallel` threads, where `is_failed`
+/// decides whether a pair of `(logical_i, logical_j)` is a logical error
+pub fn enumerate_fault_configurations(
+    simulator_compact: &SimulatorCompact,
+    general_decoder: &GeneralDecoder,
+    classes: &[FaultClass],
+    weight: usize,
+    parallel: usize,
+    is_failed: &(impl Fn((bool, bool)) -> bool + Sync),
+) -> FaultWeightResult {
+    assert!(weight > 0, "the weight must be positive");
+    let parallel = parallel.max(1);
+    let thread_results: Vec<FaultWeightResult> = std::thread::scope(|scope| {
+        let handlers: Vec<_> = (0..parallel)
+            .map(|thread_index| {
+                let mut simulator_compact = simulator_compact.clone();
+                let mut general_decoder = general_decoder.clone();
+                std::thread::Builder::new()
+                    .stack_size(128 * 1024 * 1024)
+                    .spawn_scoped(scope, move || {
+                        let mut result = FaultWeightResult {
+                            weight,
+                            configurations: 0,
+                            failing: 0,
+                            failing_error_pattern: None,
+                        };
+                        let mut visitor = |combination: &[usize]| {
+                            let faults: Vec<CompactFault> = combination.iter().map(|&index| classes[index].fault).collect();
+                            let configurations: usize =
+                                combination.iter().map(|&index| classes[index].multiplicity).product();
+                            simulator_compact.load_faults(&faults);
+                            let sparse_measurement = simulator_compact.generate_sparse_measurement();
+                            let sparse_detected_erasures = simulator_compact.generate_sparse_detected_erasures();
+                            let (correction, _) =
+                                general_decoder.decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
+                            result.configurations += configurations;
+                            if is_failed(simulator_compact.validate_correction(&correction)) {
+                                result.failing += configurations;
+                                if result.failing_error_pattern.is_none() {
+                                    result.failing_error_pattern = Some(simulator_compact.generate_sparse_error_pattern());
+                                }
+                            }
+                        };
+                        // each thread takes the combinations with a different first class
+                        for first in (thread_index..classes.len()).step_by(parallel) {
+                            for_each_combination(&mut vec![first], classes, weight, &mut visitor);
+                        }
+                        result
+                    })
+                    .unwrap()
+            })
+            .collect();
+        handlers.into_iter().map(|handler| handler.join().unwrap()).collect()
+    });
+    let mut result = FaultWeightResult {
+        weight,
+        configurations: 0,
+        failing: 0,
+        failing_error_pattern: None,
+    };
+    for thread_result in thread_results.into_iter() {
+        result.configurations += thread_result.configurations;
+        result.failing += thread_result.failing;
+        if result.failing_error_pattern.is_none() {
+            result.failing_error_pattern = thread_result.failing_error_pattern;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::*;
+    use clap::Parser;
+
+    fn fault_distance_of(arguments: &[&str]) -> String {
+        let parameters = FaultDistanceParameters::parse_from(
+            ["fault-distance"]
+                .iter()
+                .chain(arguments.iter())
+                .chain(["--code-type", "rotated-planar-code", "--parallel", "4"].iter()),
+        );
+        parameters.run().unwrap()
+    }
+
+    #[test]
+    fn fault_distance_code_capacity() {
+        // cargo test fault_distance_code_capacity -- --nocapture
+        let output = fault_distance_of(&["[3]", "[0]", "[0.01]", "--decoder", "mwpm"]);
+        println!("{output}");
+        assert!(output.contains("fault distance: 2"));
+        let output = fault_distance_of(&["[5]", "[0]", "[0.01]", "--decoder", "union-find", "--max-weight", "3"]);
+        println!("{output}");
+        assert!(output.contains("fault distance: 3"));
+    }
+
+    #[test]
+    fn fault_distance_circuit_level() {
+        // cargo test fault_distance_circuit_level -- --nocapture
+        let output = fault_distance_of(&[
+            "[3]",
+            "[1]",
+            "[0.001]",
+            "--decoder",
+            "union-find",
+            "--noise-model",
+            "stim-noise-model",
+            "--all-weights",
+            "--max-weight",
+            "2",
+        ]);
+        println!("{output}");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[1].split(' ').nth(2),
+            Some("0"),
+            "a single fault should never cause a logical error"
+        );
+        assert!(output.contains("fault distance: 2"));
+    }
+
+    #[test]
+    fn fault_distance_erasure() {
+        // cargo test fault_distance_erasure -- --nocapture
+        let output = fault_distance_of(&[
+            "[3]",
+            "[0]",
+            "[0]",
+            "--pes",
+            "[0.1]",
+            "--decoder",
+            "mwpm",
+            "--all-weights",
+            "--max-weight",
+            "3",
+        ]);
+        println!("{output}");
+        let configurations: Vec<usize> = output
+            .lines()
+            .skip(1)
+            .take(3)
+            .map(|line| line.split(' ').nth(1).unwrap().parse().unwrap())
+            .collect();
+        // each of the 9 erasure events has 4 outcomes: no detectable Pauli error, X, Y or Z
+        assert_eq!(configurations, vec![9 * 4, 36 * 4 * 4, 84 * 4 * 4 * 4]);
+        // erasures are corrected unless they cover a logical operator
+        assert!(output.contains("fault distance: 3"));
+    }
+}
@@ -37,6 +37,7 @@ extern crate urlencoding;
 #[macro_use]
 extern crate enum_dispatch;
 
+pub mod blossom_rs;
 pub mod blossom_v;
 pub mod cli;
 pub mod reproducible_rand;
@@ -45,7 +46,7 @@ pub mod tool;
 pub mod types;
 pub mod util;
 pub mod web;
-// pub mod distributed_uf_decoder;  TODO: migrate back
+pub mod distributed_uf_decoder;
 // pub mod fpga_generator;  TODO: migrate back
 // pub mod fast_benchmark;  TODO: migrate back
 pub mod code_builder;
@@ -53,6 +54,8 @@ pub mod simulator;
 #[macro_use]
 pub mod util_macros;
 pub mod complete_model_graph;
+pub mod decoder_bp_osd;
+pub mod decoder_distributed_union_find;
 #[cfg(feature = "fusion_blossom")]
 pub mod decoder_fusion;
 #[cfg(feature = "fusion_blossom")]
@@ -61,22 +64,30 @@ pub mod decoder_parallel_fusion;
 pub mod decoder_hyper_union_find;
 #[cfg(feature = "hyperion")]
 pub mod decoder_hyperion;
+pub mod decoder_lookup_table;
+pub mod decoder_maximum_likelihood;
 pub mod decoder_mwpm;
+pub mod decoder_sliding_window;
 pub mod decoder_tailored_mwpm;
 pub mod decoder_union_find;
 pub mod erasure_graph;
+pub mod fault_distance;
 pub mod model_graph;
 pub mod model_hypergraph;
 pub mod noise_model;
 pub mod noise_model_builder;
+pub mod noise_model_calibration;
 pub mod tailored_complete_model_graph;
 pub mod tailored_model_graph;
 pub mod union_find;
 pub mod visualize;
 #[cfg(feature = "python_binding")]
 use pyo3::prelude::*;
+pub mod simulator_batch;
 pub mod simulator_compact;
 pub mod simulator_file;
+pub mod soft_output;
+pub mod stim_circuit;
 
 #[cfg(feature = "python_binding")]
 #[pymodule]
@@ -141,6 +141,17 @@ pub enum WeightFunction {
     Unweighted,
 }
 
+impl WeightFunction {
+    /// the weight of an edge with probability `p`
+    pub fn weight_of(&self, p: f64) -> f64 {
+        match self {
+            Self::Autotune => weight_function::autotune(p),
+            Self::AutotuneImproved => weight_function::autotune_improved(p),
+            Self::Unweighted => weight_function::unweighted(p),
+        }
+    }
+}
+
 pub mod weight_function {
 
     pub fn autotune(p: f64) -> f64 {
@@ -290,20 +301,12 @@ impl ModelGraph {
             }
             let noise_model_node = noise_model.get_node_unwrap(position);
             // whether it's possible to have erasure error at this node
-            let possible_erasure_error =
-                noise_model_node.erasure_error_rate > 0. || noise_model_node.correlated_erasure_error_rates.is_some() || {
-                    let node = simulator.get_node_unwrap(position);
-                    if let Some(gate_peer) = node.gate_peer.as_ref() {
-                        let peer_noise_model_node = noise_model.get_node_unwrap(gate_peer);
-                        if let Some(correlated_erasure_error_rates) = &peer_noise_model_node.correlated_erasure_error_rates {
-                            correlated_erasure_error_rates.error_probability() > 0.
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    }
-                };
+            let node = simulator.get_node_unwrap(position);
+            let peer_noise_model_node = node
+                .gate_peer
+                .as_ref()
+                .map(|gate_peer| noise_model.get_node_unwrap(gate_peer));
+            let possible_erasure_error = noise_model_node.possible_erasure(peer_noise_model_node);
             for error in all_possible_errors.iter() {
                 let p = match error {
                     Either::Left(error_type) => noise_model_node.pauli_error_rates.error_rate(error_type),
@@ -2,6 +2,7 @@
 //!
 
 use super::either::Either;
+use super::erasure_graph::{ErasureEdge, ErasureGraph};
 use super::model_graph::*;
 use super::noise_model::*;
 use super::simulator::*;
@@ -15,7 +16,7 @@ use pyo3::prelude::*;
 use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::{Arc, Mutex};
 
 /// edges connecting two nontrivial measurements generated by a single error
@@ -465,8 +466,11 @@ impl ModelHypergraph {
         }
     }
 
-    #[cfg(feature = "hyperion")]
-    pub fn generate_mwpf_hypergraph(&self, max_weight: usize) -> (usize, Vec<HyperEdge>) {
+    /// scale the weights to integers within `[0, max_weight]`, returning the number of vertices and the vertices and weight
+    /// of each hyperedge in the same order as `self.weighted_edges`. The hyperedges only caused by erasures get a weight
+    /// larger than the sum of all the other weights, so that a minimum-weight solution never uses them unless they are
+    /// erased in the current shot, which sets their weights to 0, see [`Self::build_erasure_hyperedges`]
+    pub fn generate_integer_hypergraph(&self, max_weight: usize) -> (usize, Vec<(Vec<usize>, usize)>) {
         // scale all the edges
         let mut maximum_weight = 0.;
         for (_, hyperedge_group) in self.weighted_edges.iter() {
@@ -476,20 +480,91 @@ impl ModelHypergraph {
         }
         let mut weighted_edges = Vec::with_capacity(self.weighted_edges.len());
         for (defect_vertices, hyperedge_group) in self.weighted_edges.iter() {
+            let vertex_indices: Vec<_> = defect_vertices.0.iter().map(|x| self.vertex_indices[x]).collect();
             if hyperedge_group.hyperedge.probability > 0. {
-                // only add those possible edges; for erasures, handle later
                 let scaled_weight = hyperedge_group.hyperedge.weight * max_weight as f64 / maximum_weight;
                 let int_weight = scaled_weight.round();
                 assert!(int_weight.is_finite(), "weight must be normal");
                 assert!(int_weight >= 0., "weight must be non-negative");
                 assert!(int_weight <= max_weight as f64, "weight must be smaller than max weight");
-                let vertex_indices: Vec<_> = defect_vertices.0.iter().map(|x| self.vertex_indices[x]).collect();
-                weighted_edges.push(HyperEdge::new(vertex_indices, int_weight as usize));
+                weighted_edges.push((vertex_indices, Some(int_weight as usize)));
+            } else {
+                weighted_edges.push((vertex_indices, None));
             }
         }
+        let infinite_weight = weighted_edges.iter().filter_map(|(_, weight)| *weight).sum::<usize>() + 1;
+        let weighted_edges = weighted_edges
+            .into_iter()
+            .map(|(vertex_indices, weight)| (vertex_indices, weight.unwrap_or(infinite_weight)))
+            .collect();
         (self.vertex_positions.len(), weighted_edges)
     }
 
+    #[cfg(feature = "hyperion")]
+    pub fn generate_mwpf_hypergraph(&self, max_weight: usize) -> (usize, Vec<HyperEdge>) {
+        let (vertex_num, weighted_edges) = self.generate_integer_hypergraph(max_weight);
+        let weighted_edges = weighted_edges
+            .into_iter()
+            .map(|(vertex_indices, weight)| HyperEdge::new(vertex_indices, weight))
+            .collect();
+        (vertex_num, weighted_edges)
+    }
+
+    /// the hyperedges that an erasure at each position of the erasure graph can cause, by mapping each detectable Pauli
+    /// error of the erased qubit to the hyperedge with exactly the same defect vertices; unlike the edges of the erasure
+    /// graph, this includes the hyperedges of more than 2 vertices, e.g. a Y error in a CSS code. A hypergraph decoder
+    /// sets the weights of these hyperedges to 0 when erased
+    pub fn build_erasure_hyperedges(
+        &self,
+        simulator: &mut Simulator,
+        erasure_graph: &ErasureGraph,
+    ) -> HashMap<Position, Vec<usize>> {
+        let mut erasure_hyperedges = HashMap::new();
+        // clear the states in simulator including pauli, erasure errors and propagated errors
+        simulator.clear_all_errors();
+        simulator_iter!(simulator, position, {
+            if erasure_graph.get_node(position).is_none() {
+                continue;
+            }
+            let mut edge_indices = vec![];
+            for error_type in ErrorType::all_possible_errors().into_iter() {
+                let mut sparse_errors = SparseErrorPattern::new();
+                sparse_errors.add(position.clone(), error_type);
+                let (_sparse_correction, sparse_measurement, _) =
+                    simulator.fast_measurement_given_few_errors(&sparse_errors);
+                let sparse_measurement = sparse_measurement.to_vec();
+                if sparse_measurement.is_empty() {
+                    continue; // no way to detect it, ignore
+                }
+                let defect_vertices = DefectVertices::new(sparse_measurement);
+                let edge_index = *self.edge_indices.get(&defect_vertices).unwrap_or_else(|| {
+                    panic!("hyperedge of {error_type} error at {position} is missing, build with the same noise model")
+                });
+                if !edge_indices.contains(&edge_index) {
+                    edge_indices.push(edge_index);
+                }
+            }
+            if !edge_indices.is_empty() {
+                erasure_hyperedges.insert(position.clone(), edge_indices);
+            }
+        });
+        erasure_hyperedges
+    }
+
+    /// the hyperedges erased in a shot, given the result of [`Self::build_erasure_hyperedges`]
+    pub fn erased_hyperedges(
+        erasure_hyperedges: &HashMap<Position, Vec<usize>>,
+        sparse_detected_erasures: &SparseErasures,
+    ) -> Vec<usize> {
+        let mut erased = BTreeSet::new();
+        for position in sparse_detected_erasures.iter() {
+            if let Some(edge_indices) = erasure_hyperedges.get(position) {
+                erased.extend(edge_indices.iter().cloned());
+            }
+        }
+        erased.into_iter().collect()
+    }
+
     /// create json object for debugging and viewing
     pub fn to_json(&self, simulator: &Simulator) -> serde_json::Value {
         json!({
@@ -500,3 +575,231 @@ impl ModelHypergraph {
         })
     }
 }
+
+/// the model graph edges that are generated by the same hyperedge, e.g. the X and Z components of a Y error;
+/// correlated matching uses them to reweight the partner edges of a first matching before matching again
+#[derive(Debug, Clone, Default)]
+pub struct ModelGraphCorrelations {
+    /// the partner edges of each model graph edge, with the conditional probability of the partner given this edge
+    pub partners: HashMap<ErasureEdge, Vec<(ErasureEdge, f64)>>,
+}
+
+impl ModelGraphCorrelations {
+    /// find the hyperedges that decompose into exactly one edge in each of two decoding graphs
+    pub fn new(simulator: &Simulator, model_graph: &ModelGraph, model_hypergraph: &ModelHypergraph) -> Self {
+        let mut partners = HashMap::<ErasureEdge, Vec<(ErasureEdge, f64)>>::new();
+        for (defect_vertices, hyperedge_group) in model_hypergraph.weighted_edges.iter() {
+            let probability = hyperedge_group.hyperedge.probability;
+            if probability <= 0. {
+                continue;
+            }
+            // split the defect vertices by the decoding graph they belong to
+            let mut groups = Vec::<(QubitType, Vec<&Position>)>::new();
+            for position in defect_vertices.0.iter() {
+                let qubit_type = simulator.get_node_unwrap(position).qubit_type;
+                match groups.iter_mut().find(|(group_type, _)| *group_type == qubit_type) {
+                    Some((_, group)) => group.push(position),
+                    None => groups.push((qubit_type, vec![position])),
+                }
+            }
+            if groups.len() != 2 || groups.iter().any(|(_, group)| group.len() > 2) {
+                continue; // not decomposable into one edge in each of two decoding graphs
+            }
+            let edges: Vec<ErasureEdge> = groups
+                .iter()
+                .map(|(_, group)| Self::edge_of(group[0], group[group.len() - 1]))
+                .collect();
+            let edge_probabilities: Vec<Option<f64>> =
+                edges.iter().map(|edge| Self::probability_of(model_graph, edge)).collect();
+            if let [Some(probability_0), Some(probability_1)] = edge_probabilities[..] {
+                for (index, edge_probability) in [probability_0, probability_1].into_iter().enumerate() {
+                    let conditional_probability = (probability / edge_probability).min(0.5);
+                    partners
+                        .entry(edges[index].clone())
+                        .or_default()
+                        .push((edges[1 - index].clone(), conditional_probability));
+                }
+            }
+        }
+        Self { partners }
+    }
+
+    /// the model graph edge between two positions, where `position1 == position2` means the boundary edge of `position1`
+    pub fn edge_of(position1: &Position, position2: &Position) -> ErasureEdge {
+        match position1.cmp(position2) {
+            Ordering::Equal => ErasureEdge::Boundary(position1.clone()),
+            Ordering::Less => ErasureEdge::Connection(position1.clone(), position2.clone()),
+            Ordering::Greater => ErasureEdge::Connection(position2.clone(), position1.clone()),
+        }
+    }
+
+    /// the probability of an elected edge in the model graph, if it exists
+    pub fn probability_of(model_graph: &ModelGraph, edge: &ErasureEdge) -> Option<f64> {
+        match edge {
+            ErasureEdge::Connection(position1, position2) => {
+                let node = model_graph.get_node(position1).as_ref()?;
+                node.edges.get(position2).map(|edge| edge.probability)
+            }
+            ErasureEdge::Boundary(position) => {
+                let node = model_graph.get_node(position).as_ref()?;
+                node.boundary.as_ref().map(|boundary| boundary.probability)
+            }
+        }
+        .filter(|&probability| probability > 0.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::code_builder::*;
+    use super::super::noise_model_builder::*;
+    use super::*;
+
+    #[test]
+    fn model_hypergraph_integer_hypergraph() {
+        // cargo test model_hypergraph_integer_hypergraph -- --nocapture
+        let d = 3;
+        let noisy_measurements = 1;
+        let p = 0.01;
+        let pe = 0.1;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        // Pauli errors on the data qubits and erasure errors on the ancilla qubits, so the measurement errors are erasure-only
+        let mut noise_model = NoiseModel::new(&simulator);
+        let mut pauli_node = NoiseModelNode::new();
+        pauli_node.pauli_error_rates.error_rate_X = p;
+        pauli_node.pauli_error_rates.error_rate_Z = p;
+        let pauli_node = Arc::new(pauli_node);
+        let mut erasure_node = NoiseModelNode::new();
+        erasure_node.erasure_error_rate = pe;
+        let erasure_node = Arc::new(erasure_node);
+        let measurement_cycles = simulator.measurement_cycles;
+        simulator_iter_real!(simulator, position, node, {
+            if position.t >= simulator.height - measurement_cycles {
+                continue;
+            }
+            if position.t % measurement_cycles == 0 && node.qubit_type == QubitType::Data {
+                noise_model.set_node(position, Some(pauli_node.clone()));
+            }
+            if position.t % measurement_cycles == measurement_cycles - 1 && node.qubit_type != QubitType::Data {
+                noise_model.set_node(position, Some(erasure_node.clone()));
+            }
+        });
+        let noise_model = Arc::new(noise_model);
+        let mut model_hypergraph = ModelHypergraph::new(&simulator);
+        model_hypergraph.build(
+            &mut simulator,
+            Arc::clone(&noise_model),
+            &WeightFunction::AutotuneImproved,
+            1,
+            true,
+            false,
+        );
+        let max_weight = 1000;
+        let (vertex_num, weighted_edges) = model_hypergraph.generate_integer_hypergraph(max_weight);
+        assert_eq!(vertex_num, model_hypergraph.vertex_positions.len());
+        assert_eq!(weighted_edges.len(), model_hypergraph.weighted_edges.len());
+        let is_erasure_only = |edge_index: usize| model_hypergraph.weighted_edges[edge_index].1.hyperedge.probability == 0.;
+        let finite_weights: usize = (0..weighted_edges.len())
+            .filter(|&edge_index| !is_erasure_only(edge_index))
+            .map(|edge_index| weighted_edges[edge_index].1)
+            .sum();
+        let mut erasure_only_count = 0;
+        for (edge_index, (vertex_indices, weight)) in weighted_edges.iter().enumerate() {
+            let defect_vertices = &model_hypergraph.weighted_edges[edge_index].0 .0;
+            assert_eq!(vertex_indices.len(), defect_vertices.len());
+            if is_erasure_only(edge_index) {
+                // a minimum-weight solution never prefers an erasure-only hyperedge over all the other hyperedges
+                assert!(*weight > finite_weights);
+                erasure_only_count += 1;
+            } else {
+                assert!(*weight <= max_weight);
+            }
+        }
+        assert!(erasure_only_count > 0);
+        // the erasure-only hyperedges are the ones erased by the ancilla qubits
+        let mut erasure_graph = ErasureGraph::new(&simulator);
+        erasure_graph.build(&mut simulator, noise_model, 1);
+        let erasure_hyperedges = model_hypergraph.build_erasure_hyperedges(&mut simulator, &erasure_graph);
+        let mut sparse_detected_erasures = SparseErasures::new();
+        for position in erasure_hyperedges.keys() {
+            sparse_detected_erasures.insert_erasure(position);
+        }
+        let erased = ModelHypergraph::erased_hyperedges(&erasure_hyperedges, &sparse_detected_erasures);
+        let expected: Vec<usize> = (0..weighted_edges.len())
+            .filter(|&edge_index| is_erasure_only(edge_index))
+            .collect();
+        assert_eq!(erased, expected);
+        assert!(ModelHypergraph::erased_hyperedges(&erasure_hyperedges, &SparseErasures::new()).is_empty());
+    }
+
+    #[test]
+    fn model_hypergraph_erasure_hyperedges() {
+        // cargo test model_hypergraph_erasure_hyperedges -- --nocapture
+        let d = 3;
+        let noisy_measurements = 2;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::ErasureOnlyPhenomenological
+            .apply(&mut simulator, &mut noise_model, &json!({}), 0., 0.5, 0.1)
+            .unwrap();
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let mut model_hypergraph = ModelHypergraph::new(&simulator);
+        model_hypergraph.build(
+            &mut simulator,
+            Arc::clone(&noise_model),
+            &WeightFunction::AutotuneImproved,
+            1,
+            true,
+            false,
+        );
+        let mut erasure_graph = ErasureGraph::new(&simulator);
+        erasure_graph.build(&mut simulator, noise_model, 1);
+        let erasure_hyperedges = model_hypergraph.build_erasure_hyperedges(&mut simulator, &erasure_graph);
+        assert!(!erasure_hyperedges.is_empty());
+        // every erasure edge is found in the model hypergraph
+        let mut largest_hyperedge = 0;
+        simulator_iter!(simulator, position, {
+            if let Some(erasure_node) = erasure_graph.get_node(position) {
+                let edge_indices = erasure_hyperedges.get(position).cloned().unwrap_or_default();
+                let hyperedges: Vec<&Vec<Position>> = edge_indices
+                    .iter()
+                    .map(|&edge_index| &model_hypergraph.weighted_edges[edge_index].0 .0)
+                    .collect();
+                for erasure_edge in erasure_node.erasure_edges.iter() {
+                    let defect_vertices = match erasure_edge {
+                        ErasureEdge::Connection(position1, position2) => {
+                            DefectVertices::new(vec![position1.clone(), position2.clone()])
+                        }
+                        ErasureEdge::Boundary(position) => DefectVertices::new(vec![position.clone()]),
+                    };
+                    assert!(
+                        hyperedges.contains(&&defect_vertices.0),
+                        "erasure edge {erasure_edge:?} of {position}"
+                    );
+                }
+                largest_hyperedge =
+                    largest_hyperedge.max(hyperedges.iter().map(|hyperedge| hyperedge.len()).max().unwrap_or(0));
+            }
+        });
+        // Y errors on the data qubits in the bulk are hyperedges of 4 vertices, which are not in the erasure graph
+        assert_eq!(largest_hyperedge, 4);
+        // erasing a data qubit in the bulk erases the hyperedges of its X, Z and Y errors
+        let position = pos!(simulator.measurement_cycles, 2, 2);
+        let mut sparse_detected_erasures = SparseErasures::new();
+        sparse_detected_erasures.erasures.insert(position.clone());
+        let erased = ModelHypergraph::erased_hyperedges(&erasure_hyperedges, &sparse_detected_erasures);
+        let mut sizes: Vec<usize> = erased
+            .iter()
+            .map(|&edge_index| model_hypergraph.weighted_edges[edge_index].0 .0.len())
+            .collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![2, 2, 4], "erased hyperedges at {position}: {erased:?}");
+        // the positions without any possible erasure don't erase anything
+        sparse_detected_erasures.erasures.insert(pos!(0, 0, 0));
+        assert_eq!(
+            ModelHypergraph::erased_hyperedges(&erasure_hyperedges, &sparse_detected_erasures),
+            erased
+        );
+    }
+}
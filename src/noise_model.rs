@@ -21,6 +21,9 @@ pub struct NoiseModel {
     pub nodes: Vec<Vec<Vec<Option<Arc<NoiseModelNode>>>>>,
     /// additional noise that are unknown to the decoder, could be anything
     pub additional_noise: Vec<AdditionalNoise>,
+    /// high-energy impact events that are unknown to the decoder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cosmic_ray: Option<CosmicRayNoise>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +37,60 @@ pub struct AdditionalNoise {
     pub pauli_errors: SparseErrorPattern,
 }
 
+/// high-energy impact events (e.g. cosmic rays) that elevate the error rates of a region for several rounds: in each noisy
+/// measurement round, an event happens with probability `rate` centered at a uniformly random real qubit, and in the `k`-th
+/// round since then (`k = 0` being the round of the event) every node of the qubits within `radius` gets an extra
+/// depolarizing error with probability `error_rate * (1 - k / duration)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(feature = "python_binding", pyclass)]
+pub struct CosmicRayNoise {
+    /// probability of an event in each measurement round
+    pub rate: f64,
+    /// Euclidean distance in the lattice coordinates `(i, j)`
+    pub radius: f64,
+    /// number of measurement rounds affected by an event
+    pub duration: usize,
+    /// depolarizing probability at each node of the affected region in the round of the event
+    pub error_rate: f64,
+}
+
+impl CosmicRayNoise {
+    /// parse the optional `cosmic_ray` key of a noise model configuration
+    pub fn from_noise_model_configuration(noise_model_configuration: &serde_json::Value) -> Result<Option<Self>, String> {
+        match noise_model_configuration.get("cosmic_ray") {
+            Some(value) => {
+                let cosmic_ray: Self = serde_json::from_value(value.clone()).map_err(|e| format!("cosmic_ray: {e}"))?;
+                cosmic_ray.sanity_check()?;
+                Ok(Some(cosmic_ray))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn sanity_check(&self) -> Result<(), String> {
+        if !(0. ..=1.).contains(&self.rate) || !(0. ..=1.).contains(&self.error_rate) {
+            return Err(format!(
+                "cosmic_ray: rate and error_rate must be probabilities, found {self:?}"
+            ));
+        }
+        if self.radius.is_nan() || self.radius < 0. || self.duration == 0 {
+            return Err(format!(
+                "cosmic_ray: radius must be non-negative and duration positive, found {self:?}"
+            ));
+        }
+        Ok(())
+    }
+
+    /// the depolarizing probability in the `k`-th round since the event
+    pub fn error_rate_after(&self, k: usize) -> f64 {
+        if k >= self.duration {
+            return 0.;
+        }
+        self.error_rate * (1. - k as f64 / self.duration as f64)
+    }
+}
+
 impl QecpVisualizer for NoiseModel {
     fn component_info(&self, abbrev: bool) -> (String, serde_json::Value) {
         let name = "noise_model";
@@ -76,6 +133,22 @@ pub struct NoiseModelNode {
     pub correlated_pauli_error_rates: Option<CorrelatedPauliErrorRates>,
     #[serde(rename = "corr_pe")]
     pub correlated_erasure_error_rates: Option<CorrelatedErasureErrorRates>,
+    /// probability that the qubit leaks out of the computational subspace after the gate
+    #[serde(rename = "pl", default, skip_serializing_if = "is_zero")]
+    pub leakage_rate: f64,
+    /// probability that a leaked qubit returns to the computational subspace (in a maximally mixed state) after the gate
+    #[serde(rename = "ps", default, skip_serializing_if = "is_zero")]
+    pub seepage_rate: f64,
+    /// a leakage-reduction unit after the gate returns a leaked qubit to the computational subspace
+    #[serde(rename = "lru", default, skip_serializing_if = "std::ops::Not::not")]
+    pub leakage_reduction: bool,
+    /// a leaked qubit at this node is heralded, and reported as an erasure together with the gate peer that it randomizes
+    #[serde(rename = "hl", default, skip_serializing_if = "std::ops::Not::not")]
+    pub leakage_heralded: bool,
+}
+
+fn is_zero(value: &f64) -> bool {
+    *value == 0.
 }
 
 impl Default for NoiseModelNode {
@@ -94,6 +167,10 @@ impl NoiseModelNode {
             erasure_error_rate: 0.,
             correlated_pauli_error_rates: None,
             correlated_erasure_error_rates: None,
+            leakage_rate: 0.,
+            seepage_rate: 0.,
+            leakage_reduction: false,
+            leakage_heralded: false,
         }
     }
 
@@ -115,8 +192,29 @@ impl NoiseModelNode {
         {
             return false;
         }
+        if self.leakage_rate > 0. {
+            return false;
+        }
         true
     }
+
+    /// whether an erasure can be heralded at this node, either from the erasure errors or from the leakage of the qubit
+    /// itself or of its gate peer
+    pub fn possible_erasure(&self, peer: Option<&NoiseModelNode>) -> bool {
+        if self.erasure_error_rate > 0. || self.correlated_erasure_error_rates.is_some() || self.leakage_heralded {
+            return true;
+        }
+        match peer {
+            Some(peer) => {
+                peer.leakage_heralded
+                    || peer
+                        .correlated_erasure_error_rates
+                        .as_ref()
+                        .map_or(false, |rates| rates.error_probability() > 0.)
+            }
+            None => false,
+        }
+    }
 }
 
 #[cfg_attr(feature = "python_binding", cfg_eval)]
@@ -145,6 +243,7 @@ impl NoiseModel {
                 })
                 .collect(),
             additional_noise: vec![],
+            cosmic_ray: None,
         }
     }
 }
@@ -184,12 +283,25 @@ impl NoiseModel {
     pub fn set_node(&mut self, position: &Position, node: Option<Arc<NoiseModelNode>>) {
         self.nodes[position.t][position.i][position.j] = node;
     }
+
+    /// whether any node leaks, which only the full simulator can simulate because leakage is not independent between rounds
+    pub fn has_leakage(&self) -> bool {
+        self.nodes
+            .iter()
+            .flatten()
+            .flatten()
+            .flatten()
+            .any(|node| node.leakage_rate > 0.)
+    }
 }
 
 /// check if error rates are not zero at perfect measurement ranges or at (always) virtual nodes,
 /// also check for error rate constrains on virtual nodes
 pub fn noise_model_sanity_check(simulator: &Simulator, noise_model: &NoiseModel) -> Result<(), String> {
     let CodeSize { noisy_measurements, .. } = simulator.code_size;
+    if let Some(cosmic_ray) = &noise_model.cosmic_ray {
+        cosmic_ray.sanity_check()?;
+    }
     // check that no errors present in the final perfect measurement rounds
     let expected_height = simulator.measurement_cycles * (noisy_measurements + 1) + 1;
     if simulator.height != expected_height {
@@ -230,6 +342,12 @@ pub fn noise_model_sanity_check(simulator: &Simulator, noise_model: &NoiseModel)
                     position, noise_model_node.erasure_error_rate
                 ));
             }
+            if noise_model_node.leakage_rate > 0. {
+                return Err(format!(
+                    "virtual position at {} have non-zero leakage_rate: {}",
+                    position, noise_model_node.leakage_rate
+                ));
+            }
             if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
                 if correlated_pauli_error_rates.error_probability() > 0. {
                     return Err(format!(
@@ -276,5 +394,6 @@ pub(crate) fn register(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<NoiseModel>()?;
     m.add_class::<NoiseModelNode>()?;
     m.add_class::<AdditionalNoise>()?;
+    m.add_class::<CosmicRayNoise>()?;
     Ok(())
 }
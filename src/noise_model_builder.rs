@@ -4,13 +4,14 @@
 use super::clap::ValueEnum;
 use super::code_builder::*;
 use super::noise_model::*;
+use super::noise_model_calibration::*;
 use super::simulator::*;
 use super::types::*;
 use super::util_macros::*;
 use crate::serde::{Deserialize, Serialize};
 #[cfg(feature = "python_binding")]
 use pyo3::prelude::*;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 
 /// commonly used noise models
@@ -37,8 +38,17 @@ pub enum NoiseModelBuilder {
     /// Fault-tolerant weighted union-find decoding on the toric code
     DepolarizingNoise,
     /// the noise model in stim: after_clifford_depolarization, before_round_data_depolarization, before_measure_flip_probability, after_reset_flip_probability;
-    /// see https://github.com/quantumlib/Stim/blob/main/doc/python_api_reference_vDev.md#stim.Circuit.generated
+    /// see https://github.com/quantumlib/Stim/blob/main/doc/python_api_reference_vDev.md#stim.Circuit.generated;
+    /// optionally with leakage, see [`LeakageConfiguration`]
     StimNoiseModel,
+    /// per-qubit and per-coupler errors from the calibration data of a device, given as `calibration_file` (JSON or CSV)
+    /// or inline `calibration` in the noise model configuration; see [`crate::noise_model_calibration`]. it already includes
+    /// the decoherence of every qubit, so it cannot be composed with [`IdleNoise`]
+    FromCalibration,
+    /// superconducting-inspired noise model of arXiv:2108.10457: two-qubit gates depolarize with `p`, single-qubit gates
+    /// and idling in the gate layers with `p/10`, idling during the measurement and reset layers with `2p`, reset flips
+    /// with `2p` and measurement flips with `5p`; optionally with leakage, see [`LeakageConfiguration`]
+    SI1000,
 }
 
 #[cfg(feature = "python_binding")]
@@ -53,16 +63,17 @@ impl NoiseModelBuilder {
         noise_model_configuration: Option<PyObject>,
         bias_eta: f64,
         pe: f64,
-    ) {
+    ) -> PyResult<()> {
         let noise_model_configuration = noise_model_configuration
             .map(|v| crate::util::pyobject_to_json(v))
             .unwrap_or(json!({}));
         self.apply(simulator, noise_model, &noise_model_configuration, p, bias_eta, pe)
+            .map_err(pyo3::exceptions::PyValueError::new_err)
     }
 }
 
 impl NoiseModelBuilder {
-    /// apply noise model
+    /// apply noise model; returns an error if the cosmic-ray or idle noise configuration is invalid
     pub fn apply(
         &self,
         simulator: &mut Simulator,
@@ -71,7 +82,7 @@ impl NoiseModelBuilder {
         p: f64,
         bias_eta: f64,
         pe: f64,
-    ) {
+    ) -> Result<(), String> {
         // commonly used biased qubit error node
         let px = p / (1. + bias_eta) / 2.;
         let py = px;
@@ -92,6 +103,21 @@ impl NoiseModelBuilder {
         let pure_measurement_node = Arc::new(pure_measurement_node);
         // commonly used noiseless error node
         let noiseless_node = Arc::new(NoiseModelNode::new());
+        // cosmic-ray events and idle noise are independent of the noise model builder
+        noise_model.cosmic_ray = CosmicRayNoise::from_noise_model_configuration(noise_model_configuration)?;
+        let idle_noise = IdleNoise::from_noise_model_configuration(noise_model_configuration)?;
+        if idle_noise.is_some() && *self == Self::FromCalibration {
+            return Err(
+                "idle_noise cannot be composed with the calibration noise model, which already includes the decoherence"
+                    .to_string(),
+            );
+        }
+        let mut noise_model_configuration = noise_model_configuration.clone();
+        if let Some(config) = noise_model_configuration.as_object_mut() {
+            config.remove("cosmic_ray");
+            config.remove("idle_noise");
+        }
+        let noise_model_configuration = &noise_model_configuration;
         // noise model builder
         match self {
             Self::Phenomenological => {
@@ -820,6 +846,7 @@ impl NoiseModelBuilder {
                 if let Some(value) = config.remove("after_reset_flip_probability") {
                     after_reset_flip_probability = value.as_f64().expect("f64")
                 }
+                let leakage_configuration = LeakageConfiguration::from_config(config);
                 if !config.is_empty() {
                     panic!("unknown keys: {:?}", config.keys().collect::<Vec<&String>>());
                 }
@@ -891,6 +918,124 @@ impl NoiseModelBuilder {
                         }
                     }
                 });
+                leakage_configuration.apply(simulator, noise_model);
+            }
+            Self::FromCalibration => {
+                assert_eq!(pe, 0., "calibration noise model doesn't support erasure errors");
+                let mut config_cloned = noise_model_configuration.clone();
+                let config = config_cloned
+                    .as_object_mut()
+                    .expect("noise_model_configuration must be JSON object");
+                let calibration = match (config.remove("calibration_file"), config.remove("calibration")) {
+                    (Some(filepath), None) => {
+                        Calibration::from_file(filepath.as_str().expect("calibration_file must be a string"))
+                    }
+                    (None, Some(calibration)) => serde_json::from_value(calibration).map_err(|e| format!("{e}")),
+                    _ => panic!("exactly one of `calibration_file` and `calibration` should be given"),
+                };
+                if !config.is_empty() {
+                    panic!("unknown keys: {:?}", config.keys().collect::<Vec<&String>>());
+                }
+                calibration
+                    .and_then(|calibration| calibration.apply(simulator, noise_model))
+                    .unwrap_or_else(|error| panic!("invalid calibration: {error}"));
+            }
+            Self::SI1000 => {
+                assert_eq!(bias_eta, 0.5, "bias not supported yet, please use the default value 0.5");
+                assert_eq!(pe, 0., "SI1000 noise model doesn't support erasure errors");
+                let mut config_cloned = noise_model_configuration.clone();
+                let config = config_cloned
+                    .as_object_mut()
+                    .expect("noise_model_configuration must be JSON object");
+                let leakage_configuration = LeakageConfiguration::from_config(config);
+                if !config.is_empty() {
+                    panic!("unknown keys: {:?}", config.keys().collect::<Vec<&String>>());
+                }
+                assert!(
+                    simulator.measurement_cycles > 2,
+                    "SI1000 noise model requires a circuit-level simulator"
+                );
+                let depolarize = |probability: f64| PauliErrorRates {
+                    error_rate_X: probability / 3.,
+                    error_rate_Y: probability / 3.,
+                    error_rate_Z: probability / 3.,
+                };
+                // flip the outcome of the initialization or measurement in the basis of the gate
+                let flip = |gate_type: GateType, probability: f64| match gate_type {
+                    GateType::InitializeX | GateType::MeasureX => PauliErrorRates {
+                        error_rate_X: 0.,
+                        error_rate_Y: 0.,
+                        error_rate_Z: probability,
+                    },
+                    _ => PauliErrorRates {
+                        error_rate_X: probability,
+                        error_rate_Y: 0.,
+                        error_rate_Z: 0.,
+                    },
+                };
+                let measurement_cycles = simulator.measurement_cycles;
+                // the circuit is periodic, so nodes are shared among the measurement rounds
+                let mut cached_nodes = HashMap::<(usize, usize, usize), Arc<NoiseModelNode>>::new();
+                simulator_iter_real!(simulator, position, node, {
+                    // first clear error rate
+                    noise_model.set_node(position, Some(noiseless_node.clone()));
+                    if position.t == 0 || position.t >= simulator.height - measurement_cycles {
+                        // no error on the top, as a perfect measurement round
+                        continue;
+                    }
+                    let stage = position.t % measurement_cycles;
+                    if stage == 0 && node.qubit_type != QubitType::Data {
+                        // the ancilla is reset in the next layer
+                        continue;
+                    }
+                    let error_node = cached_nodes.entry((position.i, position.j, stage)).or_insert_with(|| {
+                        let mut pauli_error_rates = match stage {
+                            0 | 1 if node.qubit_type == QubitType::Data => {
+                                // data qubits idle during the measurement and the reset of the ancilla qubits
+                                depolarize(2. * p)
+                            }
+                            1 => {
+                                let reset_flip = flip(node.gate_type, 2. * p);
+                                if node.gate_type == GateType::InitializeX {
+                                    // the Hadamard gate is folded into the initialization
+                                    compose_pauli_error_rates(&reset_flip, &depolarize(p / 10.))
+                                } else {
+                                    reset_flip
+                                }
+                            }
+                            _ => {
+                                if node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual {
+                                    // the two-qubit depolarizing error is added below
+                                    PauliErrorRates::default()
+                                } else {
+                                    depolarize(p / 10.)
+                                }
+                            }
+                        };
+                        let mut error_node = NoiseModelNode::new();
+                        if stage > 1 && node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual {
+                            let peer = node.gate_peer.as_ref().unwrap();
+                            if (position.i, position.j) < (peer.i, peer.j) {
+                                // the correlated error is added only once, on one of the two qubits; 15 possible errors equally probable
+                                error_node.correlated_pauli_error_rates =
+                                    Some(CorrelatedPauliErrorRates::default_with_probability(p / 15.));
+                            }
+                        }
+                        if stage == measurement_cycles - 1 && node.qubit_type != QubitType::Data {
+                            let measurement = simulator.get_node_unwrap(&pos!(position.t + 1, position.i, position.j));
+                            if measurement.gate_type == GateType::MeasureX {
+                                // the Hadamard gate is folded into the measurement
+                                pauli_error_rates = compose_pauli_error_rates(&pauli_error_rates, &depolarize(p / 10.));
+                            }
+                            pauli_error_rates =
+                                compose_pauli_error_rates(&pauli_error_rates, &flip(measurement.gate_type, 5. * p));
+                        }
+                        error_node.pauli_error_rates = pauli_error_rates;
+                        Arc::new(error_node)
+                    });
+                    noise_model.set_node(position, Some(error_node.clone()));
+                });
+                leakage_configuration.apply(simulator, noise_model);
             }
             Self::DepolarizingNoise => {
                 let mut config_cloned = noise_model_configuration.clone();
@@ -963,6 +1108,10 @@ impl NoiseModelBuilder {
                 });
             }
         }
+        if let Some(idle_noise) = idle_noise {
+            idle_noise.apply(simulator, noise_model);
+        }
+        Ok(())
     }
 
     /// check as strictly as possible, given the user specified json noise model description
@@ -1061,6 +1210,201 @@ impl NoiseModelBuilder {
     }
 }
 
+/// the original node and the modified node in a cache keyed by the address of the original node
+type CachedNoiseModelNode = (Arc<NoiseModelNode>, Arc<NoiseModelNode>);
+
+/// leakage on top of a circuit-level noise model, given by optional keys in the noise model configuration
+#[derive(Debug, Clone, Default)]
+pub struct LeakageConfiguration {
+    /// `leakage_rate`: probability of each qubit to leak after a two-qubit gate
+    pub leakage_rate: f64,
+    /// `seepage_rate`: probability of a leaked qubit to return to the computational subspace in each layer
+    pub seepage_rate: f64,
+    /// `leakage_reduction_cycles`: a leakage-reduction unit on the data qubits in the measurement layer of every such
+    /// number of measurement rounds; 0 means no leakage-reduction unit. ancilla qubits are returned by initialization
+    pub leakage_reduction_cycles: usize,
+    /// `leakage_heralded`: leakage is heralded as erasures
+    pub leakage_heralded: bool,
+}
+
+impl LeakageConfiguration {
+    /// take the leakage keys out of the noise model configuration
+    pub fn from_config(config: &mut serde_json::Map<String, serde_json::Value>) -> Self {
+        let mut leakage_configuration = Self::default();
+        if let Some(value) = config.remove("leakage_rate") {
+            leakage_configuration.leakage_rate = value.as_f64().expect("f64");
+        }
+        if let Some(value) = config.remove("seepage_rate") {
+            leakage_configuration.seepage_rate = value.as_f64().expect("f64");
+        }
+        if let Some(value) = config.remove("leakage_reduction_cycles") {
+            leakage_configuration.leakage_reduction_cycles = value.as_u64().expect("u64") as usize;
+        }
+        if let Some(value) = config.remove("leakage_heralded") {
+            leakage_configuration.leakage_heralded = value.as_bool().expect("bool");
+        }
+        leakage_configuration
+    }
+
+    /// add leakage to the noise model, except for the first layer and the final perfect measurement round
+    pub fn apply(&self, simulator: &Simulator, noise_model: &mut NoiseModel) {
+        if self.leakage_rate == 0. {
+            return;
+        }
+        assert!(
+            (0. ..=1.).contains(&self.leakage_rate) && (0. ..=1.).contains(&self.seepage_rate),
+            "leakage_rate and seepage_rate must be probabilities"
+        );
+        let measurement_cycles = simulator.measurement_cycles;
+        // nodes are shared as much as possible; the original node is kept in the cache so that its address is not reused
+        let mut cached_nodes = HashMap::<(*const NoiseModelNode, bool, bool), CachedNoiseModelNode>::new();
+        simulator_iter_real!(simulator, position, node, {
+            if position.t == 0 || position.t >= simulator.height - measurement_cycles {
+                continue;
+            }
+            let has_two_qubit_gate = node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual;
+            let leakage_reduction = self.leakage_reduction_cycles > 0
+                && node.qubit_type == QubitType::Data
+                && position.t % measurement_cycles == 0
+                && (position.t / measurement_cycles) % self.leakage_reduction_cycles == 0;
+            let noise_model_node = noise_model.get_node_unwrap_arc(position);
+            let key = (Arc::as_ptr(&noise_model_node), has_two_qubit_gate, leakage_reduction);
+            let (_, leakage_node) = cached_nodes.entry(key).or_insert_with(|| {
+                let mut leakage_node = noise_model_node.as_ref().clone();
+                if has_two_qubit_gate {
+                    leakage_node.leakage_rate = self.leakage_rate;
+                }
+                leakage_node.seepage_rate = self.seepage_rate;
+                leakage_node.leakage_reduction = leakage_reduction;
+                leakage_node.leakage_heralded = self.leakage_heralded;
+                (noise_model_node.clone(), Arc::new(leakage_node))
+            });
+            noise_model.set_node(position, Some(leakage_node.clone()));
+        });
+    }
+}
+
+/// idle noise scheduled by gate durations, given by the optional `idle_noise` key in the noise model configuration and
+/// composed with any noise model builder except [`NoiseModelBuilder::FromCalibration`], which already includes the
+/// decoherence: every idle node gets the Pauli-twirled T1/T2 decoherence over the duration of its time slice, which is the
+/// longest operation in that slice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IdleNoise {
+    /// energy relaxation time, in the same unit as the gate durations
+    pub t1: f64,
+    /// dephasing time, in the same unit as the gate durations; physical devices have `t2 <= 2 * t1`
+    pub t2: f64,
+    pub gate_durations: GateTypeDurations,
+}
+
+/// duration of each [`GateType`], where the control and target of a two-qubit gate share the same duration
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateTypeDurations {
+    /// reset to $|0\rangle$
+    #[serde(default)]
+    pub initialize_z: f64,
+    /// reset to $|+\rangle$
+    #[serde(default)]
+    pub initialize_x: f64,
+    #[serde(default)]
+    pub cx: f64,
+    #[serde(default)]
+    pub cy: f64,
+    #[serde(default)]
+    pub cz: f64,
+    #[serde(default)]
+    pub measure_z: f64,
+    #[serde(default)]
+    pub measure_x: f64,
+}
+
+impl GateTypeDurations {
+    pub fn duration(&self, gate_type: GateType) -> f64 {
+        match gate_type {
+            GateType::InitializeZ => self.initialize_z,
+            GateType::InitializeX => self.initialize_x,
+            GateType::CXGateControl | GateType::CXGateTarget => self.cx,
+            GateType::CYGateControl | GateType::CYGateTarget => self.cy,
+            GateType::CZGate => self.cz,
+            GateType::MeasureZ => self.measure_z,
+            GateType::MeasureX => self.measure_x,
+            GateType::None => 0.,
+        }
+    }
+}
+
+impl IdleNoise {
+    /// parse the optional `idle_noise` key of a noise model configuration
+    pub fn from_noise_model_configuration(noise_model_configuration: &serde_json::Value) -> Result<Option<Self>, String> {
+        match noise_model_configuration.get("idle_noise") {
+            Some(value) => {
+                let idle_noise: Self = serde_json::from_value(value.clone()).map_err(|e| format!("idle_noise: {e}"))?;
+                idle_noise.sanity_check()?;
+                Ok(Some(idle_noise))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn sanity_check(&self) -> Result<(), String> {
+        if self.t1.is_nan() || self.t2.is_nan() || self.t1 <= 0. || self.t2 <= 0. || self.t2 > 2. * self.t1 {
+            return Err(format!("idle_noise: requires positive t1 and t2 <= 2 * t1, found {self:?}"));
+        }
+        let durations = &self.gate_durations;
+        for duration in [
+            durations.initialize_z,
+            durations.initialize_x,
+            durations.cx,
+            durations.cy,
+            durations.cz,
+            durations.measure_z,
+            durations.measure_x,
+        ] {
+            if !duration.is_finite() || duration < 0. {
+                return Err(format!(
+                    "idle_noise: gate durations must be finite and non-negative, found {durations:?}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// add the idle channel to the nodes without a physical gate, except for the first layer and the final perfect
+    /// measurement round; a gate with a virtual peer doesn't physically exist, so it's idle and doesn't take time
+    pub fn apply(&self, simulator: &Simulator, noise_model: &mut NoiseModel) {
+        let is_idle = |node: &SimulatorNode| node.gate_type == GateType::None || node.is_peer_virtual;
+        // nodes are shared as much as possible; the original node is kept in the cache so that its address is not reused
+        let mut cached_nodes = HashMap::<(*const NoiseModelNode, u64), CachedNoiseModelNode>::new();
+        for t in 1..simulator.height - simulator.measurement_cycles {
+            let mut slice_duration: f64 = 0.;
+            simulator_iter_real!(simulator, position, node, t => t, {
+                if !is_idle(node) {
+                    slice_duration = slice_duration.max(self.gate_durations.duration(node.gate_type));
+                }
+            });
+            if slice_duration == 0. {
+                continue;
+            }
+            let idle_error_rates = decoherence_error_rates(self.t1, self.t2, slice_duration);
+            simulator_iter_real!(simulator, position, node, t => t, {
+                if !is_idle(node) {
+                    continue;
+                }
+                let noise_model_node = noise_model.get_node_unwrap_arc(position);
+                let key = (Arc::as_ptr(&noise_model_node), slice_duration.to_bits());
+                let (_, idle_node) = cached_nodes.entry(key).or_insert_with(|| {
+                    let mut idle_node = noise_model_node.as_ref().clone();
+                    idle_node.pauli_error_rates = compose_pauli_error_rates(&idle_node.pauli_error_rates, &idle_error_rates);
+                    (noise_model_node.clone(), Arc::new(idle_node))
+                });
+                noise_model.set_node(position, Some(idle_node.clone()));
+            });
+        }
+    }
+}
+
 impl std::str::FromStr for NoiseModelBuilder {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -1079,3 +1423,231 @@ pub(crate) fn register(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<NoiseModelBuilder>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idle_noise_configuration() -> serde_json::Value {
+        json!({
+            "t1": 1000.,
+            "t2": 800.,
+            "gate_durations": {
+                "initialize_z": 100.,
+                "initialize_x": 100.,
+                "cx": 20.,
+                "cy": 20.,
+                "cz": 20.,
+                "measure_z": 500.,
+                "measure_x": 500.,
+            },
+        })
+    }
+
+    /// apply the builder with and without idle noise, and check that exactly the idle nodes get the decoherence of their
+    /// time slice: the measurement layer takes 500, the initialization layer takes 100 and each gate layer takes 20, unless
+    /// the slice has no physical gate at all
+    fn idle_noise_composes_with(
+        noise_model_builder: NoiseModelBuilder,
+        code_type: CodeType,
+        configuration: serde_json::Value,
+    ) {
+        let p = 0.001;
+        let mut simulator = Simulator::new(code_type, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        noise_model_builder
+            .apply(&mut simulator, &mut noise_model, &configuration, p, 0.5, 0.)
+            .unwrap();
+        let mut idle_configuration = configuration.clone();
+        idle_configuration["idle_noise"] = idle_noise_configuration();
+        let mut idle_noise_model = NoiseModel::new(&simulator);
+        noise_model_builder
+            .apply(&mut simulator, &mut idle_noise_model, &idle_configuration, p, 0.5, 0.)
+            .unwrap();
+        let measurement_cycles = simulator.measurement_cycles;
+        assert_eq!(
+            measurement_cycles, 6,
+            "expecting initialization, 4 CX layers and measurement in each round"
+        );
+        let is_idle = |node: &SimulatorNode| node.gate_type == GateType::None || node.is_peer_virtual;
+        // the first layer and the final perfect measurement round are noiseless
+        let mut has_idle_noise = vec![false; simulator.height];
+        simulator_iter_real!(simulator, position, node, {
+            has_idle_noise[position.t] |=
+                !is_idle(node) && position.t > 0 && position.t < simulator.height - measurement_cycles;
+        });
+        let mut idle_nodes = [0; 6];
+        simulator_iter_real!(simulator, position, node, {
+            let node_without_idle = noise_model.get_node_unwrap(position);
+            let node_with_idle = idle_noise_model.get_node_unwrap(position);
+            let mut expected = node_without_idle.clone();
+            if is_idle(node) && has_idle_noise[position.t] {
+                let stage = position.t % measurement_cycles;
+                let duration = match stage {
+                    0 => 500.,
+                    1 => 100.,
+                    _ => 20.,
+                };
+                let idle_error_rates = decoherence_error_rates(1000., 800., duration);
+                expected.pauli_error_rates = compose_pauli_error_rates(&expected.pauli_error_rates, &idle_error_rates);
+                idle_nodes[stage] += 1;
+            }
+            assert_eq!(json!(node_with_idle), json!(expected), "mismatch at {position}");
+        });
+        // the data qubits idle during the measurement and initialization layers
+        assert!(
+            idle_nodes[0] > 0 && idle_nodes[1] > 0,
+            "idle nodes of each stage: {idle_nodes:?}"
+        );
+    }
+
+    #[test]
+    fn noise_model_builder_idle_noise_rates() {
+        // cargo test noise_model_builder_idle_noise_rates -- --nocapture
+        let idle_error_rates = decoherence_error_rates(1000., 800., 500.);
+        let px = (1. - (-0.5f64).exp()) / 4.;
+        assert!((idle_error_rates.error_rate_X - px).abs() < 1e-12);
+        assert!((idle_error_rates.error_rate_Y - px).abs() < 1e-12);
+        assert!((idle_error_rates.error_rate_Z - ((1. - (-0.625f64).exp()) / 2. - px)).abs() < 1e-12);
+        // the idle channel composes with a noiseless node to the decoherence itself
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        let configuration = json!({ "idle_noise": idle_noise_configuration() });
+        NoiseModelBuilder::StimNoiseModel
+            .apply(&mut simulator, &mut noise_model, &configuration, 0., 0.5, 0.)
+            .unwrap();
+        let measurement_t = simulator.measurement_cycles * 2;
+        let mut checked = 0;
+        simulator_iter_real!(simulator, position, node, t => measurement_t, {
+            if node.qubit_type == QubitType::Data {
+                assert_eq!(noise_model.get_node_unwrap(position).pauli_error_rates, idle_error_rates);
+                checked += 1;
+            }
+        });
+        assert_eq!(checked, 9);
+    }
+
+    #[test]
+    fn noise_model_builder_idle_noise_composition() {
+        // cargo test noise_model_builder_idle_noise_composition -- --nocapture
+        for (noise_model_builder, code_type) in [
+            (NoiseModelBuilder::StimNoiseModel, CodeType::RotatedPlanarCode),
+            (NoiseModelBuilder::SI1000, CodeType::RotatedPlanarCode),
+            (NoiseModelBuilder::DepolarizingNoise, CodeType::RotatedPlanarCode),
+            (NoiseModelBuilder::OnlyGateErrorCircuitLevel, CodeType::RotatedPlanarCode),
+            (NoiseModelBuilder::GenericBiasedWithStandardCX, CodeType::RotatedPlanarCode),
+            (NoiseModelBuilder::GenericBiasedWithBiasedCX, CodeType::RotatedPlanarCode),
+            (
+                NoiseModelBuilder::TailoredScBellInitCircuit,
+                CodeType::RotatedTailoredCodeBellInit,
+            ),
+        ] {
+            println!("{noise_model_builder:?}");
+            idle_noise_composes_with(noise_model_builder, code_type, json!({}));
+        }
+    }
+
+    #[test]
+    fn noise_model_builder_si1000_rates() {
+        // cargo test noise_model_builder_si1000_rates -- --nocapture
+        let p = 0.001;
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::SI1000
+            .apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.)
+            .unwrap();
+        let measurement_cycles = simulator.measurement_cycles;
+        assert_eq!(
+            measurement_cycles, 6,
+            "expecting reset, 4 CX layers and measurement in each round"
+        );
+        let depolarize = |probability: f64| PauliErrorRates::default_with_probability(probability / 3.);
+        let flip_x = |probability: f64| PauliErrorRates {
+            error_rate_X: probability,
+            error_rate_Y: 0.,
+            error_rate_Z: 0.,
+        };
+        let flip_z = |probability: f64| PauliErrorRates {
+            error_rate_X: 0.,
+            error_rate_Y: 0.,
+            error_rate_Z: probability,
+        };
+        let assert_rates = |actual: &PauliErrorRates, expected: &PauliErrorRates, position: &Position| {
+            assert!(
+                (actual.error_rate_X - expected.error_rate_X).abs() < 1e-15
+                    && (actual.error_rate_Y - expected.error_rate_Y).abs() < 1e-15
+                    && (actual.error_rate_Z - expected.error_rate_Z).abs() < 1e-15,
+                "at {position}: found {actual:?}, expected {expected:?}"
+            );
+        };
+        let mut two_qubit_gates = 0;
+        let mut correlated_errors = 0;
+        let mut measurements = 0;
+        simulator_iter_real!(simulator, position, node, {
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
+                // two-qubit gates: depolarizing error with `p` in total
+                assert!((correlated_pauli_error_rates.error_probability() - p).abs() < 1e-15);
+                assert_eq!(
+                    correlated_pauli_error_rates,
+                    &CorrelatedPauliErrorRates::default_with_probability(p / 15.)
+                );
+                correlated_errors += 1;
+            }
+            if position.t == 0 || position.t >= simulator.height - measurement_cycles {
+                // the first layer and the final perfect measurement round are noiseless
+                assert_eq!(noise_model_node.pauli_error_rates, PauliErrorRates::default());
+                assert!(noise_model_node.correlated_pauli_error_rates.is_none());
+                continue;
+            }
+            let stage = position.t % measurement_cycles;
+            let is_two_qubit_gate = node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual;
+            if is_two_qubit_gate {
+                two_qubit_gates += 1;
+            }
+            let mut expected = match stage {
+                // the ancilla qubits are measured noiselessly, with the flip before the measurement
+                0 if node.qubit_type != QubitType::Data => PauliErrorRates::default(),
+                // data qubits idle during the measurement and reset with `2p`
+                0 | 1 if node.qubit_type == QubitType::Data => depolarize(2. * p),
+                // reset flips with `2p`, and the Hadamard gate after the X-basis reset errs with `p/10`
+                1 if node.gate_type == GateType::InitializeX => {
+                    compose_pauli_error_rates(&flip_z(2. * p), &depolarize(p / 10.))
+                }
+                1 => flip_x(2. * p),
+                // the two-qubit gates are correlated errors, and the idle qubits in the gate layers err with `p/10`
+                _ if is_two_qubit_gate => PauliErrorRates::default(),
+                _ => depolarize(p / 10.),
+            };
+            if stage == measurement_cycles - 1 && node.qubit_type != QubitType::Data {
+                // the Hadamard gate before the X-basis measurement errs with `p/10`, and the measurement flips with `5p`
+                let measurement = simulator.get_node_unwrap(&pos!(position.t + 1, position.i, position.j));
+                expected = match measurement.gate_type {
+                    GateType::MeasureX => compose_pauli_error_rates(
+                        &compose_pauli_error_rates(&expected, &depolarize(p / 10.)),
+                        &flip_z(5. * p),
+                    ),
+                    _ => compose_pauli_error_rates(&expected, &flip_x(5. * p)),
+                };
+                measurements += 1;
+            }
+            assert_rates(&noise_model_node.pauli_error_rates, &expected, position);
+        });
+        // each two-qubit gate has a single correlated error on one of its qubits
+        assert_eq!(2 * correlated_errors, two_qubit_gates);
+        // two noisy rounds of 8 ancilla qubits in the d=3 rotated planar code
+        assert_eq!(measurements, 2 * 8);
+    }
+
+    #[test]
+    fn noise_model_builder_idle_noise_from_calibration() {
+        // cargo test noise_model_builder_idle_noise_from_calibration -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        let configuration = json!({ "calibration": {}, "idle_noise": idle_noise_configuration() });
+        let result = NoiseModelBuilder::FromCalibration.apply(&mut simulator, &mut noise_model, &configuration, 0., 0.5, 0.);
+        assert!(result
+            .unwrap_err()
+            .contains("idle_noise cannot be composed with the calibration noise model"));
+    }
+}
@@ -0,0 +1,540 @@
+//! noise model from device calibration data
+//!
+//! A [`Calibration`] lists the measured parameters of a real device: T1 and T2, single-qubit gate error and readout errors
+//! of each qubit, the two-qubit gate error of each coupler, and the durations of each kind of gate. Qubits are keyed by
+//! their (i, j) lattice coordinates in the [`Simulator`]. The calibration is converted into Pauli-twirled errors at each
+//! position: every qubit decoheres during each layer of the circuit according to the layer duration, single-qubit gates
+//! and two-qubit gates are followed by depolarizing errors, and readout errors are flips before the measurement. A Pauli
+//! flip cannot depend on the measured state, so asymmetric readout errors are averaged with a warning.
+//!
+//! The calibration can be given as a JSON file that deserializes into [`Calibration`], or as a CSV file in the long format
+//! `i,j,peer_i,peer_j,parameter,value`. Qubit parameters leave the peer empty, coupler parameters fill all four coordinates,
+//! and gate durations leave all the coordinates empty, e.g.
+//!
+//! ```text
+//! i,j,peer_i,peer_j,parameter,value
+//! 1,1,,,t1,30000
+//! 1,1,,,t2,20000
+//! 1,1,2,2,two_qubit_gate_error,0.005
+//! ,,,,two_qubit_gate,40
+//! ```
+//!
+
+use super::noise_model::*;
+use super::simulator::*;
+use super::types::*;
+use super::util_macros::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::sync::Arc;
+
+/// the calibration data of a device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Calibration {
+    /// one entry for every real qubit in the simulator
+    pub qubits: Vec<QubitCalibration>,
+    /// one entry for every pair of qubits with a two-qubit gate in between, in either order
+    #[serde(default)]
+    pub couplers: Vec<CouplerCalibration>,
+    pub gate_durations: GateDurations,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QubitCalibration {
+    pub i: usize,
+    pub j: usize,
+    /// energy relaxation time, in the same unit as the gate durations
+    pub t1: f64,
+    /// dephasing time, in the same unit as the gate durations; physical devices have `t2 <= 2 * t1`
+    pub t2: f64,
+    /// depolarizing probability of a single-qubit gate; the circuits fold the Hadamard gates of X stabilizers into
+    /// [`GateType::InitializeX`] and [`GateType::MeasureX`], so it applies after the former and before the latter
+    pub single_qubit_gate_error: f64,
+    /// probability of reading 1 from |0>; the readout flip uses the average of `readout_error_01` and `readout_error_10`
+    pub readout_error_01: f64,
+    /// probability of reading 0 from |1>
+    pub readout_error_10: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CouplerCalibration {
+    pub i: usize,
+    pub j: usize,
+    pub peer_i: usize,
+    pub peer_j: usize,
+    /// two-qubit depolarizing probability, equally distributed among the 15 non-identity Pauli errors
+    pub two_qubit_gate_error: f64,
+}
+
+/// durations of the gates, in the same unit as T1 and T2
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateDurations {
+    pub reset: f64,
+    pub single_qubit_gate: f64,
+    pub two_qubit_gate: f64,
+    pub measurement: f64,
+}
+
+/// the header of a calibration CSV file
+pub const CALIBRATION_CSV_HEADER: &str = "i,j,peer_i,peer_j,parameter,value";
+
+impl Calibration {
+    /// load calibration from a file, as CSV if the extension is `.csv` and otherwise as JSON
+    pub fn from_file(filepath: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(filepath).map_err(|e| format!("cannot read {filepath}: {e}"))?;
+        if filepath.to_lowercase().ends_with(".csv") {
+            Self::from_csv(&content)
+        } else {
+            serde_json::from_str(&content).map_err(|e| format!("cannot parse {filepath}: {e}"))
+        }
+    }
+
+    /// parse calibration in the long CSV format `i,j,peer_i,peer_j,parameter,value`; empty lines and lines starting with
+    /// `#` are ignored
+    pub fn from_csv(content: &str) -> Result<Self, String> {
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        match lines.next() {
+            Some((_, header)) if header.replace(' ', "") == CALIBRATION_CSV_HEADER => {}
+            _ => return Err(format!("calibration CSV must start with header `{CALIBRATION_CSV_HEADER}`")),
+        }
+        let mut qubits = BTreeMap::<(usize, usize), serde_json::Map<String, serde_json::Value>>::new();
+        let mut couplers = BTreeMap::<(usize, usize, usize, usize), serde_json::Map<String, serde_json::Value>>::new();
+        let mut gate_durations = serde_json::Map::new();
+        for (line_number, line) in lines {
+            let columns: Vec<&str> = line.split(',').map(|column| column.trim()).collect();
+            if columns.len() != 6 {
+                return Err(format!("line {line_number}: expected 6 columns, found {}", columns.len()));
+            }
+            let mut coordinates = [None; 4];
+            for (coordinate, column) in coordinates.iter_mut().zip(columns.iter()) {
+                if !column.is_empty() {
+                    *coordinate = Some(
+                        column
+                            .parse::<usize>()
+                            .map_err(|e| format!("line {line_number}: invalid coordinate `{column}`: {e}"))?,
+                    );
+                }
+            }
+            let parameter = columns[4].to_string();
+            let value: f64 = columns[5]
+                .parse()
+                .map_err(|e| format!("line {line_number}: invalid value `{}`: {e}", columns[5]))?;
+            let entry = match coordinates {
+                [Some(i), Some(j), None, None] => qubits.entry((i, j)).or_default(),
+                [Some(i), Some(j), Some(peer_i), Some(peer_j)] => couplers.entry((i, j, peer_i, peer_j)).or_default(),
+                [None, None, None, None] => &mut gate_durations,
+                _ => {
+                    return Err(format!(
+                        "line {line_number}: coordinates must be `i,j`, `i,j,peer_i,peer_j` or empty"
+                    ))
+                }
+            };
+            if entry.insert(parameter.clone(), serde_json::json!(value)).is_some() {
+                return Err(format!("line {line_number}: duplicate parameter `{parameter}`"));
+            }
+        }
+        let qubits: Vec<serde_json::Value> = qubits
+            .into_iter()
+            .map(|((i, j), mut parameters)| {
+                parameters.insert("i".to_string(), serde_json::json!(i));
+                parameters.insert("j".to_string(), serde_json::json!(j));
+                serde_json::Value::Object(parameters)
+            })
+            .collect();
+        let couplers: Vec<serde_json::Value> = couplers
+            .into_iter()
+            .map(|((i, j, peer_i, peer_j), mut parameters)| {
+                parameters.insert("i".to_string(), serde_json::json!(i));
+                parameters.insert("j".to_string(), serde_json::json!(j));
+                parameters.insert("peer_i".to_string(), serde_json::json!(peer_i));
+                parameters.insert("peer_j".to_string(), serde_json::json!(peer_j));
+                serde_json::Value::Object(parameters)
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "qubits": qubits,
+            "couplers": couplers,
+            "gate_durations": gate_durations,
+        }))
+        .map_err(|e| format!("{e}"))
+    }
+
+    /// check that the calibration covers exactly the real qubits and the two-qubit gates of the simulator, and that all
+    /// the parameters are physical
+    pub fn validate(&self, simulator: &Simulator) -> Result<(), String> {
+        if simulator.measurement_cycles < 3 {
+            return Err("calibration noise model requires a circuit-level simulator".to_string());
+        }
+        let mut expected_qubits = BTreeSet::new();
+        let mut expected_couplers = BTreeSet::new();
+        simulator_iter_real!(simulator, position, node, {
+            expected_qubits.insert((position.i, position.j));
+            if node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual {
+                let peer = node.gate_peer.as_ref().expect("two-qubit gate must have a peer");
+                expected_couplers.insert(coupler_key(position.i, position.j, peer.i, peer.j));
+            }
+        });
+        let mut qubits = BTreeSet::new();
+        for qubit in self.qubits.iter() {
+            let (i, j) = (qubit.i, qubit.j);
+            if !expected_qubits.contains(&(i, j)) {
+                return Err(format!("qubit ({i}, {j}) is not a real qubit in the simulator"));
+            }
+            if !qubits.insert((i, j)) {
+                return Err(format!("duplicate qubit ({i}, {j})"));
+            }
+            if qubit.t1.is_nan() || qubit.t2.is_nan() || qubit.t1 <= 0. || qubit.t2 <= 0. {
+                return Err(format!("qubit ({i}, {j}) must have positive t1 and t2"));
+            }
+            if qubit.t2 > 2. * qubit.t1 {
+                return Err(format!("qubit ({i}, {j}) has t2 = {} > 2 * t1 = {}", qubit.t2, 2. * qubit.t1));
+            }
+            for (name, probability) in [
+                ("single_qubit_gate_error", qubit.single_qubit_gate_error),
+                ("readout_error_01", qubit.readout_error_01),
+                ("readout_error_10", qubit.readout_error_10),
+            ] {
+                if !(0. ..=1.).contains(&probability) {
+                    return Err(format!("qubit ({i}, {j}) has {name} = {probability} out of [0, 1]"));
+                }
+            }
+        }
+        if let Some((i, j)) = expected_qubits.difference(&qubits).next() {
+            return Err(format!("missing calibration of qubit ({i}, {j})"));
+        }
+        let mut couplers = BTreeSet::new();
+        for coupler in self.couplers.iter() {
+            let key = coupler_key(coupler.i, coupler.j, coupler.peer_i, coupler.peer_j);
+            let ((i, j), (peer_i, peer_j)) = key;
+            if !expected_couplers.contains(&key) {
+                return Err(format!(
+                    "no two-qubit gate between ({i}, {j}) and ({peer_i}, {peer_j}) in the simulator"
+                ));
+            }
+            if !couplers.insert(key) {
+                return Err(format!("duplicate coupler between ({i}, {j}) and ({peer_i}, {peer_j})"));
+            }
+            if !(0. ..=1.).contains(&coupler.two_qubit_gate_error) {
+                return Err(format!(
+                    "coupler between ({i}, {j}) and ({peer_i}, {peer_j}) has two_qubit_gate_error = {} out of [0, 1]",
+                    coupler.two_qubit_gate_error
+                ));
+            }
+        }
+        if let Some(((i, j), (peer_i, peer_j))) = expected_couplers.difference(&couplers).next() {
+            return Err(format!(
+                "missing calibration of coupler between ({i}, {j}) and ({peer_i}, {peer_j})"
+            ));
+        }
+        let durations = &self.gate_durations;
+        for (name, duration) in [
+            ("reset", durations.reset),
+            ("single_qubit_gate", durations.single_qubit_gate),
+            ("two_qubit_gate", durations.two_qubit_gate),
+            ("measurement", durations.measurement),
+        ] {
+            if !duration.is_finite() || duration < 0. {
+                return Err(format!("gate duration {name} = {duration} must be finite and non-negative"));
+            }
+        }
+        Ok(())
+    }
+
+    /// the qubits whose readout errors are asymmetric, which are averaged into a symmetric flip
+    pub fn asymmetric_readout_qubits(&self) -> Vec<(usize, usize)> {
+        self.qubits
+            .iter()
+            .filter(|qubit| qubit.readout_error_01 != qubit.readout_error_10)
+            .map(|qubit| (qubit.i, qubit.j))
+            .collect()
+    }
+
+    /// validate against the simulator and then set the noise model; errors are added after the gate of each layer:
+    /// - initialization layer: decoherence over `reset + single_qubit_gate`, plus single-qubit gate error after `InitializeX`
+    /// - two-qubit gate layers: decoherence over `two_qubit_gate`, plus two-qubit depolarizing error after each gate;
+    ///     the ancilla in the last one also gets the single-qubit gate error before `MeasureX` and the readout flip, whose
+    ///     probability is the average of `readout_error_01` and `readout_error_10`
+    /// - measurement layer: decoherence of the data qubits over `single_qubit_gate + measurement`
+    ///
+    /// the first layer and the final perfect measurement round are noiseless, as in the other circuit-level noise models
+    pub fn apply(&self, simulator: &Simulator, noise_model: &mut NoiseModel) -> Result<(), String> {
+        self.validate(simulator)?;
+        let asymmetric_readout_qubits = self.asymmetric_readout_qubits();
+        if !asymmetric_readout_qubits.is_empty() {
+            eprintln!(
+                "[warning] {} qubits have asymmetric readout errors, e.g. {:?}, which are averaged because a Pauli flip cannot \
+                depend on the measured state",
+                asymmetric_readout_qubits.len(),
+                asymmetric_readout_qubits[0]
+            );
+        }
+        let qubits: HashMap<(usize, usize), &QubitCalibration> =
+            self.qubits.iter().map(|qubit| ((qubit.i, qubit.j), qubit)).collect();
+        let couplers: HashMap<CouplerKey, f64> = self
+            .couplers
+            .iter()
+            .map(|coupler| {
+                let key = coupler_key(coupler.i, coupler.j, coupler.peer_i, coupler.peer_j);
+                (key, coupler.two_qubit_gate_error)
+            })
+            .collect();
+        let durations = &self.gate_durations;
+        let measurement_cycles = simulator.measurement_cycles;
+        let noiseless_node = Arc::new(NoiseModelNode::new());
+        // the circuit is periodic, so nodes are shared among the measurement rounds
+        let mut cached_nodes = HashMap::<(usize, usize, usize), Arc<NoiseModelNode>>::new();
+        simulator_iter_real!(simulator, position, node, {
+            // first clear error rate
+            noise_model.set_node(position, Some(noiseless_node.clone()));
+            if position.t == 0 || position.t >= simulator.height - measurement_cycles {
+                // no error on the top, as a perfect measurement round
+                continue;
+            }
+            let stage = position.t % measurement_cycles;
+            if stage == 0 && node.qubit_type != QubitType::Data {
+                // the ancilla is reset in the next layer
+                continue;
+            }
+            let noise_model_node = cached_nodes.entry((position.i, position.j, stage)).or_insert_with(|| {
+                let qubit = qubits[&(position.i, position.j)];
+                let duration = match stage {
+                    1 => durations.reset + durations.single_qubit_gate,
+                    0 => durations.single_qubit_gate + durations.measurement,
+                    _ => durations.two_qubit_gate,
+                };
+                let mut noise_model_node = NoiseModelNode::new();
+                let mut pauli_error_rates = decoherence_error_rates(qubit.t1, qubit.t2, duration);
+                let single_qubit_gate_error_rates = PauliErrorRates {
+                    error_rate_X: qubit.single_qubit_gate_error / 3.,
+                    error_rate_Y: qubit.single_qubit_gate_error / 3.,
+                    error_rate_Z: qubit.single_qubit_gate_error / 3.,
+                };
+                if stage == 1 && node.gate_type == GateType::InitializeX {
+                    pauli_error_rates = compose_pauli_error_rates(&pauli_error_rates, &single_qubit_gate_error_rates);
+                }
+                if stage > 1 && node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual {
+                    let peer = node.gate_peer.as_ref().unwrap();
+                    let key = coupler_key(position.i, position.j, peer.i, peer.j);
+                    if key.0 == (position.i, position.j) {
+                        // the correlated error is added only once, on one of the two qubits
+                        noise_model_node.correlated_pauli_error_rates =
+                            Some(CorrelatedPauliErrorRates::default_with_probability(couplers[&key] / 15.));
+                    }
+                }
+                if stage == measurement_cycles - 1 && node.qubit_type != QubitType::Data {
+                    let measurement = simulator.get_node_unwrap(&pos!(position.t + 1, position.i, position.j));
+                    let readout_error = (qubit.readout_error_01 + qubit.readout_error_10) / 2.;
+                    let readout_error_rates = match measurement.gate_type {
+                        GateType::MeasureX => {
+                            pauli_error_rates =
+                                compose_pauli_error_rates(&pauli_error_rates, &single_qubit_gate_error_rates);
+                            PauliErrorRates {
+                                error_rate_X: 0.,
+                                error_rate_Y: 0.,
+                                error_rate_Z: readout_error,
+                            }
+                        }
+                        _ => PauliErrorRates {
+                            error_rate_X: readout_error,
+                            error_rate_Y: 0.,
+                            error_rate_Z: 0.,
+                        },
+                    };
+                    pauli_error_rates = compose_pauli_error_rates(&pauli_error_rates, &readout_error_rates);
+                }
+                noise_model_node.pauli_error_rates = pauli_error_rates;
+                Arc::new(noise_model_node)
+            });
+            noise_model.set_node(position, Some(noise_model_node.clone()));
+        });
+        Ok(())
+    }
+}
+
+/// unordered pair of qubits
+type CouplerKey = ((usize, usize), (usize, usize));
+
+fn coupler_key(i: usize, j: usize, peer_i: usize, peer_j: usize) -> CouplerKey {
+    if (i, j) <= (peer_i, peer_j) {
+        ((i, j), (peer_i, peer_j))
+    } else {
+        ((peer_i, peer_j), (i, j))
+    }
+}
+
+/// Pauli twirling of amplitude damping and dephasing over `duration`
+pub fn decoherence_error_rates(t1: f64, t2: f64, duration: f64) -> PauliErrorRates {
+    let px = (1. - (-duration / t1).exp()) / 4.;
+    let pz = (1. - (-duration / t2).exp()) / 2. - px;
+    PauliErrorRates {
+        error_rate_X: px,
+        error_rate_Y: px,
+        error_rate_Z: pz.max(0.),
+    }
+}
+
+/// the Pauli channel of applying two independent Pauli channels in sequence
+pub fn compose_pauli_error_rates(a: &PauliErrorRates, b: &PauliErrorRates) -> PauliErrorRates {
+    let a_identity = 1. - a.error_rate_X - a.error_rate_Y - a.error_rate_Z;
+    let b_identity = 1. - b.error_rate_X - b.error_rate_Y - b.error_rate_Z;
+    PauliErrorRates {
+        error_rate_X: a_identity * b.error_rate_X
+            + a.error_rate_X * b_identity
+            + a.error_rate_Y * b.error_rate_Z
+            + a.error_rate_Z * b.error_rate_Y,
+        error_rate_Y: a_identity * b.error_rate_Y
+            + a.error_rate_Y * b_identity
+            + a.error_rate_X * b.error_rate_Z
+            + a.error_rate_Z * b.error_rate_X,
+        error_rate_Z: a_identity * b.error_rate_Z
+            + a.error_rate_Z * b_identity
+            + a.error_rate_X * b.error_rate_Y
+            + a.error_rate_Y * b.error_rate_X,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::code_builder::*;
+    use super::*;
+
+    fn uniform_calibration_csv(simulator: &Simulator, two_qubit_gate_error: f64) -> String {
+        let mut csv = format!("{CALIBRATION_CSV_HEADER}\n");
+        let mut qubits = BTreeSet::new();
+        let mut couplers = BTreeSet::new();
+        simulator_iter_real!(simulator, position, node, {
+            qubits.insert((position.i, position.j));
+            if node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual {
+                let peer = node.gate_peer.as_ref().unwrap();
+                couplers.insert(coupler_key(position.i, position.j, peer.i, peer.j));
+            }
+        });
+        for (i, j) in qubits {
+            csv += &format!("{i},{j},,,t1,30000\n{i},{j},,,t2,20000\n{i},{j},,,single_qubit_gate_error,0.001\n");
+            csv += &format!("{i},{j},,,readout_error_01,0.01\n{i},{j},,,readout_error_10,0.03\n");
+        }
+        for ((i, j), (peer_i, peer_j)) in couplers {
+            csv += &format!("{i},{j},{peer_i},{peer_j},two_qubit_gate_error,{two_qubit_gate_error}\n");
+        }
+        csv += ",,,,reset,500\n,,,,single_qubit_gate,20\n,,,,two_qubit_gate,40\n,,,,measurement,600\n";
+        csv
+    }
+
+    #[test]
+    fn noise_model_calibration_uniform() {
+        // cargo test noise_model_calibration_uniform -- --nocapture
+        let d = 3;
+        let noisy_measurements = 2;
+        let simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        let csv = uniform_calibration_csv(&simulator, 0.006);
+        let calibration = Calibration::from_csv(&csv).unwrap();
+        // JSON is equivalent
+        let json = serde_json::to_string(&calibration).unwrap();
+        assert_eq!(serde_json::from_str::<Calibration>(&json).unwrap(), calibration);
+        // the readout errors of 0.01 and 0.03 are averaged
+        assert_eq!(calibration.asymmetric_readout_qubits().len(), calibration.qubits.len());
+        let mut symmetric_calibration = calibration.clone();
+        for qubit in symmetric_calibration.qubits.iter_mut() {
+            qubit.readout_error_10 = qubit.readout_error_01;
+        }
+        assert!(symmetric_calibration.asymmetric_readout_qubits().is_empty());
+        let mut noise_model = NoiseModel::new(&simulator);
+        calibration.apply(&simulator, &mut noise_model).unwrap();
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let mc = simulator.measurement_cycles;
+        let mut correlated_count = 0;
+        let mut expected_correlated_count = 0;
+        simulator_iter_real!(simulator, position, node, {
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            let rates = &noise_model_node.pauli_error_rates;
+            if position.t == 0 || position.t >= simulator.height - mc {
+                assert_eq!(rates, &PauliErrorRates::default());
+                assert!(noise_model_node.correlated_pauli_error_rates.is_none());
+                continue;
+            }
+            if node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual {
+                expected_correlated_count += 1;
+            }
+            if let Some(correlated) = &noise_model_node.correlated_pauli_error_rates {
+                assert!((correlated.error_rate_XX - 0.006 / 15.).abs() < 1e-12);
+                correlated_count += 2;
+            }
+            let stage = position.t % mc;
+            if node.qubit_type == QubitType::Data {
+                let duration = match stage {
+                    1 => 520.,
+                    0 => 620.,
+                    _ => 40.,
+                };
+                assert_eq!(rates, &decoherence_error_rates(30000., 20000., duration));
+            } else if stage == mc - 1 {
+                // readout flip of 0.02 dominates
+                let flip_rate = match simulator
+                    .get_node_unwrap(&pos!(position.t + 1, position.i, position.j))
+                    .gate_type
+                {
+                    GateType::MeasureX => rates.error_rate_Z + rates.error_rate_Y,
+                    _ => rates.error_rate_X + rates.error_rate_Y,
+                };
+                assert!(flip_rate > 0.02 && flip_rate < 0.023, "flip rate {flip_rate}");
+            } else if stage == 0 {
+                assert_eq!(rates, &PauliErrorRates::default());
+            }
+        });
+        assert_eq!(correlated_count, expected_correlated_count);
+    }
+
+    #[test]
+    fn noise_model_calibration_validation() {
+        // cargo test noise_model_calibration_validation -- --nocapture
+        let d = 3;
+        let simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(1, d, d));
+        let csv = uniform_calibration_csv(&simulator, 0.006);
+        let calibration = Calibration::from_csv(&csv).unwrap();
+        calibration.validate(&simulator).unwrap();
+        let mut missing_qubit = calibration.clone();
+        missing_qubit.qubits.pop();
+        assert!(missing_qubit
+            .validate(&simulator)
+            .unwrap_err()
+            .contains("missing calibration of qubit"));
+        let mut missing_coupler = calibration.clone();
+        missing_coupler.couplers.pop();
+        assert!(missing_coupler
+            .validate(&simulator)
+            .unwrap_err()
+            .contains("missing calibration of coupler"));
+        let mut duplicate_coupler = calibration.clone();
+        let mut reversed = duplicate_coupler.couplers[0].clone();
+        (reversed.i, reversed.j, reversed.peer_i, reversed.peer_j) =
+            (reversed.peer_i, reversed.peer_j, reversed.i, reversed.j);
+        duplicate_coupler.couplers.push(reversed);
+        assert!(duplicate_coupler
+            .validate(&simulator)
+            .unwrap_err()
+            .contains("duplicate coupler"));
+        let mut unphysical = calibration.clone();
+        unphysical.qubits[0].t2 = 70000.;
+        assert!(unphysical.validate(&simulator).unwrap_err().contains("> 2 * t1"));
+        let mut unknown_qubit = calibration.clone();
+        unknown_qubit.qubits[0].i = 100;
+        assert!(unknown_qubit.validate(&simulator).unwrap_err().contains("not a real qubit"));
+        // CSV errors
+        assert!(Calibration::from_csv("i,j,parameter,value\n").is_err());
+        let duplicated = format!("{csv},,,,reset,100\n");
+        assert!(Calibration::from_csv(&duplicated)
+            .unwrap_err()
+            .contains("duplicate parameter"));
+        let missing_parameter = csv.replace(",,,,measurement,600\n", "");
+        assert!(Calibration::from_csv(&missing_parameter).unwrap_err().contains("measurement"));
+    }
+}
@@ -9,6 +9,7 @@ use super::types::*;
 use super::util_macros::*;
 #[cfg(feature = "python_binding")]
 use crate::pyo3::prelude::*;
+use crate::simulator_batch::*;
 use crate::simulator_compact::*;
 use crate::simulator_file::*;
 use crate::visualize::*;
@@ -24,9 +25,11 @@ use ErrorType::*;
 #[derive(Clone)]
 pub enum GeneralSimulator {
     SimulatorCompactCompressed,
+    SimulatorCompactFixedWeight,
     SimulatorCompact,
     Simulator,
     SimulatorVec,
+    SimulatorBatch,
 }
 
 #[enum_dispatch(GeneralSimulator)]
@@ -38,6 +41,10 @@ pub trait SimulatorGenerics: Clone {
     fn generate_sparse_error_pattern(&self) -> SparseErrorPattern;
     fn generate_sparse_measurement(&self) -> SparseMeasurement;
     fn validate_correction(&mut self, correction: &SparseCorrection) -> (bool, bool);
+    /// the number of cosmic-ray events in the last generated errors, see [`CosmicRayNoise`]
+    fn cosmic_ray_events(&self) -> usize {
+        0
+    }
 }
 
 #[cfg(feature = "python_binding")]
@@ -101,6 +108,9 @@ pub struct Simulator {
     /// how many cycles is there a round of measurements; default to 1
     #[cfg_attr(feature = "python_binding", pyo3(get, set))]
     pub measurement_cycles: usize,
+    /// the number of cosmic-ray events in the last generated errors
+    #[cfg_attr(feature = "python_binding", pyo3(get))]
+    pub cosmic_ray_events: usize,
 }
 
 impl QecpVisualizer for Simulator {
@@ -176,6 +186,9 @@ pub struct SimulatorNode {
     pub has_erasure: bool,
     #[cfg_attr(feature = "python_binding", pyo3(get, set))]
     pub propagated: ErrorType,
+    /// the qubit is out of the computational subspace after the gate at this node
+    #[cfg_attr(feature = "python_binding", pyo3(get, set))]
+    pub is_leaked: bool,
     /// Virtual qubit doesn't physically exist, which means they will never have errors themselves.
     /// Real qubit errors can propagate to virtual qubits, but errors will never propagate to real qubits.
     /// Virtual qubits can be understood as perfect stabilizers that only absorb propagated errors and never propagate them.
@@ -205,6 +218,7 @@ impl SimulatorNode {
             error: I,
             has_erasure: false,
             propagated: I,
+            is_leaked: false,
             is_virtual: false,
             is_peer_virtual: false,
             miscellaneous: None,
@@ -212,7 +226,7 @@ impl SimulatorNode {
     }
     #[cfg_attr(feature = "python_binding", setter)]
     pub fn set_gate_peer(&mut self, pos: Position) {
-        self.gate_peer = Option::Some(pos).map(Arc::new);
+        self.gate_peer = Some(Arc::new(pos));
     }
     #[cfg_attr(feature = "python_binding", getter)]
     pub fn get_gate_peer(&self) -> Position {
@@ -391,6 +405,7 @@ impl Clone for Simulator {
             nodes: self.nodes.clone(),
             rng: Xoroshiro128StarStar::new(), // do not copy random number generator, otherwise parallel simulation may give same result
             measurement_cycles: self.measurement_cycles,
+            cosmic_ray_events: self.cosmic_ray_events,
         }
     }
 }
@@ -410,6 +425,7 @@ impl Simulator {
             nodes: Vec::new(),
             rng: Xoroshiro128StarStar::new(),
             measurement_cycles: 1,
+            cosmic_ray_events: 0,
         };
         build_code(&mut simulator);
         simulator
@@ -531,6 +547,7 @@ impl Simulator {
             node.error = I;
             node.has_erasure = false;
             node.propagated = I;
+            node.is_leaked = false;
         });
     }
 
@@ -565,6 +582,134 @@ impl Simulator {
         }
     }
 
+    /// simulate leakage in ascending `t`, given the Pauli and erasure errors already generated. a leaked qubit is in a
+    /// maximally mixed state when it returns to the computational subspace, so it has a random Pauli error at every node
+    /// where it is leaked, and a two-qubit gate with a leaked qubit randomizes the peer. a leaked qubit is returned by
+    /// initialization, by seepage or by a leakage-reduction unit; the final perfect measurement round has no leakage.
+    /// if the leakage is heralded, all these randomized nodes are reported as erasures
+    fn generate_leakage_errors(
+        &mut self,
+        noise_model: &NoiseModel,
+        rng: &mut Xoroshiro128StarStar,
+        error_count: &mut usize,
+        erasure_count: &mut usize,
+    ) {
+        for t in 0..self.height - self.measurement_cycles {
+            simulator_iter_real!(self, position, _node, t => t, {
+                let noise_model_node = noise_model.get_node_unwrap(position);
+                let node = self.get_node_unwrap(position);
+                let leaked_before = position.t > 0
+                    && !node.gate_type.is_initialization()
+                    && self.get_node_unwrap(&pos!(position.t - 1, position.i, position.j)).is_leaked;
+                let mut is_leaked = if leaked_before {
+                    noise_model_node.seepage_rate == 0. || rng.next_f64() >= noise_model_node.seepage_rate
+                } else {
+                    noise_model_node.leakage_rate > 0. && rng.next_f64() < noise_model_node.leakage_rate
+                };
+                if noise_model_node.leakage_reduction {
+                    is_leaked = false;
+                }
+                let heralded = noise_model_node.leakage_heralded;
+                // the gate acts trivially on a leaked qubit and randomizes its peer
+                let randomized_peer = if leaked_before && node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual {
+                    node.gate_peer.as_ref().map(|gate_peer| (**gate_peer).clone())
+                } else {
+                    None
+                };
+                if leaked_before || is_leaked {
+                    let random_error = ErrorType::random(rng);
+                    self.set_leakage_error(position, random_error, heralded, error_count, erasure_count);
+                }
+                if let Some(peer_position) = randomized_peer {
+                    let random_error = self.get_node_unwrap(&peer_position).error.multiply(&ErrorType::random(rng));
+                    self.set_leakage_error(&peer_position, random_error, heralded, error_count, erasure_count);
+                }
+                self.get_node_mut_unwrap(position).is_leaked = is_leaked;
+            });
+        }
+    }
+
+    /// sample cosmic-ray events round by round and push the depolarizing errors in the affected regions; the measurement
+    /// round of a node at `t` is `(t - 1) / measurement_cycles`, and the final perfect measurement round is not affected
+    fn generate_cosmic_ray_errors(
+        &self,
+        cosmic_ray: &CosmicRayNoise,
+        rng: &mut Xoroshiro128StarStar,
+        pending_pauli_errors: &mut Vec<(Position, ErrorType)>,
+    ) -> usize {
+        let noisy_height = self.height - self.measurement_cycles;
+        let rounds = (noisy_height - 1).div_ceil(self.measurement_cycles).max(1);
+        let mut real_qubits = Vec::new();
+        simulator_iter_real!(self, position, _node, t => 0, {
+            real_qubits.push((position.i, position.j));
+        });
+        let mut events = 0;
+        for round in 0..rounds {
+            if rng.next_f64() >= cosmic_ray.rate {
+                continue;
+            }
+            events += 1;
+            let (ci, cj) = real_qubits[((rng.next_f64() * real_qubits.len() as f64) as usize).min(real_qubits.len() - 1)];
+            let region: Vec<(usize, usize)> = real_qubits
+                .iter()
+                .filter(|(i, j)| {
+                    let (di, dj) = (*i as f64 - ci as f64, *j as f64 - cj as f64);
+                    (di * di + dj * dj).sqrt() <= cosmic_ray.radius
+                })
+                .cloned()
+                .collect();
+            for k in 0..cosmic_ray.duration.min(rounds - round) {
+                let error_rate = cosmic_ray.error_rate_after(k);
+                let t_begin = if round + k == 0 {
+                    0
+                } else {
+                    (round + k) * self.measurement_cycles + 1
+                };
+                let t_end = ((round + k + 1) * self.measurement_cycles + 1).min(noisy_height);
+                for t in t_begin..t_end {
+                    for &(i, j) in region.iter() {
+                        let position = pos!(t, i, j);
+                        if self.is_node_real(&position) && rng.next_f64() < error_rate {
+                            let random_pauli = rng.next_f64();
+                            let error = if random_pauli < 1. / 3. {
+                                X
+                            } else if random_pauli < 2. / 3. {
+                                Z
+                            } else {
+                                Y
+                            };
+                            pending_pauli_errors.push((position, error));
+                        }
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// set the error caused by leakage, keeping track of the number of errors and erasures
+    fn set_leakage_error(
+        &mut self,
+        position: &Position,
+        error: ErrorType,
+        heralded: bool,
+        error_count: &mut usize,
+        erasure_count: &mut usize,
+    ) {
+        let node = self.get_node_mut_unwrap(position);
+        if node.error != I {
+            *error_count -= 1;
+        }
+        node.set_error_temp(&error);
+        if node.error != I {
+            *error_count += 1;
+        }
+        if heralded && !node.has_erasure {
+            node.has_erasure = true;
+            *erasure_count += 1;
+        }
+    }
+
     /// calculate propagated errors at one position. in order to correctly propagate every error, the order of propagation must be ascending in `t`s.
     /// note that errors are propagated to the next time, i.e. `t + 1`.
     /// when a error (other than Identity) propagates to the peer, it returns the position of the peer.
@@ -833,6 +978,10 @@ impl SimulatorGenerics for Simulator {
         self.rng = rng;
     }
 
+    fn cosmic_ray_events(&self) -> usize {
+        self.cosmic_ray_events
+    }
+
     fn generate_random_errors(&mut self, noise_model: &NoiseModel) -> (usize, usize) {
         // this size is small compared to the simulator itself
         let allocate_size = self.height * self.vertical * self.horizontal;
@@ -843,6 +992,7 @@ impl SimulatorGenerics for Simulator {
         let mut rng = self.rng.clone(); // avoid mutable borrow
         let mut error_count = 0;
         let mut erasure_count = 0;
+        let mut has_leakage = false;
         // first apply single-qubit and two-qubit correlated errors
         simulator_iter_mut!(self, position, node, {
             let noise_model_node = noise_model.get_node_unwrap(position);
@@ -867,6 +1017,10 @@ impl SimulatorGenerics for Simulator {
             let random_erasure = rng.next_f64();
             node.has_erasure = false;
             node.propagated = I; // clear propagated errors
+            node.is_leaked = false;
+            if noise_model_node.leakage_rate > 0. {
+                has_leakage = true;
+            }
             if random_erasure < noise_model_node.erasure_error_rate {
                 pending_erasure_errors.push(position.clone());
             }
@@ -922,6 +1076,11 @@ impl SimulatorGenerics for Simulator {
                 }
             }
         }
+        // then apply cosmic-ray events
+        self.cosmic_ray_events = match &noise_model.cosmic_ray {
+            Some(cosmic_ray) => self.generate_cosmic_ray_errors(cosmic_ray, &mut rng, &mut pending_pauli_errors),
+            None => 0,
+        };
         // apply pending pauli errors
         for (position, peer_error) in pending_pauli_errors.iter() {
             let node = self.get_node_mut_unwrap(position);
@@ -944,22 +1103,14 @@ impl SimulatorGenerics for Simulator {
             if node.error != I {
                 error_count -= 1;
             }
-            let random_erasure = rng.next_f64();
-            node.set_error_temp(
-                &(if random_erasure < 0.25 {
-                    X
-                } else if random_erasure < 0.5 {
-                    Z
-                } else if random_erasure < 0.75 {
-                    Y
-                } else {
-                    I
-                }),
-            );
+            node.set_error_temp(&ErrorType::random(&mut rng));
             if node.error != I {
                 error_count += 1;
             };
         }
+        if has_leakage {
+            self.generate_leakage_errors(noise_model, &mut rng, &mut error_count, &mut erasure_count);
+        }
         debug_assert!({
             // the above code avoids iterating the code multiple times when error rate is low (~1%), check correctness in debug mode
             let sparse_error_pattern = self.generate_sparse_error_pattern();
@@ -1875,6 +2026,114 @@ mod tests {
             panic!("SimulatorNode which is unexpectedly large, check if anything wrong");
         }
     }
+
+    #[test]
+    fn simulator_cosmic_ray() {
+        // cargo test simulator_cosmic_ray -- --nocapture
+        let d = 5;
+        let noisy_measurements = 3;
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        let (error_count, _) = simulator.generate_random_errors(&noise_model);
+        assert_eq!((error_count, simulator.cosmic_ray_events), (0, 0));
+        // an event in every round, lasting for one round
+        let cosmic_ray = CosmicRayNoise {
+            rate: 1.,
+            radius: 1.5,
+            duration: 1,
+            error_rate: 0.5,
+        };
+        noise_model.cosmic_ray = Some(cosmic_ray.clone());
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let mut rounds_with_errors = BTreeSet::new();
+        for _ in 0..10 {
+            let (error_count, _) = simulator.generate_random_errors(&noise_model);
+            assert_eq!(simulator.cosmic_ray_events, noisy_measurements);
+            assert_eq!(simulator.generate_sparse_error_pattern().len(), error_count);
+            for position in simulator.generate_sparse_error_pattern().errors.keys() {
+                assert!(position.t < simulator.height - simulator.measurement_cycles);
+                rounds_with_errors.insert(position.t.saturating_sub(1) / simulator.measurement_cycles);
+            }
+        }
+        assert_eq!(rounds_with_errors, (0..noisy_measurements).collect::<BTreeSet<_>>());
+        // linearly decaying error rate
+        let cosmic_ray = CosmicRayNoise {
+            duration: 4,
+            ..cosmic_ray
+        };
+        assert_eq!(cosmic_ray.error_rate_after(0), 0.5);
+        assert_eq!(cosmic_ray.error_rate_after(2), 0.25);
+        assert_eq!(cosmic_ray.error_rate_after(4), 0.);
+        let invalid = CosmicRayNoise { rate: 2., ..cosmic_ray };
+        assert!(invalid.sanity_check().is_err());
+    }
+
+    #[test]
+    fn simulator_leakage() {
+        // cargo test simulator_leakage -- --nocapture
+        let d = 3;
+        let noisy_measurements = 2;
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        let mc = simulator.measurement_cycles;
+        let t_leak = mc + 2;
+        let t_reduction = 2 * mc;
+        let find_qubit = |qubit_type: QubitType| {
+            let mut found = None;
+            simulator_iter_real!(simulator, position, node, t => t_leak, {
+                if found.is_none()
+                    && node.qubit_type == qubit_type
+                    && node.gate_type.is_two_qubit_gate()
+                    && !node.is_peer_virtual
+                {
+                    found = Some((position.i, position.j));
+                }
+            });
+            found.unwrap()
+        };
+        let (i, j) = find_qubit(QubitType::Data);
+        let (ai, aj) = find_qubit(QubitType::StabZ);
+        let mut noise_model = NoiseModel::new(&simulator);
+        let mut heralded_node = NoiseModelNode::new();
+        heralded_node.leakage_heralded = true;
+        let heralded_node = Arc::new(heralded_node);
+        simulator_iter_real!(simulator, position, _node, {
+            noise_model.set_node(position, Some(heralded_node.clone()));
+        });
+        let mut leak_node = heralded_node.as_ref().clone();
+        leak_node.leakage_rate = 1.;
+        noise_model.set_node(&pos!(t_leak, i, j), Some(Arc::new(leak_node)));
+        let mut reduction_node = heralded_node.as_ref().clone();
+        reduction_node.leakage_reduction = true;
+        noise_model.set_node(&pos!(t_reduction, i, j), Some(Arc::new(reduction_node)));
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let (_error_count, erasure_count) = simulator.generate_random_errors(&noise_model);
+        // the data qubit is leaked until the leakage-reduction unit, randomizing itself and its peers
+        let mut expected_erasures = BTreeSet::new();
+        simulator_iter_real!(simulator, position, node, {
+            let on_qubit = (position.i, position.j) == (i, j);
+            assert_eq!(node.is_leaked, on_qubit && position.t >= t_leak && position.t < t_reduction);
+            if on_qubit && position.t >= t_leak && position.t <= t_reduction {
+                expected_erasures.insert(position.clone());
+                if position.t > t_leak && node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual {
+                    expected_erasures.insert((**node.gate_peer.as_ref().unwrap()).clone());
+                }
+            }
+        });
+        let erasures = simulator.generate_sparse_detected_erasures().erasures;
+        assert_eq!(erasures, expected_erasures);
+        assert_eq!(erasure_count, expected_erasures.len());
+        // a leaked ancilla qubit is returned by the next initialization
+        let mut noise_model = NoiseModel::new(&simulator);
+        let mut leak_node = NoiseModelNode::new();
+        leak_node.leakage_rate = 1.;
+        noise_model.set_node(&pos!(t_leak, ai, aj), Some(Arc::new(leak_node)));
+        let (_error_count, erasure_count) = simulator.generate_random_errors(&noise_model);
+        assert_eq!(erasure_count, 0, "leakage is not heralded");
+        simulator_iter_real!(simulator, position, node, {
+            let on_qubit = (position.i, position.j) == (ai, aj);
+            assert_eq!(node.is_leaked, on_qubit && position.t >= t_leak && position.t <= 2 * mc);
+        });
+    }
 }
 
 #[cfg(feature = "python_binding")]
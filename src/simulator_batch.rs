@@ -0,0 +1,637 @@
+//! Batch simulator that samples [`BATCH_SIZE`] shots at once with bit-packed Pauli frames.
+//!
+//! The errors and the propagated errors of every node are stored as two bit masks (X and Z components), one bit per shot,
+//! so that the propagation through the node graph of [`Simulator`] handles all the shots of a batch with a few bitwise
+//! operations. The random errors are sampled by geometric skipping, which only draws random numbers for the shots
+//! that actually have errors, so a batch costs roughly the same as a single shot of [`Simulator`] when `p` is small.
+//! The shots of a batch are then served one by one through [`SimulatorGenerics`]. The logical errors of all the shots are also
+//! computed at once from the top layer, so validating a correction only looks at the few nodes that the correction touches;
+//! the benchmark decodes a whole batch before validating all its corrections with [`SimulatorBatch::validate_batch_corrections`].
+//!
+
+use super::code_builder::*;
+use super::noise_model::*;
+use super::reproducible_rand::Xoroshiro128StarStar;
+use super::simulator::*;
+use super::types::*;
+use super::util_macros::*;
+use rand_core::RngCore;
+use std::collections::HashMap;
+use ErrorType::*;
+
+/// the number of shots simulated at once
+pub const BATCH_SIZE: usize = 64;
+
+/// the X and Z bits of a Pauli error
+fn pauli_bits(error: ErrorType) -> (u64, u64) {
+    match error {
+        I => (0, 0),
+        X => (u64::MAX, 0),
+        Z => (0, u64::MAX),
+        Y => (u64::MAX, u64::MAX),
+    }
+}
+
+/// the logical errors caused by a single Pauli error at a top-layer node, in the order of X, Z and Y errors
+type LogicalFlips = [(bool, bool); 3];
+
+fn logical_flip(flips: &LogicalFlips, error: ErrorType) -> (bool, bool) {
+    match error {
+        I => (false, false),
+        X => flips[0],
+        Z => flips[1],
+        Y => flips[2],
+    }
+}
+
+fn pauli_from_bits(x: bool, z: bool) -> ErrorType {
+    match (x, z) {
+        (false, false) => I,
+        (true, false) => X,
+        (false, true) => Z,
+        (true, true) => Y,
+    }
+}
+
+/// sample [`BATCH_SIZE`] independent events of probability `p` as a bit mask, skipping geometrically over the shots without events
+fn random_mask(rng: &mut Xoroshiro128StarStar, p: f64) -> u64 {
+    if p <= 0. {
+        return 0;
+    }
+    if p >= 1. {
+        return u64::MAX;
+    }
+    let mut mask = 0;
+    if p > 0.1 {
+        for shot in 0..BATCH_SIZE {
+            if rng.next_f64() < p {
+                mask |= 1 << shot;
+            }
+        }
+        return mask;
+    }
+    let log_no_event = (-p).ln_1p();
+    let mut shot = 0;
+    loop {
+        let skip = ((1. - rng.next_f64()).ln() / log_no_event).floor();
+        if skip >= (BATCH_SIZE - shot) as f64 {
+            return mask;
+        }
+        shot += skip as usize;
+        mask |= 1 << shot;
+        shot += 1;
+    }
+}
+
+/// iterate over the shots whose bits are set
+fn mask_shots(mut mask: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (mask != 0).then(|| {
+            let shot = mask.trailing_zeros() as usize;
+            mask &= mask - 1;
+            shot
+        })
+    })
+}
+
+/// a node of the simulator with the information to propagate errors
+#[derive(Debug, Clone)]
+struct BatchNode {
+    position: Position,
+    /// the index in the bit-packed arrays
+    index: usize,
+    is_initialization: bool,
+    /// the index of the gate peer at the same time step, if exists
+    peer: Option<usize>,
+    /// the errors propagated to the peer when this node has an X or a Z error, if errors can propagate to the peer
+    propagate_peer: Option<[(u64, u64); 2]>,
+}
+
+/// a real stabilizer measurement that reports a defect when its outcome differs from the previous one
+#[derive(Debug, Clone)]
+struct BatchDetector {
+    position: Position,
+    index: usize,
+    previous_index: usize,
+    /// the measurement outcome flips when the propagated error has these X or Z bits
+    sensitivity: (u64, u64),
+    previous_sensitivity: (u64, u64),
+}
+
+#[cfg_attr(feature = "python_binding", cfg_eval)]
+#[cfg_attr(feature = "python_binding", pyclass)]
+#[derive(Debug)]
+pub struct SimulatorBatch {
+    /// the node graph, also used to validate corrections
+    simulator: Simulator,
+    /// use embedded random number generator
+    pub rng: Xoroshiro128StarStar,
+    /// nodes grouped by time, in the same order as [`Simulator::propagate_errors`]
+    layers: Vec<Vec<BatchNode>>,
+    detectors: Vec<BatchDetector>,
+    /// the number of indices in a time step
+    layer_size: usize,
+    error_x: Vec<u64>,
+    error_z: Vec<u64>,
+    erasure: Vec<u64>,
+    propagated_x: Vec<u64>,
+    propagated_z: Vec<u64>,
+    /// the current shot in the batch
+    pub shot: usize,
+    error_counts: [usize; BATCH_SIZE],
+    erasure_counts: [usize; BATCH_SIZE],
+    sparse_measurements: Vec<SparseMeasurement>,
+    sparse_detected_erasures: Vec<SparseErasures>,
+    /// the logical errors flipped by each top-layer node, if the logical errors of the code are the parities of these flips
+    logical_flips: Option<HashMap<usize, LogicalFlips>>,
+    /// the logical errors of all the shots before any correction, one bit per shot
+    logical_errors: (u64, u64),
+}
+
+impl Clone for SimulatorBatch {
+    fn clone(&self) -> Self {
+        Self {
+            simulator: self.simulator.clone(),
+            rng: Xoroshiro128StarStar::new(), // do not copy random number generator, otherwise parallel simulation may give same result
+            layers: self.layers.clone(),
+            detectors: self.detectors.clone(),
+            layer_size: self.layer_size,
+            error_x: self.error_x.clone(),
+            error_z: self.error_z.clone(),
+            erasure: self.erasure.clone(),
+            propagated_x: self.propagated_x.clone(),
+            propagated_z: self.propagated_z.clone(),
+            shot: BATCH_SIZE, // the batch is regenerated with the new random number generator
+            error_counts: self.error_counts,
+            erasure_counts: self.erasure_counts,
+            sparse_measurements: vec![],
+            sparse_detected_erasures: vec![],
+            logical_flips: self.logical_flips.clone(),
+            logical_errors: self.logical_errors,
+        }
+    }
+}
+
+impl SimulatorBatch {
+    pub fn from_simulator(mut simulator: Simulator) -> Self {
+        simulator.clear_all_errors();
+        let (layer_size, horizontal) = (simulator.vertical * simulator.horizontal, simulator.horizontal);
+        let index_of = |position: &Position| position.t * layer_size + position.i * horizontal + position.j;
+        let mut layers = vec![vec![]; simulator.height];
+        simulator_iter!(simulator, position, node, {
+            let peer = node.gate_peer.as_ref().map(|peer| index_of(peer));
+            // propagation from virtual to real is forbidden
+            let propagate_to_peer_forbidden = node.is_virtual && !node.is_peer_virtual;
+            let propagate_peer = (node.gate_type.is_two_qubit_gate() && !propagate_to_peer_forbidden)
+                .then(|| [X, Z].map(|error| pauli_bits(node.gate_type.propagate_peer(&error))));
+            layers[position.t].push(BatchNode {
+                position: position.clone(),
+                index: index_of(position),
+                is_initialization: node.gate_type.is_initialization(),
+                peer,
+                propagate_peer,
+            });
+        });
+        let sensitivity = |gate_type: GateType| {
+            let (x, _) = pauli_bits(if gate_type.stabilizer_measurement(&X) { X } else { I });
+            let (z, _) = pauli_bits(if gate_type.stabilizer_measurement(&Z) { X } else { I });
+            (x, z)
+        };
+        let mut detectors = vec![];
+        for t in (simulator.measurement_cycles..simulator.height).step_by(simulator.measurement_cycles) {
+            simulator_iter_real!(simulator, position, node, t => t, {
+                if node.gate_type.is_measurement() {
+                    let mut previous_position = position.clone();
+                    loop {
+                        previous_position.t -= simulator.measurement_cycles;
+                        let previous_node = simulator.get_node_unwrap(&previous_position);
+                        if previous_node.gate_type.is_measurement() {
+                            detectors.push(BatchDetector {
+                                position: position.clone(),
+                                index: index_of(position),
+                                previous_index: index_of(&previous_position),
+                                sensitivity: sensitivity(node.gate_type),
+                                previous_sensitivity: sensitivity(previous_node.gate_type),
+                            });
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+        let size = simulator.height * layer_size;
+        let logical_flips = Self::logical_flips(&mut simulator, index_of);
+        Self {
+            simulator,
+            rng: Xoroshiro128StarStar::new(),
+            layers,
+            detectors,
+            layer_size,
+            error_x: vec![0; size],
+            error_z: vec![0; size],
+            erasure: vec![0; size],
+            propagated_x: vec![0; size],
+            propagated_z: vec![0; size],
+            shot: BATCH_SIZE, // the first shot generates a new batch
+            error_counts: [0; BATCH_SIZE],
+            erasure_counts: [0; BATCH_SIZE],
+            sparse_measurements: vec![],
+            sparse_detected_erasures: vec![],
+            logical_flips,
+            logical_errors: (0, 0),
+        }
+    }
+
+    /// probe [`code_builder_validate_correction`] with a single Pauli error at every top-layer node; this is exact when every
+    /// logical error is the parity of the individual flips, which holds for all the codes except the periodic tailored code
+    fn logical_flips(
+        simulator: &mut Simulator,
+        index_of: impl Fn(&Position) -> usize,
+    ) -> Option<HashMap<usize, LogicalFlips>> {
+        if simulator.code_type == CodeType::PeriodicRotatedTailoredCode {
+            return None; // a logical error is the OR of two parities
+        }
+        let (logical_i, logical_j) = code_builder_validate_correction(simulator, &SparseCorrection::new())?;
+        let top_t = simulator.height - 1;
+        let mut positions = vec![];
+        simulator_iter!(simulator, position, _node, t => top_t, {
+            positions.push(position.clone());
+        });
+        let mut logical_flips = HashMap::new();
+        for position in positions {
+            let flips = [X, Z, Y].map(|error| {
+                let mut correction = SparseCorrection::new();
+                correction.add(position.clone(), error);
+                let (flipped_i, flipped_j) = code_builder_validate_correction(simulator, &correction).unwrap();
+                (flipped_i != logical_i, flipped_j != logical_j)
+            });
+            if flips != [(false, false); 3] {
+                logical_flips.insert(index_of(&position), flips);
+            }
+        }
+        Some(logical_flips)
+    }
+
+    fn index_of(&self, position: &Position) -> usize {
+        position.t * self.layer_size + position.i * self.simulator.horizontal + position.j
+    }
+
+    fn add_error(&mut self, index: usize, error: ErrorType, mask: u64) {
+        let (x, z) = pauli_bits(error);
+        self.error_x[index] ^= x & mask;
+        self.error_z[index] ^= z & mask;
+    }
+
+    /// sample the random errors of [`BATCH_SIZE`] shots following the same noise model as [`Simulator::generate_random_errors`]
+    pub fn generate_batch(&mut self, noise_model: &NoiseModel) {
+        assert!(
+            noise_model.cosmic_ray.is_none(),
+            "batch simulator cannot simulate cosmic-ray events, use the full simulator"
+        );
+        let mut rng = self.rng.clone(); // avoid mutable borrow
+        for array in [&mut self.error_x, &mut self.error_z, &mut self.erasure] {
+            array.fill(0);
+        }
+        let layers = std::mem::take(&mut self.layers);
+        for node in layers.iter().flatten() {
+            let noise_model_node = noise_model.get_node_unwrap(&node.position);
+            assert!(
+                noise_model_node.leakage_rate == 0.,
+                "batch simulator cannot simulate leakage because it's not independent between rounds, use the full simulator"
+            );
+            let pauli_error_rates = &noise_model_node.pauli_error_rates;
+            let p = pauli_error_rates.error_probability();
+            for shot in mask_shots(random_mask(&mut rng, p)) {
+                let random_pauli = rng.next_f64() * p;
+                let error = if random_pauli < pauli_error_rates.error_rate_X {
+                    X
+                } else if random_pauli < pauli_error_rates.error_rate_X + pauli_error_rates.error_rate_Z {
+                    Z
+                } else {
+                    Y
+                };
+                self.add_error(node.index, error, 1 << shot);
+            }
+            self.erasure[node.index] |= random_mask(&mut rng, noise_model_node.erasure_error_rate);
+            if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
+                let p = correlated_pauli_error_rates.error_probability();
+                for shot in mask_shots(random_mask(&mut rng, p)) {
+                    let error_type = correlated_pauli_error_rates.generate_random_error(rng.next_f64() * p);
+                    let peer = node
+                        .peer
+                        .expect("correlated pauli error must corresponds to a two-qubit gate");
+                    self.add_error(node.index, error_type.my_error(), 1 << shot);
+                    self.add_error(peer, error_type.peer_error(), 1 << shot);
+                }
+            }
+            if let Some(correlated_erasure_error_rates) = &noise_model_node.correlated_erasure_error_rates {
+                let p = correlated_erasure_error_rates.error_probability();
+                for shot in mask_shots(random_mask(&mut rng, p)) {
+                    let error_type = correlated_erasure_error_rates.generate_random_erasure_error(rng.next_f64() * p);
+                    let peer = node
+                        .peer
+                        .expect("correlated erasure error must corresponds to a two-qubit gate");
+                    if error_type.my_error() {
+                        self.erasure[node.index] |= 1 << shot;
+                    }
+                    if error_type.peer_error() {
+                        self.erasure[peer] |= 1 << shot;
+                    }
+                }
+            }
+        }
+        for additional_noise in noise_model.additional_noise.iter() {
+            let mask = random_mask(&mut rng, additional_noise.probability);
+            for position in additional_noise.erasures.iter() {
+                let index = self.index_of(position);
+                self.erasure[index] |= mask;
+            }
+            for (position, error) in additional_noise.pauli_errors.iter() {
+                self.add_error(self.index_of(position), *error, mask);
+            }
+        }
+        // an erased qubit suffers from a uniformly random Pauli error, regardless of the previous errors
+        for node in layers.iter().flatten() {
+            let erasure = self.erasure[node.index];
+            if erasure != 0 {
+                self.error_x[node.index] = (self.error_x[node.index] & !erasure) | (rng.next_u64() & erasure);
+                self.error_z[node.index] = (self.error_z[node.index] & !erasure) | (rng.next_u64() & erasure);
+            }
+        }
+        // save the random number generator
+        self.rng = rng;
+        // propagate errors
+        self.propagated_x.fill(0);
+        self.propagated_z.fill(0);
+        for node in layers[..layers.len() - 1].iter().flatten() {
+            let (propagated_x, propagated_z) = (self.propagated_x[node.index], self.propagated_z[node.index]);
+            let next = node.index + self.layer_size;
+            self.propagated_x[next] ^= self.error_x[node.index] ^ propagated_x;
+            self.propagated_z[next] ^= self.error_z[node.index] ^ propagated_z;
+            if node.is_initialization {
+                self.propagated_x[next] = 0; // no error after initialization
+                self.propagated_z[next] = 0;
+            }
+            if let Some([(x_to_x, x_to_z), (z_to_x, z_to_z)]) = node.propagate_peer {
+                let peer_next = node.peer.unwrap() + self.layer_size;
+                self.propagated_x[peer_next] ^= (propagated_x & x_to_x) ^ (propagated_z & z_to_x);
+                self.propagated_z[peer_next] ^= (propagated_x & x_to_z) ^ (propagated_z & z_to_z);
+            }
+        }
+        // collect the results of each shot
+        self.error_counts = [0; BATCH_SIZE];
+        self.erasure_counts = [0; BATCH_SIZE];
+        self.sparse_measurements = vec![SparseMeasurement::new(); BATCH_SIZE];
+        self.sparse_detected_erasures = vec![SparseErasures::new(); BATCH_SIZE];
+        for node in layers.iter().flatten() {
+            for shot in mask_shots(self.error_x[node.index] | self.error_z[node.index]) {
+                self.error_counts[shot] += 1;
+            }
+            let is_real = self.simulator.is_node_real(&node.position);
+            if is_real {
+                for shot in mask_shots(self.erasure[node.index]) {
+                    self.erasure_counts[shot] += 1;
+                    self.sparse_detected_erasures[shot].erasures.insert(node.position.clone());
+                }
+            }
+        }
+        for detector in self.detectors.iter() {
+            let outcome = |index: usize, (x, z): (u64, u64)| (self.propagated_x[index] & x) ^ (self.propagated_z[index] & z);
+            let defects = outcome(detector.index, detector.sensitivity)
+                ^ outcome(detector.previous_index, detector.previous_sensitivity);
+            for shot in mask_shots(defects) {
+                self.sparse_measurements[shot].insert_defect_measurement(&detector.position);
+            }
+        }
+        if let Some(logical_flips) = self.logical_flips.as_ref() {
+            self.logical_errors = (0, 0);
+            for (&index, flips) in logical_flips.iter() {
+                let (x, z) = (self.propagated_x[index], self.propagated_z[index]);
+                for (shots, &(flip_i, flip_j)) in [x & !z, !x & z, x & z].iter().zip(flips.iter()) {
+                    if flip_i {
+                        self.logical_errors.0 ^= shots;
+                    }
+                    if flip_j {
+                        self.logical_errors.1 ^= shots;
+                    }
+                }
+            }
+        }
+        self.layers = layers;
+        self.shot = 0;
+    }
+
+    /// validate the corrections of the first `corrections.len()` shots of the current batch
+    pub fn validate_batch_corrections(&mut self, corrections: &[SparseCorrection]) -> Vec<(bool, bool)> {
+        assert!(corrections.len() <= BATCH_SIZE, "at most {BATCH_SIZE} corrections in a batch");
+        corrections
+            .iter()
+            .enumerate()
+            .map(|(shot, correction)| self.validate_shot_correction(shot, correction))
+            .collect()
+    }
+
+    fn validate_shot_correction(&mut self, shot: usize, correction: &SparseCorrection) -> (bool, bool) {
+        let bit = 1 << shot;
+        let top_t = self.simulator.height - 1;
+        if let Some(logical_flips) = self.logical_flips.as_ref() {
+            // only the nodes touched by the correction change the logical errors
+            let (mut logical_i, mut logical_j) = (self.logical_errors.0 & bit != 0, self.logical_errors.1 & bit != 0);
+            for (position, error) in correction.iter() {
+                assert_eq!(position.t, top_t, "correction pattern must only be at top layer");
+                let index = self.index_of(position);
+                if let Some(flips) = logical_flips.get(&index) {
+                    let propagated =
+                        pauli_from_bits(self.propagated_x[index] & bit != 0, self.propagated_z[index] & bit != 0);
+                    let before = logical_flip(flips, propagated);
+                    let after = logical_flip(flips, propagated.multiply(error));
+                    logical_i ^= before.0 != after.0;
+                    logical_j ^= before.1 != after.1;
+                }
+            }
+            return (logical_i, logical_j);
+        }
+        // load the propagated errors of the shot at the top layer
+        for node in self.layers.last().unwrap().iter() {
+            let propagated = pauli_from_bits(
+                self.propagated_x[node.index] & bit != 0,
+                self.propagated_z[node.index] & bit != 0,
+            );
+            self.simulator.get_node_mut_unwrap(&node.position).propagated = propagated;
+        }
+        if let Some((logical_i, logical_j)) = code_builder_validate_correction(&mut self.simulator, correction) {
+            return (logical_i, logical_j);
+        }
+        unimplemented!("correction validation method not found for this code");
+    }
+}
+
+#[cfg(feature = "python_binding")]
+bind_trait_simulator_generics! {SimulatorBatch}
+
+impl SimulatorGenerics for SimulatorBatch {
+    fn set_rng(&mut self, rng: Xoroshiro128StarStar) {
+        self.rng = rng;
+        self.shot = BATCH_SIZE; // discard the current batch
+    }
+    fn generate_random_errors(&mut self, noise_model: &NoiseModel) -> (usize, usize) {
+        if self.shot + 1 >= BATCH_SIZE {
+            self.generate_batch(noise_model);
+        } else {
+            self.shot += 1;
+        }
+        (self.error_counts[self.shot], self.erasure_counts[self.shot])
+    }
+    fn generate_sparse_detected_erasures(&self) -> SparseErasures {
+        self.sparse_detected_erasures[self.shot].clone()
+    }
+    fn generate_sparse_error_pattern(&self) -> SparseErrorPattern {
+        let mut sparse_error_pattern = SparseErrorPattern::new();
+        for node in self.layers.iter().flatten() {
+            let bit = 1 << self.shot;
+            let error = pauli_from_bits(self.error_x[node.index] & bit != 0, self.error_z[node.index] & bit != 0);
+            if error != I {
+                sparse_error_pattern.add(node.position.clone(), error);
+            }
+        }
+        sparse_error_pattern
+    }
+    fn generate_sparse_measurement(&self) -> SparseMeasurement {
+        self.sparse_measurements[self.shot].clone()
+    }
+    fn validate_correction(&mut self, correction: &SparseCorrection) -> (bool, bool) {
+        self.validate_shot_correction(self.shot, correction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise_model_builder::*;
+    use rand_core::SeedableRng;
+    use std::sync::Arc;
+
+    #[test]
+    fn simulator_batch_random_mask() {
+        // cargo test simulator_batch_random_mask -- --nocapture
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(0);
+        for p in [0.001, 0.05, 0.3] {
+            let batches = 20000;
+            let events: u32 = (0..batches).map(|_| random_mask(&mut rng, p).count_ones()).sum();
+            let average = events as f64 / (batches * BATCH_SIZE) as f64;
+            println!("p = {p}, average = {average}");
+            assert!((average - p).abs() < 0.1 * p, "p = {p}, average = {average}");
+        }
+    }
+
+    /// every shot of the batch simulator must be consistent with [`Simulator`] given the same errors
+    fn simulator_batch_consistency(simulator: Simulator, noise_model: &NoiseModel) {
+        let mut simulator_batch = SimulatorBatch::from_simulator(simulator.clone());
+        simulator_batch.set_rng(Xoroshiro128StarStar::seed_from_u64(0));
+        let mut simulator = simulator;
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(1);
+        let top_t = simulator.height - 1;
+        let mut data_positions = vec![];
+        simulator_iter_real!(simulator, position, node, t => top_t, {
+            if node.qubit_type == QubitType::Data {
+                data_positions.push(position.clone());
+            }
+        });
+        let mut has_defect = false;
+        let mut has_logical_error = false;
+        let mut corrections = vec![];
+        let mut logical_errors = vec![];
+        for _ in 0..2 * BATCH_SIZE {
+            let (error_count, erasure_count) = simulator_batch.generate_random_errors(noise_model);
+            let sparse_error_pattern = simulator_batch.generate_sparse_error_pattern();
+            assert_eq!(error_count, sparse_error_pattern.len());
+            assert!(erasure_count >= simulator_batch.generate_sparse_detected_erasures().len());
+            simulator
+                .load_sparse_error_pattern(&sparse_error_pattern, noise_model)
+                .unwrap();
+            simulator.clear_propagate_errors();
+            simulator.propagate_errors();
+            let sparse_measurement = simulator_batch.generate_sparse_measurement();
+            assert_eq!(sparse_measurement.to_vec(), simulator.generate_sparse_measurement().to_vec());
+            has_defect |= !sparse_measurement.is_empty();
+            assert_eq!(
+                simulator_batch.validate_correction(&SparseCorrection::new()),
+                simulator.validate_correction(&SparseCorrection::new())
+            );
+            let mut correction = SparseCorrection::new();
+            for position in data_positions.iter() {
+                if rng.next_f64() < 0.2 {
+                    correction.add(position.clone(), [X, Z, Y][rng.next_u32() as usize % 3]);
+                }
+            }
+            let logical_error = simulator_batch.validate_correction(&correction);
+            assert_eq!(logical_error, simulator.validate_correction(&correction));
+            has_logical_error |= logical_error != (false, false);
+            corrections.push(correction);
+            logical_errors.push(logical_error);
+            if simulator_batch.shot == BATCH_SIZE - 1 {
+                assert_eq!(simulator_batch.validate_batch_corrections(&corrections), logical_errors);
+                corrections.clear();
+                logical_errors.clear();
+            }
+        }
+        assert!(has_defect);
+        assert!(has_logical_error);
+    }
+
+    #[test]
+    fn simulator_batch_consistent_with_simulator() {
+        // cargo test simulator_batch_consistent_with_simulator -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel
+            .apply(&mut simulator, &mut noise_model, &json!({}), 0.02, 0.5, 0.)
+            .unwrap();
+        simulator_batch_consistency(simulator, &noise_model);
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.01, 0.01, 0.01, 0.05);
+        simulator_batch_consistency(simulator, &noise_model);
+        // the logical errors of the color code are computed by the bit-packed parities as well
+        let mut simulator = Simulator::new(CodeType::ColorCode666, CodeSize::new(2, 5, 5));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.01, 0.01, 0.01, 0.);
+        assert!(SimulatorBatch::from_simulator(simulator.clone()).logical_flips.is_some());
+        simulator_batch_consistency(simulator, &noise_model);
+        // the logical errors of the periodic tailored code are not parities, so each shot is validated individually
+        let mut simulator = Simulator::new(CodeType::PeriodicRotatedTailoredCode, CodeSize::new(2, 4, 4));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.01, 0.01, 0.01, 0.);
+        assert!(SimulatorBatch::from_simulator(simulator.clone()).logical_flips.is_none());
+        simulator_batch_consistency(simulator, &noise_model);
+    }
+
+    #[test]
+    fn simulator_batch_statistics() {
+        // cargo test simulator_batch_statistics -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel
+            .apply(&mut simulator, &mut noise_model, &json!({}), 0.005, 0.5, 0.)
+            .unwrap();
+        let noise_model = Arc::new(noise_model);
+        let mut simulator_batch = SimulatorBatch::from_simulator(simulator.clone());
+        simulator.set_rng(Xoroshiro128StarStar::seed_from_u64(0));
+        simulator_batch.set_rng(Xoroshiro128StarStar::seed_from_u64(1));
+        let shots = 5000;
+        let mut statistics = [[0.; 2]; 2];
+        for _ in 0..shots {
+            let (error_count, _) = simulator.generate_random_errors(&noise_model);
+            statistics[0][0] += error_count as f64 / shots as f64;
+            statistics[0][1] += simulator.generate_sparse_measurement().len() as f64 / shots as f64;
+            let (error_count, _) = simulator_batch.generate_random_errors(&noise_model);
+            statistics[1][0] += error_count as f64 / shots as f64;
+            statistics[1][1] += simulator_batch.generate_sparse_measurement().len() as f64 / shots as f64;
+        }
+        println!("statistics: {:?}", statistics);
+        for index in 0..2 {
+            let relative_difference = (statistics[0][index] - statistics[1][index]).abs() / statistics[0][index];
+            assert!(relative_difference < 0.1, "statistics differ: {:?}", statistics);
+        }
+    }
+}
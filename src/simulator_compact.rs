@@ -1,10 +1,12 @@
 //! A compact simulator that tracks all the error sources globally and cache the defect measurements it generates.
 //!
 
+use super::code_builder::*;
 use super::either::Either;
 use super::noise_model::*;
 use super::reproducible_rand::Xoroshiro128StarStar;
 use super::simulator::*;
+use super::stim_circuit::*;
 use super::types::*;
 use super::util_macros::*;
 use serde::{Deserialize, Serialize};
@@ -30,6 +32,9 @@ pub struct SimulatorCompact {
     /// the measured defects
     #[serde(skip)]
     defects: BTreeSet<Position>,
+    /// the detected erasures
+    #[serde(skip)]
+    erasures: BTreeSet<Position>,
     /// optional simulator for the purpose of validate the correction
     #[serde(skip)]
     simulator: Option<Simulator>,
@@ -43,6 +48,7 @@ impl Clone for SimulatorCompact {
             errors: BTreeMap::new(),
             corrections: BTreeMap::new(),
             defects: BTreeSet::new(),
+            erasures: BTreeSet::new(),
             simulator: self.simulator.clone(),
         }
     }
@@ -56,9 +62,27 @@ pub enum ErrorSource {
         errors: Vec<(Position, ErrorType)>,
         correction: Vec<(Position, ErrorType)>,
     },
+    /// heralded erasure of one or two qubits, which then suffer from uniformly random Pauli errors;
+    /// each Pauli error is an `ErrorSource::Pauli` whose `p` is the probability conditioned on the erasure,
+    /// and the undetectable ones (including the identity) are omitted
+    Erasure {
+        p: f64,
+        erasures: Vec<Position>,
+        paulis: Vec<ErrorSource>,
+    },
 }
 
+/// a deterministic outcome of an error source: the index of the error source and, for an erasure error source,
+/// the index of the Pauli error it causes, or `None` for an undetectable Pauli error
+pub type CompactFault = (usize, Option<usize>);
+
 impl ErrorSource {
+    /// the probability that this error source happens
+    pub fn probability(&self) -> f64 {
+        match self {
+            Self::Pauli { p, .. } | Self::Erasure { p, .. } => *p,
+        }
+    }
     pub fn get_error_t(&self) -> usize {
         match self {
             Self::Pauli { errors, .. } => {
@@ -69,6 +93,14 @@ impl ErrorSource {
                 );
                 t
             }
+            Self::Erasure { erasures, .. } => {
+                let t = erasures[0].t;
+                debug_assert!(
+                    erasures.iter().all(|position| position.t == t),
+                    "an error source cannot happen at multiple time point..."
+                );
+                t
+            }
         }
     }
     pub fn get_correction_t(&self) -> usize {
@@ -81,6 +113,7 @@ impl ErrorSource {
                 );
                 t
             }
+            Self::Erasure { paulis, .. } => paulis[0].get_correction_t(),
         }
     }
     pub fn shift_error_t(&mut self, delta_t: usize) {
@@ -93,6 +126,14 @@ impl ErrorSource {
                     position.t += delta_t;
                 }
             }
+            Self::Erasure { erasures, paulis, .. } => {
+                for position in erasures.iter_mut() {
+                    position.t += delta_t;
+                }
+                for pauli in paulis.iter_mut() {
+                    pauli.shift_error_t(delta_t);
+                }
+            }
         }
     }
     pub fn shift_correction_t(&mut self, delta_height: usize) {
@@ -102,6 +143,116 @@ impl ErrorSource {
                     position.t += delta_height;
                 }
             }
+            Self::Erasure { paulis, .. } => {
+                for pauli in paulis.iter_mut() {
+                    pauli.shift_correction_t(delta_height);
+                }
+            }
+        }
+    }
+    /// apply a Pauli error source that happens, where the errors and the defects are shifted by `delta_t`
+    fn apply_pauli(
+        &self,
+        sparse_errors: &mut BTreeMap<Position, ErrorType>,
+        sparse_corrections: &mut BTreeMap<Position, ErrorType>,
+        sparse_defects: &mut BTreeSet<Position>,
+        error_count: &mut usize,
+        delta_t: usize,
+    ) {
+        let Self::Pauli {
+            errors,
+            defects,
+            correction,
+            ..
+        } = self
+        else {
+            unreachable!("only Pauli error source can be applied")
+        };
+        // apply error
+        for (position, error) in errors.iter() {
+            let mut position = position.clone();
+            position.t += delta_t;
+            if let Some(existing_error) = sparse_errors.get_mut(&position) {
+                if *existing_error != I {
+                    *error_count -= 1;
+                }
+                *existing_error = existing_error.multiply(error);
+                if *existing_error != I {
+                    *error_count += 1;
+                }
+            } else {
+                sparse_errors.insert(position, *error);
+                if *error != I {
+                    *error_count += 1;
+                }
+            }
+        }
+        // apply perfect correction
+        for (position, correct_pauli) in correction.iter() {
+            if let Some(existing_correct_pauli) = sparse_corrections.get_mut(position) {
+                *existing_correct_pauli = existing_correct_pauli.multiply(correct_pauli);
+            } else {
+                sparse_corrections.insert(position.clone(), *correct_pauli);
+            }
+        }
+        // apply defect measurements
+        for position in defects.iter() {
+            let mut position = position.clone();
+            position.t += delta_t;
+            if sparse_defects.contains(&position) {
+                sparse_defects.remove(&position);
+            } else {
+                sparse_defects.insert(position);
+            }
+        }
+    }
+    /// apply the error source if it happens, where the errors and the defects are shifted by `delta_t`;
+    /// return the erased positions (not shifted) if it's an erasure error source that happens
+    fn apply_random(
+        &self,
+        rng: &mut Xoroshiro128StarStar,
+        sparse_errors: &mut BTreeMap<Position, ErrorType>,
+        sparse_corrections: &mut BTreeMap<Position, ErrorType>,
+        sparse_defects: &mut BTreeSet<Position>,
+        error_count: &mut usize,
+        delta_t: usize,
+    ) -> Option<&[Position]> {
+        let random_value = rng.next_f64();
+        if random_value >= self.probability() {
+            return None;
+        }
+        self.apply_happened(rng, sparse_errors, sparse_corrections, sparse_defects, error_count, delta_t)
+    }
+    /// apply the error source given that it happens, see [`Self::apply_random`]
+    fn apply_happened(
+        &self,
+        rng: &mut Xoroshiro128StarStar,
+        sparse_errors: &mut BTreeMap<Position, ErrorType>,
+        sparse_corrections: &mut BTreeMap<Position, ErrorType>,
+        sparse_defects: &mut BTreeSet<Position>,
+        error_count: &mut usize,
+        delta_t: usize,
+    ) -> Option<&[Position]> {
+        match self {
+            Self::Pauli { .. } => {
+                self.apply_pauli(sparse_errors, sparse_corrections, sparse_defects, error_count, delta_t);
+                None
+            }
+            Self::Erasure { erasures, paulis, .. } => {
+                // choose one of the Pauli errors, or an undetectable one
+                let mut random_pauli = rng.next_f64();
+                for pauli in paulis.iter() {
+                    let Self::Pauli { p, .. } = pauli else {
+                        unreachable!("the errors caused by erasure must be Pauli error sources")
+                    };
+                    if random_pauli < *p {
+                        pauli.apply_pauli(sparse_errors, sparse_corrections, sparse_defects, error_count, delta_t);
+                        break;
+                    }
+                    random_pauli -= p;
+                }
+                Some(erasures)
+            }
         }
     }
 }
@@ -119,57 +270,24 @@ impl SimulatorGenerics for SimulatorCompact {
         let mut rng = self.rng.clone(); // avoid mutable borrow
         let mut error_count = 0;
         for error_source in self.error_sources.iter() {
-            match error_source {
-                ErrorSource::Pauli {
-                    p,
-                    errors,
-                    defects,
-                    correction,
-                } => {
-                    let random_value = rng.next_f64();
-                    if random_value < *p {
-                        // apply error
-                        for (position, error) in errors.iter() {
-                            if let Some(existing_error) = self.errors.get_mut(position) {
-                                if *existing_error != I {
-                                    error_count -= 1;
-                                }
-                                *existing_error = existing_error.multiply(error);
-                                if *existing_error != I {
-                                    error_count += 1;
-                                }
-                            } else {
-                                self.errors.insert(position.clone(), *error);
-                                if *error != I {
-                                    error_count += 1;
-                                }
-                            }
-                        }
-                        // apply perfect correction
-                        for (position, correct_pauli) in correction.iter() {
-                            if let Some(existing_correct_pauli) = self.corrections.get_mut(position) {
-                                *existing_correct_pauli = existing_correct_pauli.multiply(correct_pauli);
-                            } else {
-                                self.corrections.insert(position.clone(), *correct_pauli);
-                            }
-                        }
-                        // apply defect measurements
-                        for position in defects.iter() {
-                            if self.defects.contains(position) {
-                                self.defects.remove(position);
-                            } else {
-                                self.defects.insert(position.clone());
-                            }
-                        }
-                    }
-                }
+            if let Some(erasures) = error_source.apply_random(
+                &mut rng,
+                &mut self.errors,
+                &mut self.corrections,
+                &mut self.defects,
+                &mut error_count,
+                0,
+            ) {
+                self.erasures.extend(erasures.iter().cloned());
             }
         }
         self.rng = rng; // save the random number generator
-        (error_count, 0) // doesn't support erasure errors yet
+        (error_count, self.erasures.len())
     }
     fn generate_sparse_detected_erasures(&self) -> SparseErasures {
-        SparseErasures::new() // doesn't support erasure errors yet
+        SparseErasures {
+            erasures: self.erasures.clone(),
+        }
     }
     fn generate_sparse_error_pattern(&self) -> SparseErrorPattern {
         SparseErrorPattern::new_map(self.errors.clone())
@@ -184,7 +302,8 @@ impl SimulatorGenerics for SimulatorCompact {
         );
         let simulator = self.simulator.as_mut().unwrap();
         let top_t = simulator.height - 1;
-        simulator_iter_mut_real!(simulator, position, node, t => top_t, {  // only clear propagated errors on top later
+        // only clear propagated errors on top layer, including the virtual nodes that may carry logical observables
+        simulator_iter_mut!(simulator, position, node, t => top_t, {
             node.propagated = I;
         });
         // set the desired correction, which is the result of the final propagated errors
@@ -206,12 +325,17 @@ impl SimulatorGenerics for SimulatorCompact {
 
 impl SimulatorCompact {
     pub fn from_simulator(mut simulator: Simulator, noise_model: Arc<NoiseModel>, parallel: usize) -> Self {
+        assert!(
+            noise_model.cosmic_ray.is_none(),
+            "compact simulator cannot simulate cosmic-ray events, use the full simulator"
+        );
         let mut simulator_compact = Self {
             error_sources: vec![],
             rng: Xoroshiro128StarStar::new(),
             errors: BTreeMap::new(),
             corrections: BTreeMap::new(),
             defects: BTreeSet::new(),
+            erasures: BTreeSet::new(),
             simulator: None,
         };
         if parallel <= 1 {
@@ -249,6 +373,12 @@ impl SimulatorCompact {
         simulator_compact
     }
 
+    /// load a Stim detector error model, see [`crate::stim_circuit::simulator_from_detector_error_model`]
+    pub fn from_detector_error_model(detector_error_model: &str, parallel: usize) -> Result<Self, String> {
+        let (simulator, noise_model, _noise_model_graph) = simulator_from_detector_error_model(detector_error_model)?;
+        Ok(Self::from_simulator(simulator, Arc::new(noise_model), parallel))
+    }
+
     fn build_error_sources_region(
         &mut self,
         simulator: &mut Simulator,
@@ -272,22 +402,10 @@ impl SimulatorCompact {
                 continue;
             }
             let noise_model_node = noise_model.get_node_unwrap(position);
-            // whether it's possible to have erasure error at this node
-            let possible_erasure_error =
-                noise_model_node.erasure_error_rate > 0. || noise_model_node.correlated_erasure_error_rates.is_some() || {
-                    let node = simulator.get_node_unwrap(position);
-                    if let Some(gate_peer) = node.gate_peer.as_ref() {
-                        let peer_noise_model_node = noise_model.get_node_unwrap(gate_peer);
-                        if let Some(correlated_erasure_error_rates) = &peer_noise_model_node.correlated_erasure_error_rates {
-                            correlated_erasure_error_rates.error_probability() > 0.
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    }
-                };
-            assert!(!possible_erasure_error, "not implemented");
+            assert!(
+                noise_model_node.leakage_rate == 0.,
+                "compact simulator cannot simulate leakage because it's not independent between rounds, use the full simulator"
+            );
             for error in all_possible_errors.iter() {
                 let p = match error {
                     Either::Left(error_type) => noise_model_node.pauli_error_rates.error_rate(error_type),
@@ -329,13 +447,228 @@ impl SimulatorCompact {
                     })
                 }
             }
+            // erasure errors at this node, including the correlated ones at two-qubit gates
+            let mut possible_erasures = vec![(noise_model_node.erasure_error_rate, vec![position.clone()])];
+            if let Some(correlated_erasure_error_rates) = &noise_model_node.correlated_erasure_error_rates {
+                let node = simulator.get_node_unwrap(position);
+                let gate_peer = node
+                    .gate_peer
+                    .as_ref()
+                    .expect("correlated erasure error must corresponds to a two-qubit gate");
+                for error_type in CorrelatedErasureErrorType::all_possible_errors().drain(..) {
+                    let mut erasures = vec![];
+                    if error_type.my_error() {
+                        erasures.push(position.clone());
+                    }
+                    if error_type.peer_error() {
+                        erasures.push((**gate_peer).clone());
+                    }
+                    possible_erasures.push((correlated_erasure_error_rates.error_rate(&error_type), erasures));
+                }
+            }
+            for (p, erasures) in possible_erasures.into_iter() {
+                if p > 0. && !erasures.is_empty() {
+                    let paulis = Self::build_erasure_paulis(simulator, &erasures);
+                    // only the erasures of real qubits are detected, the same as the other simulators
+                    let erasures = erasures
+                        .into_iter()
+                        .filter(|position| simulator.is_node_real(position))
+                        .collect();
+                    self.error_sources.push(ErrorSource::Erasure { p, erasures, paulis });
+                }
+            }
         });
     }
 
+    /// an erased qubit suffers from a uniformly random Pauli error, the same as [`Simulator::generate_random_errors`];
+    /// only the detectable Pauli errors are kept, each with its probability conditioned on the erasure
+    fn build_erasure_paulis(simulator: &mut Simulator, erasures: &[Position]) -> Vec<ErrorSource> {
+        let mut combinations: Vec<Vec<(Position, ErrorType)>> = vec![vec![]];
+        for position in erasures.iter() {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    [I, X, Z, Y].into_iter().map(move |error| {
+                        let mut combination = combination.clone();
+                        if error != I {
+                            combination.push((position.clone(), error));
+                        }
+                        combination
+                    })
+                })
+                .collect();
+        }
+        let conditional_p = 1. / combinations.len() as f64;
+        let mut paulis = vec![];
+        for combination in combinations.into_iter() {
+            if combination.is_empty() {
+                continue;
+            }
+            let mut sparse_errors = SparseErrorPattern::new();
+            for (position, error) in combination.into_iter() {
+                sparse_errors.add(position, error);
+            }
+            let (sparse_correction, sparse_measurement_real, _sparse_measurement_virtual) =
+                simulator.fast_measurement_given_few_errors(&sparse_errors);
+            let sparse_measurement_real = sparse_measurement_real.to_vec();
+            if sparse_measurement_real.is_empty() {
+                continue; // no way to detect it, ignore
+            }
+            paulis.push(ErrorSource::Pauli {
+                p: conditional_p,
+                defects: sparse_measurement_real,
+                correction: sparse_correction.to_vec(),
+                errors: sparse_errors.to_vec(),
+            });
+        }
+        paulis
+    }
+
+    /// all the possible faults, where each error source is a single fault unless it's an erasure error source,
+    /// which has a fault for each Pauli error it causes
+    pub fn faults(&self) -> Vec<CompactFault> {
+        let mut faults = vec![];
+        for (index, error_source) in self.error_sources.iter().enumerate() {
+            faults.push((index, None));
+            if let ErrorSource::Erasure { paulis, .. } = error_source {
+                faults.extend((0..paulis.len()).map(|pauli_index| (index, Some(pauli_index))));
+            }
+        }
+        faults
+    }
+
+    /// load the errors of faults that happen together, replacing the existing errors;
+    /// return the number of errors and erasures like [`SimulatorGenerics::generate_random_errors`]
+    pub fn load_faults(&mut self, faults: &[CompactFault]) -> (usize, usize) {
+        self.clear();
+        let mut error_count = 0;
+        for &(index, pauli_index) in faults.iter() {
+            let error_source = &self.error_sources[index];
+            match error_source {
+                ErrorSource::Pauli { .. } => error_source.apply_pauli(
+                    &mut self.errors,
+                    &mut self.corrections,
+                    &mut self.defects,
+                    &mut error_count,
+                    0,
+                ),
+                ErrorSource::Erasure { erasures, paulis, .. } => {
+                    if let Some(pauli_index) = pauli_index {
+                        paulis[pauli_index].apply_pauli(
+                            &mut self.errors,
+                            &mut self.corrections,
+                            &mut self.defects,
+                            &mut error_count,
+                            0,
+                        );
+                    }
+                    self.erasures.extend(erasures.iter().cloned());
+                }
+            }
+        }
+        (error_count, self.erasures.len())
+    }
+
     pub fn clear(&mut self) {
         self.errors.clear();
         self.corrections.clear();
         self.defects.clear();
+        self.erasures.clear();
+    }
+
+    /// all the detectors sorted by (t, i, j), including every real stabilizer measurement of the simulator that can
+    /// report a defect (in the same order as the `DETECTOR` instructions of [`crate::stim_circuit::simulator_to_stim_circuit`])
+    /// and every defect of the error sources; the index in this list is the detector index `D{index}` of the detector error model
+    pub fn detector_positions(&self) -> Vec<Position> {
+        let mut detector_positions = BTreeSet::new();
+        if let Some(simulator) = self.simulator.as_ref() {
+            for t in (simulator.measurement_cycles..simulator.height).step_by(simulator.measurement_cycles) {
+                simulator_iter_real!(simulator, position, node, t => t, {
+                    if node.gate_type.is_measurement() {
+                        detector_positions.insert(position.clone());
+                    }
+                });
+            }
+        }
+        for error_source in self.error_sources.iter() {
+            match error_source {
+                ErrorSource::Pauli { defects, .. } => detector_positions.extend(defects.iter().cloned()),
+                ErrorSource::Erasure { paulis, .. } => {
+                    for pauli in paulis.iter() {
+                        if let ErrorSource::Pauli { defects, .. } = pauli {
+                            detector_positions.extend(defects.iter().cloned());
+                        }
+                    }
+                }
+            }
+        }
+        detector_positions.into_iter().collect()
+    }
+
+    /// serialize the error sources as a Stim detector error model, where the logical observable `L0` (`L1`) is flipped
+    /// if the error source causes `logical_i` (`logical_j`) when no correction is applied
+    pub fn to_detector_error_model(&self) -> String {
+        let detector_positions = self.detector_positions();
+        let detector_indices: BTreeMap<&Position, usize> = detector_positions
+            .iter()
+            .enumerate()
+            .map(|(index, position)| (position, index))
+            .collect();
+        assert!(
+            self.simulator.is_some(),
+            "a simulator must be provided to find the logical observables"
+        );
+        let mut simulator = self.simulator.clone().unwrap();
+        let top_t = simulator.height - 1;
+        let mut detector_error_model = String::new();
+        for position in detector_positions.iter() {
+            detector_error_model += &format!(
+                "detector({}, {}, {}) D{}\n",
+                position.j, position.i, position.t, detector_indices[position]
+            );
+        }
+        // heralded erasures cannot be expressed, so their Pauli errors are listed as independent errors instead
+        let mut pauli_error_sources = vec![];
+        for error_source in self.error_sources.iter() {
+            match error_source {
+                ErrorSource::Pauli { p, .. } => pauli_error_sources.push((*p, error_source)),
+                ErrorSource::Erasure { p, paulis, .. } => {
+                    for pauli in paulis.iter() {
+                        if let ErrorSource::Pauli { p: conditional_p, .. } = pauli {
+                            pauli_error_sources.push((p * conditional_p, pauli));
+                        }
+                    }
+                }
+            }
+        }
+        for (p, error_source) in pauli_error_sources.into_iter() {
+            match error_source {
+                ErrorSource::Pauli { defects, correction, .. } => {
+                    let mut targets: Vec<String> = defects
+                        .iter()
+                        .map(|defect| format!("D{}", detector_indices[defect]))
+                        .collect();
+                    let mut sparse_correction = SparseCorrection::new();
+                    for (position, correct_pauli) in correction.iter() {
+                        let mut position = position.clone();
+                        position.t = top_t; // shift down, in case it's generated by an extender
+                        sparse_correction.add(position, *correct_pauli);
+                    }
+                    if let Some((logical_i, logical_j)) =
+                        code_builder_validate_correction(&mut simulator, &sparse_correction)
+                    {
+                        for (observable, flipped) in [logical_i, logical_j].into_iter().enumerate() {
+                            if flipped {
+                                targets.push(format!("L{}", observable));
+                            }
+                        }
+                    }
+                    detector_error_model += &format!("error({}) {}\n", p, targets.join(" "));
+                }
+                ErrorSource::Erasure { .. } => unreachable!(),
+            }
+        }
+        detector_error_model
     }
 
     pub fn assert_eq(&self, other: &Self) -> Result<(), String> {
@@ -413,62 +746,29 @@ impl SimulatorGenerics for SimulatorCompactCompressed {
         let mut base_errors = self.extender.base.errors.clone();
         let mut base_corrections: BTreeMap<Position, ErrorType> = self.extender.base.corrections.clone();
         let mut base_defects = self.extender.base.defects.clone();
+        let mut base_erasures = self.extender.base.erasures.clone();
         for (error_source, delta_t) in self.extender.iter(self.noisy_measurements) {
-            match &error_source {
-                ErrorSource::Pauli {
-                    p,
-                    errors,
-                    defects,
-                    correction,
-                } => {
-                    let random_value = rng.next_f64();
-                    if random_value < *p {
-                        // apply error
-                        for (position, error) in errors.iter() {
-                            let mut position = position.clone();
-                            position.t += delta_t;
-                            if let Some(existing_error) = base_errors.get_mut(&position) {
-                                if *existing_error != I {
-                                    error_count -= 1;
-                                }
-                                *existing_error = existing_error.multiply(error);
-                                if *existing_error != I {
-                                    error_count += 1;
-                                }
-                            } else {
-                                base_errors.insert(position.clone(), *error);
-                                if *error != I {
-                                    error_count += 1;
-                                }
-                            }
-                        }
-                        // apply perfect correction
-                        for (position, correct_pauli) in correction.iter() {
-                            if let Some(existing_correct_pauli) = base_corrections.get_mut(position) {
-                                *existing_correct_pauli = existing_correct_pauli.multiply(correct_pauli);
-                            } else {
-                                base_corrections.insert(position.clone(), *correct_pauli);
-                            }
-                        }
-                        // apply defect measurements
-                        for position in defects.iter() {
-                            let mut position = position.clone();
-                            position.t += delta_t;
-                            if base_defects.contains(&position) {
-                                base_defects.remove(&position);
-                            } else {
-                                base_defects.insert(position);
-                            }
-                        }
-                    }
+            if let Some(erasures) = error_source.apply_random(
+                &mut rng,
+                &mut base_errors,
+                &mut base_corrections,
+                &mut base_defects,
+                &mut error_count,
+                delta_t,
+            ) {
+                for position in erasures.iter() {
+                    let mut position = position.clone();
+                    position.t += delta_t;
+                    base_erasures.insert(position);
                 }
             }
         }
         self.extender.base.errors = base_errors;
         self.extender.base.corrections = base_corrections;
         self.extender.base.defects = base_defects;
+        self.extender.base.erasures = base_erasures;
         self.extender.base.rng = rng; // save the random number generator
-        (error_count, 0) // doesn't support erasure errors yet
+        (error_count, self.extender.base.erasures.len())
     }
     fn generate_sparse_detected_erasures(&self) -> SparseErasures {
         self.extender.base.generate_sparse_detected_erasures()
@@ -484,6 +784,156 @@ impl SimulatorGenerics for SimulatorCompactCompressed {
     }
 }
 
+/// Sample the error sources of a `SimulatorCompact` conditioned on exactly `weight` of them happening, which is used
+/// for importance sampling at low physical error rates: the logical error rate is recombined from the failure rate
+/// of each weight, see [`SimulatorCompactFixedWeight::estimate_logical_error_rate`].
+#[cfg_attr(feature = "python_binding", cfg_eval)]
+#[cfg_attr(feature = "python_binding", pyclass)]
+#[derive(Debug)]
+pub struct SimulatorCompactFixedWeight {
+    pub base: SimulatorCompact,
+    /// the number of happening error sources
+    pub weight: usize,
+    /// the maximum weight that can be sampled
+    pub max_weight: usize,
+    /// `weight_probabilities[j * (max_weight + 1) + k]` is the probability of exactly `k` error sources happening
+    /// among the error sources starting from index `j`
+    weight_probabilities: Arc<Vec<f64>>,
+}
+
+impl Clone for SimulatorCompactFixedWeight {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            weight: self.weight,
+            max_weight: self.max_weight,
+            weight_probabilities: self.weight_probabilities.clone(),
+        }
+    }
+}
+
+impl SimulatorCompactFixedWeight {
+    pub fn new(base: SimulatorCompact, max_weight: usize) -> Self {
+        let width = max_weight + 1;
+        let length = base.error_sources.len();
+        let mut weight_probabilities = vec![0.; (length + 1) * width];
+        weight_probabilities[length * width] = 1.;
+        for (j, error_source) in base.error_sources.iter().enumerate().rev() {
+            let p = error_source.probability();
+            for k in 0..width {
+                let mut probability = (1. - p) * weight_probabilities[(j + 1) * width + k];
+                if k > 0 {
+                    probability += p * weight_probabilities[(j + 1) * width + k - 1];
+                }
+                weight_probabilities[j * width + k] = probability;
+            }
+        }
+        Self {
+            base,
+            weight: 0,
+            max_weight,
+            weight_probabilities: Arc::new(weight_probabilities),
+        }
+    }
+
+    /// the probability of exactly `weight` error sources happening
+    pub fn weight_probability(&self, weight: usize) -> f64 {
+        assert!(weight <= self.max_weight);
+        self.weight_probabilities[weight]
+    }
+
+    /// the probability of more than `weight` error sources happening, which is not covered by sampling up to `weight`
+    pub fn tail_probability(&self, weight: usize) -> f64 {
+        let covered: f64 = (0..=weight).map(|weight| self.weight_probability(weight)).sum();
+        (1. - covered).max(0.)
+    }
+
+    /// recombine the `(shots, failed)` of each weight `0..results.len()` into the logical error rate, returning the estimated
+    /// logical error rate and the half width of its 95% confidence interval; the failure rate of each weight is bounded by
+    /// its Wilson score interval, so that a weight without any failed shot still contributes to the uncertainty.
+    /// the logical errors caused by more error sources are not included, which underestimates the logical error rate
+    /// by at most [`Self::tail_probability`] of the largest sampled weight
+    pub fn estimate_logical_error_rate(&self, results: &[(usize, usize)]) -> (f64, f64) {
+        assert!(results.len() <= self.max_weight + 1);
+        let mut logical_error_rate = 0.;
+        let mut variance = 0.;
+        for (weight, &(shots, failed)) in results.iter().enumerate() {
+            if shots == 0 {
+                continue;
+            }
+            let weight_probability = self.weight_probability(weight);
+            let failure_rate = failed as f64 / shots as f64;
+            logical_error_rate += weight_probability * failure_rate;
+            let half_width = wilson_score_upper_bound(shots, failed, 1.96) - failure_rate;
+            variance += weight_probability * weight_probability * half_width * half_width;
+        }
+        (logical_error_rate, variance.sqrt())
+    }
+}
+
+/// the upper bound of the Wilson score interval of a binomial proportion, which is positive even if `failed = 0`
+fn wilson_score_upper_bound(shots: usize, failed: usize, z: f64) -> f64 {
+    let n = shots as f64;
+    let p = failed as f64 / n;
+    let z2 = z * z;
+    let center = (p + z2 / (2. * n)) / (1. + z2 / n);
+    let half_width = z / (1. + z2 / n) * (p * (1. - p) / n + z2 / (4. * n * n)).sqrt();
+    (center + half_width).min(1.)
+}
+
+#[cfg(feature = "python_binding")]
+bind_trait_simulator_generics! {SimulatorCompactFixedWeight}
+
+impl SimulatorGenerics for SimulatorCompactFixedWeight {
+    fn set_rng(&mut self, rng: Xoroshiro128StarStar) {
+        self.base.rng = rng;
+    }
+
+    fn generate_random_errors(&mut self, _noise_model: &NoiseModel) -> (usize, usize) {
+        assert!(self.weight <= self.max_weight);
+        let width = self.max_weight + 1;
+        self.base.clear();
+        let mut rng = self.base.rng.clone(); // avoid mutable borrow
+        let mut error_count = 0;
+        let mut remaining = self.weight;
+        for (j, error_source) in self.base.error_sources.iter().enumerate() {
+            if remaining == 0 {
+                break;
+            }
+            // the probability of this error source happening, conditioned on exactly `remaining` error sources happening from now on
+            let happen = error_source.probability() * self.weight_probabilities[(j + 1) * width + remaining - 1];
+            if rng.next_f64() * self.weight_probabilities[j * width + remaining] >= happen {
+                continue;
+            }
+            remaining -= 1;
+            if let Some(erasures) = error_source.apply_happened(
+                &mut rng,
+                &mut self.base.errors,
+                &mut self.base.corrections,
+                &mut self.base.defects,
+                &mut error_count,
+                0,
+            ) {
+                self.base.erasures.extend(erasures.iter().cloned());
+            }
+        }
+        self.base.rng = rng; // save the random number generator
+        (error_count, self.base.erasures.len())
+    }
+    fn generate_sparse_detected_erasures(&self) -> SparseErasures {
+        self.base.generate_sparse_detected_erasures()
+    }
+    fn generate_sparse_error_pattern(&self) -> SparseErrorPattern {
+        self.base.generate_sparse_error_pattern()
+    }
+    fn generate_sparse_measurement(&self) -> SparseMeasurement {
+        self.base.generate_sparse_measurement()
+    }
+    fn validate_correction(&mut self, correction: &SparseCorrection) -> (bool, bool) {
+        self.base.validate_correction(correction)
+    }
+}
+
 /// The extender takes two `SimulatorCompact` as input, assuming the first one has T and the second one has T+1 noisy measurement rounds.
 /// It works by finding an efficient representation that can generate a `SimulatorCompact` for arbitrarily large T.
 /// Usually `noisy_measurements = 4` is good enough, e.g., when `measurement_cycle = 6`, the whole graph is 0<=t<=24, and the repeated region is 12<=t<=18.
@@ -635,8 +1085,8 @@ impl SimulatorCompactExtender {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::code_builder::*;
     use crate::noise_model_builder::*;
+    use rand_core::SeedableRng;
 
     #[test]
     fn simulator_compact_extender() {
@@ -647,7 +1097,9 @@ mod tests {
         let build_simulator = |noisy_measurements: usize| -> (Simulator, NoiseModel, SimulatorCompact) {
             let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, di, dj));
             let mut noise_model = NoiseModel::new(&simulator);
-            NoiseModelBuilder::StimNoiseModel.apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.);
+            NoiseModelBuilder::StimNoiseModel
+                .apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.)
+                .unwrap();
             code_builder_sanity_check(&simulator).unwrap();
             noise_model_sanity_check(&simulator, &noise_model).unwrap();
             let simulator_compact = SimulatorCompact::from_simulator(simulator.clone(), Arc::new(noise_model.clone()), 1);
@@ -664,4 +1116,222 @@ mod tests {
         let (_, _, ground_truth) = build_simulator(test_noisy_measurement);
         generated.assert_eq(&ground_truth).unwrap();
     }
+
+    #[test]
+    fn simulator_compact_detector_error_model() {
+        // cargo test simulator_compact_detector_error_model -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel
+            .apply(&mut simulator, &mut noise_model, &json!({}), 0.001, 0.5, 0.)
+            .unwrap();
+        let simulator_compact = SimulatorCompact::from_simulator(simulator.clone(), Arc::new(noise_model.clone()), 1);
+        let detector_error_model = simulator_compact.to_detector_error_model();
+        let error_lines: Vec<&str> = detector_error_model
+            .lines()
+            .filter(|line| line.starts_with("error("))
+            .collect();
+        assert_eq!(error_lines.len(), simulator_compact.error_sources.len());
+        assert!(error_lines.iter().any(|line| line.contains(" L0")));
+        assert!(error_lines.iter().any(|line| line.contains(" L1")));
+        // the detectors are indexed in the same order as the exported Stim circuit
+        let detector_coordinates: Vec<String> = detector_error_model
+            .lines()
+            .filter_map(|line| line.strip_prefix("detector("))
+            .map(|line| line.split(')').next().unwrap().to_string())
+            .collect();
+        let circuit = crate::stim_circuit::simulator_to_stim_circuit(&simulator, &noise_model).unwrap();
+        let circuit_detector_coordinates: Vec<String> = circuit
+            .lines()
+            .filter_map(|line| line.strip_prefix("DETECTOR("))
+            .map(|line| line.split(')').next().unwrap().to_string())
+            .collect();
+        assert_eq!(detector_coordinates, circuit_detector_coordinates);
+    }
+
+    /// add erasure errors to the idle data qubits and correlated erasure errors to the two-qubit gates
+    fn add_erasure_noise(simulator: &mut Simulator, noise_model: &mut NoiseModel, pe: f64) {
+        simulator_iter_real!(simulator, position, node, {
+            if position.t >= simulator.height - simulator.measurement_cycles {
+                continue;
+            }
+            let mut noise_model_node = noise_model.get_node_unwrap(position).clone();
+            if node.gate_type == GateType::CXGateControl && !node.is_peer_virtual {
+                noise_model_node.correlated_erasure_error_rates =
+                    Some(CorrelatedErasureErrorRates::default_with_probability(pe / 3.));
+            } else if node.qubit_type == QubitType::Data && node.gate_type == GateType::None {
+                noise_model_node.erasure_error_rate = pe;
+            }
+            noise_model.set_node(position, Some(Arc::new(noise_model_node)));
+        });
+    }
+
+    #[test]
+    fn simulator_compact_erasure() {
+        // cargo test simulator_compact_erasure -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel
+            .apply(&mut simulator, &mut noise_model, &json!({}), 0.005, 0.5, 0.)
+            .unwrap();
+        add_erasure_noise(&mut simulator, &mut noise_model, 0.02);
+        code_builder_sanity_check(&simulator).unwrap();
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let mut simulator_compact = SimulatorCompact::from_simulator(simulator.clone(), Arc::new(noise_model.clone()), 1);
+        assert!(simulator_compact
+            .error_sources
+            .iter()
+            .any(|error_source| matches!(error_source, ErrorSource::Erasure { erasures, .. } if erasures.len() == 2)));
+        // the average number of erasures and defects should match the full simulator
+        simulator.set_rng(Xoroshiro128StarStar::seed_from_u64(0));
+        simulator_compact.set_rng(Xoroshiro128StarStar::seed_from_u64(1));
+        let shots = 5000;
+        let mut statistics = [[0.; 2]; 2];
+        for _ in 0..shots {
+            let (_, erasure_count) = simulator.generate_random_errors(&noise_model);
+            assert_eq!(erasure_count, simulator.generate_sparse_detected_erasures().len());
+            statistics[0][0] += erasure_count as f64 / shots as f64;
+            statistics[0][1] += simulator.generate_sparse_measurement().len() as f64 / shots as f64;
+            let (_, erasure_count) = simulator_compact.generate_random_errors(&noise_model);
+            assert_eq!(erasure_count, simulator_compact.generate_sparse_detected_erasures().len());
+            statistics[1][0] += erasure_count as f64 / shots as f64;
+            statistics[1][1] += simulator_compact.generate_sparse_measurement().len() as f64 / shots as f64;
+        }
+        println!("statistics: {:?}", statistics);
+        for index in 0..2 {
+            assert!(statistics[0][index] > 0.);
+            let relative_difference = (statistics[0][index] - statistics[1][index]).abs() / statistics[0][index];
+            assert!(relative_difference < 0.1, "statistics differ: {:?}", statistics);
+        }
+    }
+
+    #[test]
+    fn simulator_compact_erasure_virtual_peer() {
+        // cargo test simulator_compact_erasure_virtual_peer -- --nocapture
+        let simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        // correlated erasure errors at the two-qubit gates with a virtual peer, whose erasures are never detected
+        simulator_iter_real!(simulator, position, node, {
+            if position.t < simulator.height - simulator.measurement_cycles
+                && node.gate_type == GateType::CXGateControl
+                && node.is_peer_virtual
+            {
+                let mut noise_model_node = noise_model.get_node_unwrap(position).clone();
+                noise_model_node.correlated_erasure_error_rates =
+                    Some(CorrelatedErasureErrorRates::default_with_probability(0.05));
+                noise_model.set_node(position, Some(Arc::new(noise_model_node)));
+            }
+        });
+        let mut simulator_compact = SimulatorCompact::from_simulator(simulator.clone(), Arc::new(noise_model.clone()), 1);
+        for error_source in simulator_compact.error_sources.iter() {
+            if let ErrorSource::Erasure { erasures, .. } = error_source {
+                assert!(erasures.iter().all(|position| simulator.is_node_real(position)));
+            }
+        }
+        simulator_compact.set_rng(Xoroshiro128StarStar::seed_from_u64(0));
+        for _ in 0..1000 {
+            let (_, erasure_count) = simulator_compact.generate_random_errors(&noise_model);
+            assert_eq!(erasure_count, simulator_compact.generate_sparse_detected_erasures().len());
+        }
+    }
+
+    #[test]
+    fn simulator_compact_fixed_weight() {
+        // cargo test simulator_compact_fixed_weight -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel
+            .apply(&mut simulator, &mut noise_model, &json!({}), 0.005, 0.5, 0.)
+            .unwrap();
+        add_erasure_noise(&mut simulator, &mut noise_model, 0.005);
+        let noise_model = Arc::new(noise_model);
+        let mut simulator_compact = SimulatorCompact::from_simulator(simulator, noise_model.clone(), 1);
+        let max_weight = 12;
+        let mut fixed_weight = SimulatorCompactFixedWeight::new(simulator_compact.clone(), max_weight);
+        // the weight distribution is the Poisson binomial distribution
+        let expected_weight: f64 = simulator_compact
+            .error_sources
+            .iter()
+            .map(|error_source| error_source.probability())
+            .sum();
+        let weight_distribution: Vec<f64> = (0..=max_weight)
+            .map(|weight| fixed_weight.weight_probability(weight))
+            .collect();
+        let mean_weight: f64 = weight_distribution
+            .iter()
+            .enumerate()
+            .map(|(weight, p)| weight as f64 * p)
+            .sum();
+        println!("expected weight: {expected_weight}, weight distribution: {weight_distribution:?}");
+        assert!(fixed_weight.tail_probability(max_weight) < 1e-6);
+        assert!(fixed_weight.tail_probability(1) > fixed_weight.tail_probability(2));
+        assert!((mean_weight - expected_weight).abs() < 1e-6);
+        // the average number of defects recombined from fixed weight sampling should match the direct sampling
+        fixed_weight.set_rng(Xoroshiro128StarStar::seed_from_u64(0));
+        simulator_compact.set_rng(Xoroshiro128StarStar::seed_from_u64(1));
+        let shots = 2000;
+        let mut recombined_defects = 0.;
+        for weight in 0..=max_weight {
+            fixed_weight.weight = weight;
+            let mut defects = 0;
+            for _ in 0..shots {
+                fixed_weight.generate_random_errors(&noise_model);
+                defects += fixed_weight.generate_sparse_measurement().len();
+            }
+            recombined_defects += fixed_weight.weight_probability(weight) * defects as f64 / shots as f64;
+        }
+        let mut direct_defects = 0.;
+        for _ in 0..5 * shots {
+            simulator_compact.generate_random_errors(&noise_model);
+            direct_defects += simulator_compact.generate_sparse_measurement().len() as f64 / (5 * shots) as f64;
+        }
+        println!("recombined defects: {recombined_defects}, direct defects: {direct_defects}");
+        assert!((recombined_defects - direct_defects).abs() / direct_defects < 0.1);
+        // the estimated failure rate of a constant failure rate is exact
+        let results: Vec<(usize, usize)> = (0..=max_weight).map(|_| (100, 10)).collect();
+        let (logical_error_rate, _) = fixed_weight.estimate_logical_error_rate(&results);
+        assert!((logical_error_rate - 0.1).abs() < 1e-6);
+        // a weight without any failed shot still has a positive upper bound of its failure rate
+        let results = vec![(0, 0), (1000, 0)];
+        let (logical_error_rate, half_width) = fixed_weight.estimate_logical_error_rate(&results);
+        assert_eq!(logical_error_rate, 0.);
+        let expected_upper_bound = 1.96 * 1.96 / (1000. + 1.96 * 1.96); // the Wilson upper bound of 0 failures
+        assert!((half_width - fixed_weight.weight_probability(1) * expected_upper_bound).abs() < 1e-12);
+    }
+
+    #[test]
+    fn simulator_compact_extender_erasure() {
+        // cargo test simulator_compact_extender_erasure -- --nocapture
+        let build_simulator_compact = |noisy_measurements: usize| -> SimulatorCompact {
+            let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, 3, 3));
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::StimNoiseModel
+                .apply(&mut simulator, &mut noise_model, &json!({}), 0.001, 0.5, 0.)
+                .unwrap();
+            add_erasure_noise(&mut simulator, &mut noise_model, 0.01);
+            SimulatorCompact::from_simulator(simulator, Arc::new(noise_model), 1)
+        };
+        let extender = SimulatorCompactExtender::new(build_simulator_compact(2), build_simulator_compact(3), 2);
+        extender.generate(5).assert_eq(&build_simulator_compact(5)).unwrap();
+        // the compressed simulator generates the same random errors as the expanded one
+        let mut generated = extender.generate(5);
+        let mut compressed = SimulatorCompactCompressed::new(extender, 5);
+        let noise_model = NoiseModel::new(&Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(5, 3, 3)));
+        for seed in 0..100 {
+            generated.set_rng(Xoroshiro128StarStar::seed_from_u64(seed));
+            compressed.set_rng(Xoroshiro128StarStar::seed_from_u64(seed));
+            assert_eq!(
+                generated.generate_random_errors(&noise_model),
+                compressed.generate_random_errors(&noise_model)
+            );
+            assert_eq!(
+                generated.generate_sparse_detected_erasures().erasures,
+                compressed.generate_sparse_detected_erasures().erasures
+            );
+            assert_eq!(
+                generated.generate_sparse_measurement().to_vec(),
+                compressed.generate_sparse_measurement().to_vec()
+            );
+        }
+    }
 }
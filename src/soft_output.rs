@@ -0,0 +1,548 @@
+//! Soft Output
+//!
+//! A per-shot confidence value of the decoding result, useful for post-selection and magic state distillation studies.
+//! The standard one is the complementary gap: the weight difference between the minimum-weight correction and the
+//! minimum-weight correction in the other logical class. A large gap means the decoder is confident about the logical class,
+//! while a gap close to 0 means the two logical classes are almost equally probable.
+//!
+//! The gap is computed by decoding twice on the model graph with the logical boundary flipped. All the boundaries that can
+//! flip a logical observable are grouped into a single logical boundary, and the parity of the paths that terminate on it
+//! decides the logical class. The logical boundary is then treated as a regular vertex with (or without) an extra defect,
+//! so that the minimum-weight perfect matching is restricted to either logical class.
+//!
+
+use super::blossom_v;
+use super::erasure_graph::ErasureEdge;
+use super::float_ord::FloatOrd;
+use super::model_graph::*;
+use super::priority_queue::PriorityQueue;
+use super::simulator::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// the standardized soft output of decoders, reported as `soft_output` in the runtime statistics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SoftOutput {
+    /// the complementary gap of logical i, `None` if no correction in the other logical class exists
+    pub complementary_gap_i: Option<f64>,
+    /// the complementary gap of logical j, `None` if no correction in the other logical class exists
+    pub complementary_gap_j: Option<f64>,
+}
+
+impl SoftOutput {
+    /// the complementary gap of the logical observables that are not ignored; a smaller gap means less confidence
+    pub fn complementary_gap(&self, ignore_logical_i: bool, ignore_logical_j: bool) -> f64 {
+        let mut complementary_gap = f64::INFINITY;
+        if !ignore_logical_i {
+            complementary_gap = complementary_gap.min(self.complementary_gap_i.unwrap_or(f64::INFINITY));
+        }
+        if !ignore_logical_j {
+            complementary_gap = complementary_gap.min(self.complementary_gap_j.unwrap_or(f64::INFINITY));
+        }
+        complementary_gap
+    }
+
+    /// read the soft output from the runtime statistics of a decoder
+    pub fn from_runtime_statistics(runtime_statistics: &serde_json::Value) -> Option<Self> {
+        serde_json::from_value(runtime_statistics.get("soft_output")?.clone()).ok()
+    }
+}
+
+/// computes the complementary gap on a model graph, immutably shared between threads
+#[derive(Debug)]
+pub struct ComplementaryGapSolver {
+    /// index to position mapping
+    pub index_to_position: Vec<Position>,
+    /// position to index mapping
+    pub position_to_index: HashMap<Position, usize>,
+    /// the neighbors of each node and the weight of the edge
+    pub neighbors: Vec<Vec<(usize, f64)>>,
+    /// the weight of the boundary edge of each node, and whether it terminates on the logical boundary of logical i and j
+    pub boundaries: Vec<Option<(f64, [bool; 2])>>,
+}
+
+impl ComplementaryGapSolver {
+    /// the logical effect of each edge is computed by validating its correction on a clean simulator
+    pub fn new(simulator: &Simulator, model_graph: &ModelGraph) -> Self {
+        let mut simulator = simulator.clone();
+        simulator.clear_all_errors();
+        let mut index_to_position = Vec::<Position>::new();
+        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if model_graph.is_node_exist(position) {
+            index_to_position.push(position.clone());
+        });
+        let mut position_to_index = HashMap::with_capacity(index_to_position.len());
+        for (index, position) in index_to_position.iter().enumerate() {
+            position_to_index.insert(position.clone(), index);
+        }
+        let mut logical_effect = |correction: &SparseCorrection| -> [bool; 2] {
+            let (logical_i, logical_j) = simulator.validate_correction(correction);
+            simulator.validate_correction(correction); // apply the same correction again to recover the clean state
+            [logical_i, logical_j]
+        };
+        let mut neighbors = Vec::with_capacity(index_to_position.len());
+        let mut neighbor_logical_effects = Vec::with_capacity(index_to_position.len());
+        for position in index_to_position.iter() {
+            let node = model_graph.get_node_unwrap(position);
+            neighbors.push(
+                node.edges
+                    .iter()
+                    .map(|(peer, edge)| (position_to_index[peer], edge.weight))
+                    .collect::<Vec<_>>(),
+            );
+            neighbor_logical_effects.push(
+                node.edges
+                    .values()
+                    .map(|edge| logical_effect(&edge.correction))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        // the logical effects of the internal edges form a gauge: it's equivalent to move the logical observable to the
+        // boundary by flipping the logical effect of all the edges around some nodes, which only changes the logical class by
+        // a constant that depends on the defects; this is how the logical boundary is identified
+        let mut gauges: Vec<Option<[bool; 2]>> = vec![None; index_to_position.len()];
+        for root in 0..index_to_position.len() {
+            if gauges[root].is_some() {
+                continue;
+            }
+            gauges[root] = Some([false, false]);
+            let mut queue = VecDeque::from([root]);
+            while let Some(index) = queue.pop_front() {
+                let gauge = gauges[index].unwrap();
+                for (&(peer, _), effect) in neighbors[index].iter().zip(neighbor_logical_effects[index].iter()) {
+                    if gauges[peer].is_none() {
+                        gauges[peer] = Some([gauge[0] ^ effect[0], gauge[1] ^ effect[1]]);
+                        queue.push_back(peer);
+                    }
+                }
+            }
+        }
+        let boundaries = index_to_position
+            .iter()
+            .enumerate()
+            .map(|(index, position)| {
+                let node = model_graph.get_node_unwrap(position);
+                node.boundary.as_ref().map(|boundary| {
+                    let effect = logical_effect(&boundary.correction);
+                    let gauge = gauges[index].unwrap();
+                    (boundary.weight, [effect[0] ^ gauge[0], effect[1] ^ gauge[1]])
+                })
+            })
+            .collect();
+        Self {
+            index_to_position,
+            position_to_index,
+            neighbors,
+            boundaries,
+        }
+    }
+
+    /// compute the complementary gap of both logical observables, where the erased edges have 0 weight
+    pub fn solve(&self, defects: &[Position], erasure_edges: &[ErasureEdge]) -> SoftOutput {
+        let mut erased_edges = HashSet::<(usize, usize)>::new();
+        let mut erased_boundaries = HashSet::<usize>::new();
+        for erasure_edge in erasure_edges.iter() {
+            match erasure_edge {
+                ErasureEdge::Connection(position1, position2) => {
+                    let index1 = self.position_to_index[position1];
+                    let index2 = self.position_to_index[position2];
+                    erased_edges.insert((index1, index2));
+                    erased_edges.insert((index2, index1));
+                }
+                ErasureEdge::Boundary(position) => {
+                    erased_boundaries.insert(self.position_to_index[position]);
+                }
+            }
+        }
+        let boundary_weight = |index: usize, weight: f64| -> f64 {
+            if erased_boundaries.contains(&index) {
+                0.
+            } else {
+                weight
+            }
+        };
+        // shortest paths from each defect to other defects and to both sides of each logical boundary
+        let defect_indices: Vec<usize> = defects.iter().map(|position| self.position_to_index[position]).collect();
+        let mut pair_distances = Vec::with_capacity(defect_indices.len());
+        let mut side_distances = Vec::with_capacity(defect_indices.len());
+        for &defect_index in defect_indices.iter() {
+            let distances = self.dijkstra(&[(defect_index, 0.)], &erased_edges);
+            pair_distances.push(defect_indices.iter().map(|&index| distances[index]).collect::<Vec<_>>());
+            let mut sides = [[f64::INFINITY; 2]; 2];
+            for (index, boundary) in self.boundaries.iter().enumerate() {
+                if let Some((weight, is_logical_boundary)) = boundary {
+                    let distance = distances[index] + boundary_weight(index, *weight);
+                    for (logical, side) in sides.iter_mut().enumerate() {
+                        let side = &mut side[is_logical_boundary[logical] as usize];
+                        *side = side.min(distance);
+                    }
+                }
+            }
+            side_distances.push(sides);
+        }
+        let mut complementary_gaps = [None; 2];
+        for (logical, complementary_gap) in complementary_gaps.iter_mut().enumerate() {
+            // the minimum weight of a logical operator, i.e. a path from the normal boundary to the logical boundary
+            let sources: Vec<(usize, f64)> = (0..self.boundaries.len())
+                .filter_map(|index| match self.boundaries[index] {
+                    Some((weight, is_logical_boundary)) if !is_logical_boundary[logical] => {
+                        Some((index, boundary_weight(index, weight)))
+                    }
+                    _ => None,
+                })
+                .collect();
+            let distances = self.dijkstra(&sources, &erased_edges);
+            let mut logical_weight = f64::INFINITY;
+            for (index, boundary) in self.boundaries.iter().enumerate() {
+                if let Some((weight, is_logical_boundary)) = boundary {
+                    if is_logical_boundary[logical] {
+                        logical_weight = logical_weight.min(distances[index] + boundary_weight(index, *weight));
+                    }
+                }
+            }
+            if logical_weight == f64::INFINITY {
+                continue; // the logical class cannot be flipped
+            }
+            // a path to one side of the boundary can be extended to the other side with a logical operator
+            let sides: Vec<[f64; 2]> = side_distances
+                .iter()
+                .map(|sides| {
+                    let [normal, flipped] = sides[logical];
+                    [normal.min(flipped + logical_weight), flipped.min(normal + logical_weight)]
+                })
+                .collect();
+            if sides.iter().any(|side| side[0] == f64::INFINITY) {
+                continue; // some defect cannot be matched to the boundary
+            }
+            let even_weight = self.match_with_logical_boundary(&pair_distances, &sides, logical_weight, false);
+            let odd_weight = self.match_with_logical_boundary(&pair_distances, &sides, logical_weight, true);
+            *complementary_gap = Some((even_weight - odd_weight).abs());
+        }
+        SoftOutput {
+            complementary_gap_i: complementary_gaps[0],
+            complementary_gap_j: complementary_gaps[1],
+        }
+    }
+
+    /// the minimum weight of the perfect matching where the number of paths terminating on the logical boundary is odd or even;
+    /// the virtual boundary of defect `i` is `i + m_len`, and the logical boundary is `2 * m_len` when odd
+    fn match_with_logical_boundary(
+        &self,
+        pair_distances: &[Vec<f64>],
+        sides: &[[f64; 2]],
+        logical_weight: f64,
+        odd: bool,
+    ) -> f64 {
+        let m_len = sides.len();
+        let mut weighted_edges = Vec::<(usize, usize, f64)>::new();
+        for (i, side) in sides.iter().enumerate() {
+            weighted_edges.push((i, i + m_len, side[0]));
+            for (j, peer_side) in sides.iter().enumerate().skip(i + 1) {
+                // two paths terminating on the logical boundary don't change the parity
+                let weight = pair_distances[i][j].min(side[1] + peer_side[1]);
+                if weight != f64::INFINITY {
+                    weighted_edges.push((i, j, weight));
+                }
+                weighted_edges.push((i + m_len, j + m_len, 0.));
+            }
+        }
+        let mut node_num = m_len * 2;
+        if odd {
+            // the logical boundary is matched with either a defect or, through a logical operator, an extra virtual boundary
+            let logical_boundary = node_num;
+            let extra_boundary = node_num + 1;
+            node_num += 2;
+            for (i, side) in sides.iter().enumerate() {
+                weighted_edges.push((i, logical_boundary, side[1]));
+                weighted_edges.push((i + m_len, extra_boundary, 0.));
+            }
+            weighted_edges.push((logical_boundary, extra_boundary, logical_weight));
+        }
+        if weighted_edges.iter().all(|(_, _, weight)| *weight == 0.) {
+            return 0.; // e.g. no defect, or every defect is matched through erased edges
+        }
+        let matching = blossom_v::safe_minimum_weight_perfect_matching(node_num, weighted_edges.clone());
+        weighted_edges
+            .iter()
+            .filter(|(i, j, _)| matching[*i] == *j)
+            .map(|(_, _, weight)| weight)
+            .sum()
+    }
+
+    /// shortest distances from the sources with initial distances
+    fn dijkstra(&self, sources: &[(usize, f64)], erased_edges: &HashSet<(usize, usize)>) -> Vec<f64> {
+        let mut distances = vec![f64::INFINITY; self.neighbors.len()];
+        let mut pq = PriorityQueue::<usize, Reverse<FloatOrd<f64>>>::new();
+        for &(index, distance) in sources.iter() {
+            pq.push_increase(index, Reverse(FloatOrd(distance)));
+        }
+        while let Some((index, Reverse(FloatOrd(distance)))) = pq.pop() {
+            distances[index] = distance;
+            for &(peer, weight) in self.neighbors[index].iter() {
+                if distances[peer] != f64::INFINITY {
+                    continue; // already visited
+                }
+                let weight = if erased_edges.contains(&(index, peer)) { 0. } else { weight };
+                pq.push_increase(peer, Reverse(FloatOrd(distance + weight)));
+            }
+        }
+        distances
+    }
+}
+
+/// the bins of [`ComplementaryGapHistogram`] per doubling of the complementary gap, i.e. a relative resolution of about 1%
+const COMPLEMENTARY_GAP_BINS_PER_OCTAVE: f64 = 64.;
+/// the complementary gaps below `2^-COMPLEMENTARY_GAP_MAX_EXPONENT` share the first bin and those above
+/// `2^COMPLEMENTARY_GAP_MAX_EXPONENT` (including infinity) share the last bin
+const COMPLEMENTARY_GAP_MAX_EXPONENT: f64 = 32.;
+
+/// the distribution of the complementary gaps and the failed shots over logarithmic bins, so that the memory usage and
+/// the cost of a query are bounded regardless of the number of shots
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComplementaryGapHistogram {
+    /// the number of shots and failed shots of each bin in increasing complementary gap, allocated on the first record
+    pub bins: Vec<(usize, usize)>,
+    /// the number of recorded shots
+    pub shots: usize,
+}
+
+impl ComplementaryGapHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn bin_index(complementary_gap: f64) -> usize {
+        let max_exponent = COMPLEMENTARY_GAP_MAX_EXPONENT;
+        let last = (2. * max_exponent * COMPLEMENTARY_GAP_BINS_PER_OCTAVE) as usize + 1;
+        if complementary_gap.is_nan() || complementary_gap < (-max_exponent).exp2() {
+            0
+        } else if complementary_gap >= max_exponent.exp2() {
+            last
+        } else {
+            1 + ((complementary_gap.log2() + max_exponent) * COMPLEMENTARY_GAP_BINS_PER_OCTAVE) as usize
+        }
+    }
+
+    pub fn record(&mut self, complementary_gap: f64, is_qec_failed: bool) {
+        if self.bins.is_empty() {
+            self.bins = vec![(0, 0); Self::bin_index(f64::INFINITY) + 1];
+        }
+        let bin = &mut self.bins[Self::bin_index(complementary_gap)];
+        bin.0 += 1;
+        if is_qec_failed {
+            bin.1 += 1;
+        }
+        self.shots += 1;
+    }
+
+    /// the logical error rate of the accepted shots after aborting the given fraction of shots with the smallest
+    /// complementary gap; the failed shots of the bin at the threshold are aborted proportionally; NaN if no shot is accepted
+    pub fn abort_logical_error_rate(&self, abort_rate: f64) -> f64 {
+        let aborted = ((self.shots as f64) * abort_rate).ceil() as usize;
+        if aborted >= self.shots {
+            return f64::NAN;
+        }
+        let mut remaining_aborted = aborted;
+        let mut accepted_failed = 0.;
+        for &(shots, failed) in self.bins.iter() {
+            if remaining_aborted >= shots {
+                remaining_aborted -= shots;
+                continue;
+            }
+            accepted_failed += failed as f64 * (shots - remaining_aborted) as f64 / shots as f64;
+            remaining_aborted = 0;
+        }
+        accepted_failed / (self.shots - aborted) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::code_builder::*;
+    use super::super::decoder_mwpm::*;
+    use super::super::decoder_union_find::*;
+    use super::super::noise_model::*;
+    use super::super::reproducible_rand::Xoroshiro128StarStar;
+    use super::super::types::ErrorType::*;
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn soft_output_complementary_gap_brute_force() {
+        // cargo test soft_output_complementary_gap_brute_force -- --nocapture
+        let d = 3;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.1;
+        // build simulator with only X errors, so that all the subsets of edges can be enumerated
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, 0., 0., 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(
+            &mut simulator,
+            Arc::new(noise_model),
+            &WeightFunction::AutotuneImproved,
+            1,
+            false,
+            false,
+        );
+        // randomize the weights so that the gap is not always a multiple of the same weight
+        let mut rng = Xoroshiro128StarStar::new();
+        let mut edges = vec![]; // (position, peer or boundary, correction)
+        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if model_graph.is_node_exist(position) {
+            let node = model_graph.get_node_unwrap(position);
+            for (peer, edge) in node.edges.iter() {
+                if position < peer {
+                    edges.push((position.clone(), Some(peer.clone()), edge.correction.clone()));
+                }
+            }
+            if let Some(boundary) = node.boundary.as_ref() {
+                edges.push((position.clone(), None, boundary.correction.clone()));
+            }
+        });
+        let mut weights = vec![];
+        for (position, peer, _) in edges.iter() {
+            let weight = 1. + 4. * rng.next_f64();
+            weights.push(weight);
+            match peer {
+                Some(peer) => {
+                    model_graph.get_node_mut_unwrap(position).edges.get_mut(peer).unwrap().weight = weight;
+                    model_graph.get_node_mut_unwrap(peer).edges.get_mut(position).unwrap().weight = weight;
+                }
+                None => model_graph.get_node_mut_unwrap(position).boundary.as_mut().unwrap().weight = weight,
+            }
+        }
+        let solver = ComplementaryGapSolver::new(&simulator, &model_graph);
+        let mut clean_simulator = simulator.clone();
+        clean_simulator.clear_all_errors();
+        let logical_effects: Vec<bool> = edges
+            .iter()
+            .map(|(_, _, correction)| {
+                let (logical_i, logical_j) = clean_simulator.validate_correction(correction);
+                clean_simulator.validate_correction(correction);
+                assert!(!logical_i, "X errors only affect logical j");
+                logical_j
+            })
+            .collect();
+        // enumerate all the subsets of edges, optionally with an erased internal edge or boundary edge
+        let erased_internal = edges.iter().position(|(_, peer, _)| peer.is_some()).unwrap();
+        let erased_boundary = edges.iter().position(|(_, peer, _)| peer.is_none()).unwrap();
+        for erased in [None, Some(erased_internal), Some(erased_boundary)] {
+            let mut brute_force = HashMap::<Vec<Position>, [f64; 2]>::new();
+            for subset in 0..(1usize << edges.len()) {
+                let mut defects = std::collections::BTreeSet::<Position>::new();
+                let mut logical_j = false;
+                let mut weight = 0.;
+                for (index, (position, peer, _)) in edges.iter().enumerate() {
+                    if subset & (1 << index) != 0 {
+                        for position in std::iter::once(position).chain(peer.iter()) {
+                            if !defects.remove(position) {
+                                defects.insert(position.clone());
+                            }
+                        }
+                        logical_j ^= logical_effects[index];
+                        if erased != Some(index) {
+                            weight += weights[index];
+                        }
+                    }
+                }
+                let minimum_weights = brute_force.entry(defects.into_iter().collect()).or_insert([f64::INFINITY; 2]);
+                let minimum_weight = &mut minimum_weights[logical_j as usize];
+                *minimum_weight = minimum_weight.min(weight);
+            }
+            let erasure_edges: Vec<ErasureEdge> = erased
+                .map(|index| match &edges[index] {
+                    (position, Some(peer), _) => ErasureEdge::Connection(position.clone(), peer.clone()),
+                    (position, None, _) => ErasureEdge::Boundary(position.clone()),
+                })
+                .into_iter()
+                .collect();
+            for (defects, minimum_weights) in brute_force.iter() {
+                let soft_output = solver.solve(defects, &erasure_edges);
+                assert_eq!(soft_output.complementary_gap_i, None);
+                let expected = (minimum_weights[0] - minimum_weights[1]).abs();
+                let complementary_gap = soft_output.complementary_gap_j.unwrap();
+                assert!(
+                    (complementary_gap - expected).abs() < 1e-6,
+                    "defects: {defects:?}, expected gap {expected}, found {complementary_gap}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn soft_output_decoders() {
+        // cargo test soft_output_decoders -- --nocapture
+        let d = 5;
+        let noisy_measurements = 0; // perfect measurement
+        let p = 0.01;
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p / 3., p / 3., p / 3., 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        let decoder_config = json!({ "soft_output": true, "ucp": false });
+        let mut mwpm_decoder = MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &decoder_config, 1, false).unwrap();
+        let mut union_find_decoder = UnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &decoder_config, 1, false);
+        let weight = WeightFunction::AutotuneImproved.weight_of(p / 3.);
+        // a single X error in the center: the other class needs `d - 1` errors, while the other logical needs `d` errors
+        simulator.clear_all_errors();
+        simulator.set_error_check(&noise_model, &pos!(0, d, d), &X);
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        let (_, runtime_statistics) = mwpm_decoder.decode(&sparse_measurement);
+        let soft_output = SoftOutput::from_runtime_statistics(&runtime_statistics).unwrap();
+        let mut complementary_gaps = [
+            soft_output.complementary_gap_i.unwrap(),
+            soft_output.complementary_gap_j.unwrap(),
+        ];
+        complementary_gaps.sort_by(f64::total_cmp);
+        assert!((complementary_gaps[0] - (d - 2) as f64 * weight).abs() < 1e-6);
+        assert!((complementary_gaps[1] - d as f64 * weight).abs() < 1e-6);
+        assert!((soft_output.complementary_gap(false, false) - complementary_gaps[0]).abs() < 1e-9);
+        // the soft output is the same for all the decoders
+        let (_, runtime_statistics) = union_find_decoder.decode(&sparse_measurement);
+        assert_eq!(
+            SoftOutput::from_runtime_statistics(&runtime_statistics),
+            Some(soft_output.clone())
+        );
+        #[cfg(feature = "fusion_blossom")]
+        {
+            use super::super::decoder_fusion::*;
+            let mut fusion_decoder = FusionDecoder::new(&simulator, Arc::clone(&noise_model), &decoder_config, 1, false);
+            let (_, runtime_statistics) = fusion_decoder.decode(&sparse_measurement);
+            assert_eq!(SoftOutput::from_runtime_statistics(&runtime_statistics), Some(soft_output));
+        }
+    }
+
+    #[test]
+    fn soft_output_complementary_gap_histogram() {
+        // cargo test soft_output_complementary_gap_histogram -- --nocapture
+        let mut histogram = ComplementaryGapHistogram::new();
+        assert!(histogram.abort_logical_error_rate(0.).is_nan());
+        // the shots with small gaps fail more often
+        for index in 0..1000 {
+            let complementary_gap = index as f64 / 10.;
+            histogram.record(complementary_gap, index % 10 == 0 || (index < 100 && index % 2 == 0));
+        }
+        histogram.record(f64::INFINITY, false);
+        assert_eq!(histogram.shots, 1001);
+        assert_eq!(histogram.bins.len(), 4098);
+        assert!((histogram.abort_logical_error_rate(0.) - 140. / 1001.).abs() < 1e-12);
+        // aborting the 101 shots with gap up to 10.0: 89 of the 900 accepted shots fail, up to the bin resolution
+        let abort_rate = 101. / 1001.;
+        assert!((histogram.abort_logical_error_rate(abort_rate) - 89. / 900.).abs() < 1e-3);
+        assert!(histogram.abort_logical_error_rate(1.).is_nan());
+        // the memory usage doesn't grow with the number of shots
+        for _ in 0..10000 {
+            histogram.record(1., true);
+        }
+        assert_eq!(histogram.bins.len(), 4098);
+    }
+}
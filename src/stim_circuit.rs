@@ -0,0 +1,2234 @@
+//! # Stim Circuit
+//!
+//! Import a [Stim](https://github.com/quantumlib/Stim) circuit into a [`Simulator`] with `CodeType::Customized` nodes and
+//! a matching [`NoiseModel`], so that circuits designed elsewhere can be decoded with the decoders in this crate.
+//! Conversely, [`simulator_to_stim_circuit`] exports any simulator and noise model as an equivalent Stim circuit.
+//!
+//! The simulator only reports defects by comparing two measurements at the same position, while Stim detectors are
+//! parities of arbitrary measurement records. To bridge the two, every `DETECTOR` becomes a dedicated detector qubit
+//! that is measured at the very beginning and the very end of the simulation. Each Stim measurement is replaced by
+//! noiseless CNOT gates that copy its outcome flip onto the detectors and observables that include it, so a detector
+//! qubit flips exactly when the Stim detector does; a detector mixing both bases is measured in X basis, where the
+//! flips of Z basis measurements are copied by CZ gates. Every detector has a virtual twin that flips together with it,
+//! serving as the boundary vertex of single-defect errors. Each `OBSERVABLE_INCLUDE(k)` becomes a virtual data qubit
+//! marked with `{"observable": k}`, which [`code_builder_validate_correction`] reads as `logical_i` (k = 0) or `logical_j` (k = 1).
+//!
+//! Supported instructions: `R`/`RZ`/`RX`, `M`/`MZ`/`MX`/`MY`, `MR`/`MRZ`/`MRX` (with optional flip probability), the
+//! single-qubit Clifford gates `I`, `X`, `Y`, `Z`, `H`/`H_XZ`, `H_XY`, `H_YZ`, `S`/`SQRT_Z`, `SQRT_X`, `SQRT_Y` (and their
+//! `_DAG` variants), `C_XYZ` and `C_ZYX`, the controlled-Pauli gates `CX`/`CNOT`, `CY`, `CZ` and `XCX`, `XCY`, `XCZ`,
+//! `YCX`, `YCY`, `YCZ`, `X_ERROR`/`Y_ERROR`/`Z_ERROR`, `DEPOLARIZE1`/`DEPOLARIZE2`, `PAULI_CHANNEL_1`/`PAULI_CHANNEL_2`,
+//! `DETECTOR`, `OBSERVABLE_INCLUDE`, `QUBIT_COORDS`, `SHIFT_COORDS`, `TICK` and `REPEAT` blocks. Two-qubit noise must
+//! follow a two-qubit gate on the same pair of qubits.
+//!
+//! The simulator has no single-qubit Clifford gate, so each Stim qubit keeps a frame that maps its Pauli operators to the
+//! simulated ones. A single-qubit Clifford gate right after a reset changes the basis of the reset instead; otherwise it
+//! only changes the frame, and later gates, noise and measurements are translated through it. A controlled-Pauli gate
+//! is supported as long as one of its two qubits controls it in the Z basis of the simulator, e.g., `H 0` followed by
+//! `CX 1 0` becomes a CZ gate, but `H 0` followed by `CX 0 1` is rejected. Similarly, a measurement is rejected if it is
+//! in the Y basis of the simulator.
+//!
+//! Not supported: `RY` and `MRY`, `MPP`, the two-qubit Clifford gates outside the controlled-Pauli family (e.g. `SWAP`,
+//! `ISWAP`, `SQRT_XX`), classically controlled gates (e.g. `CX rec[-1] 0`), `CORRELATED_ERROR`/`E`,
+//! `ELSE_CORRELATED_ERROR`, `HERALDED_ERASE`, `MPAD`, `OBSERVABLE_INCLUDE` of observables other than 0 and 1, and any
+//! other instruction, all of which are reported as an error.
+//!
+//! The exported circuit follows the simulator layer by layer. The simulator assumes that the initial state is already
+//! in the code space and that the measurements at t = 0 are perfect references; in Stim they become noiseless `MPP`
+//! measurements of the stabilizers, found by probing which errors at t = 0 flip the first round of detectors.
+//! Similarly, the logical operators are found by probing [`code_builder_validate_correction`] and are measured by
+//! `MPP` at the beginning and the end. When the two logical operators anticommute, they are entangled with a noiseless
+//! reference qubit so that both observables are deterministic. Erasure errors become `HERALDED_ERASE` (Stim v1.13+).
+//!
+//! A Stim detector error model is loaded directly into a simulator whose detectors follow their coordinates, see
+//! [`simulator_from_detector_error_model`]. Decomposed errors (`^`) are sampled as a whole, while the decoding graph is
+//! built from their graph-like components.
+//!
+
+use super::code_builder::*;
+use super::noise_model::*;
+use super::simulator::*;
+use super::types::*;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use std::sync::Arc;
+
+/// a single instruction in a Stim circuit; `REPEAT` blocks are already flattened
+#[derive(Debug, Clone, PartialEq)]
+pub struct StimInstruction {
+    pub name: String,
+    pub arguments: Vec<f64>,
+    pub targets: Vec<StimTarget>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StimTarget {
+    /// qubit index; an inverted target `!q` is read as `q` because inversion never changes whether a measurement flips
+    Qubit(usize),
+    /// measurement record `rec[-k]`, stored as `k`
+    Record(usize),
+    /// Pauli target like `X3`, used by `MPP` and `CORRELATED_ERROR`
+    Pauli(ErrorType, usize),
+    /// the `*` between the Pauli targets of a product
+    Combiner,
+    /// detector `D{index}` in a detector error model
+    Detector(usize),
+    /// logical observable `L{index}` in a detector error model
+    Observable(usize),
+    /// the `^` between the components of a decomposed error in a detector error model
+    Separator,
+}
+
+/// parse a Stim circuit into a flat list of instructions
+pub fn parse_stim_circuit(circuit: &str) -> Result<Vec<StimInstruction>, String> {
+    let mut lines = circuit.lines().enumerate();
+    let instructions = parse_stim_block(&mut lines, false)?;
+    Ok(instructions)
+}
+
+fn parse_stim_block<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    is_repeat_block: bool,
+) -> Result<Vec<StimInstruction>, String> {
+    let mut instructions = vec![];
+    while let Some((line_index, line)) = lines.next() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "}" {
+            if is_repeat_block {
+                return Ok(instructions);
+            }
+            return Err(format!("line {}: unmatched `}}`", line_index + 1));
+        }
+        if line.to_uppercase().starts_with("REPEAT") {
+            let count_str = line[6..].trim().strip_suffix('{').map(|count| count.trim());
+            let count: usize = count_str.and_then(|count| count.parse().ok()).ok_or(format!(
+                "line {}: invalid REPEAT block `{}`",
+                line_index + 1,
+                line
+            ))?;
+            let block = parse_stim_block(lines, true)?;
+            for _ in 0..count {
+                instructions.extend(block.iter().cloned());
+            }
+            continue;
+        }
+        instructions.push(parse_stim_instruction(line).map_err(|message| format!("line {}: {}", line_index + 1, message))?);
+    }
+    if is_repeat_block {
+        return Err("REPEAT block is not closed by `}`".to_string());
+    }
+    Ok(instructions)
+}
+
+fn parse_stim_instruction(line: &str) -> Result<StimInstruction, String> {
+    let name_end = line
+        .find(|c: char| c.is_whitespace() || c == '(' || c == '[')
+        .unwrap_or(line.len());
+    let name = line[..name_end].to_uppercase();
+    let mut rest = &line[name_end..];
+    if rest.starts_with('[') {
+        // skip the instruction tag
+        let tag_end = rest.find(']').ok_or(format!("unclosed tag in `{}`", line))?;
+        rest = &rest[tag_end + 1..];
+    }
+    let mut arguments = vec![];
+    if rest.starts_with('(') {
+        let arguments_end = rest.find(')').ok_or(format!("unclosed arguments in `{}`", line))?;
+        for argument in rest[1..arguments_end].split(',').map(|argument| argument.trim()) {
+            if argument.is_empty() {
+                continue;
+            }
+            arguments.push(
+                argument
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid argument `{}`", argument))?,
+            );
+        }
+        rest = &rest[arguments_end + 1..];
+    }
+    let mut targets = vec![];
+    for target in rest.split_whitespace() {
+        if let Some(lookback) = target.strip_prefix("rec[-").and_then(|target| target.strip_suffix(']')) {
+            let lookback: usize = lookback.parse().map_err(|_| format!("invalid record target `{}`", target))?;
+            if lookback == 0 {
+                return Err(format!("invalid record target `{}`", target));
+            }
+            targets.push(StimTarget::Record(lookback));
+        } else if target == "^" {
+            targets.push(StimTarget::Separator);
+        } else if let Some(index) = target.strip_prefix('D').and_then(|index| index.parse().ok()) {
+            targets.push(StimTarget::Detector(index));
+        } else if let Some(index) = target.strip_prefix('L').and_then(|index| index.parse().ok()) {
+            targets.push(StimTarget::Observable(index));
+        } else {
+            for (index, part) in target.split('*').enumerate() {
+                if index > 0 {
+                    targets.push(StimTarget::Combiner);
+                }
+                let part = part.strip_prefix('!').unwrap_or(part);
+                if part.is_empty() {
+                    continue;
+                }
+                let (pauli, qubit) = match part.chars().next().unwrap().to_ascii_uppercase() {
+                    'X' => (Some(ErrorType::X), &part[1..]),
+                    'Y' => (Some(ErrorType::Y), &part[1..]),
+                    'Z' => (Some(ErrorType::Z), &part[1..]),
+                    _ => (None, part),
+                };
+                let qubit: usize = qubit.parse().map_err(|_| format!("unsupported target `{}`", target))?;
+                targets.push(match pauli {
+                    Some(pauli) => StimTarget::Pauli(pauli, qubit),
+                    None => StimTarget::Qubit(qubit),
+                });
+            }
+        }
+    }
+    Ok(StimInstruction {
+        name,
+        arguments,
+        targets,
+    })
+}
+
+/// build a simulator and its noise model from a Stim circuit
+pub fn simulator_from_stim_circuit(circuit: &str) -> Result<(Simulator, NoiseModel), String> {
+    let instructions = parse_stim_circuit(circuit)?;
+    let mut builder = StimCircuitBuilder::new(&instructions)?;
+    for instruction in instructions.iter() {
+        builder.apply(instruction)?;
+    }
+    Ok(builder.build())
+}
+
+/// a detector error model with `repeat` blocks flattened and `shift_detectors` applied
+struct DetectorErrorModel {
+    /// each error mechanism has a probability and its components, each flipping some detectors and observables
+    errors: Vec<(f64, Vec<[BTreeSet<usize>; 2]>)>,
+    detector_num: usize,
+    /// the coordinates given by `detector(...)`, including the shifts of `shift_detectors`
+    detector_coordinates: BTreeMap<usize, Vec<f64>>,
+}
+
+impl DetectorErrorModel {
+    fn parse(detector_error_model: &str) -> Result<Self, String> {
+        let instructions = parse_stim_circuit(detector_error_model)?;
+        let mut detector_offset = 0;
+        let mut coordinate_shift: Vec<f64> = vec![];
+        let mut detector_num = 0;
+        let mut observable_num = 0;
+        let mut errors = vec![];
+        let mut detector_coordinates = BTreeMap::new();
+        for instruction in instructions.iter() {
+            let argument = instruction.arguments.first().cloned().unwrap_or(0.);
+            match instruction.name.as_str() {
+                "ERROR" => {
+                    let mut components = vec![[BTreeSet::new(), BTreeSet::new()]];
+                    for target in instruction.targets.iter() {
+                        let (kind, index) = match *target {
+                            StimTarget::Detector(index) => (0, index + detector_offset),
+                            StimTarget::Observable(index) => (1, index),
+                            StimTarget::Separator => {
+                                components.push([BTreeSet::new(), BTreeSet::new()]);
+                                continue;
+                            }
+                            _ => return Err(format!("invalid target {:?} of error", target)),
+                        };
+                        let flipped = &mut components.last_mut().unwrap()[kind];
+                        if !flipped.remove(&index) {
+                            flipped.insert(index);
+                        }
+                    }
+                    errors.push((argument, components));
+                }
+                "DETECTOR" => {
+                    let mut coordinates = instruction.arguments.clone();
+                    for (coordinate, shift) in coordinates.iter_mut().zip(coordinate_shift.iter()) {
+                        *coordinate += shift;
+                    }
+                    for target in instruction.targets.iter() {
+                        match *target {
+                            StimTarget::Detector(index) => {
+                                detector_coordinates.insert(index + detector_offset, coordinates.clone());
+                            }
+                            _ => return Err(format!("invalid target {:?} of detector", target)),
+                        }
+                    }
+                }
+                "LOGICAL_OBSERVABLE" => {}
+                "SHIFT_DETECTORS" => {
+                    for (index, shift) in instruction.arguments.iter().enumerate() {
+                        if coordinate_shift.len() <= index {
+                            coordinate_shift.push(0.);
+                        }
+                        coordinate_shift[index] += shift;
+                    }
+                    if let Some(StimTarget::Qubit(shift)) = instruction.targets.first() {
+                        detector_offset += shift;
+                    }
+                }
+                _ => return Err(format!("unsupported detector error model instruction `{}`", instruction.name)),
+            }
+            for target in instruction.targets.iter() {
+                match *target {
+                    StimTarget::Detector(index) => detector_num = detector_num.max(index + detector_offset + 1),
+                    StimTarget::Observable(index) => observable_num = observable_num.max(index + 1),
+                    _ => {}
+                }
+            }
+        }
+        if observable_num > 2 {
+            return Err(format!(
+                "only observables 0 and 1 are supported, found {}",
+                observable_num - 1
+            ));
+        }
+        Ok(Self {
+            errors,
+            detector_num,
+            detector_coordinates,
+        })
+    }
+
+    /// place the detectors by their coordinates `(x, y, t)`: the detectors at the same `(x, y)` share a track and are
+    /// measured in the order of `t`; when some coordinates are missing or two detectors have the same coordinates, every
+    /// detector gets its own track and all of them are measured in a single round
+    fn layout(&self) -> DetectorLayout {
+        let coordinates: Vec<Option<&Vec<f64>>> = (0..self.detector_num)
+            .map(|detector| {
+                self.detector_coordinates
+                    .get(&detector)
+                    .filter(|coordinate| coordinate.len() >= 2)
+            })
+            .collect();
+        if coordinates.iter().all(|coordinate| coordinate.is_some()) {
+            let mut track_indices = BTreeMap::<(u64, u64), usize>::new();
+            let mut track_coordinates = BTreeMap::<usize, Vec<f64>>::new();
+            let mut times = vec![];
+            let mut detector_tracks = vec![];
+            for coordinate in coordinates.iter().map(|coordinate| coordinate.unwrap()) {
+                let next_index = track_indices.len();
+                let track = *track_indices
+                    .entry((coordinate[0].to_bits(), coordinate[1].to_bits()))
+                    .or_insert(next_index);
+                track_coordinates.insert(track, coordinate[..2].to_vec());
+                detector_tracks.push(track);
+                times.push(coordinate.get(2).cloned().unwrap_or(0.));
+            }
+            let mut distinct_times = times.clone();
+            distinct_times.sort_by(f64::total_cmp);
+            distinct_times.dedup();
+            let detectors: Vec<(usize, usize)> = detector_tracks
+                .iter()
+                .zip(times.iter())
+                .map(|(&track, time)| (track, distinct_times.binary_search_by(|t| t.total_cmp(time)).unwrap()))
+                .collect();
+            if detectors.iter().collect::<BTreeSet<_>>().len() == detectors.len() {
+                return DetectorLayout {
+                    positions: qubit_positions(track_indices.len(), &track_coordinates),
+                    detectors,
+                    round_num: distinct_times.len(),
+                };
+            }
+        }
+        let width = ((self.detector_num as f64).sqrt().ceil() as usize).max(1);
+        DetectorLayout {
+            positions: (0..self.detector_num)
+                .map(|detector| (detector / width, detector % width))
+                .collect(),
+            detectors: (0..self.detector_num).map(|detector| (detector, 0)).collect(),
+            round_num: 1,
+        }
+    }
+
+    /// the error mechanisms to be placed in the simulator: a decomposed error is sampled as a whole but decoded as
+    /// independent components
+    fn faults(&self) -> Vec<DetectorErrorModelFault> {
+        let mut faults = vec![];
+        let mut push_fault = |p: f64, p_graph: f64, [detectors, observables]: [BTreeSet<usize>; 2]| {
+            if !detectors.is_empty() || !observables.is_empty() {
+                faults.push(DetectorErrorModelFault {
+                    p,
+                    p_graph,
+                    detectors: detectors.into_iter().collect(),
+                    observables: observables.into_iter().collect(),
+                });
+            }
+        };
+        for (p, components) in self.errors.iter() {
+            if components.len() == 1 {
+                push_fault(*p, *p, components[0].clone());
+                continue;
+            }
+            let mut combined = [BTreeSet::new(), BTreeSet::new()];
+            for component in components.iter() {
+                for kind in 0..2 {
+                    combined[kind] = combined[kind].symmetric_difference(&component[kind]).cloned().collect();
+                }
+            }
+            push_fault(*p, 0., combined);
+            for component in components.iter() {
+                push_fault(0., *p, component.clone());
+            }
+        }
+        faults
+    }
+}
+
+struct DetectorLayout {
+    /// the (i, j) position of each detector track
+    positions: Vec<(usize, usize)>,
+    /// the track and the measurement round of each detector
+    detectors: Vec<(usize, usize)>,
+    round_num: usize,
+}
+
+/// an error mechanism with its probability in the noise model for sampling and the one for the decoding graph
+struct DetectorErrorModelFault {
+    p: f64,
+    p_graph: f64,
+    detectors: Vec<usize>,
+    observables: Vec<usize>,
+}
+
+/// the target of a noiseless CNOT gate that copies an error mechanism onto a detector or an observable
+#[derive(Clone, Copy)]
+enum FaultTarget {
+    Detector(usize),
+    Observable(usize),
+}
+
+/// the qubit of an error mechanism, reset at `reset_t`, and its CNOT gates indexed by time
+#[derive(Clone, Default)]
+struct ScheduledFault {
+    reset_t: usize,
+    qubit: usize,
+    gates: Vec<(usize, FaultTarget)>,
+}
+
+/// the time of every gate in the simulator built from a detector error model
+struct FaultSchedule {
+    measurement_cycles: usize,
+    faults: Vec<ScheduledFault>,
+    fault_qubit_num: usize,
+    /// the observable index of each observable track
+    observable_tracks: Vec<usize>,
+}
+
+impl FaultSchedule {
+    /// greedily place the CNOT gates of the error mechanisms, each detector in the measurement window of its round; returns
+    /// `None` if some window is too short
+    fn new(layout: &DetectorLayout, faults: &[DetectorErrorModelFault], measurement_cycles: usize) -> Option<Self> {
+        let height = (layout.round_num + 1) * measurement_cycles + 1;
+        let body_end = height - measurement_cycles; // the last round is noiseless
+        let mut busy = vec![vec![false; height]; layout.positions.len()];
+        // a detector can be flipped after the previous measurement on its track and before it's copied to the twin
+        let mut windows = vec![(0, 0); layout.detectors.len()];
+        let mut detectors_by_time: Vec<usize> = (0..layout.detectors.len()).collect();
+        detectors_by_time.sort_by_key(|&detector| layout.detectors[detector]);
+        let mut previous_measurement = vec![0; layout.positions.len()];
+        for &detector in detectors_by_time.iter() {
+            let (track, round) = layout.detectors[detector];
+            let t = (round + 1) * measurement_cycles;
+            busy[track][t - 1] = true;
+            busy[track][t] = true;
+            // the first 2 layers are left for resetting the qubit of an error mechanism and applying the error
+            windows[detector] = ((previous_measurement[track] + 1).max(3), t - 2);
+            previous_measurement[track] = t;
+        }
+        let mut observable_busy: Vec<(usize, Vec<bool>)> = vec![];
+        let mut order: Vec<usize> = (0..faults.len()).collect();
+        order.sort_by_key(|&fault| faults[fault].detectors.iter().map(|&detector| windows[detector].0).min());
+        let mut scheduled = vec![ScheduledFault::default(); faults.len()];
+        let mut intervals = vec![];
+        for fault in order {
+            let mut used = BTreeSet::new();
+            let mut gates = vec![];
+            let mut detectors = faults[fault].detectors.clone();
+            detectors.sort_by_key(|&detector| windows[detector]);
+            for detector in detectors {
+                let (track, _) = layout.detectors[detector];
+                let (begin, end) = windows[detector];
+                let t = (begin..=end).find(|&t| !busy[track][t] && !used.contains(&t))?;
+                busy[track][t] = true;
+                used.insert(t);
+                gates.push((t, FaultTarget::Detector(track)));
+            }
+            let begin = used.first().cloned().unwrap_or(3);
+            for &observable in faults[fault].observables.iter() {
+                let t = (begin..body_end).find(|t| !used.contains(t))?;
+                used.insert(t);
+                let track = match observable_busy
+                    .iter()
+                    .position(|(index, busy)| *index == observable && !busy[t])
+                {
+                    Some(track) => track,
+                    None => {
+                        observable_busy.push((observable, vec![false; height]));
+                        observable_busy.len() - 1
+                    }
+                };
+                observable_busy[track].1[t] = true;
+                gates.push((t, FaultTarget::Observable(track)));
+            }
+            let reset_t = begin - 2;
+            intervals.push((reset_t, *used.last().unwrap(), fault));
+            scheduled[fault] = ScheduledFault {
+                reset_t,
+                qubit: 0,
+                gates,
+            };
+        }
+        // reuse the qubits of the error mechanisms that have finished
+        intervals.sort();
+        let mut finished = BinaryHeap::<Reverse<(usize, usize)>>::new();
+        let mut fault_qubit_num = 0;
+        for (reset_t, end_t, fault) in intervals {
+            let qubit = match finished.peek() {
+                Some(&Reverse((finish_t, qubit))) if finish_t < reset_t => {
+                    finished.pop();
+                    qubit
+                }
+                _ => {
+                    fault_qubit_num += 1;
+                    fault_qubit_num - 1
+                }
+            };
+            scheduled[fault].qubit = qubit;
+            finished.push(Reverse((end_t, qubit)));
+        }
+        Some(Self {
+            measurement_cycles,
+            faults: scheduled,
+            fault_qubit_num,
+            observable_tracks: observable_busy.into_iter().map(|(index, _)| index).collect(),
+        })
+    }
+}
+
+/// build a simulator from a Stim detector error model, together with the noise model for sampling and the noise model
+/// for building the decoding graph.
+///
+/// The simulator is built directly from the error mechanisms. Each detector is a stabilizer measurement placed at its
+/// `detector(x, y, t)` coordinates: detectors with the same `(x, y)` share a track and are measured in successive rounds
+/// ordered by `t`, so the real measurement nodes sort in the same order as the detector indices of a model exported by
+/// [`crate::simulator_compact::SimulatorCompact::to_detector_error_model`]. If some coordinates are missing or collide,
+/// the detectors are placed in a square and measured in a single round instead. Each error mechanism is an `X` error on
+/// a freshly reset qubit, which noiseless CNOT gates then copy onto the detectors and observables it flips within their
+/// measurement windows; the qubit is reused by later error mechanisms, so the size of the simulator scales with the
+/// total number of targets instead of the square of the number of error mechanisms.
+///
+/// A decomposed error `error(p) D0 D1 ^ D2 D3` (with any number of components) flips all its components together in
+/// the noise model for sampling, while each component is an independent error of probability `p` in the noise model for
+/// the decoding graph, so that the matching-based decoders see every component as an edge.
+pub fn simulator_from_detector_error_model(
+    detector_error_model: &str,
+) -> Result<(Simulator, NoiseModel, NoiseModel), String> {
+    let detector_error_model = DetectorErrorModel::parse(detector_error_model)?;
+    let layout = detector_error_model.layout();
+    let faults = detector_error_model.faults();
+    // each measurement window needs a slot for every error mechanism that flips the detector
+    let mut flip_counts = vec![0; detector_error_model.detector_num];
+    for fault in faults.iter() {
+        for &detector in fault.detectors.iter() {
+            flip_counts[detector] += 1;
+        }
+    }
+    let mut measurement_cycles = flip_counts.iter().cloned().max().unwrap_or(0) + 5;
+    let schedule = loop {
+        if let Some(schedule) = FaultSchedule::new(&layout, &faults, measurement_cycles) {
+            break schedule;
+        }
+        measurement_cycles += measurement_cycles / 2;
+    };
+    let measurement_cycles = schedule.measurement_cycles;
+    let round_num = layout.round_num;
+    let height = (round_num + 1) * measurement_cycles + 1;
+    // tracks are ordered as detectors, their twins, observables and then the qubits of error mechanisms
+    let detector_track_num = layout.positions.len();
+    let observable_begin = 2 * detector_track_num;
+    let fault_begin = observable_begin + schedule.observable_tracks.len();
+    let track_num = fault_begin + schedule.fault_qubit_num;
+    let detector_vertical = layout.positions.iter().map(|&(i, _)| i + 1).max().unwrap_or(0);
+    let detector_horizontal = layout.positions.iter().map(|&(_, j)| j + 1).max().unwrap_or(0);
+    let horizontal = detector_horizontal
+        .max((schedule.fault_qubit_num as f64).sqrt().ceil() as usize)
+        .max(1);
+    let fault_row = 2 * detector_vertical;
+    let observable_row = fault_row + (schedule.fault_qubit_num + horizontal - 1) / horizontal;
+    let vertical = observable_row + (schedule.observable_tracks.len() + horizontal - 1) / horizontal;
+    let mut positions = layout.positions.clone();
+    for &(i, j) in layout.positions.iter() {
+        positions.push((detector_vertical + i, j));
+    }
+    for observable in 0..schedule.observable_tracks.len() {
+        positions.push((observable_row + observable / horizontal, observable % horizontal));
+    }
+    for qubit in 0..schedule.fault_qubit_num {
+        positions.push((fault_row + qubit / horizontal, qubit % horizontal));
+    }
+    let mut gates = vec![BTreeMap::<usize, (GateType, Option<usize>)>::new(); track_num];
+    for track in 0..detector_track_num {
+        let twin = detector_track_num + track;
+        for track in [track, twin] {
+            gates[track].insert(0, (GateType::MeasureZ, None));
+            gates[track].insert(1, (GateType::InitializeZ, None));
+        }
+    }
+    for &(track, round) in layout.detectors.iter() {
+        let twin = detector_track_num + track;
+        let t = (round + 1) * measurement_cycles;
+        // the twin is reset after every measurement and then copies the detector, so that they always flip together
+        gates[track].insert(t - 1, (GateType::CXGateControl, Some(twin)));
+        gates[twin].insert(t - 1, (GateType::CXGateTarget, Some(track)));
+        gates[track].insert(t, (GateType::MeasureZ, None));
+        gates[twin].insert(t, (GateType::MeasureZ, None));
+        gates[twin].insert(t + 1, (GateType::InitializeZ, None));
+    }
+    let mut noise_model_nodes = vec![];
+    for (fault, scheduled) in faults.iter().zip(schedule.faults.iter()) {
+        let qubit = fault_begin + scheduled.qubit;
+        gates[qubit].insert(scheduled.reset_t, (GateType::InitializeZ, None));
+        for &(t, target) in scheduled.gates.iter() {
+            let target = match target {
+                FaultTarget::Detector(track) => track,
+                FaultTarget::Observable(track) => observable_begin + track,
+            };
+            gates[qubit].insert(t, (GateType::CXGateControl, Some(target)));
+            gates[target].insert(t, (GateType::CXGateTarget, Some(qubit)));
+        }
+        noise_model_nodes.push((qubit, scheduled.reset_t + 1, fault.p, fault.p_graph));
+    }
+    let qubit_types: Vec<QubitType> = (0..track_num)
+        .map(|track| {
+            if (observable_begin..fault_begin).contains(&track) {
+                QubitType::Data
+            } else {
+                QubitType::StabZ
+            }
+        })
+        .collect();
+    let simulator = simulator_from_tracks(
+        CodeSize::new(round_num, 0, 0),
+        measurement_cycles,
+        height,
+        (vertical, horizontal),
+        &positions,
+        &gates,
+        &qubit_types,
+        |track| (detector_track_num..fault_begin).contains(&track),
+        |track| {
+            (observable_begin..fault_begin)
+                .contains(&track)
+                .then(|| schedule.observable_tracks[track - observable_begin])
+        },
+    );
+    let mut noise_model = NoiseModel::new(&simulator);
+    let mut noise_model_graph = NoiseModel::new(&simulator);
+    for &(qubit, t, p, p_graph) in noise_model_nodes.iter() {
+        let (i, j) = positions[qubit];
+        for (noise_model, p) in [(&mut noise_model, p), (&mut noise_model_graph, p_graph)] {
+            if p > 0. {
+                let mut noise_model_node = NoiseModelNode::new();
+                noise_model_node.pauli_error_rates.error_rate_X = p;
+                noise_model.set_node(&pos!(t, i, j), Some(Arc::new(noise_model_node)));
+            }
+        }
+    }
+    let mut simulator = simulator;
+    simulator.compress_error_rates(&mut noise_model);
+    simulator.compress_error_rates(&mut noise_model_graph);
+    Ok((simulator, noise_model, noise_model_graph))
+}
+
+/// build a customized simulator where each track is a row of nodes at a fixed (i, j) position, with the gates of each
+/// track indexed by time; `observable_of` marks the virtual data qubits read by [`code_builder_validate_correction`]
+#[allow(clippy::too_many_arguments)]
+fn simulator_from_tracks(
+    code_size: CodeSize,
+    measurement_cycles: usize,
+    height: usize,
+    (vertical, horizontal): (usize, usize),
+    positions: &[(usize, usize)],
+    gates: &[BTreeMap<usize, (GateType, Option<usize>)>],
+    qubit_types: &[QubitType],
+    is_virtual: impl Fn(usize) -> bool,
+    observable_of: impl Fn(usize) -> Option<usize>,
+) -> Simulator {
+    let mut track_at = vec![vec![None; horizontal]; vertical];
+    for (track, &(i, j)) in positions.iter().enumerate() {
+        track_at[i][j] = Some(track);
+    }
+    let mut simulator = Simulator::new(CodeType::Customized, code_size);
+    simulator.measurement_cycles = measurement_cycles;
+    simulator.height = height;
+    simulator.vertical = vertical;
+    simulator.horizontal = horizontal;
+    simulator.nodes = (0..height)
+        .map(|t| {
+            track_at
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|track| {
+                            track.map(|track| {
+                                let (gate_type, peer) = gates[track].get(&t).cloned().unwrap_or((GateType::None, None));
+                                let gate_peer = peer.map(|peer| {
+                                    let (i, j) = positions[peer];
+                                    pos!(t, i, j)
+                                });
+                                let miscellaneous =
+                                    observable_of(track).map(|observable| json!({ "observable": observable }));
+                                Box::new(
+                                    SimulatorNode::new(qubit_types[track], gate_type, gate_peer)
+                                        .set_virtual(is_virtual(track), peer.map_or(false, &is_virtual))
+                                        .with_miscellaneous(miscellaneous),
+                                )
+                            })
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+    simulator
+}
+
+/// Pauli operators are indexed by (x bit) | (z bit) << 1, so that multiplication is XOR of the indices
+const PAULI_I: usize = 0;
+const PAULI_X: usize = 1;
+const PAULI_Z: usize = 2;
+const PAULI_Y: usize = 3;
+
+fn pauli_index(letter: char) -> usize {
+    match letter {
+        'X' => PAULI_X,
+        'Y' => PAULI_Y,
+        'Z' => PAULI_Z,
+        _ => PAULI_I,
+    }
+}
+
+/// compose two independent Pauli channels over the same number of qubits
+fn compose_channels(first: &[f64], second: &[f64]) -> Vec<f64> {
+    let mut composed = vec![0.; first.len()];
+    for (a, pa) in first.iter().enumerate() {
+        for (b, pb) in second.iter().enumerate() {
+            composed[a ^ b] += pa * pb;
+        }
+    }
+    composed
+}
+
+/// the frame of a freshly reset qubit, where every Stim Pauli operator is the same simulated one
+const PAULI_FRAME_IDENTITY: [usize; 4] = [PAULI_I, PAULI_X, PAULI_Z, PAULI_Y];
+
+/// the action `P -> G P G^\dagger` of a single-qubit Clifford gate `G` on the Pauli operators indexed by [`pauli_index`];
+/// signs are irrelevant to Pauli errors and thus dropped
+fn single_qubit_clifford(name: &str) -> Option<[usize; 4]> {
+    let (x, z, y) = match name {
+        "I" | "X" | "Y" | "Z" => (PAULI_X, PAULI_Z, PAULI_Y),
+        "H" | "H_XZ" | "SQRT_Y" | "SQRT_Y_DAG" => (PAULI_Z, PAULI_X, PAULI_Y),
+        "H_XY" | "S" | "S_DAG" | "SQRT_Z" | "SQRT_Z_DAG" => (PAULI_Y, PAULI_Z, PAULI_X),
+        "H_YZ" | "SQRT_X" | "SQRT_X_DAG" => (PAULI_X, PAULI_Y, PAULI_Z),
+        "C_XYZ" => (PAULI_Y, PAULI_X, PAULI_Z),
+        "C_ZYX" => (PAULI_Z, PAULI_Y, PAULI_X),
+        _ => return None,
+    };
+    Some([PAULI_I, x, z, y])
+}
+
+/// the channel of the Pauli operators `frame[P]` given the channel of `P`
+fn permute_channel(channel: &[f64], frame: &[usize; 4]) -> Vec<f64> {
+    let mut permuted = vec![0.; 4];
+    for (pauli, &probability) in channel.iter().enumerate() {
+        permuted[frame[pauli]] = probability;
+    }
+    permuted
+}
+
+fn correlated_pauli_error_rates(channel: &[f64]) -> CorrelatedPauliErrorRates {
+    let rate = |my: usize, peer: usize| channel[my * 4 + peer];
+    CorrelatedPauliErrorRates {
+        error_rate_IX: rate(PAULI_I, PAULI_X),
+        error_rate_IZ: rate(PAULI_I, PAULI_Z),
+        error_rate_IY: rate(PAULI_I, PAULI_Y),
+        error_rate_XI: rate(PAULI_X, PAULI_I),
+        error_rate_XX: rate(PAULI_X, PAULI_X),
+        error_rate_XZ: rate(PAULI_X, PAULI_Z),
+        error_rate_XY: rate(PAULI_X, PAULI_Y),
+        error_rate_ZI: rate(PAULI_Z, PAULI_I),
+        error_rate_ZX: rate(PAULI_Z, PAULI_X),
+        error_rate_ZZ: rate(PAULI_Z, PAULI_Z),
+        error_rate_ZY: rate(PAULI_Z, PAULI_Y),
+        error_rate_YI: rate(PAULI_Y, PAULI_I),
+        error_rate_YX: rate(PAULI_Y, PAULI_X),
+        error_rate_YZ: rate(PAULI_Y, PAULI_Z),
+        error_rate_YY: rate(PAULI_Y, PAULI_Y),
+    }
+}
+
+/// a track is a row of nodes at a fixed (i, j) position: a Stim qubit, a detector, a detector twin or an observable
+struct StimCircuitBuilder {
+    qubit_num: usize,
+    detector_num: usize,
+    /// the detector and observable tracks that include each measurement
+    measurement_references: Vec<Vec<usize>>,
+    measurement_count: usize,
+    positions: Vec<(usize, usize)>,
+    vertical: usize,
+    horizontal: usize,
+    /// the latest time of each track
+    time: Vec<usize>,
+    /// gates cannot be placed before the latest `TICK`
+    barrier: usize,
+    gates: Vec<BTreeMap<usize, (GateType, Option<usize>)>>,
+    /// single-qubit Pauli channels indexed by [`pauli_index`]
+    pauli_channels: Vec<BTreeMap<usize, Vec<f64>>>,
+    /// two-qubit Pauli channels at the two-qubit gates, indexed by `my * 4 + peer`
+    correlated_channels: Vec<BTreeMap<usize, Vec<f64>>>,
+    /// the simulated Pauli operator of each Stim Pauli operator (indexed by [`pauli_index`]) on each Stim qubit, which
+    /// changes with the single-qubit Clifford gates applied since its latest reset
+    frames: Vec<[usize; 4]>,
+    /// time of the latest reset if only single-qubit operations are applied after it
+    pending_reset: Vec<Option<usize>>,
+    first_reset: Vec<Option<GateType>>,
+    /// the measurement basis (true for X basis) of the detectors and observables
+    is_x_basis: Vec<Option<bool>>,
+}
+
+impl StimCircuitBuilder {
+    fn new(instructions: &[StimInstruction]) -> Result<Self, String> {
+        let mut qubit_num = 0;
+        let mut coordinates = BTreeMap::<usize, Vec<f64>>::new();
+        let mut measurement_count = 0;
+        let mut detectors: Vec<BTreeSet<usize>> = vec![];
+        let mut observables: Vec<BTreeSet<usize>> = vec![];
+        for instruction in instructions.iter() {
+            for target in instruction.targets.iter() {
+                if let StimTarget::Qubit(qubit) = target {
+                    qubit_num = qubit_num.max(qubit + 1);
+                }
+            }
+            let mut included_measurements = BTreeSet::new();
+            for target in instruction.targets.iter() {
+                if let StimTarget::Record(lookback) = *target {
+                    if lookback > measurement_count {
+                        return Err(format!(
+                            "`rec[-{}]` refers to a measurement before the circuit starts",
+                            lookback
+                        ));
+                    }
+                    let measurement = measurement_count - lookback;
+                    if !included_measurements.remove(&measurement) {
+                        included_measurements.insert(measurement);
+                    }
+                }
+            }
+            match instruction.name.as_str() {
+                "QUBIT_COORDS" => {
+                    for target in instruction.targets.iter() {
+                        if let StimTarget::Qubit(qubit) = target {
+                            coordinates.insert(*qubit, instruction.arguments.clone());
+                        }
+                    }
+                }
+                "M" | "MZ" | "MX" | "MY" | "MR" | "MRZ" | "MRX" => {
+                    measurement_count += instruction.targets.len();
+                }
+                "DETECTOR" => {
+                    detectors.push(included_measurements);
+                }
+                "OBSERVABLE_INCLUDE" => {
+                    let index = instruction.arguments.first().cloned().unwrap_or(0.) as usize;
+                    if index >= 2 {
+                        return Err(format!("only observables 0 and 1 are supported, found {}", index));
+                    }
+                    if observables.len() <= index {
+                        observables.resize(index + 1, BTreeSet::new());
+                    }
+                    observables[index] = observables[index]
+                        .symmetric_difference(&included_measurements)
+                        .cloned()
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        let detector_num = detectors.len();
+        let observable_num = observables.len();
+        let mut measurement_references = vec![vec![]; measurement_count];
+        for (detector, measurements) in detectors.iter().enumerate() {
+            for &measurement in measurements.iter() {
+                measurement_references[measurement].push(qubit_num + detector);
+            }
+        }
+        for (observable, measurements) in observables.iter().enumerate() {
+            for &measurement in measurements.iter() {
+                measurement_references[measurement].push(qubit_num + 2 * detector_num + observable);
+            }
+        }
+        // qubits follow `QUBIT_COORDS` if possible, detectors and their twins are placed below them
+        let mut positions = qubit_positions(qubit_num, &coordinates);
+        let qubit_vertical = positions.iter().map(|&(i, _)| i + 1).max().unwrap_or(0);
+        let qubit_horizontal = positions.iter().map(|&(_, j)| j + 1).max().unwrap_or(0);
+        let detector_horizontal = qubit_horizontal.max((detector_num as f64).sqrt().ceil() as usize).max(1);
+        let detector_rows = (detector_num + detector_horizontal - 1) / detector_horizontal;
+        for detector in 0..detector_num {
+            positions.push((
+                qubit_vertical + 1 + 2 * (detector / detector_horizontal),
+                detector % detector_horizontal,
+            ));
+        }
+        for detector in 0..detector_num {
+            positions.push((
+                qubit_vertical + 2 + 2 * (detector / detector_horizontal),
+                detector % detector_horizontal,
+            ));
+        }
+        let observable_row = qubit_vertical + 2 * detector_rows + 2;
+        for observable in 0..observable_num {
+            positions.push((observable_row, observable));
+        }
+        let track_num = positions.len();
+        let mut time = vec![0; track_num];
+        for time in time.iter_mut().skip(qubit_num).take(2 * detector_num) {
+            *time = 1; // detectors are initialized at t = 1
+        }
+        Ok(Self {
+            qubit_num,
+            detector_num,
+            measurement_references,
+            measurement_count: 0,
+            vertical: observable_row + 1,
+            horizontal: detector_horizontal.max(observable_num),
+            positions,
+            time,
+            barrier: 0,
+            gates: vec![BTreeMap::new(); track_num],
+            pauli_channels: vec![BTreeMap::new(); track_num],
+            correlated_channels: vec![BTreeMap::new(); track_num],
+            frames: vec![PAULI_FRAME_IDENTITY; qubit_num],
+            pending_reset: vec![None; qubit_num],
+            first_reset: vec![None; qubit_num],
+            is_x_basis: vec![None; track_num],
+        })
+    }
+
+    fn qubit_targets(instruction: &StimInstruction) -> Result<Vec<usize>, String> {
+        instruction
+            .targets
+            .iter()
+            .map(|target| match target {
+                StimTarget::Qubit(qubit) => Ok(*qubit),
+                _ => Err(format!("{} only accepts qubit targets", instruction.name)),
+            })
+            .collect()
+    }
+
+    fn qubit_pairs(instruction: &StimInstruction) -> Result<Vec<(usize, usize)>, String> {
+        let qubits = Self::qubit_targets(instruction)?;
+        if qubits.len() % 2 != 0 {
+            return Err(format!("{} requires an even number of targets", instruction.name));
+        }
+        Ok(qubits.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+    }
+
+    /// place a gate on the tracks at the earliest common time after the latest `TICK`
+    fn place(&mut self, tracks: &[usize]) -> usize {
+        let t = tracks.iter().map(|&track| self.time[track]).max().unwrap().max(self.barrier) + 1;
+        for &track in tracks.iter() {
+            self.time[track] = t;
+        }
+        t
+    }
+
+    fn place_two_qubit_gate(&mut self, gate_type: GateType, control: usize, target: usize) -> usize {
+        let t = self.place(&[control, target]);
+        self.gates[control].insert(t, (gate_type, Some(target)));
+        self.gates[target].insert(t, (gate_type.peer_gate(), Some(control)));
+        t
+    }
+
+    /// errors at initialization are discarded by the simulator, so noise after a reset needs an idle node
+    fn noise_time(&mut self, qubit: usize) -> usize {
+        let t = self.time[qubit];
+        if matches!(self.gates[qubit].get(&t), Some((gate_type, _)) if gate_type.is_initialization()) {
+            self.time[qubit] = t + 1;
+        }
+        self.time[qubit]
+    }
+
+    /// add a Pauli channel given in the frame of the Stim qubit
+    fn add_pauli_channel(&mut self, qubit: usize, channel: &[f64]) {
+        let mut channel = permute_channel(channel, &self.frames[qubit]);
+        channel[PAULI_I] = 1. - channel[1..].iter().sum::<f64>();
+        let t = self.noise_time(qubit);
+        let existing = self.pauli_channels[qubit].remove(&t);
+        let channel = match existing {
+            Some(existing) => compose_channels(&existing, &channel),
+            None => channel,
+        };
+        self.pauli_channels[qubit].insert(t, channel);
+    }
+
+    /// add a two-qubit Pauli channel given in the frames of the Stim qubits, indexed by `my * 4 + peer`
+    fn add_correlated_channel(&mut self, qubit: usize, peer: usize, channel_of_stim: &[f64]) -> Result<(), String> {
+        let t = self.time[qubit];
+        if self.time[peer] != t || !matches!(self.gates[qubit].get(&t), Some((_, Some(gate_peer))) if *gate_peer == peer) {
+            return Err(format!(
+                "two-qubit noise on qubits {} and {} must follow a two-qubit gate between them",
+                qubit, peer
+            ));
+        }
+        let (frame, peer_frame) = (self.frames[qubit], self.frames[peer]);
+        let mut channel = vec![0.; 16];
+        for (index, &probability) in channel_of_stim.iter().enumerate() {
+            channel[frame[index / 4] * 4 + peer_frame[index % 4]] = probability;
+        }
+        channel[0] = 1. - channel[1..].iter().sum::<f64>();
+        let existing = self.correlated_channels[qubit].remove(&t);
+        let channel = match existing {
+            Some(existing) => compose_channels(&existing, &channel),
+            None => channel,
+        };
+        self.correlated_channels[qubit].insert(t, channel);
+        Ok(())
+    }
+
+    fn reset(&mut self, qubit: usize, gate_type: GateType) {
+        let t = self.place(&[qubit]);
+        self.gates[qubit].insert(t, (gate_type, None));
+        self.frames[qubit] = PAULI_FRAME_IDENTITY;
+        self.pending_reset[qubit] = Some(t);
+        self.first_reset[qubit].get_or_insert(gate_type);
+    }
+
+    /// apply a single-qubit Clifford gate given by its action on the Pauli operators, see [`single_qubit_clifford`]
+    fn single_qubit_clifford(&mut self, qubit: usize, conjugation: &[usize; 4]) {
+        let basis = |gate_type: GateType| {
+            if gate_type == GateType::InitializeZ {
+                PAULI_Z
+            } else {
+                PAULI_X
+            }
+        };
+        let prepared = self.pending_reset[qubit]
+            .map(|reset_t| (reset_t, conjugation[basis(self.gates[qubit][&reset_t].0)]))
+            .filter(|&(_, prepared)| prepared != PAULI_Y);
+        if let Some((reset_t, prepared)) = prepared {
+            // the qubit is freshly prepared: change the basis of the preparation and the noise after it instead
+            let (gate_type, _) = self.gates[qubit].get_mut(&reset_t).unwrap();
+            *gate_type = if prepared == PAULI_Z {
+                GateType::InitializeZ
+            } else {
+                GateType::InitializeX
+            };
+            for (_, channel) in self.pauli_channels[qubit].range_mut(reset_t..) {
+                *channel = permute_channel(channel, conjugation);
+            }
+        } else {
+            // a Stim operator `P` after the gate is `G^\dagger P G` before it
+            let mut inverse = PAULI_FRAME_IDENTITY;
+            for pauli in 0..4 {
+                inverse[conjugation[pauli]] = pauli;
+            }
+            let frame = self.frames[qubit];
+            self.frames[qubit] = [0, 1, 2, 3].map(|pauli| frame[inverse[pauli]]);
+            self.pending_reset[qubit] = None;
+        }
+        self.place(&[qubit]);
+    }
+
+    /// measure the Stim operator `basis` (indexed by [`pauli_index`]) of a qubit
+    fn measure(&mut self, qubit: usize, basis: usize, flip_probability: f64) -> Result<(), String> {
+        let is_x_basis = match self.frames[qubit][basis] {
+            PAULI_X => true,
+            PAULI_Z => false,
+            _ => {
+                return Err(format!(
+                    "measurement of qubit {} is in the Y basis of the simulator, which is not supported",
+                    qubit
+                ))
+            }
+        };
+        if flip_probability > 0. {
+            let mut channel = vec![0.; 4];
+            channel[if basis == PAULI_Z { PAULI_X } else { PAULI_Z }] = flip_probability;
+            self.add_pauli_channel(qubit, &channel);
+        }
+        let references = self.measurement_references[self.measurement_count].clone();
+        self.measurement_count += 1;
+        for &reference in references.iter() {
+            let reference_is_x_basis = *self.is_x_basis[reference].get_or_insert(is_x_basis);
+            if is_x_basis && !reference_is_x_basis {
+                self.switch_to_x_basis(reference);
+            }
+            if is_x_basis {
+                self.place_two_qubit_gate(GateType::CXGateControl, reference, qubit);
+            } else if reference_is_x_basis {
+                // a flipped Z basis measurement is an X error, which CZ copies as a Z error onto the X basis reference
+                self.place_two_qubit_gate(GateType::CZGate, qubit, reference);
+            } else {
+                self.place_two_qubit_gate(GateType::CXGateControl, qubit, reference);
+            }
+        }
+        if references.is_empty() {
+            self.place(&[qubit]);
+        }
+        self.pending_reset[qubit] = None;
+        Ok(())
+    }
+
+    /// a detector or an observable that includes measurements in both bases is measured in X basis, where the flips of the
+    /// Z basis measurements copied before are Z errors copied by CZ instead of X errors copied by CX
+    fn switch_to_x_basis(&mut self, reference: usize) {
+        let copies: Vec<(usize, usize)> = self.gates[reference]
+            .iter()
+            .filter_map(|(&t, &(_, peer))| peer.map(|peer| (t, peer)))
+            .collect();
+        for (t, qubit) in copies {
+            self.gates[reference].insert(t, (GateType::CZGate, Some(qubit)));
+            self.gates[qubit].insert(t, (GateType::CZGate, Some(reference)));
+        }
+        self.is_x_basis[reference] = Some(true);
+    }
+
+    /// apply a controlled-Pauli gate that applies `target_pauli` to `target` if `control_pauli` of `control` is -1, e.g.,
+    /// `CX` is `(Z, X)`; the simulator only has the gates whose control (or target) is `Z` in its frame
+    fn controlled_pauli(
+        &mut self,
+        name: &str,
+        (control, control_pauli): (usize, usize),
+        (target, target_pauli): (usize, usize),
+    ) -> Result<(), String> {
+        let gate_type_of = |pauli: usize| match pauli {
+            PAULI_X => GateType::CXGateControl,
+            PAULI_Y => GateType::CYGateControl,
+            _ => GateType::CZGate,
+        };
+        let (control_pauli, target_pauli) = (self.frames[control][control_pauli], self.frames[target][target_pauli]);
+        if control_pauli == PAULI_Z {
+            self.place_two_qubit_gate(gate_type_of(target_pauli), control, target);
+        } else if target_pauli == PAULI_Z {
+            self.place_two_qubit_gate(gate_type_of(control_pauli), target, control);
+        } else {
+            return Err(format!(
+                "{} on qubits {} and {} is not supported: after the preceding single-qubit Clifford gates, neither qubit \
+                controls it in the Z basis",
+                name, control, target
+            ));
+        }
+        self.pending_reset[control] = None;
+        self.pending_reset[target] = None;
+        Ok(())
+    }
+
+    fn apply(&mut self, instruction: &StimInstruction) -> Result<(), String> {
+        let name = instruction.name.as_str();
+        let argument = instruction.arguments.first().cloned().unwrap_or(0.);
+        match name {
+            "QUBIT_COORDS" | "SHIFT_COORDS" | "DETECTOR" | "OBSERVABLE_INCLUDE" => {}
+            "TICK" => {
+                self.barrier = self.time[..self.qubit_num].iter().cloned().max().unwrap_or(0);
+            }
+            "R" | "RZ" | "RX" => {
+                let gate_type = if name == "RX" {
+                    GateType::InitializeX
+                } else {
+                    GateType::InitializeZ
+                };
+                for qubit in Self::qubit_targets(instruction)? {
+                    self.reset(qubit, gate_type);
+                }
+            }
+            "M" | "MZ" | "MX" | "MY" | "MR" | "MRZ" | "MRX" => {
+                let basis = pauli_index(name.chars().last().unwrap());
+                let basis = if basis == PAULI_I { PAULI_Z } else { basis };
+                for qubit in Self::qubit_targets(instruction)? {
+                    self.measure(qubit, basis, argument)?;
+                    if name.starts_with("MR") {
+                        self.reset(
+                            qubit,
+                            if basis == PAULI_X {
+                                GateType::InitializeX
+                            } else {
+                                GateType::InitializeZ
+                            },
+                        );
+                    }
+                }
+            }
+            "CX" | "CNOT" | "ZCX" | "CY" | "ZCY" | "CZ" | "ZCZ" | "XCX" | "XCY" | "XCZ" | "YCX" | "YCY" | "YCZ" => {
+                let (control_pauli, target_pauli) = match name {
+                    "CX" | "CNOT" => (PAULI_Z, PAULI_X),
+                    "CY" => (PAULI_Z, PAULI_Y),
+                    "CZ" => (PAULI_Z, PAULI_Z),
+                    _ => (
+                        pauli_index(name.chars().next().unwrap()),
+                        pauli_index(name.chars().last().unwrap()),
+                    ),
+                };
+                for (control, target) in Self::qubit_pairs(instruction)? {
+                    self.controlled_pauli(name, (control, control_pauli), (target, target_pauli))?;
+                }
+            }
+            _ if single_qubit_clifford(name).is_some() => {
+                let conjugation = single_qubit_clifford(name).unwrap();
+                for qubit in Self::qubit_targets(instruction)? {
+                    self.single_qubit_clifford(qubit, &conjugation);
+                }
+            }
+            "X_ERROR" | "Y_ERROR" | "Z_ERROR" | "DEPOLARIZE1" | "PAULI_CHANNEL_1" => {
+                let mut channel = vec![0.; 4];
+                match name {
+                    "DEPOLARIZE1" => {
+                        channel[PAULI_X] = argument / 3.;
+                        channel[PAULI_Y] = argument / 3.;
+                        channel[PAULI_Z] = argument / 3.;
+                    }
+                    "PAULI_CHANNEL_1" => {
+                        if instruction.arguments.len() != 3 {
+                            return Err("PAULI_CHANNEL_1 requires 3 arguments".to_string());
+                        }
+                        channel[PAULI_X] = instruction.arguments[0];
+                        channel[PAULI_Y] = instruction.arguments[1];
+                        channel[PAULI_Z] = instruction.arguments[2];
+                    }
+                    _ => {
+                        channel[pauli_index(name.chars().next().unwrap())] = argument;
+                    }
+                }
+                for qubit in Self::qubit_targets(instruction)? {
+                    self.add_pauli_channel(qubit, &channel);
+                }
+            }
+            "DEPOLARIZE2" | "PAULI_CHANNEL_2" => {
+                let mut channel = vec![argument / 15.; 16];
+                if name == "PAULI_CHANNEL_2" {
+                    if instruction.arguments.len() != 15 {
+                        return Err("PAULI_CHANNEL_2 requires 15 arguments".to_string());
+                    }
+                    // Stim orders the arguments as IX, IY, IZ, XI, XX, ..., ZZ
+                    for (index, &probability) in instruction.arguments.iter().enumerate() {
+                        let letters = ['I', 'X', 'Y', 'Z'];
+                        let my = pauli_index(letters[(index + 1) / 4]);
+                        let peer = pauli_index(letters[(index + 1) % 4]);
+                        channel[my * 4 + peer] = probability;
+                    }
+                }
+                for (qubit, peer) in Self::qubit_pairs(instruction)? {
+                    self.add_correlated_channel(qubit, peer, &channel)?;
+                }
+            }
+            _ => return Err(format!("unsupported Stim instruction `{}`", name)),
+        }
+        Ok(())
+    }
+
+    fn build(self) -> (Simulator, NoiseModel) {
+        let detector_begin = self.qubit_num;
+        let twin_begin = detector_begin + self.detector_num;
+        let observable_begin = twin_begin + self.detector_num;
+        // the whole circuit lies within the first measurement cycle, the second one is left noiseless
+        let body_end = self.time.iter().cloned().max().unwrap_or(0);
+        let measurement_cycles = body_end.max(2);
+        let height = 2 * measurement_cycles + 1;
+        let mut gates = self.gates;
+        for detector in 0..self.detector_num {
+            let (detector, twin) = (detector_begin + detector, twin_begin + detector);
+            let is_x_basis = self.is_x_basis[detector].unwrap_or(false);
+            let (initialize, measure) = if is_x_basis {
+                (GateType::InitializeX, GateType::MeasureX)
+            } else {
+                (GateType::InitializeZ, GateType::MeasureZ)
+            };
+            for track in [detector, twin] {
+                gates[track].insert(0, (measure, None));
+                gates[track].insert(1, (initialize, None));
+                gates[track].insert(height - 1, (measure, None));
+            }
+            // the twin copies the detector so that the detector is always flipped together with it
+            let (control, target) = if is_x_basis { (twin, detector) } else { (detector, twin) };
+            gates[control].insert(measurement_cycles + 1, (GateType::CXGateControl, Some(target)));
+            gates[target].insert(measurement_cycles + 1, (GateType::CXGateTarget, Some(control)));
+        }
+        let qubit_types: Vec<QubitType> = (0..self.positions.len())
+            .map(|track| {
+                if track < detector_begin {
+                    match self.first_reset[track] {
+                        Some(GateType::InitializeZ) => QubitType::StabZ,
+                        Some(GateType::InitializeX) => QubitType::StabX,
+                        _ => QubitType::Data,
+                    }
+                } else if track < observable_begin {
+                    let detector = detector_begin + (track - detector_begin) % self.detector_num;
+                    if self.is_x_basis[detector] == Some(true) {
+                        QubitType::StabX
+                    } else {
+                        QubitType::StabZ
+                    }
+                } else {
+                    QubitType::Data
+                }
+            })
+            .collect();
+        let mut simulator = simulator_from_tracks(
+            CodeSize::new(1, 0, 0),
+            measurement_cycles,
+            height,
+            (self.vertical, self.horizontal),
+            &self.positions,
+            &gates,
+            &qubit_types,
+            |track| track >= twin_begin,
+            |track| track.checked_sub(observable_begin),
+        );
+        let mut noise_model = NoiseModel::new(&simulator);
+        let mut noise_model_nodes = BTreeMap::<(usize, usize), NoiseModelNode>::new();
+        for (track, channels) in self.pauli_channels.iter().enumerate() {
+            for (&t, channel) in channels.iter() {
+                let noise_model_node = noise_model_nodes.entry((track, t)).or_default();
+                noise_model_node.pauli_error_rates.error_rate_X = channel[PAULI_X];
+                noise_model_node.pauli_error_rates.error_rate_Y = channel[PAULI_Y];
+                noise_model_node.pauli_error_rates.error_rate_Z = channel[PAULI_Z];
+            }
+        }
+        for (track, channels) in self.correlated_channels.iter().enumerate() {
+            for (&t, channel) in channels.iter() {
+                let noise_model_node = noise_model_nodes.entry((track, t)).or_default();
+                noise_model_node.correlated_pauli_error_rates = Some(correlated_pauli_error_rates(channel));
+            }
+        }
+        for ((track, t), noise_model_node) in noise_model_nodes.into_iter() {
+            let (i, j) = self.positions[track];
+            noise_model.set_node(&pos!(t, i, j), Some(Arc::new(noise_model_node)));
+        }
+        simulator.compress_error_rates(&mut noise_model);
+        (simulator, noise_model)
+    }
+}
+
+/// export a simulator and its noise model as an equivalent Stim circuit, with a `DETECTOR` for every defect measurement
+/// reported by [`Simulator::generate_sparse_measurement`] and an `OBSERVABLE_INCLUDE` for each logical operator
+pub fn simulator_to_stim_circuit(simulator: &Simulator, noise_model: &NoiseModel) -> Result<String, String> {
+    let mut simulator = simulator.clone();
+    let height = simulator.height;
+    let top_t = height - 1;
+    let measurement_cycles = simulator.measurement_cycles;
+    // every position that has a real node becomes a Stim qubit
+    let mut qubit_index = BTreeMap::<(usize, usize), usize>::new();
+    for i in 0..simulator.vertical {
+        for j in 0..simulator.horizontal {
+            if (0..height).any(|t| simulator.is_node_real(&pos!(t, i, j))) {
+                qubit_index.insert((i, j), qubit_index.len());
+            }
+        }
+    }
+    // find the previous measurement of every detector in the same way as `generate_sparse_measurement`
+    let mut detectors = BTreeMap::<Position, usize>::new();
+    for t in (measurement_cycles..height).step_by(measurement_cycles) {
+        simulator_iter_real!(simulator, position, node, t => t, {
+            if node.gate_type.is_measurement() {
+                let mut previous_t = t;
+                loop {
+                    if previous_t < measurement_cycles {
+                        return Err(format!("cannot find the previous measurement of {}", position));
+                    }
+                    previous_t -= measurement_cycles;
+                    if simulator.get_node_unwrap(&pos!(previous_t, position.i, position.j)).gate_type.is_measurement() {
+                        break;
+                    }
+                }
+                detectors.insert(position.clone(), previous_t);
+            }
+        });
+    }
+    // the stabilizer measured by a first-round detector anticommutes with the errors at t = 0 that flip it
+    let mut stabilizers = BTreeMap::<(usize, usize), BTreeMap<usize, ErrorType>>::new();
+    for (&(i, j), &qubit) in qubit_index.iter() {
+        let position = pos!(0, i, j);
+        if !simulator.is_node_real(&position) {
+            continue;
+        }
+        for probe in [ErrorType::X, ErrorType::Z] {
+            simulator.clear_all_errors();
+            simulator.get_node_mut_unwrap(&position).error = probe;
+            simulator.propagate_errors();
+            for defect in simulator.generate_sparse_measurement().iter() {
+                if detectors.get(defect) == Some(&0) {
+                    let component = stabilizers
+                        .entry((defect.i, defect.j))
+                        .or_default()
+                        .entry(qubit)
+                        .or_insert(ErrorType::I);
+                    *component = component.multiply(&anticommuting_pauli(probe));
+                }
+            }
+        }
+    }
+    simulator.clear_all_errors();
+    // the logical operators anticommute with the corrections that flip the logical results
+    let mut logicals = [BTreeMap::<usize, ErrorType>::new(), BTreeMap::new()];
+    for (&(i, j), &qubit) in qubit_index.iter() {
+        let position = pos!(top_t, i, j);
+        if !simulator.is_node_real(&position) {
+            continue;
+        }
+        for probe in [ErrorType::X, ErrorType::Z] {
+            let mut correction = SparseCorrection::new();
+            correction.add(position.clone(), probe);
+            if let Some((logical_i, logical_j)) = code_builder_validate_correction(&mut simulator, &correction) {
+                for (logical, flipped) in logicals.iter_mut().zip([logical_i, logical_j]) {
+                    if flipped {
+                        let component = logical.entry(qubit).or_insert(ErrorType::I);
+                        *component = component.multiply(&anticommuting_pauli(probe));
+                    }
+                }
+            }
+        }
+    }
+    let anticommuting_count = logicals[0]
+        .iter()
+        .filter(|(qubit, pauli)| logicals[1].get(qubit).map_or(false, |peer| pauli_anticommute(pauli, peer)))
+        .count();
+    if anticommuting_count % 2 == 1 {
+        // entangle the logical qubit with a reference qubit so that both logical operators are measured deterministically
+        let reference_qubit = qubit_index.len();
+        logicals[0].insert(reference_qubit, ErrorType::X);
+        logicals[1].insert(reference_qubit, ErrorType::Z);
+    }
+    // write the circuit
+    let mut circuit = String::new();
+    for (&(i, j), &qubit) in qubit_index.iter() {
+        circuit += &format!("QUBIT_COORDS({}, {}) {}\n", j, i, qubit);
+    }
+    let mut measurement_count = 0;
+    let mut measurement_records = BTreeMap::<Position, usize>::new();
+    let mut initial_logical_records = [None, None];
+    for t in 0..height {
+        let mut gates = BTreeMap::<&str, Vec<usize>>::new();
+        let mut measurements = vec![];
+        simulator_iter_real!(simulator, position, node, t => t, {
+            let qubit = qubit_index[&(position.i, position.j)];
+            let peer = node.gate_peer.as_ref().filter(|_| !node.is_peer_virtual).map(|peer| qubit_index[&(peer.i, peer.j)]);
+            match (node.gate_type, peer) {
+                (GateType::InitializeZ, _) => gates.entry("R").or_default().push(qubit),
+                (GateType::InitializeX, _) => gates.entry("RX").or_default().push(qubit),
+                (GateType::MeasureZ | GateType::MeasureX, _) if t != 0 => measurements.push((position.clone(), node.gate_type)),
+                (GateType::CXGateControl, Some(peer)) => gates.entry("CX").or_default().extend([qubit, peer]),
+                (GateType::CYGateControl, Some(peer)) => gates.entry("CY").or_default().extend([qubit, peer]),
+                (GateType::CZGate, Some(peer)) if qubit < peer => gates.entry("CZ").or_default().extend([qubit, peer]),
+                _ => {}
+            }
+        });
+        for (name, targets) in gates.iter() {
+            circuit += &format!("{}{}\n", name, stim_targets(targets));
+        }
+        for (measurement_basis, name) in [(GateType::MeasureZ, "M"), (GateType::MeasureX, "MX")] {
+            let mut targets = vec![];
+            for (position, gate_type) in measurements.iter() {
+                if *gate_type == measurement_basis {
+                    targets.push(qubit_index[&(position.i, position.j)]);
+                    measurement_records.insert(position.clone(), measurement_count);
+                    measurement_count += 1;
+                }
+            }
+            if !targets.is_empty() {
+                circuit += &format!("{}{}\n", name, stim_targets(&targets));
+            }
+        }
+        if t == 0 {
+            // perfect reference measurements, see module documentation
+            let mut products = vec![];
+            for ((i, j), stabilizer) in stabilizers.iter() {
+                measurement_records.insert(pos!(0, *i, *j), measurement_count);
+                measurement_count += 1;
+                products.push(pauli_product(stabilizer));
+            }
+            for (logical, record) in logicals.iter().zip(initial_logical_records.iter_mut()) {
+                if !logical.is_empty() {
+                    *record = Some(measurement_count);
+                    measurement_count += 1;
+                    products.push(pauli_product(logical));
+                }
+            }
+            if !products.is_empty() {
+                circuit += &format!("MPP {}\n", products.join(" "));
+            }
+        }
+        // noise channels after the gates; errors right after initialization are discarded by the simulator
+        let mut noises = BTreeMap::<String, Vec<usize>>::new();
+        let mut heralded_erasure_count = 0;
+        let mut error_message = None;
+        simulator_iter_real!(simulator, position, node, t => t, {
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            if node.gate_type.is_initialization() {
+                continue;
+            }
+            let qubit = qubit_index[&(position.i, position.j)];
+            let pauli_error_rates = &noise_model_node.pauli_error_rates;
+            if pauli_error_rates.error_rate_X + pauli_error_rates.error_rate_Y + pauli_error_rates.error_rate_Z > 0. {
+                let name = format!(
+                    "PAULI_CHANNEL_1({}, {}, {})",
+                    pauli_error_rates.error_rate_X, pauli_error_rates.error_rate_Y, pauli_error_rates.error_rate_Z
+                );
+                noises.entry(name).or_default().push(qubit);
+            }
+            if noise_model_node.erasure_error_rate > 0. {
+                noises.entry(format!("HERALDED_ERASE({})", noise_model_node.erasure_error_rate)).or_default().push(qubit);
+                heralded_erasure_count += 1;
+            }
+            if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
+                let rates = stim_pauli_channel_2_arguments(correlated_pauli_error_rates);
+                if rates.iter().any(|&rate| rate > 0.) {
+                    match node.gate_peer.as_ref().filter(|_| !node.is_peer_virtual) {
+                        Some(peer) => {
+                            let rates: Vec<String> = rates.iter().map(|rate| rate.to_string()).collect();
+                            let name = format!("PAULI_CHANNEL_2({})", rates.join(", "));
+                            noises.entry(name).or_default().extend([qubit, qubit_index[&(peer.i, peer.j)]]);
+                        }
+                        None => error_message = Some(format!("correlated Pauli errors at {} require a real gate peer", position)),
+                    }
+                }
+            }
+            if let Some(correlated_erasure_error_rates) = &noise_model_node.correlated_erasure_error_rates {
+                let rates = correlated_erasure_error_rates;
+                if rates.error_rate_IE + rates.error_rate_EI + rates.error_rate_EE > 0. {
+                    error_message = Some(format!("correlated erasure errors at {} are not supported by Stim", position));
+                }
+            }
+        });
+        if let Some(error_message) = error_message {
+            return Err(error_message);
+        }
+        for (name, targets) in noises.iter() {
+            circuit += &format!("{}{}\n", name, stim_targets(targets));
+        }
+        measurement_count += heralded_erasure_count;
+        for additional_noise in noise_model.additional_noise.iter() {
+            if !additional_noise.erasures.is_empty() {
+                return Err("erasures in additional noise are not supported by Stim".to_string());
+            }
+            if additional_noise.pauli_errors.iter().any(|(position, _)| position.t != t) {
+                if additional_noise.pauli_errors.iter().any(|(position, _)| position.t == t) {
+                    return Err("additional noise spanning multiple time steps is not supported by Stim".to_string());
+                }
+                continue;
+            }
+            let mut targets = vec![];
+            for (position, error) in additional_noise.pauli_errors.iter() {
+                if simulator.is_node_real(position) && !simulator.get_node_unwrap(position).gate_type.is_initialization() {
+                    targets.push(format!("{:?}{}", error, qubit_index[&(position.i, position.j)]));
+                }
+            }
+            if !targets.is_empty() {
+                circuit += &format!("CORRELATED_ERROR({}) {}\n", additional_noise.probability, targets.join(" "));
+            }
+        }
+        // a detector compares a measurement with the previous one at the same position
+        for (position, previous_t) in detectors.range(pos!(t, 0, 0)..pos!(t + 1, 0, 0)) {
+            let mut records = vec![measurement_records[position]];
+            records.extend(measurement_records.get(&pos!(*previous_t, position.i, position.j)));
+            let lookbacks: Vec<String> = records
+                .iter()
+                .map(|record| format!("rec[-{}]", measurement_count - record))
+                .collect();
+            circuit += &format!("DETECTOR({}, {}, {}) {}\n", position.j, position.i, t, lookbacks.join(" "));
+        }
+        if t != top_t {
+            circuit += "TICK\n";
+        }
+    }
+    let mut final_products = vec![];
+    for logical in logicals.iter().filter(|logical| !logical.is_empty()) {
+        final_products.push(pauli_product(logical));
+    }
+    if !final_products.is_empty() {
+        circuit += &format!("MPP {}\n", final_products.join(" "));
+        measurement_count += final_products.len();
+        let mut final_record = measurement_count - final_products.len();
+        for (observable, initial_record) in initial_logical_records.iter().enumerate() {
+            if let Some(initial_record) = initial_record {
+                circuit += &format!(
+                    "OBSERVABLE_INCLUDE({}) rec[-{}] rec[-{}]\n",
+                    observable,
+                    measurement_count - final_record,
+                    measurement_count - initial_record
+                );
+                final_record += 1;
+            }
+        }
+    }
+    Ok(circuit)
+}
+
+/// a Pauli operator that anticommutes with the probe `X` or `Z`
+fn anticommuting_pauli(probe: ErrorType) -> ErrorType {
+    if probe == ErrorType::X {
+        ErrorType::Z
+    } else {
+        ErrorType::X
+    }
+}
+
+fn pauli_anticommute(a: &ErrorType, b: &ErrorType) -> bool {
+    a != &ErrorType::I && b != &ErrorType::I && a != b
+}
+
+fn pauli_product(operator: &BTreeMap<usize, ErrorType>) -> String {
+    let paulis: Vec<String> = operator
+        .iter()
+        .filter(|(_, pauli)| **pauli != ErrorType::I)
+        .map(|(qubit, pauli)| format!("{:?}{}", pauli, qubit))
+        .collect();
+    paulis.join("*")
+}
+
+fn stim_targets(targets: &[usize]) -> String {
+    let targets: Vec<String> = targets.iter().map(|target| target.to_string()).collect();
+    format!(" {}", targets.join(" "))
+}
+
+/// correlated Pauli error rates in the argument order of Stim's `PAULI_CHANNEL_2`, i.e. IX, IY, IZ, XI, XX, ..., ZZ
+fn stim_pauli_channel_2_arguments(rates: &CorrelatedPauliErrorRates) -> [f64; 15] {
+    [
+        rates.error_rate_IX,
+        rates.error_rate_IY,
+        rates.error_rate_IZ,
+        rates.error_rate_XI,
+        rates.error_rate_XX,
+        rates.error_rate_XY,
+        rates.error_rate_XZ,
+        rates.error_rate_YI,
+        rates.error_rate_YX,
+        rates.error_rate_YY,
+        rates.error_rate_YZ,
+        rates.error_rate_ZI,
+        rates.error_rate_ZX,
+        rates.error_rate_ZY,
+        rates.error_rate_ZZ,
+    ]
+}
+
+/// use `QUBIT_COORDS` when every qubit has distinct integer (or half-integer) coordinates, otherwise put qubits in a row
+fn qubit_positions(qubit_num: usize, coordinates: &BTreeMap<usize, Vec<f64>>) -> Vec<(usize, usize)> {
+    let row = (0..qubit_num).map(|qubit| (0, qubit)).collect();
+    let mut points = vec![];
+    for qubit in 0..qubit_num {
+        match coordinates.get(&qubit) {
+            Some(coordinate) if !coordinate.is_empty() => {
+                points.push((coordinate.get(1).cloned().unwrap_or(0.), coordinate[0]));
+            }
+            _ => return row,
+        }
+    }
+    for scale in [1., 2.] {
+        let is_integer = |value: f64| (value * scale - (value * scale).round()).abs() < 1e-6;
+        if !points.iter().all(|&(y, x)| is_integer(y) && is_integer(x)) {
+            continue;
+        }
+        let scaled: Vec<(i64, i64)> = points
+            .iter()
+            .map(|&(y, x)| ((y * scale).round() as i64, (x * scale).round() as i64))
+            .collect();
+        let min_i = scaled.iter().map(|&(i, _)| i).min().unwrap_or(0);
+        let min_j = scaled.iter().map(|&(_, j)| j).min().unwrap_or(0);
+        let positions: Vec<(usize, usize)> = scaled
+            .iter()
+            .map(|&(i, j)| ((i - min_i) as usize, (j - min_j) as usize))
+            .collect();
+        if positions.iter().collect::<BTreeSet<_>>().len() == positions.len() {
+            return positions;
+        }
+        return row;
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder_mwpm::*;
+    use crate::decoder_union_find::*;
+    use crate::reproducible_rand::Xoroshiro128StarStar;
+    use crate::simulator_compact::*;
+    use rand::SeedableRng;
+
+    /// distance-3 repetition code with 3 rounds of circuit-level noise; the ancillas are measured in X basis
+    /// when `x_basis` is true, in which case the ancillas are rotated by Hadamard gates
+    fn repetition_code_circuit(p: f64, x_basis: bool) -> String {
+        let (reset, measure, data_measure, hadamard) = if x_basis {
+            ("RX", "MR", "MX", "H 1 3\nTICK\n")
+        } else {
+            ("R", "MR", "M", "")
+        };
+        let round = format!(
+            "{hadamard}CX {c1}\nDEPOLARIZE2({p}) {c1}\nTICK\nCX {c2}\nDEPOLARIZE2({p}) {c2}\nTICK\n{hadamard}X_ERROR({p}) 1 3\n{measure} 1 3\nDEPOLARIZE1({p}) 0 2 4\n",
+            c1 = if x_basis { "1 0 3 2" } else { "0 1 2 3" },
+            c2 = if x_basis { "1 2 3 4" } else { "2 1 4 3" },
+        );
+        format!(
+            "QUBIT_COORDS(0) 0\nQUBIT_COORDS(1) 1\nQUBIT_COORDS(2) 2\nQUBIT_COORDS(3) 3\nQUBIT_COORDS(4) 4\n\
+            {reset} 0 2 4\nR 1 3\nX_ERROR({p}) 1 3\nTICK\n\
+            {round}DETECTOR(1, 0) rec[-2]\nDETECTOR(3, 0) rec[-1]\n\
+            REPEAT 2 {{\n  TICK\n{round}  SHIFT_COORDS(0, 1)\n  DETECTOR(1, 0) rec[-2] rec[-4]\n  DETECTOR(3, 0) rec[-1] rec[-3]\n}}\n\
+            X_ERROR({p}) 0 2 4\n{data_measure} 0 2 4\n\
+            DETECTOR(1, 1) rec[-2] rec[-3] rec[-5]\nDETECTOR(3, 1) rec[-1] rec[-2] rec[-4]\nOBSERVABLE_INCLUDE({observable}) rec[-1]\n",
+            observable = if x_basis { 1 } else { 0 },
+        )
+    }
+
+    #[test]
+    fn stim_circuit_parse() {
+        // cargo test stim_circuit_parse -- --nocapture
+        let instructions =
+            parse_stim_circuit("R 0 1 # reset\nREPEAT 2 {\n    CX 0 1\n    M(0.01) !1\n}\nDETECTOR(1, 2) rec[-1] rec[-2]")
+                .unwrap();
+        assert_eq!(instructions.len(), 6);
+        assert_eq!(instructions[2].targets, vec![StimTarget::Qubit(1)]);
+        assert_eq!(instructions[4].arguments, vec![0.01]);
+        assert_eq!(instructions[5].targets, vec![StimTarget::Record(1), StimTarget::Record(2)]);
+        assert!(parse_stim_circuit("REPEAT 2 {\nH 0").is_err());
+        assert!(simulator_from_stim_circuit("R 0\nSWAP 0 1").is_err());
+        assert!(simulator_from_stim_circuit("R 0 1\nTICK\nDEPOLARIZE2(0.01) 0 1").is_err());
+        // a detector can mix measurements in both bases
+        assert!(simulator_from_stim_circuit("R 0\nRX 1\nM 0\nMX 1\nDETECTOR rec[-1] rec[-2]").is_ok());
+        // neither qubit controls the second CX in the Z basis of the simulator
+        assert!(simulator_from_stim_circuit("R 0 1\nCX 0 1\nH 0\nCX 0 1").is_err());
+        assert!(simulator_from_stim_circuit("R 0\nMY 0").is_err());
+    }
+
+    /// every single fault of a distance-3 circuit must be corrected by the MWPM decoder
+    fn stim_circuit_single_fault_correction(circuit: &str, expected_detectors: usize) {
+        let (mut simulator, noise_model) = simulator_from_stim_circuit(circuit).unwrap();
+        code_builder_sanity_check(&simulator).unwrap();
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let mut detectors = 0;
+        simulator_iter_real!(simulator, position, node, t => simulator.height - 1, {
+            if node.gate_type.is_measurement() {
+                detectors += 1;
+            }
+        });
+        assert_eq!(detectors, expected_detectors);
+        let noise_model = Arc::new(noise_model);
+        let mut mwpm_decoder =
+            MWPMDecoder::new(&Arc::new(simulator.clone()), Arc::clone(&noise_model), &json!({}), 1, false).unwrap();
+        let mut single_faults = vec![];
+        simulator_iter!(simulator, position, _node, {
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            for error in ErrorType::all_possible_errors() {
+                if noise_model_node.pauli_error_rates.error_rate(&error) > 0. {
+                    single_faults.push(vec![(position.clone(), error)]);
+                }
+            }
+            if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
+                let peer = simulator
+                    .get_node_unwrap(position)
+                    .gate_peer
+                    .as_ref()
+                    .unwrap()
+                    .as_ref()
+                    .clone();
+                for error in CorrelatedPauliErrorType::all_possible_errors() {
+                    if correlated_pauli_error_rates.error_rate(&error) > 0. {
+                        let mut fault = vec![];
+                        if error.my_error() != ErrorType::I {
+                            fault.push((position.clone(), error.my_error()));
+                        }
+                        if error.peer_error() != ErrorType::I {
+                            fault.push((peer.clone(), error.peer_error()));
+                        }
+                        single_faults.push(fault);
+                    }
+                }
+            }
+        });
+        assert!(!single_faults.is_empty());
+        let mut detected_faults = 0;
+        for fault in single_faults.iter() {
+            simulator.clear_all_errors();
+            for (position, error) in fault.iter() {
+                simulator.set_error_check(&noise_model, position, error);
+            }
+            simulator.propagate_errors();
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            if !sparse_measurement.is_empty() {
+                detected_faults += 1;
+            }
+            let (correction, _runtime_statistics) = mwpm_decoder.decode(&sparse_measurement);
+            let (logical_i, logical_j) = simulator.validate_correction(&correction);
+            assert!(!logical_i && !logical_j, "fault {:?} is not corrected", fault);
+        }
+        assert!(detected_faults > 0);
+    }
+
+    #[test]
+    fn stim_circuit_repetition_code() {
+        // cargo test stim_circuit_repetition_code -- --nocapture
+        stim_circuit_single_fault_correction(&repetition_code_circuit(0.001, false), 8);
+    }
+
+    #[test]
+    fn stim_circuit_repetition_code_hadamard() {
+        // cargo test stim_circuit_repetition_code_hadamard -- --nocapture
+        stim_circuit_single_fault_correction(&repetition_code_circuit(0.001, true), 8);
+    }
+
+    #[test]
+    fn stim_circuit_repetition_code_clifford() {
+        // cargo test stim_circuit_repetition_code_clifford -- --nocapture
+        // the first CX layer is written as H CZ H, so that the second CX layer acts on Hadamard-conjugated ancillas, and the
+        // data qubits are conjugated by S in between
+        let circuit = repetition_code_circuit(0.001, false)
+            .replace(
+                "CX 0 1 2 3\nDEPOLARIZE2(0.001) 0 1 2 3\n",
+                "S 0 2 4\nH 1 3\nTICK\nCZ 0 1 2 3\nDEPOLARIZE2(0.001) 0 1 2 3\nTICK\nH 1 3\nTICK\n",
+            )
+            .replace(
+                "DEPOLARIZE2(0.001) 2 1 4 3\n",
+                "DEPOLARIZE2(0.001) 2 1 4 3\nTICK\nS_DAG 0 2 4\n",
+            );
+        assert_eq!(circuit.matches("S_DAG").count(), 2);
+        let (simulator, _noise_model) = simulator_from_stim_circuit(&circuit).unwrap();
+        // the ancillas are measured in X basis because they are conjugated by H in the simulator
+        let mut x_measurements = 0;
+        simulator_iter_real!(simulator, position, node, {
+            if node.gate_type == GateType::MeasureX && node.qubit_type == QubitType::StabX {
+                x_measurements += 1;
+            }
+        });
+        assert!(x_measurements > 0);
+        stim_circuit_single_fault_correction(&circuit, 8);
+    }
+
+    /// the rate of `logical_i` errors of the detector error model of a simulator, decoded by the MWPM decoder on the
+    /// decoding graph of the detector error model, so that the result only depends on the detector error model
+    fn detector_error_model_logical_error_rate(simulator: Simulator, noise_model: NoiseModel, shots: usize) -> f64 {
+        let detector_error_model =
+            SimulatorCompact::from_simulator(simulator, Arc::new(noise_model), 1).to_detector_error_model();
+        let (simulator, noise_model, noise_model_graph) =
+            simulator_from_detector_error_model(&detector_error_model).unwrap();
+        let (noise_model, noise_model_graph) = (Arc::new(noise_model), Arc::new(noise_model_graph));
+        let mut mwpm_decoder = MWPMDecoder::new(&simulator, noise_model_graph, &json!({}), 1, false).unwrap();
+        let mut simulator_compact = SimulatorCompact::from_simulator(simulator, Arc::clone(&noise_model), 1);
+        simulator_compact.set_rng(Xoroshiro128StarStar::seed_from_u64(0));
+        let mut logical_errors = 0;
+        for _ in 0..shots {
+            simulator_compact.generate_random_errors(&noise_model);
+            let (correction, _runtime_statistics) = mwpm_decoder.decode(&simulator_compact.generate_sparse_measurement());
+            let (logical_i, _logical_j) = simulator_compact.validate_correction(&correction);
+            if logical_i {
+                logical_errors += 1;
+            }
+        }
+        logical_errors as f64 / shots as f64
+    }
+
+    #[test]
+    fn stim_circuit_generated_surface_code() {
+        // cargo test stim_circuit_generated_surface_code -- --nocapture
+        let circuit = include_str!("stim_circuit_test/surface_code_rotated_memory_z.stim");
+        // 4 Z stabilizers in the first and the final round, all 8 stabilizers in the other 2 rounds
+        stim_circuit_single_fault_correction(circuit, 24);
+        // the native code has no Hadamard gate, so the gate noise is removed to make both noise models equivalent
+        let p: f64 = 0.005;
+        let circuit = circuit
+            .lines()
+            .filter(|line| !line.trim().starts_with("DEPOLARIZE2") && line.trim() != "DEPOLARIZE1(0.005) 2 11 16 25")
+            .collect::<Vec<&str>>()
+            .join("\n");
+        assert_eq!(circuit.matches("DEPOLARIZE1").count(), 2);
+        let (simulator, noise_model) = simulator_from_stim_circuit(&circuit).unwrap();
+        // only X errors flip the observable: the data qubits are flipped after reset, depolarized at the beginning of each
+        // round and flipped before the final measurement, while each Z stabilizer measurement is flipped either before
+        // the measurement or right after the previous reset
+        let native_simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(3, 3, 3));
+        let mut native_noise_model = NoiseModel::new(&native_simulator);
+        let mut depolarize_node = NoiseModelNode::new();
+        depolarize_node.pauli_error_rates.error_rate_X = p / 3.;
+        depolarize_node.pauli_error_rates.error_rate_Y = p / 3.;
+        depolarize_node.pauli_error_rates.error_rate_Z = p / 3.;
+        let mut data_flip_node = NoiseModelNode::new();
+        data_flip_node.pauli_error_rates.error_rate_X = p;
+        let mut measurement_flip_node = NoiseModelNode::new();
+        measurement_flip_node.pauli_error_rates.error_rate_X = 2. * p * (1. - p);
+        let (depolarize_node, data_flip_node, measurement_flip_node) = (
+            Arc::new(depolarize_node),
+            Arc::new(data_flip_node),
+            Arc::new(measurement_flip_node),
+        );
+        let mc = native_simulator.measurement_cycles;
+        let noisy_height = native_simulator.height - mc;
+        simulator_iter_real!(native_simulator, position, node, {
+            let noise_model_node = if position.t >= noisy_height {
+                None
+            } else if node.qubit_type == QubitType::Data && (position.t == 0 || position.t + 1 == noisy_height) {
+                Some(&data_flip_node)
+            } else if node.qubit_type == QubitType::Data && position.t % mc == 1 {
+                Some(&depolarize_node)
+            } else if node.qubit_type == QubitType::StabZ && position.t % mc == mc - 1 {
+                Some(&measurement_flip_node)
+            } else {
+                None
+            };
+            if let Some(noise_model_node) = noise_model_node {
+                native_noise_model.set_node(position, Some(Arc::clone(noise_model_node)));
+            }
+        });
+        let shots = 50000;
+        let logical_error_rate = detector_error_model_logical_error_rate(simulator, noise_model, shots);
+        let native_logical_error_rate = detector_error_model_logical_error_rate(native_simulator, native_noise_model, shots);
+        println!("logical error rate: imported {logical_error_rate}, native {native_logical_error_rate}");
+        let standard_deviation = ((logical_error_rate + native_logical_error_rate) / shots as f64).sqrt();
+        assert!(native_logical_error_rate > 0.);
+        assert!((logical_error_rate - native_logical_error_rate).abs() < 4. * standard_deviation);
+    }
+
+    #[test]
+    fn stim_circuit_logical_error() {
+        // cargo test stim_circuit_logical_error -- --nocapture
+        let (mut simulator, noise_model) = simulator_from_stim_circuit(&repetition_code_circuit(0.001, false)).unwrap();
+        // flipping every data qubit after the last round is a logical error that no detector can see
+        let mut t = simulator.height - 1;
+        while simulator.get_node_unwrap(&pos!(t, 0, 0)).gate_type != GateType::CXGateControl {
+            t -= 1;
+        }
+        simulator.clear_all_errors();
+        for j in [0, 2, 4] {
+            // the data qubits are idle before their final measurement
+            let mut position = pos!(t, 0, j);
+            while noise_model.get_node_unwrap(&position).pauli_error_rates.error_rate_X == 0. {
+                position.t -= 1;
+            }
+            simulator.set_error_check(&noise_model, &position, &ErrorType::X);
+        }
+        simulator.propagate_errors();
+        assert!(simulator.generate_sparse_measurement().is_empty());
+        assert_eq!(simulator.validate_correction(&SparseCorrection::new()), (true, false));
+    }
+
+    /// Pauli frame simulation of a Stim circuit with a single Pauli error injected right before the `t`-th `TICK`,
+    /// returning the flipped detectors at their (t, i, j) coordinates and the flipped observables
+    fn stim_circuit_frame_simulation(
+        instructions: &[StimInstruction],
+        error: (usize, usize, ErrorType),
+    ) -> (BTreeSet<Position>, [bool; 2]) {
+        let mut x = BTreeMap::<usize, bool>::new();
+        let mut z = BTreeMap::<usize, bool>::new();
+        let mut records: Vec<bool> = vec![];
+        let mut detectors = BTreeSet::new();
+        let mut observables = [false; 2];
+        let mut tick = 0;
+        for instruction in instructions.iter() {
+            let qubits: Vec<usize> = instruction
+                .targets
+                .iter()
+                .filter_map(|target| match target {
+                    StimTarget::Qubit(qubit) => Some(*qubit),
+                    _ => None,
+                })
+                .collect();
+            let flipped = |records: &Vec<bool>| {
+                instruction.targets.iter().fold(false, |flipped, target| match target {
+                    StimTarget::Record(lookback) => flipped ^ records[records.len() - lookback],
+                    _ => flipped,
+                })
+            };
+            let (x, z) = (&mut x, &mut z);
+            let get = |map: &mut BTreeMap<usize, bool>, q: usize| *map.entry(q).or_default();
+            match instruction.name.as_str() {
+                "TICK" => {
+                    let (t, q, pauli) = error;
+                    if tick == t {
+                        *x.entry(q).or_default() ^= matches!(pauli, ErrorType::X | ErrorType::Y);
+                        *z.entry(q).or_default() ^= matches!(pauli, ErrorType::Z | ErrorType::Y);
+                    }
+                    tick += 1;
+                }
+                "R" | "RX" => {
+                    for &q in qubits.iter() {
+                        x.insert(q, false);
+                        z.insert(q, false);
+                    }
+                }
+                "M" => records.extend(qubits.iter().map(|&q| get(x, q))),
+                "MX" => records.extend(qubits.iter().map(|&q| get(z, q))),
+                "HERALDED_ERASE" => records.extend(qubits.iter().map(|_| false)),
+                "CX" | "CY" | "CZ" => {
+                    for pair in qubits.chunks(2) {
+                        let (xc, zc, xt, zt) = (get(x, pair[0]), get(z, pair[0]), get(x, pair[1]), get(z, pair[1]));
+                        let (xc, zc, xt, zt) = match instruction.name.as_str() {
+                            "CX" => (xc, zc ^ zt, xt ^ xc, zt),
+                            "CY" => (xc, zc ^ xt ^ zt, xt ^ xc, zt ^ xc),
+                            _ => (xc, zc ^ xt, xt, zt ^ xc),
+                        };
+                        x.insert(pair[0], xc);
+                        z.insert(pair[0], zc);
+                        x.insert(pair[1], xt);
+                        z.insert(pair[1], zt);
+                    }
+                }
+                "MPP" => {
+                    // products are separated by Pauli targets without a combiner in between
+                    let mut is_combined = false;
+                    for target in instruction.targets.iter() {
+                        if let StimTarget::Pauli(pauli, q) = *target {
+                            let anticommute = (matches!(pauli, ErrorType::X | ErrorType::Y) && get(z, q))
+                                ^ (matches!(pauli, ErrorType::Z | ErrorType::Y) && get(x, q));
+                            if is_combined {
+                                *records.last_mut().unwrap() ^= anticommute;
+                            } else {
+                                records.push(anticommute);
+                            }
+                            is_combined = false;
+                        } else {
+                            is_combined = true;
+                        }
+                    }
+                }
+                "DETECTOR" => {
+                    if flipped(&records) {
+                        let coordinates = &instruction.arguments;
+                        detectors.insert(pos!(
+                            coordinates[2] as usize,
+                            coordinates[1] as usize,
+                            coordinates[0] as usize
+                        ));
+                    }
+                }
+                "OBSERVABLE_INCLUDE" => {
+                    observables[instruction.arguments[0] as usize] ^= flipped(&records);
+                }
+                _ => {}
+            }
+        }
+        (detectors, observables)
+    }
+
+    /// every single Pauli error must flip the same detectors and observables in the simulator and the exported circuit
+    fn stim_circuit_export_equivalence(code_type: CodeType, code_size: CodeSize) {
+        let mut simulator = Simulator::new(code_type, code_size);
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.001, 0.001, 0.001, 0.001);
+        simulator.compress_error_rates(&mut noise_model);
+        let circuit = simulator_to_stim_circuit(&simulator, &noise_model).unwrap();
+        let instructions = parse_stim_circuit(&circuit).unwrap();
+        let qubit_index: BTreeMap<(usize, usize), usize> = instructions
+            .iter()
+            .filter(|instruction| instruction.name == "QUBIT_COORDS")
+            .map(|instruction| {
+                let StimTarget::Qubit(qubit) = instruction.targets[0] else {
+                    unreachable!()
+                };
+                ((instruction.arguments[1] as usize, instruction.arguments[0] as usize), qubit)
+            })
+            .collect();
+        let detector_num = instructions
+            .iter()
+            .filter(|instruction| instruction.name == "DETECTOR")
+            .count();
+        let observable_num = instructions
+            .iter()
+            .filter(|instruction| instruction.name == "OBSERVABLE_INCLUDE")
+            .count();
+        assert!(detector_num > 0);
+        assert_eq!(observable_num, 2);
+        simulator_iter_real!(simulator, position, node, {
+            // errors right after initialization are discarded, and errors at the final layer never propagate
+            if node.gate_type.is_initialization() || position.t == simulator.height - 1 {
+                continue;
+            }
+            for error in [ErrorType::X, ErrorType::Z] {
+                let qubit = qubit_index[&(position.i, position.j)];
+                let (detectors, observables) = stim_circuit_frame_simulation(&instructions, (position.t, qubit, error));
+                let mut simulator = simulator.clone();
+                simulator.clear_all_errors();
+                simulator.get_node_mut_unwrap(position).error = error;
+                simulator.propagate_errors();
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                let expected_detectors: BTreeSet<Position> = sparse_measurement.iter().cloned().collect();
+                assert_eq!(detectors, expected_detectors, "{:?} at {}", error, position);
+                let (logical_i, logical_j) = simulator.validate_correction(&SparseCorrection::new());
+                assert_eq!(observables, [logical_i, logical_j], "{:?} at {}", error, position);
+            }
+        });
+    }
+
+    #[test]
+    fn stim_circuit_export() {
+        // cargo test stim_circuit_export -- --nocapture
+        stim_circuit_export_equivalence(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        stim_circuit_export_equivalence(CodeType::RotatedPlanarCode, CodeSize::new(1, 3, 3));
+        stim_circuit_export_equivalence(CodeType::StandardXZZXCode, CodeSize::new(1, 3, 3));
+        stim_circuit_export_equivalence(CodeType::ColorCode666, CodeSize::new(1, 3, 3));
+    }
+
+    /// the sorted `error(...)` lines of a detector error model
+    fn detector_error_model_error_lines(detector_error_model: &str) -> Vec<String> {
+        let mut lines: Vec<String> = detector_error_model
+            .lines()
+            .filter(|line| line.starts_with("error("))
+            .map(|line| line.to_string())
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn stim_circuit_detector_error_model_parse() {
+        // cargo test stim_circuit_detector_error_model_parse -- --nocapture
+        // a repetition code with 2 detectors per round, where the last error is decomposed into 3 components
+        let detector_error_model = "detector(0, 0, 0) D0\ndetector(2, 0, 0) D1\nrepeat 2 {\n    error(0.01) D0 D2\n\
+            error(0.01) D1 D3\n    error(0.01) D2 L0\n    error(0.01) D2 D3\n    error(0.01) D3\n\
+            shift_detectors(0, 0, 1) 2\n    detector(0, 0, 0) D0\n    detector(2, 0, 0) D1\n}\n\
+            error(0.001) D0 ^ D1 ^ D0 L0\nlogical_observable L0\n";
+        let (simulator, noise_model, noise_model_graph) = simulator_from_detector_error_model(detector_error_model).unwrap();
+        code_builder_sanity_check(&simulator).unwrap();
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        noise_model_sanity_check(&simulator, &noise_model_graph).unwrap();
+        // detectors follow their coordinates: 2 tracks measured in 3 rounds
+        let mut detectors = vec![];
+        for t in (simulator.measurement_cycles..simulator.height).step_by(simulator.measurement_cycles) {
+            simulator_iter_real!(simulator, position, node, t => t, {
+                if node.gate_type.is_measurement() {
+                    detectors.push(position.clone());
+                }
+            });
+        }
+        let mc = simulator.measurement_cycles;
+        assert_eq!(
+            detectors,
+            vec![
+                pos!(mc, 0, 0),
+                pos!(mc, 0, 2),
+                pos!(2 * mc, 0, 0),
+                pos!(2 * mc, 0, 2),
+                pos!(3 * mc, 0, 0),
+                pos!(3 * mc, 0, 2)
+            ]
+        );
+        // the decomposed error is sampled as a whole
+        let simulator_compact = SimulatorCompact::from_detector_error_model(detector_error_model, 1).unwrap();
+        let mut expected_lines = vec!["error(0.001) D5 L0".to_string()];
+        for offset in [0, 2] {
+            for targets in ["D0 D2", "D1 D3", "D2 L0", "D2 D3", "D3"] {
+                let targets: Vec<String> = targets
+                    .split(' ')
+                    .map(|target| match target.strip_prefix('D') {
+                        Some(index) => format!("D{}", index.parse::<usize>().unwrap() + offset),
+                        None => target.to_string(),
+                    })
+                    .collect();
+                expected_lines.push(format!("error(0.01) {}", targets.join(" ")));
+            }
+        }
+        expected_lines.sort();
+        assert_eq!(
+            detector_error_model_error_lines(&simulator_compact.to_detector_error_model()),
+            expected_lines
+        );
+        // while the decoding graph is built from its components
+        let simulator_compact_graph = SimulatorCompact::from_simulator(simulator, Arc::new(noise_model_graph), 1);
+        let graph_lines = detector_error_model_error_lines(&simulator_compact_graph.to_detector_error_model());
+        assert_eq!(graph_lines.len(), 13);
+        for line in ["error(0.001) D4", "error(0.001) D5", "error(0.001) D4 L0"] {
+            assert!(graph_lines.contains(&line.to_string()), "{line} not found in {graph_lines:?}");
+        }
+        assert!(simulator_from_detector_error_model("error(0.1) rec[-1]").is_err());
+        assert!(simulator_from_detector_error_model("error(0.1) D0 L2").is_err());
+        // detectors without coordinates are measured in a single round
+        let (simulator, _, _) = simulator_from_detector_error_model("error(0.1) D0 D1 ^ D2 ^ D3 L0\nerror(0.1) D3").unwrap();
+        assert_eq!(simulator.height, 2 * simulator.measurement_cycles + 1);
+    }
+
+    /// the detector error model of a rotated surface code with circuit-level noise
+    fn rotated_planar_code_detector_error_model(noisy_measurements: usize) -> String {
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.001, 0., 0.001, 0.);
+        SimulatorCompact::from_simulator(simulator, Arc::new(noise_model), 1).to_detector_error_model()
+    }
+
+    #[test]
+    fn stim_circuit_detector_error_model_round_trip() {
+        // cargo test stim_circuit_detector_error_model_round_trip -- --nocapture
+        for noisy_measurements in [0, 3] {
+            let detector_error_model = rotated_planar_code_detector_error_model(noisy_measurements);
+            let simulator_compact = SimulatorCompact::from_detector_error_model(&detector_error_model, 1).unwrap();
+            assert_eq!(
+                detector_error_model_error_lines(&detector_error_model),
+                detector_error_model_error_lines(&simulator_compact.to_detector_error_model())
+            );
+        }
+    }
+
+    /// every graph-like error mechanism of the detector error model must be corrected by the decoder built from its
+    /// decoding graph
+    fn stim_circuit_detector_error_model_decode(
+        decode: &mut dyn FnMut(&SparseMeasurement) -> SparseCorrection,
+        simulator: &Simulator,
+        noise_model: &NoiseModel,
+    ) {
+        let mut simulator = simulator.clone();
+        let mut single_faults = vec![];
+        simulator_iter!(simulator, position, _node, {
+            if noise_model.get_node_unwrap(position).pauli_error_rates.error_rate_X > 0. {
+                single_faults.push(position.clone());
+            }
+        });
+        assert!(!single_faults.is_empty());
+        for position in single_faults.iter() {
+            simulator.clear_all_errors();
+            simulator.set_error_check(noise_model, position, &ErrorType::X);
+            simulator.propagate_errors();
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            if sparse_measurement.len() > 2 {
+                continue; // hyperedges are not in the decoding graph
+            }
+            let correction = decode(&sparse_measurement);
+            let (logical_i, logical_j) = simulator.validate_correction(&correction);
+            assert!(!logical_i && !logical_j, "error at {} is not corrected", position);
+        }
+    }
+
+    #[test]
+    fn stim_circuit_detector_error_model_decoders() {
+        // cargo test stim_circuit_detector_error_model_decoders -- --nocapture
+        // a distance-3 repetition code whose last error is decomposed into two graph-like components
+        let repetition_code_detector_error_model = "error(0.01) D0 L0\nerror(0.01) D0 D1\nerror(0.01) D1\n\
+            error(0.001) D0 L0 ^ D1\ndetector(1, 0) D0\ndetector(3, 0) D1\n";
+        for detector_error_model in [
+            rotated_planar_code_detector_error_model(0).as_str(),
+            rotated_planar_code_detector_error_model(2).as_str(),
+            repetition_code_detector_error_model,
+        ] {
+            let (simulator, _noise_model, noise_model_graph) =
+                simulator_from_detector_error_model(detector_error_model).unwrap();
+            let noise_model_graph = Arc::new(noise_model_graph);
+            let mut union_find_decoder =
+                UnionFindDecoder::new(&simulator, Arc::clone(&noise_model_graph), &json!({}), 1, false);
+            stim_circuit_detector_error_model_decode(
+                &mut |sparse_measurement| union_find_decoder.decode(sparse_measurement).0,
+                &simulator,
+                &noise_model_graph,
+            );
+            #[cfg(feature = "fusion_blossom")]
+            {
+                let mut fusion_decoder = crate::decoder_fusion::FusionDecoder::new(
+                    &simulator,
+                    Arc::clone(&noise_model_graph),
+                    &json!({}),
+                    1,
+                    false,
+                );
+                stim_circuit_detector_error_model_decode(
+                    &mut |sparse_measurement| fusion_decoder.decode(sparse_measurement).0,
+                    &simulator,
+                    &noise_model_graph,
+                );
+            }
+        }
+    }
+}
@@ -3,6 +3,8 @@
 use crate::cli::*;
 use crate::code_builder::*;
 use crate::complete_model_graph::*;
+use crate::decoder_bp_osd::*;
+use crate::decoder_distributed_union_find::*;
 #[cfg(feature = "fusion_blossom")]
 use crate::decoder_fusion::*;
 #[cfg(feature = "fusion_blossom")]
@@ -11,18 +13,25 @@ use crate::decoder_parallel_fusion::*;
 use crate::decoder_hyper_union_find::*;
 #[cfg(feature = "hyperion")]
 use crate::decoder_hyperion::*;
+use crate::decoder_lookup_table::*;
+use crate::decoder_maximum_likelihood::*;
 use crate::decoder_mwpm::*;
+use crate::decoder_sliding_window::*;
 use crate::decoder_tailored_mwpm::*;
 use crate::decoder_union_find::*;
 use crate::erasure_graph::*;
+use crate::fault_distance::*;
 use crate::model_graph::*;
 use crate::model_hypergraph::*;
 use crate::noise_model::*;
 use crate::noise_model_builder::*;
 use crate::reproducible_rand::Xoroshiro128StarStar;
 use crate::simulator::*;
+use crate::simulator_batch::*;
 use crate::simulator_compact::*;
 use crate::simulator_file::*;
+use crate::soft_output::*;
+use crate::stim_circuit::*;
 use crate::tailored_complete_model_graph::*;
 use crate::tailored_model_graph::*;
 use crate::util::local_get_temporary_store;
@@ -48,6 +57,7 @@ impl ToolCommands {
     pub fn run(self) -> Result<String, String> {
         match self {
             Self::Benchmark(benchmark_parameters) => benchmark_parameters.run(),
+            Self::FaultDistance(fault_distance_parameters) => fault_distance_parameters.run(),
         }
     }
 }
@@ -76,6 +86,10 @@ pub enum BenchmarkDebugPrint {
     ErasureGraph,
     /// syndrome file for fusion-blossom library to use, output to `output_filename`
     FusionBlossomSyndromeFile,
+    /// equivalent Stim circuit with detectors and observables, useful to cross-check logical error rates with Stim
+    StimCircuit,
+    /// Stim detector error model of the compact simulator, useful to feed the same noise model to other decoders
+    DetectorErrorModel,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -118,6 +132,26 @@ pub enum BenchmarkDecoder {
     Hyperion,
     /// parallel fusion blossom
     ParallelFusion,
+    /// belief propagation + ordered statistics decoder on hypergraph
+    BPOSD,
+    /// distributed union-find decoder with fast channels, reporting the simulated clock cycles
+    DistributedUnionFind,
+    /// exact maximum-likelihood decoder by tensor network contraction, only for small codes
+    MaximumLikelihood,
+    /// lookup table of the syndromes of the most likely error patterns, falling back to another decoder on table miss
+    LookupTable,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "python_binding", cfg_eval)]
+#[cfg_attr(feature = "python_binding", pyclass)]
+pub enum BenchmarkSimulator {
+    /// the full simulator that propagates the errors of each shot through the circuit
+    Full,
+    /// compact simulator that samples the error sources and their precomputed defects
+    Compact,
+    /// bit-packed batch simulator that samples 64 shots at once
+    Batch,
 }
 
 /// progress variable shared between threads to update information
@@ -128,6 +162,20 @@ pub struct BenchmarkControl {
     pub total_repeats: usize,
     pub qec_failed: usize,
     pub external_termination: bool,
+    /// the shots discarded by post-selection, which are still counted in `total_repeats`
+    pub discarded: usize,
+    /// the failed shots among the discarded ones, which are still counted in `qec_failed`
+    pub discarded_qec_failed: usize,
+    /// the distribution of the complementary gaps and the failed shots, only recorded when `soft_output_abort_rates` is given
+    pub complementary_gaps: ComplementaryGapHistogram,
+    /// the shots with at least one cosmic-ray event
+    pub cosmic_ray_repeats: usize,
+    /// the failed shots among the ones with cosmic-ray events
+    pub cosmic_ray_qec_failed: usize,
+    /// the shots decoded from the table of the lookup-table decoder
+    pub lookup_table_hits: usize,
+    /// the shots decoded by the fallback decoder of the lookup-table decoder
+    pub lookup_table_misses: usize,
 }
 
 impl BenchmarkControl {
@@ -136,23 +184,103 @@ impl BenchmarkControl {
             total_repeats: 0,
             qec_failed: 0,
             external_termination: false,
+            discarded: 0,
+            discarded_qec_failed: 0,
+            complementary_gaps: ComplementaryGapHistogram::new(),
+            cosmic_ray_repeats: 0,
+            cosmic_ray_qec_failed: 0,
+            lookup_table_hits: 0,
+            lookup_table_misses: 0,
         }
     }
-    fn update_data_should_terminate(&mut self, is_qec_failed: bool, max_repeats: usize, min_failed_cases: usize) -> bool {
+    #[allow(clippy::too_many_arguments)]
+    fn update_data_should_terminate(
+        &mut self,
+        is_qec_failed: bool,
+        is_discarded: bool,
+        complementary_gap: Option<f64>,
+        has_cosmic_ray: bool,
+        lookup_table_hit: Option<bool>,
+        max_repeats: usize,
+        min_failed_cases: usize,
+    ) -> bool {
         self.total_repeats += 1;
         if is_qec_failed {
             self.qec_failed += 1;
         }
+        if has_cosmic_ray {
+            self.cosmic_ray_repeats += 1;
+            if is_qec_failed {
+                self.cosmic_ray_qec_failed += 1;
+            }
+        }
+        match lookup_table_hit {
+            Some(true) => self.lookup_table_hits += 1,
+            Some(false) => self.lookup_table_misses += 1,
+            None => {}
+        }
+        if is_discarded {
+            self.discarded += 1;
+            if is_qec_failed {
+                self.discarded_qec_failed += 1;
+            }
+        }
+        if let Some(complementary_gap) = complementary_gap {
+            self.complementary_gaps.record(complementary_gap, is_qec_failed);
+        }
         self.should_terminate(max_repeats, min_failed_cases)
     }
+    /// the logical error rate of the accepted shots after aborting the given fractions of shots with the smallest complementary gap
+    fn abort_logical_error_rates(&self, abort_rates: &[f64]) -> Vec<f64> {
+        abort_rates
+            .iter()
+            .map(|&abort_rate| self.complementary_gaps.abort_logical_error_rate(abort_rate))
+            .collect()
+    }
+    /// the failed shots that are accepted by post-selection, which is all the failed shots if post-selection is disabled
+    fn accepted_qec_failed(&self) -> usize {
+        self.qec_failed - self.discarded_qec_failed
+    }
+    /// `max_repeats` limits all the simulated shots while `min_failed_cases` only counts the accepted failed shots
     fn should_terminate(&self, max_repeats: usize, min_failed_cases: usize) -> bool {
-        self.external_termination || self.total_repeats >= max_repeats || self.qec_failed >= min_failed_cases
+        self.external_termination || self.total_repeats >= max_repeats || self.accepted_qec_failed() >= min_failed_cases
     }
     fn set_external_terminate(&mut self) {
         self.external_termination = true;
     }
 }
 
+/// the rules of post-selection in the benchmark, where a shot is discarded if any of the rules is violated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PostSelection {
+    /// discard the shot if the complementary gap is smaller than this value, requiring a decoder with `soft_output` enabled;
+    /// see [`SoftOutput::complementary_gap`]
+    #[serde(alias = "mcg")] // abbreviation
+    #[serde(default)]
+    pub min_complementary_gap: Option<f64>,
+    /// discard the shot if more than this number of erasures are detected, including heralded leakage
+    #[serde(alias = "mde")] // abbreviation
+    #[serde(default)]
+    pub max_detected_erasures: Option<usize>,
+}
+
+impl PostSelection {
+    pub fn should_discard(&self, complementary_gap: Option<f64>, detected_erasures: usize) -> bool {
+        if let Some(min_complementary_gap) = self.min_complementary_gap {
+            if complementary_gap.expect("complementary gap required") < min_complementary_gap {
+                return true;
+            }
+        }
+        if let Some(max_detected_erasures) = self.max_detected_erasures {
+            if detected_erasures > max_detected_erasures {
+                return true;
+            }
+        }
+        false
+    }
+}
+
 /// decoder might suffer from rare deadlock, and this controller will record the necessary information for debugging with low runtime overhead
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BenchmarkThreadDebugger {
@@ -239,6 +367,12 @@ pub struct SimulationConfigs {
     parallel_init: usize,
     noise_model_modifier: Option<serde_json::Value>,
     deterministic_seed: Option<u64>,
+    /// the content of `load_stim_circuit_from_file`, not logged because the file name is already in the parameters
+    #[serde(skip)]
+    stim_circuit: Option<String>,
+    /// the content of `load_detector_error_model_from_file`, not logged for the same reason
+    #[serde(skip)]
+    detector_error_model: Option<String>,
 }
 
 impl BenchmarkParameters {
@@ -264,7 +398,22 @@ impl BenchmarkParameters {
         }
         // first list all configurations and validate them at the beginning
         let mut output = "".to_string();
-        let titles = "format: <p> <di> <nm> <shots> <failed> <pL> <dj> <pL_dev> <pe>".to_string();
+        let mut titles = "format: <p> <di> <nm> <shots> <failed> <pL> <dj> <pL_dev> <pe>".to_string();
+        if self.sliding_window_commit_rounds.is_some() {
+            titles += " <pL_round>";
+        }
+        if self.post_select.is_some() {
+            titles += " <discarded> <accept_rate> <accept_rate_dev> <pL_accepted> <pL_accepted_dev>";
+        }
+        for abort_rate in self.soft_output_abort_rates.iter().flatten() {
+            titles += &format!(" <pL_abort_{abort_rate}>");
+        }
+        if self.noise_model_configuration.get("cosmic_ray").is_some() {
+            titles += " <cosmic_ray_shots> <pL_cosmic_ray> <pL_cosmic_ray_dev> <pL_no_cosmic_ray> <pL_no_cosmic_ray_dev>";
+        }
+        if self.decoder == BenchmarkDecoder::LookupTable && self.sliding_window_commit_rounds.is_none() {
+            titles += " <lookup_table_hits> <lookup_table_misses>";
+        }
         eprintln!("{}", titles); // compatible with old scripts
         if self.debug_print.is_none() {
             // debug print only, outputs user specified debug info
@@ -352,6 +501,36 @@ impl BenchmarkParameters {
             },
             None => None,
         };
+        if self.load_stim_circuit_from_file.is_some() && self.load_detector_error_model_from_file.is_some() {
+            return Err("[error] cannot load both stim circuit and detector error model".to_string());
+        }
+        let stim_circuit = match &self.load_stim_circuit_from_file {
+            Some(stim_circuit_filepath) => match fs::read_to_string(stim_circuit_filepath) {
+                Ok(value) => Some(value),
+                Err(_) => return Err(format!("[error] stim circuit file cannot open: {}", stim_circuit_filepath)),
+            },
+            None => None,
+        };
+        let detector_error_model = match &self.load_detector_error_model_from_file {
+            Some(detector_error_model_filepath) => match fs::read_to_string(detector_error_model_filepath) {
+                Ok(value) => {
+                    simulator_from_detector_error_model(&value)?; // report errors early
+                    Some(value)
+                }
+                Err(_) => {
+                    return Err(format!(
+                        "[error] detector error model file cannot open: {}",
+                        detector_error_model_filepath
+                    ))
+                }
+            },
+            None => None,
+        };
+        if (stim_circuit.is_some() || detector_error_model.is_some())
+            && self.simulator_compact_extender_noisy_measurements.is_some()
+        {
+            return Err("[error] simulator extender doesn't support stim circuit".to_string());
+        }
         Ok(SimulationConfigs {
             dis,
             djs,
@@ -366,6 +545,8 @@ impl BenchmarkParameters {
             parallel_init,
             noise_model_modifier,
             deterministic_seed: self.deterministic_seed,
+            stim_circuit,
+            detector_error_model,
         })
     }
 
@@ -404,6 +585,34 @@ impl BenchmarkParameters {
         configurations
     }
 
+    /// build the simulator either from `code_type` or from the Stim circuit
+    pub fn construct_simulator(
+        &self,
+        configs: &SimulationConfigs,
+        config: &SingleSimulationConfig,
+    ) -> Result<Simulator, String> {
+        if let Some(detector_error_model) = &configs.detector_error_model {
+            return Ok(simulator_from_detector_error_model(detector_error_model)?.0);
+        }
+        match &configs.stim_circuit {
+            Some(stim_circuit) => Ok(simulator_from_stim_circuit(stim_circuit)?.0),
+            None => Ok(Simulator::new(
+                self.code_type,
+                CodeSize::new(config.noisy_measurements, config.di, config.dj),
+            )),
+        }
+    }
+
+    /// the simulator that generates syndromes; a detector error model is sampled from its error mechanisms by default,
+    /// because its simulator is much larger than a code's
+    pub fn simulator_type(&self, configs: &SimulationConfigs) -> BenchmarkSimulator {
+        match self.simulator {
+            Some(simulator_type) => simulator_type,
+            None if self.use_compact_simulator || configs.detector_error_model.is_some() => BenchmarkSimulator::Compact,
+            None => BenchmarkSimulator::Full,
+        }
+    }
+
     pub fn construct_noise_model(
         &self,
         simulator: &mut Simulator,
@@ -411,13 +620,29 @@ impl BenchmarkParameters {
         config: &SingleSimulationConfig,
         use_p_graph: bool,
     ) -> Result<Arc<NoiseModel>, String> {
-        let mut noise_model: NoiseModel = NoiseModel::new(simulator);
         let p = if use_p_graph { config.p_graph } else { config.p };
         let pe = if use_p_graph { config.pe_graph } else { config.pe };
-        let px = p / (1. + self.bias_eta) / 2.;
-        let py = px;
-        let pz = p - 2. * px;
-        simulator.set_error_rates(&mut noise_model, px, py, pz, pe);
+        let mut noise_model: NoiseModel = match (&configs.stim_circuit, &configs.detector_error_model) {
+            // the Stim circuit fully specifies its own noise, `p` and `pe` are only used by the noise model builder
+            (Some(stim_circuit), _) => simulator_from_stim_circuit(stim_circuit)?.1,
+            // a decomposed detector error model is decoded by its components
+            (_, Some(detector_error_model)) => {
+                let (_, noise_model, noise_model_graph) = simulator_from_detector_error_model(detector_error_model)?;
+                if use_p_graph {
+                    noise_model_graph
+                } else {
+                    noise_model
+                }
+            }
+            (None, None) => {
+                let mut noise_model = NoiseModel::new(simulator);
+                let px = p / (1. + self.bias_eta) / 2.;
+                let py = px;
+                let pz = p - 2. * px;
+                simulator.set_error_rates(&mut noise_model, px, py, pz, pe);
+                noise_model
+            }
+        };
         // apply customized noise model
         if let Some(noise_model_builder) = &self.noise_model_builder {
             noise_model_builder.apply(
@@ -427,7 +652,16 @@ impl BenchmarkParameters {
                 p,
                 self.bias_eta,
                 pe,
-            );
+            )?;
+        } else {
+            noise_model.cosmic_ray = CosmicRayNoise::from_noise_model_configuration(&self.noise_model_configuration)?;
+            if let Some(idle_noise) = IdleNoise::from_noise_model_configuration(&self.noise_model_configuration)? {
+                idle_noise.apply(simulator, &mut noise_model);
+            }
+        }
+        if use_p_graph {
+            // cosmic-ray events are unknown to the decoder
+            noise_model.cosmic_ray = None;
         }
         // apply noise model modifier
         match &configs.noise_model_modifier {
@@ -556,6 +790,17 @@ impl BenchmarkParameters {
                     serde_json::to_string(&complete_tailored_model_graph.to_json(simulator)).unwrap()
                 )));
             }
+            Some(BenchmarkDebugPrint::StimCircuit) => {
+                return Ok(Some(simulator_to_stim_circuit(simulator, noise_model)?));
+            }
+            Some(BenchmarkDebugPrint::DetectorErrorModel) => {
+                if noise_model.has_leakage() {
+                    return Err("a detector error model cannot describe leakage".to_string());
+                }
+                let simulator_compact =
+                    SimulatorCompact::from_simulator(simulator.clone(), noise_model.clone(), configs.parallel_init);
+                return Ok(Some(simulator_compact.to_detector_error_model()));
+            }
             Some(BenchmarkDebugPrint::ErasureGraph) => {
                 let mut erasure_graph = ErasureGraph::new(simulator);
                 erasure_graph.build(simulator, noise_model.clone(), configs.parallel_init);
@@ -643,13 +888,32 @@ impl BenchmarkParameters {
         log_runtime_statistics_file: &Option<Arc<Mutex<File>>>,
     ) -> Result<String, String> {
         // first use p_graph and pe_graph to build decoder graph, then go back to real noise model for simulation; a mismatch between decoding graph and real noise model is realistic
-        let mut simulator = Simulator::new(self.code_type, CodeSize::new(config.noisy_measurements, config.di, config.dj));
+        let mut simulator = self.construct_simulator(configs, config)?;
         let noise_model_graph = self.construct_noise_model(&mut simulator, configs, config, true)?;
         if let Some(terminate_message) = self.execute_debug_print(configs, &mut simulator, &noise_model_graph)? {
             return Ok(terminate_message); // debug print terminates
         }
         // build decoder instances
         let general_decoder = GeneralDecoder::from_parameters(self, configs, config, &simulator, &noise_model_graph)?;
+        let post_selection = match self.post_select.as_ref() {
+            Some(post_select) => {
+                let post_selection: PostSelection =
+                    serde_json::from_value(post_select.clone()).map_err(|x| x.to_string())?;
+                if post_selection.min_complementary_gap.is_some() && !general_decoder.supports_soft_output() {
+                    return Err("`min_complementary_gap` requires a decoder with `soft_output` enabled".to_string());
+                }
+                Some(post_selection)
+            }
+            None => None,
+        };
+        if let Some(abort_rates) = self.soft_output_abort_rates.as_ref() {
+            if !general_decoder.supports_soft_output() {
+                return Err("`soft_output_abort_rates` requires a decoder with `soft_output` enabled".to_string());
+            }
+            if abort_rates.iter().any(|abort_rate| !(0. ..1.).contains(abort_rate)) {
+                return Err(format!("abort rates must be in [0, 1), found {abort_rates:?}"));
+            }
+        }
         // prepare fusion blossom exporter
         cfg_if::cfg_if! { if #[cfg(feature="fusion_blossom")] {
             let mut fusion_blossom_syndrome_exporter = None;
@@ -664,18 +928,30 @@ impl BenchmarkParameters {
         } }
         // then prepare the real noise model
         let noise_model = self.construct_noise_model(&mut simulator, configs, config, false)?;
+        let simulator_type = self.simulator_type(configs);
+        if simulator_type != BenchmarkSimulator::Compact
+            && (self.simulator_compact_extender_noisy_measurements.is_some()
+                || self.use_compact_simulator_compressed
+                || self.importance_sampling_max_weight.is_some())
+        {
+            return Err("extender, compressed and importance sampling options require the compact simulator".to_string());
+        }
+        let use_compact_simulator = simulator_type == BenchmarkSimulator::Compact;
+        let use_batch_simulator = simulator_type == BenchmarkSimulator::Batch;
+        if noise_model.cosmic_ray.is_some()
+            && (use_compact_simulator || use_batch_simulator || self.importance_sampling_max_weight.is_some())
+        {
+            return Err("cosmic-ray events require the full simulator without importance sampling".to_string());
+        }
+        if noise_model.has_leakage()
+            && (use_compact_simulator || use_batch_simulator || self.importance_sampling_max_weight.is_some())
+        {
+            return Err("leakage requires the full simulator without importance sampling".to_string());
+        }
         // prepare visualizer
         let visualizer = self.prepare_visualizer(&mut simulator, &noise_model, &noise_model_graph, configs)?;
-        // prepare result variables for simulation
-        let benchmark_control = Arc::new(Mutex::new(BenchmarkControl::new()));
-        // setup progress bar
-        let mut pb = ProgressBar::on(std::io::stderr(), configs.max_repeats as u64);
-        pb.set(0);
-        // spawn threads to do simulation
-        let mut handlers = Vec::new();
-        let mut threads_debugger: Vec<Arc<Mutex<BenchmarkThreadDebugger>>> = Vec::new();
-        let mut threads_ended = Vec::new(); // keep updating progress bar until all threads ends
-        let general_simulator: GeneralSimulator = if self.use_compact_simulator {
+        // prepare simulator
+        let general_simulator: GeneralSimulator = if use_compact_simulator {
             let first = SimulatorCompact::from_simulator(simulator, noise_model.clone(), configs.parallel_init);
             if let Some(simulator_compact_extender_noisy_measurements) = self.simulator_compact_extender_noisy_measurements {
                 self.assert_single_configuration(configs)?;
@@ -707,32 +983,104 @@ impl BenchmarkParameters {
             let sparse_error_pattern: SparseErrorPattern = serde_json::from_value(error_pattern.clone()).unwrap();
             let simulator_vec = SimulatorVec::from_simulator(simulator, vec![sparse_error_pattern]);
             GeneralSimulator::SimulatorVec(simulator_vec)
+        } else if use_batch_simulator {
+            GeneralSimulator::SimulatorBatch(SimulatorBatch::from_simulator(simulator))
         } else {
             GeneralSimulator::Simulator(simulator)
         };
+        let benchmark_control = Arc::new(Mutex::new(BenchmarkControl::new()));
+        let worker = SimulationWorker {
+            benchmark_control: benchmark_control.clone(),
+            general_simulator,
+            noise_model: noise_model.clone(),
+            log_runtime_statistics_file: log_runtime_statistics_file.clone(),
+            visualizer: visualizer.clone(),
+            general_decoder: general_decoder.clone(),
+            #[cfg(feature = "fusion_blossom")]
+            fusion_blossom_syndrome_exporter: fusion_blossom_syndrome_exporter.clone(),
+            thread_debugger: Arc::new(Mutex::new(BenchmarkThreadDebugger::new())),
+            thread_ended: Arc::new(AtomicBool::new(false)),
+            parameters: self.clone(),
+            post_selection,
+        };
+        if let Some(importance_sampling_max_weight) = self.importance_sampling_max_weight {
+            return self.run_importance_sampling(configs, config, worker, importance_sampling_max_weight);
+        }
+        let progress_information = || -> String {
+            let benchmark_control = benchmark_control.lock().unwrap();
+            let total_repeats = benchmark_control.total_repeats;
+            let qec_failed = benchmark_control.qec_failed;
+            let discarded = benchmark_control.discarded;
+            let accepted_qec_failed = benchmark_control.accepted_qec_failed();
+            let abort_logical_error_rates = self
+                .soft_output_abort_rates
+                .as_ref()
+                .map(|abort_rates| benchmark_control.abort_logical_error_rates(abort_rates));
+            let cosmic_ray_repeats = benchmark_control.cosmic_ray_repeats;
+            let cosmic_ray_qec_failed = benchmark_control.cosmic_ray_qec_failed;
+            let lookup_table_hits = benchmark_control.lookup_table_hits;
+            let lookup_table_misses = benchmark_control.lookup_table_misses;
+            drop(benchmark_control);
+            // compute simulation results
+            let error_rate = qec_failed as f64 / total_repeats as f64;
+            let confidence_interval_95_percent =
+                1.96 * (error_rate * (1. - error_rate) / (total_repeats as f64)).sqrt() / error_rate;
+            let mut information =
+                format_simulation_result(config, total_repeats, qec_failed, error_rate, confidence_interval_95_percent);
+            if let GeneralDecoder::SlidingWindow(sliding_window_decoder) = &general_decoder {
+                information += &format!(
+                    "{} ",
+                    logical_error_rate_per_round(error_rate, sliding_window_decoder.noisy_measurements)
+                );
+            }
+            if self.post_select.is_some() {
+                information += &format_post_selection_result(total_repeats, discarded, accepted_qec_failed);
+            }
+            for abort_logical_error_rate in abort_logical_error_rates.iter().flatten() {
+                information += &format!("{} ", abort_logical_error_rate);
+            }
+            if noise_model.cosmic_ray.is_some() {
+                information +=
+                    &format_cosmic_ray_result(total_repeats, qec_failed, cosmic_ray_repeats, cosmic_ray_qec_failed);
+            }
+            if let GeneralDecoder::LookupTable(_) = &general_decoder {
+                information += &format!("{lookup_table_hits} {lookup_table_misses} ");
+            }
+            information
+        };
+        self.run_workers(configs, &worker, &progress_information);
+        Ok(progress_information())
+    }
+
+    /// spawn `configs.parallel` threads of the worker and display the progress until the termination condition is met
+    fn run_workers(
+        &self,
+        configs: &SimulationConfigs,
+        worker: &SimulationWorker,
+        progress_information: &dyn Fn() -> String,
+    ) {
+        let benchmark_control = &worker.benchmark_control;
+        // setup progress bar
+        let mut pb = ProgressBar::on(std::io::stderr(), configs.max_repeats as u64);
+        pb.set(0);
+        // spawn threads to do simulation
+        let mut handlers = Vec::new();
+        let mut threads_debugger: Vec<Arc<Mutex<BenchmarkThreadDebugger>>> = Vec::new();
+        let mut threads_ended = Vec::new(); // keep updating progress bar until all threads ends
         for parallel_idx in 0..configs.parallel {
             let thread_debugger = Arc::new(Mutex::new(BenchmarkThreadDebugger::new()));
             threads_debugger.push(thread_debugger.clone());
             let thread_ended = Arc::new(AtomicBool::new(false));
             threads_ended.push(Arc::clone(&thread_ended));
-            let mut thread_general_simulator = general_simulator.clone();
+            let mut worker_state = worker.clone();
+            worker_state.thread_debugger = thread_debugger;
+            worker_state.thread_ended = thread_ended;
             if let Some(deterministic_seed) = configs.deterministic_seed {
                 let seed: u64 = deterministic_seed + parallel_idx as u64;
-                thread_general_simulator.set_rng(Xoroshiro128StarStar::seed_from_u64(seed));
-            }
-            let mut worker_state = SimulationWorker {
-                benchmark_control: benchmark_control.clone(),
-                general_simulator: thread_general_simulator,
-                noise_model: noise_model.clone(),
-                log_runtime_statistics_file: log_runtime_statistics_file.clone(),
-                visualizer: visualizer.clone(),
-                general_decoder: general_decoder.clone(),
-                #[cfg(feature = "fusion_blossom")]
-                fusion_blossom_syndrome_exporter: fusion_blossom_syndrome_exporter.clone(),
-                thread_debugger,
-                thread_ended,
-                parameters: self.clone(),
-            };
+                worker_state
+                    .general_simulator
+                    .set_rng(Xoroshiro128StarStar::seed_from_u64(seed));
+            }
             handlers.push(
                 std::thread::Builder::new()
                     .stack_size(128 * 1024 * 1024)
@@ -744,27 +1092,6 @@ impl BenchmarkParameters {
         }
         // monitor results and display them using progress bar
         let repeat_begin = Instant::now();
-        let progress_information = || -> String {
-            let benchmark_control = benchmark_control.lock().unwrap().clone();
-            let total_repeats = benchmark_control.total_repeats;
-            let qec_failed = benchmark_control.qec_failed;
-            // compute simulation results
-            let error_rate = qec_failed as f64 / total_repeats as f64;
-            let confidence_interval_95_percent =
-                1.96 * (error_rate * (1. - error_rate) / (total_repeats as f64)).sqrt() / error_rate;
-            format!(
-                "{} {} {} {} {} {} {} {:.1e} {} ",
-                config.p,
-                config.di,
-                config.noisy_measurements,
-                total_repeats,
-                qec_failed,
-                error_rate,
-                config.dj,
-                confidence_interval_95_percent,
-                config.pe
-            )
-        };
         loop {
             let time_elapsed = repeat_begin.elapsed().as_secs_f64();
             if let Some(time_budget) = self.time_budget {
@@ -776,9 +1103,10 @@ impl BenchmarkParameters {
             pb.message(progress_information().as_str());
             {
                 // estimate running time cleverer
-                let benchmark_control = benchmark_control.lock().unwrap().clone();
+                let benchmark_control = benchmark_control.lock().unwrap();
                 let total_repeats = benchmark_control.total_repeats;
-                let qec_failed = benchmark_control.qec_failed;
+                let qec_failed = benchmark_control.accepted_qec_failed();
+                drop(benchmark_control);
                 let ratio_total_rounds = (total_repeats as f64) / (configs.max_repeats as f64);
                 let ratio_qec_failed = (qec_failed as f64) / (configs.min_failed_cases as f64);
                 let (mut pb_total, mut set_progress) = if ratio_total_rounds >= ratio_qec_failed {
@@ -815,7 +1143,7 @@ impl BenchmarkParameters {
                 pb.set(set_progress);
             }
             // synchronize statistics log file to make sure data is not lost when interrupting
-            if let Some(log_runtime_statistics_file) = &log_runtime_statistics_file {
+            if let Some(log_runtime_statistics_file) = &worker.log_runtime_statistics_file {
                 let log_runtime_statistics_file = log_runtime_statistics_file.lock().unwrap();
                 log_runtime_statistics_file.sync_data().unwrap();
             }
@@ -871,10 +1199,222 @@ impl BenchmarkParameters {
         }
         pb.finish();
         eprintln!("{}", progress_information());
-        Ok(progress_information())
+    }
+
+    /// estimate the logical error rate by sampling exactly `weight` error sources at a time for increasing weights up to
+    /// `max_weight` and then recombining the failure rates of each weight, see [`SimulatorCompactFixedWeight`].
+    /// `max_repeats`, `min_failed_cases` and `time_budget` are split evenly across the weights; the sampling stops at the
+    /// first weight whose tail probability is negligible compared to the estimated logical error rate, otherwise the
+    /// logical error rate is only a lower bound and a warning is printed
+    fn run_importance_sampling(
+        &self,
+        configs: &SimulationConfigs,
+        config: &SingleSimulationConfig,
+        mut worker: SimulationWorker,
+        max_weight: usize,
+    ) -> Result<String, String> {
+        let GeneralSimulator::SimulatorCompact(simulator_compact) = &worker.general_simulator else {
+            return Err("importance sampling only supports the compact simulator without compression".to_string());
+        };
+        if max_weight == 0 {
+            return Err("importance sampling requires a positive max weight".to_string());
+        }
+        let mut simulator_fixed_weight = SimulatorCompactFixedWeight::new(simulator_compact.clone(), max_weight);
+        let mut weight_parameters = self.clone();
+        let mut weight_configs = configs.clone();
+        weight_configs.max_repeats = std::cmp::max(configs.max_repeats / max_weight, 1);
+        weight_configs.min_failed_cases = std::cmp::max(configs.min_failed_cases / max_weight, 1);
+        weight_parameters.max_repeats = weight_configs.max_repeats;
+        weight_parameters.min_failed_cases = weight_configs.min_failed_cases;
+        weight_parameters.time_budget = self.time_budget.map(|time_budget| time_budget / max_weight as f64);
+        worker.parameters = weight_parameters.clone();
+        // the weight 0 never fails because there is no error
+        let mut results = vec![(0, 0)];
+        let (mut error_rate, mut confidence_interval) = (0., 0.);
+        for weight in 1..=max_weight {
+            simulator_fixed_weight.weight = weight;
+            let weight_probability = simulator_fixed_weight.weight_probability(weight);
+            let benchmark_control = Arc::new(Mutex::new(BenchmarkControl::new()));
+            worker.benchmark_control = benchmark_control.clone();
+            worker.general_simulator = GeneralSimulator::SimulatorCompactFixedWeight(simulator_fixed_weight.clone());
+            let progress_information = || -> String {
+                let benchmark_control = benchmark_control.lock().unwrap().clone();
+                let total_repeats = benchmark_control.total_repeats;
+                let qec_failed = benchmark_control.qec_failed;
+                format!(
+                    "[weight {}] probability: {:.3e}, shots: {}, failed: {}, failure rate: {}",
+                    weight,
+                    weight_probability,
+                    total_repeats,
+                    qec_failed,
+                    qec_failed as f64 / total_repeats as f64
+                )
+            };
+            weight_parameters.run_workers(&weight_configs, &worker, &progress_information);
+            let benchmark_control = benchmark_control.lock().unwrap().clone();
+            results.push((benchmark_control.total_repeats, benchmark_control.qec_failed));
+            (error_rate, confidence_interval) = simulator_fixed_weight.estimate_logical_error_rate(&results);
+            if simulator_fixed_weight.tail_probability(weight)
+                <= IMPORTANCE_SAMPLING_TAIL_TOLERANCE * importance_sampling_scale(error_rate, confidence_interval)
+            {
+                break;
+            }
+        }
+        // the unsampled weights may contribute at most their total probability
+        let sampled_max_weight = results.len() - 1;
+        let tail_probability = simulator_fixed_weight.tail_probability(sampled_max_weight);
+        let scale = importance_sampling_scale(error_rate, confidence_interval);
+        if tail_probability > IMPORTANCE_SAMPLING_TAIL_TOLERANCE * scale {
+            eprintln!(
+                "[warning] the logical error rate is a lower bound: more than {} error sources happen with probability {:.3e}, \
+                consider increasing the importance sampling max weight",
+                sampled_max_weight, tail_probability
+            );
+        }
+        let confidence_interval_95_percent = if error_rate > 0. {
+            (confidence_interval + tail_probability) / error_rate
+        } else {
+            // no sampled shot fails, so only an absolute upper bound of the logical error rate is known
+            let upper_bound = confidence_interval + tail_probability;
+            eprintln!("[info] no sampled shot fails, the logical error rate is below {upper_bound:.3e} with 95% confidence");
+            upper_bound
+        };
+        let total_repeats = results.iter().map(|(shots, _)| shots).sum();
+        let qec_failed = results.iter().map(|(_, failed)| failed).sum();
+        let information =
+            format_simulation_result(config, total_repeats, qec_failed, error_rate, confidence_interval_95_percent);
+        eprintln!("{}", information);
+        Ok(information)
+    }
+}
+
+/// importance sampling stops once the probability of the unsampled weights is below this fraction of the logical error rate
+const IMPORTANCE_SAMPLING_TAIL_TOLERANCE: f64 = 0.01;
+
+/// the logical error rate that the unsampled weights are compared with; when no sampled shot fails, the estimated logical
+/// error rate is 0 and its upper bound is used instead
+fn importance_sampling_scale(error_rate: f64, confidence_interval: f64) -> f64 {
+    if error_rate > 0. {
+        error_rate
+    } else {
+        confidence_interval
     }
 }
 
+impl FaultDistanceParameters {
+    pub fn run(&self) -> Result<String, String> {
+        let parameters = &self.benchmark_parameters;
+        let configs = parameters.fill_in_default_parameters()?;
+        parameters.assert_single_configuration(&configs)?;
+        let config = &parameters.extract_simulation_configurations(&configs)[0];
+        // the decoder uses `p_graph` and `pe_graph` while the faults come from the real noise model, same as `benchmark`
+        let mut simulator = parameters.construct_simulator(&configs, config)?;
+        let noise_model_graph = parameters.construct_noise_model(&mut simulator, &configs, config, true)?;
+        let general_decoder = GeneralDecoder::from_parameters(parameters, &configs, config, &simulator, &noise_model_graph)?;
+        let noise_model = parameters.construct_noise_model(&mut simulator, &configs, config, false)?;
+        if noise_model.has_leakage() {
+            return Err("fault distance cannot enumerate leakage because it's not independent between rounds".to_string());
+        }
+        let mut simulator_compact = SimulatorCompact::from_simulator(simulator, noise_model, configs.parallel_init);
+        let classes = fault_classes(&mut simulator_compact);
+        eprintln!(
+            "[info] {} faults are merged into {} classes",
+            simulator_compact.faults().len(),
+            classes.len()
+        );
+        let is_failed = |(logical_i, logical_j): (bool, bool)| {
+            (logical_i && !parameters.ignore_logical_i) || (logical_j && !parameters.ignore_logical_j)
+        };
+        let titles = "format: <weight> <configurations> <failing>".to_string();
+        eprintln!("{}", titles);
+        let mut output = titles + "\n";
+        let mut fault_distance = None;
+        for weight in 1..=self.max_weight {
+            let result = enumerate_fault_configurations(
+                &simulator_compact,
+                &general_decoder,
+                &classes,
+                weight,
+                configs.parallel,
+                &is_failed,
+            );
+            let information = format!("{} {} {}", weight, result.configurations, result.failing);
+            eprintln!("{}", information);
+            output += &(information + "\n");
+            if result.failing > 0 && fault_distance.is_none() {
+                fault_distance = Some((weight, result.failing_error_pattern));
+                if !self.all_weights {
+                    break;
+                }
+            }
+        }
+        match fault_distance {
+            Some((weight, failing_error_pattern)) => {
+                output += &format!("fault distance: {}\n", weight);
+                output += &format!("failing error pattern: {}\n", json!(failing_error_pattern));
+            }
+            None => output += &format!("no logical error within {} faults\n", self.max_weight),
+        }
+        Ok(output)
+    }
+}
+
+/// one line of the benchmark output, see the `format` line printed at the beginning
+fn format_simulation_result(
+    config: &SingleSimulationConfig,
+    total_repeats: usize,
+    qec_failed: usize,
+    error_rate: f64,
+    confidence_interval_95_percent: f64,
+) -> String {
+    format!(
+        "{} {} {} {} {} {} {} {:.1e} {} ",
+        config.p,
+        config.di,
+        config.noisy_measurements,
+        total_repeats,
+        qec_failed,
+        error_rate,
+        config.dj,
+        confidence_interval_95_percent,
+        config.pe
+    )
+}
+
+/// the post-selection columns appended to the benchmark output: the number of discarded shots, the fraction of accepted shots
+/// and the logical error rate conditioned on acceptance, each with the relative 95% confidence interval;
+/// the columns that are undefined because all the shots are discarded are printed as `-`
+fn format_post_selection_result(total_repeats: usize, discarded: usize, accepted_qec_failed: usize) -> String {
+    let accepted = total_repeats - discarded;
+    if accepted == 0 {
+        return format!("{discarded} 0 - - - ");
+    }
+    let accept_rate = accepted as f64 / total_repeats as f64;
+    let accept_rate_dev = 1.96 * (accept_rate * (1. - accept_rate) / (total_repeats as f64)).sqrt() / accept_rate;
+    let error_rate = accepted_qec_failed as f64 / accepted as f64;
+    let error_rate_dev = 1.96 * (error_rate * (1. - error_rate) / (accepted as f64)).sqrt() / error_rate;
+    format!("{discarded} {accept_rate} {accept_rate_dev:.1e} {error_rate} {error_rate_dev:.1e} ")
+}
+
+/// the cosmic-ray columns appended to the benchmark output: the number of shots with cosmic-ray events, and the logical
+/// error rates conditioned on the shots with and without them, each with the relative 95% confidence interval
+fn format_cosmic_ray_result(
+    total_repeats: usize,
+    qec_failed: usize,
+    cosmic_ray_repeats: usize,
+    cosmic_ray_qec_failed: usize,
+) -> String {
+    let conditional_error_rate = |repeats: usize, failed: usize| {
+        let error_rate = failed as f64 / repeats as f64;
+        let error_rate_dev = 1.96 * (error_rate * (1. - error_rate) / (repeats as f64)).sqrt() / error_rate;
+        (error_rate, error_rate_dev)
+    };
+    let (error_rate, error_rate_dev) = conditional_error_rate(cosmic_ray_repeats, cosmic_ray_qec_failed);
+    let (no_error_rate, no_error_rate_dev) =
+        conditional_error_rate(total_repeats - cosmic_ray_repeats, qec_failed - cosmic_ray_qec_failed);
+    format!("{cosmic_ray_repeats} {error_rate} {error_rate_dev:.1e} {no_error_rate} {no_error_rate_dev:.1e} ")
+}
+
 /// general class of all supported decoders in QECP
 #[derive(Clone)]
 pub enum GeneralDecoder {
@@ -890,6 +1430,11 @@ pub enum GeneralDecoder {
     HyperUnionFind(HyperUnionFindDecoder),
     #[cfg(feature = "hyperion")]
     Hyperion(HyperionDecoder),
+    BPOSD(BPOSDDecoder),
+    DistributedUnionFind(DistributedUnionFindDecoder),
+    MaximumLikelihood(MaximumLikelihoodDecoder),
+    LookupTable(LookupTableDecoder),
+    SlidingWindow(SlidingWindowDecoder),
 }
 
 impl GeneralDecoder {
@@ -900,6 +1445,34 @@ impl GeneralDecoder {
         simulator: &Simulator,
         noise_model_graph: &Arc<NoiseModel>,
     ) -> Result<Self, String> {
+        // these decoders enumerate the error sources of a compact simulator, which cannot describe leakage
+        let uses_simulator_compact = parameters.sliding_window_commit_rounds.is_some()
+            || matches!(
+                parameters.decoder,
+                BenchmarkDecoder::BPOSD | BenchmarkDecoder::MaximumLikelihood | BenchmarkDecoder::LookupTable
+            );
+        if uses_simulator_compact && noise_model_graph.has_leakage() {
+            return Err(
+                "the sliding window, BP-OSD, maximum-likelihood and lookup-table decoders cannot decode leakage".to_string(),
+            );
+        }
+        if let Some(commit_rounds) = parameters.sliding_window_commit_rounds {
+            parameters.assert_single_configuration(configs)?;
+            // the window decoder is built on the original simulator instead of the extended one
+            let mut window_parameters = parameters.clone();
+            window_parameters.sliding_window_commit_rounds = None;
+            window_parameters.simulator_compact_extender_noisy_measurements = None;
+            let window_decoder = Self::from_parameters(&window_parameters, configs, config, simulator, noise_model_graph)?;
+            return Ok(GeneralDecoder::SlidingWindow(SlidingWindowDecoder::new(
+                simulator,
+                noise_model_graph.clone(),
+                window_decoder,
+                parameters.simulator_compact_extender_noisy_measurements.unwrap(),
+                commit_rounds,
+                configs.parallel_init,
+                parameters.use_brief_edge,
+            )?));
+        }
         Ok(match parameters.decoder {
             BenchmarkDecoder::None => {
                 // if parameters.decoder_config.is_object() && parameters.decoder_config.as_object().ok_or("decoder config is not json object")?.len() != 0 {
@@ -913,7 +1486,7 @@ impl GeneralDecoder {
                 &parameters.decoder_config,
                 configs.parallel_init,
                 parameters.use_brief_edge,
-            )),
+            )?),
             #[cfg(feature = "fusion_blossom")]
             BenchmarkDecoder::Fusion => {
                 let first = FusionDecoder::new(
@@ -927,6 +1500,9 @@ impl GeneralDecoder {
                     parameters.simulator_compact_extender_noisy_measurements
                 {
                     parameters.assert_single_configuration(configs)?;
+                    if first.config.soft_output {
+                        return Err("soft output is not supported by the extended fusion decoder".to_string());
+                    }
                     if simulator_compact_extender_noisy_measurements < config.noisy_measurements {
                         return Err(format!("extender only works for larger noisy_measurement than nms[0], now {simulator_compact_extender_noisy_measurements} < {}", config.noisy_measurements));
                     } else {
@@ -963,6 +1539,7 @@ impl GeneralDecoder {
                             adaptor: Arc::new(generated),
                             fusion_solver,
                             config: first.config,
+                            complementary_gap_solver: None,
                         })
                     }
                 } else {
@@ -1013,6 +1590,47 @@ impl GeneralDecoder {
             BenchmarkDecoder::HyperUnionFind => {
                 return Err("decoder is not available; try enable feature `hyperion`".to_string())
             }
+            BenchmarkDecoder::BPOSD => GeneralDecoder::BPOSD(BPOSDDecoder::new(
+                simulator,
+                noise_model_graph.clone(),
+                &parameters.decoder_config,
+                configs.parallel_init,
+                parameters.use_brief_edge,
+            )?),
+            BenchmarkDecoder::DistributedUnionFind => {
+                GeneralDecoder::DistributedUnionFind(DistributedUnionFindDecoder::new(
+                    simulator,
+                    noise_model_graph.clone(),
+                    &parameters.decoder_config,
+                    configs.parallel_init,
+                    parameters.use_brief_edge,
+                ))
+            }
+            BenchmarkDecoder::MaximumLikelihood => GeneralDecoder::MaximumLikelihood(MaximumLikelihoodDecoder::new(
+                simulator,
+                noise_model_graph.clone(),
+                &parameters.decoder_config,
+                configs.parallel_init,
+            )?),
+            BenchmarkDecoder::LookupTable => {
+                let lookup_table_config: LookupTableDecoderConfig =
+                    serde_json::from_value(parameters.decoder_config.clone()).map_err(|error| error.to_string())?;
+                let mut fallback_parameters = parameters.clone();
+                fallback_parameters.decoder = BenchmarkDecoder::from_str(&lookup_table_config.fallback, true)?;
+                if fallback_parameters.decoder == BenchmarkDecoder::LookupTable {
+                    return Err("the fallback decoder cannot be a lookup table".to_string());
+                }
+                fallback_parameters.decoder_config = lookup_table_config.fallback_config.clone();
+                let fallback_decoder =
+                    Self::from_parameters(&fallback_parameters, configs, config, simulator, noise_model_graph)?;
+                GeneralDecoder::LookupTable(LookupTableDecoder::new(
+                    simulator,
+                    noise_model_graph.clone(),
+                    &parameters.decoder_config,
+                    fallback_decoder,
+                    configs.parallel_init,
+                )?)
+            }
             #[cfg(feature = "hyperion")]
             BenchmarkDecoder::Hyperion => GeneralDecoder::Hyperion(HyperionDecoder::new(
                 simulator,
@@ -1056,10 +1674,62 @@ impl GeneralDecoder {
             Self::Hyperion(hyperion_decoder) => {
                 hyperion_decoder.decode_with_erasure(sparse_measurement, sparse_detected_erasures)
             }
+            Self::BPOSD(bp_osd_decoder) => bp_osd_decoder.decode_with_erasure(sparse_measurement, sparse_detected_erasures),
+            Self::DistributedUnionFind(distributed_union_find_decoder) => {
+                distributed_union_find_decoder.decode_with_erasure(sparse_measurement, sparse_detected_erasures)
+            }
+            Self::MaximumLikelihood(maximum_likelihood_decoder) => {
+                maximum_likelihood_decoder.decode_with_erasure(sparse_measurement, sparse_detected_erasures)
+            }
+            Self::LookupTable(lookup_table_decoder) => {
+                lookup_table_decoder.decode_with_erasure(sparse_measurement, sparse_detected_erasures)
+            }
+            Self::SlidingWindow(sliding_window_decoder) => {
+                sliding_window_decoder.decode_with_erasure(sparse_measurement, sparse_detected_erasures)
+            }
+        }
+    }
+
+    /// the complete model graph on which the decoder finds the matching, if the decoder keeps one
+    pub fn complete_model_graph_mut(&mut self) -> Option<&mut CompleteModelGraph> {
+        match self {
+            Self::MWPM(mwpm_decoder) => Some(&mut mwpm_decoder.complete_model_graph),
+            _ => None,
+        }
+    }
+
+    /// whether the decoder reports the soft output of each shot, see [`SoftOutput`]
+    pub fn supports_soft_output(&self) -> bool {
+        match self {
+            Self::MWPM(mwpm_decoder) => mwpm_decoder.complementary_gap_solver.is_some(),
+            #[cfg(feature = "fusion_blossom")]
+            Self::Fusion(fusion_decoder) => fusion_decoder.complementary_gap_solver.is_some(),
+            Self::UnionFind(union_find_decoder) => union_find_decoder.complementary_gap_solver.is_some(),
+            _ => false,
+        }
+    }
+
+    /// decode and also return the matching of the defects, where a defect matched with the boundary has no peer;
+    /// only `mwpm` and `fusion` decoders report the matching
+    pub fn decode_matching_with_erasure(
+        &mut self,
+        sparse_measurement: &SparseMeasurement,
+        sparse_detected_erasures: &SparseErasures,
+    ) -> Result<(SparseCorrection, MatchedDefects, serde_json::Value), String> {
+        match self {
+            Self::MWPM(mwpm_decoder) => {
+                Ok(mwpm_decoder.decode_matching_with_erasure(sparse_measurement, sparse_detected_erasures))
+            }
+            #[cfg(feature = "fusion_blossom")]
+            Self::Fusion(fusion_decoder) => {
+                Ok(fusion_decoder.decode_matching_with_erasure(sparse_measurement, sparse_detected_erasures))
+            }
+            _ => Err("the decoder doesn't report the matching of the defects".to_string()),
         }
     }
 }
 
+#[derive(Clone)]
 pub struct SimulationWorker {
     pub benchmark_control: Arc<Mutex<BenchmarkControl>>,
     pub general_simulator: GeneralSimulator,
@@ -1072,138 +1742,546 @@ pub struct SimulationWorker {
     pub thread_debugger: Arc<Mutex<BenchmarkThreadDebugger>>,
     pub thread_ended: Arc<AtomicBool>,
     pub parameters: BenchmarkParameters,
+    pub post_selection: Option<PostSelection>,
+}
+
+/// a decoded shot waiting for its correction to be validated
+struct DecodedShot {
+    cosmic_ray_events: usize,
+    sparse_detected_erasures: SparseErasures,
+    sparse_measurement: SparseMeasurement,
+    runtime_statistics: serde_json::Value,
+    /// only recorded when the simulator moves on to the next shot before the validation
+    sparse_error_pattern: Option<SparseErrorPattern>,
+    simulate_elapsed: f64,
+    decode_elapsed: f64,
 }
 
 impl SimulationWorker {
     pub fn run(&mut self) {
-        for thread_counter in 0..usize::MAX {
-            let parameters = &self.parameters;
-            if parameters.thread_timeout >= 0. {
-                self.thread_debugger.lock().unwrap().update_thread_counter(thread_counter);
+        // the batch simulator validates the corrections of all the shots in a batch at once
+        let batch_size = match &self.general_simulator {
+            GeneralSimulator::SimulatorBatch(_) => BATCH_SIZE,
+            _ => 1,
+        };
+        // the simulator moves on before the validation, so record the error patterns that may be reported afterwards
+        let record_error_pattern = batch_size > 1
+            && (matches!(self.parameters.debug_print, Some(BenchmarkDebugPrint::FailedErrorPattern))
+                || (self.log_runtime_statistics_file.is_some() && self.parameters.log_error_pattern_when_logical_error)
+                || self.visualizer.is_some());
+        let mut decoded_shots = Vec::with_capacity(batch_size);
+        let mut corrections = Vec::with_capacity(batch_size);
+        'simulation: for thread_counter in 0..usize::MAX {
+            let (decoded_shot, correction) = self.simulate_and_decode(thread_counter, record_error_pattern);
+            decoded_shots.push(decoded_shot);
+            corrections.push(correction);
+            if corrections.len() < batch_size {
+                continue;
             }
-            // generate random errors and the corresponding measurement
+            // validate corrections
             let begin = Instant::now();
-            let (error_count, erasure_count) = self.general_simulator.generate_random_errors(&self.noise_model);
-            let sparse_detected_erasures = if erasure_count != 0 {
-                self.general_simulator.generate_sparse_detected_erasures()
-            } else {
-                SparseErasures::new()
+            let logical_errors = match &mut self.general_simulator {
+                GeneralSimulator::SimulatorBatch(simulator_batch) => {
+                    debug_assert_eq!(simulator_batch.shot + 1, corrections.len(), "must validate a whole batch");
+                    simulator_batch.validate_batch_corrections(&corrections)
+                }
+                general_simulator => vec![general_simulator.validate_correction(&corrections[0])],
             };
-            if parameters.thread_timeout >= 0. {
-                let mut thread_debugger = self.thread_debugger.lock().unwrap();
-                thread_debugger.error_pattern = Some(self.general_simulator.generate_sparse_error_pattern());
-                thread_debugger.detected_erasures = Some(sparse_detected_erasures.clone());
-            } // runtime debug: find deadlock cases
-            if matches!(parameters.debug_print, Some(BenchmarkDebugPrint::AllErrorPattern)) {
-                let sparse_error_pattern = self.general_simulator.generate_sparse_error_pattern();
-                eprint!(
-                    "{}",
-                    serde_json::to_string(&sparse_error_pattern).expect("serialize should success")
-                );
-                if !sparse_detected_erasures.is_empty() {
-                    // has detected erasures, report as well
-                    eprintln!(
-                        ", {}",
-                        serde_json::to_string(&sparse_detected_erasures).expect("serialize should success")
-                    );
-                } else {
-                    eprintln!();
+            let validate_elapsed = begin.elapsed().as_secs_f64() / corrections.len() as f64;
+            for ((decoded_shot, correction), (logical_i, logical_j)) in
+                decoded_shots.drain(..).zip(corrections.drain(..)).zip(logical_errors)
+            {
+                if self.finish_shot(decoded_shot, correction, logical_i, logical_j, validate_elapsed) {
+                    break 'simulation;
                 }
             }
-            let sparse_measurement = if error_count != 0 {
-                self.general_simulator.generate_sparse_measurement()
+        }
+        self.thread_ended.store(true, Ordering::SeqCst);
+    }
+
+    /// simulate a shot and decode it, recording the error pattern if `record_error_pattern` is set
+    fn simulate_and_decode(&mut self, thread_counter: usize, record_error_pattern: bool) -> (DecodedShot, SparseCorrection) {
+        let parameters = &self.parameters;
+        if parameters.thread_timeout >= 0. {
+            self.thread_debugger.lock().unwrap().update_thread_counter(thread_counter);
+        }
+        // generate random errors and the corresponding measurement
+        let begin = Instant::now();
+        let (error_count, erasure_count) = self.general_simulator.generate_random_errors(&self.noise_model);
+        let cosmic_ray_events = self.general_simulator.cosmic_ray_events();
+        let sparse_detected_erasures = if erasure_count != 0 {
+            self.general_simulator.generate_sparse_detected_erasures()
+        } else {
+            SparseErasures::new()
+        };
+        if parameters.thread_timeout >= 0. {
+            let mut thread_debugger = self.thread_debugger.lock().unwrap();
+            thread_debugger.error_pattern = Some(self.general_simulator.generate_sparse_error_pattern());
+            thread_debugger.detected_erasures = Some(sparse_detected_erasures.clone());
+        } // runtime debug: find deadlock cases
+        if matches!(parameters.debug_print, Some(BenchmarkDebugPrint::AllErrorPattern)) {
+            let sparse_error_pattern = self.general_simulator.generate_sparse_error_pattern();
+            eprint!(
+                "{}",
+                serde_json::to_string(&sparse_error_pattern).expect("serialize should success")
+            );
+            if !sparse_detected_erasures.is_empty() {
+                // has detected erasures, report as well
+                eprintln!(
+                    ", {}",
+                    serde_json::to_string(&sparse_detected_erasures).expect("serialize should success")
+                );
             } else {
-                SparseMeasurement::new()
-            };
-            if parameters.thread_timeout >= 0. {
-                self.thread_debugger.lock().unwrap().measurement = Some(sparse_measurement.clone());
-            } // runtime debug: find deadlock cases
-            let simulate_elapsed = begin.elapsed().as_secs_f64();
-            cfg_if::cfg_if! { if #[cfg(feature="fusion_blossom")] {
-                if let Some(fusion_blossom_syndrome_exporter) = self.fusion_blossom_syndrome_exporter.as_ref() {
-                    fusion_blossom_syndrome_exporter.add_syndrome(&sparse_measurement, &sparse_detected_erasures);
-                }
-            } }
-            // decode
-            let begin = Instant::now();
-            let (correction, mut runtime_statistics) = self
-                .general_decoder
-                .decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
-            if parameters.thread_timeout >= 0. {
-                self.thread_debugger.lock().unwrap().correction = Some(correction.clone());
-            } // runtime debug: find deadlock cases
-            let decode_elapsed = begin.elapsed().as_secs_f64();
-            // validate correction
-            let begin = Instant::now();
-            let mut is_qec_failed = false;
-            let (logical_i, logical_j) = self.general_simulator.validate_correction(&correction);
-            if logical_i && !parameters.ignore_logical_i {
-                is_qec_failed = true;
-            }
-            if logical_j && !parameters.ignore_logical_j {
-                is_qec_failed = true;
-            }
-            let validate_elapsed = begin.elapsed().as_secs_f64();
-            if is_qec_failed && matches!(parameters.debug_print, Some(BenchmarkDebugPrint::FailedErrorPattern)) {
-                let sparse_error_pattern = self.general_simulator.generate_sparse_error_pattern();
-                eprint!(
-                    "{}",
-                    serde_json::to_string(&sparse_error_pattern).expect("serialize should success")
+                eprintln!();
+            }
+        }
+        let sparse_measurement = if error_count != 0 {
+            self.general_simulator.generate_sparse_measurement()
+        } else {
+            SparseMeasurement::new()
+        };
+        if parameters.thread_timeout >= 0. {
+            self.thread_debugger.lock().unwrap().measurement = Some(sparse_measurement.clone());
+        } // runtime debug: find deadlock cases
+        let simulate_elapsed = begin.elapsed().as_secs_f64();
+        cfg_if::cfg_if! { if #[cfg(feature="fusion_blossom")] {
+            if let Some(fusion_blossom_syndrome_exporter) = self.fusion_blossom_syndrome_exporter.as_ref() {
+                fusion_blossom_syndrome_exporter.add_syndrome(&sparse_measurement, &sparse_detected_erasures);
+            }
+        } }
+        // decode
+        let begin = Instant::now();
+        let (correction, runtime_statistics) = self
+            .general_decoder
+            .decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
+        if parameters.thread_timeout >= 0. {
+            self.thread_debugger.lock().unwrap().correction = Some(correction.clone());
+        } // runtime debug: find deadlock cases
+        let decode_elapsed = begin.elapsed().as_secs_f64();
+        let sparse_error_pattern = if record_error_pattern {
+            Some(self.general_simulator.generate_sparse_error_pattern())
+        } else {
+            None
+        };
+        let decoded_shot = DecodedShot {
+            cosmic_ray_events,
+            sparse_detected_erasures,
+            sparse_measurement,
+            runtime_statistics,
+            sparse_error_pattern,
+            simulate_elapsed,
+            decode_elapsed,
+        };
+        (decoded_shot, correction)
+    }
+
+    /// update the statistics with a validated shot, returning whether the benchmark should terminate
+    fn finish_shot(
+        &mut self,
+        decoded_shot: DecodedShot,
+        correction: SparseCorrection,
+        logical_i: bool,
+        logical_j: bool,
+        validate_elapsed: f64,
+    ) -> bool {
+        let parameters = &self.parameters;
+        let DecodedShot {
+            cosmic_ray_events,
+            sparse_detected_erasures,
+            sparse_measurement,
+            mut runtime_statistics,
+            sparse_error_pattern,
+            simulate_elapsed,
+            decode_elapsed,
+        } = decoded_shot;
+        // the simulator still holds this shot unless its error pattern has been recorded
+        let generate_sparse_error_pattern = |general_simulator: &GeneralSimulator| {
+            sparse_error_pattern
+                .clone()
+                .unwrap_or_else(|| general_simulator.generate_sparse_error_pattern())
+        };
+        let is_qec_failed = (logical_i && !parameters.ignore_logical_i) || (logical_j && !parameters.ignore_logical_j);
+        let lookup_table_hit = match &self.general_decoder {
+            GeneralDecoder::LookupTable(_) => runtime_statistics["hit"].as_bool(),
+            _ => None,
+        };
+        // post-selection based on the per-shot flags
+        let needs_complementary_gap = parameters.soft_output_abort_rates.is_some()
+            || matches!(&self.post_selection, Some(post_selection) if post_selection.min_complementary_gap.is_some());
+        let complementary_gap = if needs_complementary_gap {
+            let soft_output =
+                SoftOutput::from_runtime_statistics(&runtime_statistics).expect("the decoder must report the soft output");
+            Some(soft_output.complementary_gap(parameters.ignore_logical_i, parameters.ignore_logical_j))
+        } else {
+            None
+        };
+        let is_discarded = match &self.post_selection {
+            Some(post_selection) => post_selection.should_discard(complementary_gap, sparse_detected_erasures.len()),
+            None => false,
+        };
+        if is_qec_failed && matches!(parameters.debug_print, Some(BenchmarkDebugPrint::FailedErrorPattern)) {
+            let sparse_error_pattern = generate_sparse_error_pattern(&self.general_simulator);
+            eprint!(
+                "{}",
+                serde_json::to_string(&sparse_error_pattern).expect("serialize should success")
+            );
+            if !sparse_detected_erasures.is_empty() {
+                // has detected erasures, report as well
+                eprintln!(
+                    ", {}",
+                    serde_json::to_string(&sparse_detected_erasures).expect("serialize should success")
                 );
-                if !sparse_detected_erasures.is_empty() {
-                    // has detected erasures, report as well
-                    eprintln!(
-                        ", {}",
-                        serde_json::to_string(&sparse_detected_erasures).expect("serialize should success")
-                    );
-                } else {
-                    eprintln!();
-                }
+            } else {
+                eprintln!();
             }
-            // update statistic information
-            if let Some(log_runtime_statistics_file) = &self.log_runtime_statistics_file {
-                runtime_statistics["qec_failed"] = json!(is_qec_failed);
-                if parameters.log_error_pattern_when_logical_error && is_qec_failed {
-                    runtime_statistics["error_pattern"] = json!(self.general_simulator.generate_sparse_error_pattern());
-                }
-                runtime_statistics["elapsed"] = json!({
-                    "simulate": simulate_elapsed,
-                    "decode": decode_elapsed,
-                    "validate": validate_elapsed,
+        }
+        // update statistic information
+        if let Some(log_runtime_statistics_file) = &self.log_runtime_statistics_file {
+            runtime_statistics["qec_failed"] = json!(is_qec_failed);
+            if self.post_selection.is_some() {
+                runtime_statistics["discarded"] = json!(is_discarded);
+            }
+            if self.noise_model.cosmic_ray.is_some() {
+                runtime_statistics["cosmic_ray_events"] = json!(cosmic_ray_events);
+            }
+            if parameters.log_error_pattern_when_logical_error && is_qec_failed {
+                runtime_statistics["error_pattern"] = json!(generate_sparse_error_pattern(&self.general_simulator));
+            }
+            runtime_statistics["elapsed"] = json!({
+                "simulate": simulate_elapsed,
+                "decode": decode_elapsed,
+                "validate": validate_elapsed,
+            });
+            let to_be_written = format!("{}\n", runtime_statistics);
+            let mut log_runtime_statistics_file = log_runtime_statistics_file.lock().unwrap();
+            log_runtime_statistics_file.write_all(to_be_written.as_bytes()).unwrap();
+        }
+        // update visualizer
+        if let Some(visualizer) = &self.visualizer {
+            if !parameters.visualizer_skip_success_cases || is_qec_failed {
+                let case = json!({
+                    "error_pattern": generate_sparse_error_pattern(&self.general_simulator),
+                    "measurement": sparse_measurement,
+                    "detected_erasures": sparse_detected_erasures,
+                    "correction": correction,
+                    "qec_failed": is_qec_failed,
+                    "elapsed": {
+                        "simulate": simulate_elapsed,
+                        "decode": decode_elapsed,
+                        "validate": validate_elapsed,
+                    },
+                    "runtime_statistics": runtime_statistics,
                 });
-                let to_be_written = format!("{}\n", runtime_statistics);
-                let mut log_runtime_statistics_file = log_runtime_statistics_file.lock().unwrap();
-                log_runtime_statistics_file.write_all(to_be_written.as_bytes()).unwrap();
-            }
-            // update visualizer
-            if let Some(visualizer) = &self.visualizer {
-                if !parameters.visualizer_skip_success_cases || is_qec_failed {
-                    let case = json!({
-                        "error_pattern": self.general_simulator.generate_sparse_error_pattern(),
-                        "measurement": sparse_measurement,
-                        "detected_erasures": sparse_detected_erasures,
-                        "correction": correction,
-                        "qec_failed": is_qec_failed,
-                        "elapsed": {
-                            "simulate": simulate_elapsed,
-                            "decode": decode_elapsed,
-                            "validate": validate_elapsed,
-                        },
-                        "runtime_statistics": runtime_statistics,
-                    });
-                    let mut visualizer = visualizer.lock().unwrap();
-                    visualizer.add_case(case).unwrap();
-                }
+                let mut visualizer = visualizer.lock().unwrap();
+                visualizer.add_case(case).unwrap();
             }
-            // update simulation counters, then break the loop if benchmark should terminate
-            if self.benchmark_control.lock().unwrap().update_data_should_terminate(
+        }
+        // update simulation counters, then tell whether the benchmark should terminate
+        self.benchmark_control.lock().unwrap().update_data_should_terminate(
+            is_qec_failed,
+            is_discarded,
+            complementary_gap.filter(|_| parameters.soft_output_abort_rates.is_some()),
+            cosmic_ray_events > 0,
+            lookup_table_hit,
+            parameters.max_repeats,
+            parameters.min_failed_cases,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn benchmark_post_selection_accounting() {
+        // cargo test benchmark_post_selection_accounting -- --nocapture
+        let (max_repeats, min_failed_cases) = (100, 2);
+        let mut benchmark_control = BenchmarkControl::new();
+        // (is_qec_failed, is_discarded): the discarded shots are counted in the total but not in the accepted failures
+        for (is_qec_failed, is_discarded) in [
+            (false, false),
+            (true, true),
+            (true, false),
+            (false, true),
+            (true, true),
+            (false, false),
+        ] {
+            assert!(!benchmark_control.update_data_should_terminate(
                 is_qec_failed,
-                parameters.max_repeats,
-                parameters.min_failed_cases,
-            ) {
-                break;
-            }
+                is_discarded,
+                None,
+                false,
+                None,
+                max_repeats,
+                min_failed_cases
+            ));
         }
-        self.thread_ended.store(true, Ordering::SeqCst);
+        assert_eq!(benchmark_control.total_repeats, 6);
+        assert_eq!(benchmark_control.qec_failed, 3);
+        assert_eq!(benchmark_control.discarded, 3);
+        assert_eq!(benchmark_control.discarded_qec_failed, 2);
+        assert_eq!(benchmark_control.accepted_qec_failed(), 1);
+        // only the accepted failed shots count towards `min_failed_cases`
+        assert!(!benchmark_control.update_data_should_terminate(
+            true,
+            true,
+            None,
+            false,
+            None,
+            max_repeats,
+            min_failed_cases
+        ));
+        assert!(benchmark_control.update_data_should_terminate(
+            true,
+            false,
+            None,
+            false,
+            None,
+            max_repeats,
+            min_failed_cases
+        ));
+        // 8 shots, 4 discarded and 2 out of the 4 accepted shots failed
+        let information = format_post_selection_result(
+            benchmark_control.total_repeats,
+            benchmark_control.discarded,
+            benchmark_control.accepted_qec_failed(),
+        );
+        let columns: Vec<f64> = information.split_whitespace().map(|value| value.parse().unwrap()).collect();
+        assert_eq!(columns[0], 4.);
+        assert_eq!(columns[1], 0.5);
+        assert!((columns[2] - 1.96 * (0.5f64 * 0.5 / 8.).sqrt() / 0.5).abs() < 0.05);
+        assert_eq!(columns[3], 0.5);
+        assert!((columns[4] - 1.96 * (0.5f64 * 0.5 / 4.).sqrt() / 0.5).abs() < 0.05);
+        // the rules of post-selection
+        let post_selection: PostSelection =
+            serde_json::from_value(json!({"min_complementary_gap": 5, "max_detected_erasures": 2})).unwrap();
+        assert!(!post_selection.should_discard(Some(5.), 2));
+        assert!(post_selection.should_discard(Some(4.9), 0));
+        assert!(post_selection.should_discard(Some(f64::INFINITY), 3));
+    }
+
+    #[test]
+    fn benchmark_post_selection_conditional_error_rate() {
+        // cargo test benchmark_post_selection_conditional_error_rate -- --nocapture
+        // with only erasure errors, the shots without detected erasures never fail
+        let parameters = BenchmarkParameters::parse_from([
+            "benchmark",
+            "[3]",
+            "[0]",
+            "[0]",
+            "--pes",
+            "[0.1]",
+            "--max-repeats",
+            "2000",
+            "--min-failed-cases",
+            "2000",
+            "--post-select",
+            r#"{"max_detected_erasures":0}"#,
+            "--deterministic-seed",
+            "0",
+        ]);
+        let output = parameters.run().unwrap();
+        println!("{output}");
+        let columns: Vec<f64> = output
+            .lines()
+            .last()
+            .unwrap()
+            .split_whitespace()
+            .map(|value| value.parse().unwrap())
+            .collect();
+        let (total_repeats, qec_failed) = (columns[3], columns[4]);
+        let (discarded, accept_rate, conditional_error_rate) = (columns[9], columns[10], columns[12]);
+        assert_eq!(total_repeats, 2000.);
+        assert!(qec_failed > 0.);
+        assert_eq!(accept_rate, (total_repeats - discarded) / total_repeats);
+        assert_eq!(conditional_error_rate, 0.);
+        // 13 data qubits in the d=3 planar code: about 1 - 0.9^13 = 75% of the shots are discarded
+        assert!((accept_rate - 0.9f64.powi(13)).abs() < 0.05, "accept rate {accept_rate}");
+    }
+
+    #[test]
+    fn benchmark_post_selection_all_discarded() {
+        // cargo test benchmark_post_selection_all_discarded -- --nocapture
+        // almost every shot has a detected erasure, so all of them are discarded
+        let parameters = BenchmarkParameters::parse_from([
+            "benchmark",
+            "[3]",
+            "[0]",
+            "[0]",
+            "--pes",
+            "[0.5]",
+            "--max-repeats",
+            "100",
+            "--post-select",
+            r#"{"max_detected_erasures":0}"#,
+            "--deterministic-seed",
+            "0",
+        ]);
+        let output = parameters.run().unwrap();
+        println!("{output}");
+        let columns: Vec<&str> = output.lines().last().unwrap().split_whitespace().collect();
+        assert_eq!(columns[3], "100");
+        assert_eq!(columns[9..14], ["100", "0", "-", "-", "-"]);
+        assert_eq!(format_post_selection_result(10, 10, 0), "10 0 - - - ");
+    }
+
+    #[test]
+    fn benchmark_lookup_table_hits_and_misses() {
+        // cargo test benchmark_lookup_table_hits_and_misses -- --nocapture
+        // the table only holds single errors, so the shots with more errors fall back to MWPM
+        let parameters = BenchmarkParameters::parse_from([
+            "benchmark",
+            "[3]",
+            "[0]",
+            "[0.05]",
+            "--decoder",
+            "lookup-table",
+            "--decoder-config",
+            r#"{"max_weight":1}"#,
+            "--max-repeats",
+            "1000",
+            "--min-failed-cases",
+            "1000",
+            "--deterministic-seed",
+            "0",
+        ]);
+        let output = parameters.run().unwrap();
+        println!("{output}");
+        let titles = output.lines().next().unwrap();
+        assert!(titles.ends_with("<lookup_table_hits> <lookup_table_misses>"));
+        let columns: Vec<f64> = output
+            .lines()
+            .last()
+            .unwrap()
+            .split_whitespace()
+            .map(|value| value.parse().unwrap())
+            .collect();
+        let (total_repeats, lookup_table_hits, lookup_table_misses) = (columns[3], columns[9], columns[10]);
+        assert_eq!(total_repeats, 1000.);
+        assert_eq!(lookup_table_hits + lookup_table_misses, total_repeats);
+        assert!(lookup_table_hits > 0. && lookup_table_misses > 0.);
+    }
+
+    #[test]
+    fn benchmark_batch_simulator() {
+        // cargo test benchmark_batch_simulator -- --nocapture
+        // the batch simulator decodes a whole batch before validating the corrections at once
+        let run_with = |simulator: &str| {
+            let parameters = BenchmarkParameters::parse_from([
+                "benchmark",
+                "[3]",
+                "[0]",
+                "[0.05]",
+                "--simulator",
+                simulator,
+                "--max-repeats",
+                "4000",
+                "--min-failed-cases",
+                "4000",
+                "--deterministic-seed",
+                "0",
+            ]);
+            let output = parameters.run().unwrap();
+            println!("{output}");
+            let columns: Vec<f64> = output
+                .lines()
+                .last()
+                .unwrap()
+                .split_whitespace()
+                .map(|value| value.parse().unwrap())
+                .collect();
+            (columns[3], columns[5])
+        };
+        let (total_repeats, error_rate) = run_with("batch");
+        let (full_total_repeats, full_error_rate) = run_with("full");
+        assert_eq!(total_repeats, 4000.);
+        assert_eq!(full_total_repeats, 4000.);
+        // both logical error rates are about 4%, with a standard deviation of about 0.3% each
+        assert!(
+            (error_rate - full_error_rate).abs() < 0.015,
+            "{error_rate} vs {full_error_rate}"
+        );
+    }
+
+    #[test]
+    fn benchmark_leakage_requires_full_simulator() {
+        // cargo test benchmark_leakage_requires_full_simulator -- --nocapture
+        let run_with = |arguments: &[&str]| {
+            let parameters = BenchmarkParameters::parse_from(
+                [
+                    "benchmark",
+                    "[3]",
+                    "[1]",
+                    "[0.001]",
+                    "--noise-model-builder",
+                    "stim-noise-model",
+                    "--noise-model-configuration",
+                    r#"{"leakage_rate":0.001}"#,
+                    "--max-repeats",
+                    "10",
+                ]
+                .iter()
+                .chain(arguments.iter()),
+            );
+            parameters.run()
+        };
+        assert!(run_with(&[]).is_ok());
+        for arguments in [
+            &["--use-compact-simulator"][..],
+            &["--simulator", "batch"],
+            &["--simulator", "compact"],
+            &["--use-compact-simulator", "--importance-sampling-max-weight", "2"],
+            &["--decoder", "lookup-table"],
+            &["--decoder", "bposd"],
+            &["--debug-print", "detector-error-model"],
+        ] {
+            let error = run_with(arguments).unwrap_err();
+            println!("{arguments:?}: {error}");
+            assert!(error.contains("leakage"));
+        }
+        let parameters = FaultDistanceParameters::parse_from([
+            "fault-distance",
+            "[3]",
+            "[1]",
+            "[0.001]",
+            "--noise-model-builder",
+            "stim-noise-model",
+            "--noise-model-configuration",
+            r#"{"leakage_rate":0.001}"#,
+        ]);
+        assert!(parameters.run().unwrap_err().contains("leakage"));
+    }
+
+    #[test]
+    fn benchmark_importance_sampling_without_failure() {
+        // cargo test benchmark_importance_sampling_without_failure -- --nocapture
+        let parameters = BenchmarkParameters::parse_from([
+            "benchmark",
+            "[5]",
+            "[0]",
+            "[0.0001]",
+            "--code-type",
+            "rotated-planar-code",
+            "--use-compact-simulator",
+            "--importance-sampling-max-weight",
+            "8",
+            "--max-repeats",
+            "800",
+        ]);
+        let output = parameters.run().unwrap();
+        println!("{output}");
+        let columns: Vec<&str> = output.lines().last().unwrap().split_whitespace().collect();
+        // neither 1 nor 2 errors can fail a distance-5 code, so only an upper bound is reported
+        let qec_failed: usize = columns[4].parse().unwrap();
+        let error_rate: f64 = columns[5].parse().unwrap();
+        let upper_bound: f64 = columns[7].parse().unwrap();
+        assert_eq!(qec_failed, 0);
+        assert_eq!(error_rate, 0.);
+        assert!(upper_bound.is_finite() && upper_bound > 0. && upper_bound < 1e-3);
+        // the sampling stops as soon as the upper bound makes the unsampled weights negligible
+        let total_repeats: usize = columns[3].parse().unwrap();
+        assert!(total_repeats < 800, "sampled {total_repeats} shots");
     }
 }
@@ -1,4 +1,5 @@
 #![allow(non_snake_case)]
+use super::reproducible_rand::Xoroshiro128StarStar;
 #[cfg(feature = "python_binding")]
 use pyo3::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -56,6 +57,20 @@ impl std::fmt::Display for ErrorType {
 }
 
 impl ErrorType {
+    /// uniformly random among `I`, `X`, `Y` and `Z`, i.e. the maximally mixed state
+    pub fn random(rng: &mut Xoroshiro128StarStar) -> Self {
+        let random_number = rng.next_f64();
+        if random_number < 0.25 {
+            Self::X
+        } else if random_number < 0.5 {
+            Self::Z
+        } else if random_number < 0.75 {
+            Self::Y
+        } else {
+            Self::I
+        }
+    }
+
     /// multiply two pauli operator
     #[inline]
     pub fn multiply(&self, err: &Self) -> Self {
@@ -433,9 +448,9 @@ impl CorrelatedErasureErrorType {
             Self::IE | Self::EE => true,
         }
     }
-    // pub fn all_possible_errors() -> Vec::<Self> {
-    //     vec![Self::II, Self::IE, Self::EI, Self::EE]
-    // }
+    pub fn all_possible_errors() -> Vec<Self> {
+        vec![Self::II, Self::IE, Self::EI, Self::EE]
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -468,14 +483,14 @@ impl CorrelatedErasureErrorRates {
     pub fn no_error_probability(&self) -> f64 {
         1. - self.error_probability()
     }
-    // pub fn error_rate(&self, error_type: &CorrelatedErasureErrorType) -> f64 {
-    //     match error_type {
-    //         CorrelatedErasureErrorType::II => self.no_error_probability(),
-    //         CorrelatedErasureErrorType::IE => self.error_rate_IE,
-    //         CorrelatedErasureErrorType::EI => self.error_rate_EI,
-    //         CorrelatedErasureErrorType::EE => self.error_rate_EE,
-    //     }
-    // }
+    pub fn error_rate(&self, error_type: &CorrelatedErasureErrorType) -> f64 {
+        match error_type {
+            CorrelatedErasureErrorType::II => self.no_error_probability(),
+            CorrelatedErasureErrorType::IE => self.error_rate_IE,
+            CorrelatedErasureErrorType::EI => self.error_rate_EI,
+            CorrelatedErasureErrorType::EE => self.error_rate_EE,
+        }
+    }
     pub fn sanity_check(&self) {
         assert!(
             self.no_error_probability() >= 0.,